@@ -36,3 +36,104 @@ create table if not exists sessions (
     expired timestamp default date_add(current_timestamp, interval 48 hour)
     );
 
+
+create table if not exists saved_views (
+    id int unsigned auto_increment primary key,
+    user_name varchar(128) not null references users(name),
+    name varchar(128) not null,
+    filter varchar(1024) not null default '',
+    sort_order varchar(16) not null default 'EndAsc',
+    only_incomplete bool not null default true,
+    unique (user_name, name)
+    );
+
+create table if not exists user_settings (
+    user_name varchar(128) primary key references users(name),
+    only_incomplete bool not null default true,
+    item_sort_order varchar(16) not null default 'EndAsc',
+    default_start_offset int not null default 0,
+    default_end_offset int,
+    theme varchar(16) not null default 'System'
+    );
+
+# 検索用インデックスと外部キー
+# 列定義中のreferences句は、MariaDBでは無視されるため、改めて外部キーを定義する。
+
+create index if not exists idx_todo_user_done_start on todo(user_name, done, start_date);
+create index if not exists idx_todo_user_end on todo(user_name, end_date);
+create index if not exists idx_todo_user_update on todo(user_name, update_date);
+create index if not exists idx_todo_tag_tag on todo_tag(tag_name);
+create index if not exists idx_sessions_user on sessions(user_name);
+create index if not exists idx_sessions_expired on sessions(expired);
+
+alter table todo
+    add constraint fk_todo_user foreign key if not exists (user_name)
+        references users(name) on update cascade on delete cascade;
+alter table todo_tag
+    add constraint fk_todo_tag_todo foreign key if not exists (todo_id)
+        references todo(id) on delete cascade,
+    add constraint fk_todo_tag_tag foreign key if not exists (tag_name)
+        references tag(name) on update cascade on delete cascade;
+alter table sessions
+    add constraint fk_sessions_user foreign key if not exists (user_name)
+        references users(name) on update cascade on delete cascade;
+alter table saved_views
+    add constraint fk_saved_views_user foreign key if not exists (user_name)
+        references users(name) on update cascade on delete cascade;
+alter table user_settings
+    add constraint fk_user_settings_user foreign key if not exists (user_name)
+        references users(name) on update cascade on delete cascade;
+
+# todoの繰り返し規則(iCalendarのRRULEの値)
+
+create table if not exists todo_recurrence (
+    todo_id int unsigned primary key,
+    rrule varchar(512) not null,
+    constraint fk_todo_recurrence_todo foreign key (todo_id)
+        references todo(id) on delete cascade
+    );
+
+# ICSフィード購読用の、ユーザーごとの秘密トークン
+
+create table if not exists feed_tokens (
+    user_name varchar(128) primary key,
+    token varchar(64) not null unique,
+    constraint fk_feed_tokens_user foreign key (user_name)
+        references users(name) on update cascade on delete cascade
+    );
+
+# 作業報告用に、todoの作成日と完了日を記録する
+
+alter table todo
+    add column if not exists create_date date,
+    add column if not exists done_date date;
+
+# 既存のtodoは、更新日を作成日・完了日とみなす
+update todo set create_date = update_date where create_date is null;
+update todo set done_date = update_date where done and done_date is null;
+
+# todoの変更履歴(作成・編集・完了状態の変更・削除)
+# todoを削除しても履歴を残すため、todo_idには外部キーを設定しない。
+
+create table if not exists todo_history (
+    id bigint unsigned auto_increment primary key,
+    todo_id int unsigned not null,
+    user_name varchar(128) not null,
+    action varchar(16) not null,
+    old_value text,
+    new_value text,
+    changed_at datetime(6) not null default current_timestamp(6),
+    session varchar(40),
+    client varchar(32) not null,
+    constraint fk_todo_history_user foreign key (user_name)
+        references users(name) on update cascade on delete cascade
+    );
+
+create index if not exists idx_todo_history_todo on todo_history(todo_id, changed_at);
+create index if not exists idx_todo_history_user on todo_history(user_name, changed_at);
+
+# 複数の端末からの同時編集を検出するため、todoに版番号を持たせる
+# 編集・完了状態の変更のたびに1増やす。
+
+alter table todo
+    add column if not exists revision int unsigned not null default 0;
@@ -0,0 +1,11 @@
+# 保存済みビュー(スマートリスト)
+
+create table if not exists saved_views (
+    id int unsigned auto_increment primary key,
+    user_name varchar(128) not null references users(name),
+    name varchar(128) not null,
+    filter varchar(1024) not null default '',
+    sort_order varchar(16) not null default 'EndAsc',
+    only_incomplete bool not null default true,
+    unique (user_name, name)
+    );
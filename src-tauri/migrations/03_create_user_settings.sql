@@ -0,0 +1,10 @@
+# ユーザーごとの表示設定
+
+create table if not exists user_settings (
+    user_name varchar(128) primary key references users(name),
+    only_incomplete bool not null default true,
+    item_sort_order varchar(16) not null default 'EndAsc',
+    default_start_offset int not null default 0,
+    default_end_offset int,
+    theme varchar(16) not null default 'System'
+    );
@@ -0,0 +1,35 @@
+# 検索用インデックスと外部キーの追加
+# 列定義中のreferences句は、MariaDBでは無視されるため、改めて外部キーを定義する。
+
+# 外部キー定義の前に、参照先のない行を削除する。
+delete from todo where user_name is null or user_name not in (select name from users);
+delete from sessions where user_name is null or user_name not in (select name from users);
+delete from saved_views where user_name not in (select name from users);
+delete from user_settings where user_name not in (select name from users);
+delete from todo_tag where todo_id not in (select id from todo);
+delete from todo_tag where tag_name not in (select name from tag);
+
+create index if not exists idx_todo_user_done_start on todo(user_name, done, start_date);
+create index if not exists idx_todo_user_end on todo(user_name, end_date);
+create index if not exists idx_todo_user_update on todo(user_name, update_date);
+create index if not exists idx_todo_tag_tag on todo_tag(tag_name);
+create index if not exists idx_sessions_user on sessions(user_name);
+create index if not exists idx_sessions_expired on sessions(expired);
+
+alter table todo
+    add constraint fk_todo_user foreign key if not exists (user_name)
+        references users(name) on update cascade on delete cascade;
+alter table todo_tag
+    add constraint fk_todo_tag_todo foreign key if not exists (todo_id)
+        references todo(id) on delete cascade,
+    add constraint fk_todo_tag_tag foreign key if not exists (tag_name)
+        references tag(name) on update cascade on delete cascade;
+alter table sessions
+    add constraint fk_sessions_user foreign key if not exists (user_name)
+        references users(name) on update cascade on delete cascade;
+alter table saved_views
+    add constraint fk_saved_views_user foreign key if not exists (user_name)
+        references users(name) on update cascade on delete cascade;
+alter table user_settings
+    add constraint fk_user_settings_user foreign key if not exists (user_name)
+        references users(name) on update cascade on delete cascade;
@@ -0,0 +1,8 @@
+# todoの繰り返し規則(iCalendarのRRULEの値)
+
+create table if not exists todo_recurrence (
+    todo_id int unsigned primary key,
+    rrule varchar(512) not null,
+    constraint fk_todo_recurrence_todo foreign key (todo_id)
+        references todo(id) on delete cascade
+    );
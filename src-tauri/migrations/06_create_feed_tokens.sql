@@ -0,0 +1,8 @@
+# ICSフィード購読用の、ユーザーごとの秘密トークン
+
+create table if not exists feed_tokens (
+    user_name varchar(128) primary key,
+    token varchar(64) not null unique,
+    constraint fk_feed_tokens_user foreign key (user_name)
+        references users(name) on update cascade on delete cascade
+    );
@@ -0,0 +1,9 @@
+# 作業報告用に、todoの作成日と完了日を記録する
+
+alter table todo
+    add column if not exists create_date date,
+    add column if not exists done_date date;
+
+# 既存のtodoは、更新日を作成日・完了日とみなす
+update todo set create_date = update_date where create_date is null;
+update todo set done_date = update_date where done and done_date is null;
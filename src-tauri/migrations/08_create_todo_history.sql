@@ -0,0 +1,19 @@
+# todoの変更履歴(作成・編集・完了状態の変更・削除)
+# todoを削除しても履歴を残すため、todo_idには外部キーを設定しない。
+
+create table if not exists todo_history (
+    id bigint unsigned auto_increment primary key,
+    todo_id int unsigned not null,
+    user_name varchar(128) not null,
+    action varchar(16) not null,
+    old_value text,
+    new_value text,
+    changed_at datetime(6) not null default current_timestamp(6),
+    session varchar(40),
+    client varchar(32) not null,
+    constraint fk_todo_history_user foreign key (user_name)
+        references users(name) on update cascade on delete cascade
+    );
+
+create index if not exists idx_todo_history_todo on todo_history(todo_id, changed_at);
+create index if not exists idx_todo_history_user on todo_history(user_name, changed_at);
@@ -0,0 +1,5 @@
+# 複数の端末からの同時編集を検出するため、todoに版番号を持たせる
+# 編集・完了状態の変更のたびに1増やす。
+
+alter table todo
+    add column if not exists revision int unsigned not null default 0;
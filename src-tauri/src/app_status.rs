@@ -1,26 +1,63 @@
 //! アプリケーション全体のステータスを保持する。
 
-use crate::{config::NekoTodoConfig, todo::Todo};
-use std::sync::{Arc, Mutex};
+use crate::{
+    config::NekoTodoConfig,
+    database::{ConnectConfig, PoolConfig},
+    todo::Todo,
+};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex, RwLock,
+};
 
 pub struct AppStatus {
     config: Arc<Mutex<NekoTodoConfig>>,
-    todo: Todo,
+    /// プロファイルの切り替え時に、作り直す。
+    todo: RwLock<Arc<Todo>>,
+    /// データベースの接続情報が設定済みか
+    configured: AtomicBool,
 }
 
 impl AppStatus {
     pub fn new(config: NekoTodoConfig, todo: Todo) -> Self {
         Self {
             config: Arc::new(Mutex::new(config)),
-            todo,
+            todo: RwLock::new(Arc::new(todo)),
+            configured: AtomicBool::new(true),
         }
     }
 
+    /// データベースの接続情報が未設定の状態で生成する。
+    /// 接続情報が設定されるまで、Todoはデータベースに接続しない。
+    pub fn not_configured(config: NekoTodoConfig) -> Self {
+        let pool = PoolConfig {
+            min_connections: 0,
+            ..Default::default()
+        };
+        let todo = Todo::new_offline(&ConnectConfig::default(), &pool);
+        Self {
+            configured: AtomicBool::new(false),
+            ..Self::new(config, todo)
+        }
+    }
+
+    pub fn is_configured(&self) -> bool {
+        self.configured.load(Ordering::Relaxed)
+    }
+
     pub fn config(&self) -> &Mutex<NekoTodoConfig> {
         &self.config
     }
 
-    pub fn todo(&self) -> &Todo {
-        &self.todo
+    /// 使用中のTodoを取得する。
+    /// 切り替え前に取得したものは、処理が終わるまで切り替え前のデータベースを使用する。
+    pub fn todo(&self) -> Arc<Todo> {
+        self.todo.read().unwrap().clone()
+    }
+
+    /// Todoを入れ替える。入れ替え後は、接続情報が設定済みとなる。
+    pub fn set_todo(&self, todo: Todo) {
+        *self.todo.write().unwrap() = Arc::new(todo);
+        self.configured.store(true, Ordering::Relaxed);
     }
 }
@@ -0,0 +1,131 @@
+//! ユーザーデータ一式のバックアップと復元
+//!
+//! todo・タグ・繰り返し規則・保存済みビュー・表示設定を、一つのアーカイブにまとめる。
+//! アーカイブは、次の形式とする。
+//! - 先頭: 識別子"NEKOBAK"、アーカイブの版(1バイト)、フラグ(1バイト)、
+//!   データベースの構造の版(4バイト、リトルエンディアン)
+//! - 暗号化時: ソルト(16バイト)、ナンス(12バイト)
+//! - 本体: JSONを、必要に応じてzlibで圧縮し、さらにパスワードで暗号化したもの
+//!
+//! 暗号化は、Argon2idでパスワードから鍵を導出し、AES-256-GCMで行う。
+//! 先頭部分は改ざん検出の対象(AAD)に含める。
+mod archive;
+#[cfg(test)]
+mod test;
+
+use crate::database::{UserData, SCHEMA_VERSION};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+pub use archive::{read_archive, write_archive};
+
+/// バックアップの内容の版
+pub const BACKUP_VERSION: u32 = 1;
+
+/// バックアップの内容
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Backup {
+    pub version: u32,
+    /// 作成時のデータベースの構造の版
+    pub schema_version: u32,
+    pub created_at: DateTime<Local>,
+    /// バックアップ元のユーザー名
+    pub user_name: String,
+    #[serde(flatten)]
+    pub data: UserData,
+}
+
+/// アーカイブの作成方法
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ArchiveOptions {
+    /// 圧縮するか
+    pub compress: bool,
+    /// 暗号化のパスワード。Noneなら暗号化しない。
+    pub password: Option<String>,
+}
+
+/// 復元の方法
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum RestoreMode {
+    /// 既存のデータに追加する。重複するtodo・ビューは復元しない。
+    #[default]
+    Merge,
+    /// 既存のデータをすべて削除してから復元する。
+    Replace,
+}
+
+#[derive(Error, Debug)]
+pub enum BackupError {
+    #[error("バックアップファイルではありません。")]
+    NotBackup,
+    #[error("バックアップファイルが壊れています。")]
+    Broken,
+    #[error("未対応のバックアップの版です。:{0}")]
+    UnsupportedVersion(u32),
+    #[error("新しい版のアプリケーションで作成されたバックアップです。(データベースの版:{0}、対応する版:{SCHEMA_VERSION}まで)")]
+    NewerSchema(u32),
+    #[error("暗号化されたバックアップです。パスワードを指定してください。")]
+    PasswordRequired,
+    #[error("パスワードが違うか、バックアップファイルが改ざんされています。")]
+    WrongPassword,
+    #[error("暗号鍵の生成に失敗しました。:{0}")]
+    KeyDerivation(String),
+    #[error("未対応の復元方法です。:{0}")]
+    InvalidMode(String),
+    #[error("バックアップの内容が不正です。:{0}")]
+    Json(#[from] serde_json::Error),
+    #[error("ファイルの読み書きに失敗しました。:{0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl Backup {
+    pub fn new(user_name: &str, data: UserData) -> Self {
+        Self {
+            version: BACKUP_VERSION,
+            schema_version: SCHEMA_VERSION,
+            created_at: Local::now(),
+            user_name: user_name.to_string(),
+            data,
+        }
+    }
+
+    /// バックアップをファイルに書き込む。
+    pub fn write_to_file(
+        &self,
+        path: &std::path::Path,
+        options: &ArchiveOptions,
+    ) -> Result<(), BackupError> {
+        std::fs::write(path, write_archive(self, options)?)?;
+        Ok(())
+    }
+
+    /// ファイルからバックアップを読み込む。
+    pub fn read_from_file(
+        path: &std::path::Path,
+        password: Option<&str>,
+    ) -> Result<Self, BackupError> {
+        read_archive(&std::fs::read(path)?, password)
+    }
+}
+
+impl std::fmt::Display for RestoreMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Merge => write!(f, "merge"),
+            Self::Replace => write!(f, "replace"),
+        }
+    }
+}
+
+impl std::str::FromStr for RestoreMode {
+    type Err = BackupError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "merge" => Ok(Self::Merge),
+            "replace" => Ok(Self::Replace),
+            _ => Err(BackupError::InvalidMode(s.to_string())),
+        }
+    }
+}
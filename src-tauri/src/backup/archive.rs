@@ -0,0 +1,133 @@
+//! バックアップアーカイブの読み書き
+
+use super::*;
+use aes_gcm::{
+    aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng, Payload},
+    Aes256Gcm, Key, Nonce,
+};
+use argon2::Argon2;
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use std::io::{Read, Write};
+
+/// アーカイブの識別子
+const MAGIC: &[u8; 7] = b"NEKOBAK";
+/// アーカイブの形式の版
+const ARCHIVE_VERSION: u8 = 1;
+/// フラグ: 圧縮
+const FLAG_COMPRESSED: u8 = 0x01;
+/// フラグ: 暗号化
+const FLAG_ENCRYPTED: u8 = 0x02;
+/// 先頭部分の長さ(識別子・版・フラグ・構造の版)
+const HEADER_LEN: usize = MAGIC.len() + 1 + 1 + 4;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// パスワードとソルトから、暗号鍵を導出する。
+fn derive_key(password: &str, salt: &[u8]) -> Result<Key<Aes256Gcm>, BackupError> {
+    let mut key = Key::<Aes256Gcm>::default();
+    Argon2::default()
+        .hash_password_into(password.as_bytes(), salt, &mut key)
+        .map_err(|e| BackupError::KeyDerivation(e.to_string()))?;
+    Ok(key)
+}
+
+/// バックアップを、アーカイブのバイト列にする。
+pub fn write_archive(backup: &Backup, options: &ArchiveOptions) -> Result<Vec<u8>, BackupError> {
+    let mut body = serde_json::to_vec(backup)?;
+    let mut flags = 0;
+    if options.compress {
+        flags |= FLAG_COMPRESSED;
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&body)?;
+        body = encoder.finish()?;
+    }
+    if options.password.is_some() {
+        flags |= FLAG_ENCRYPTED;
+    }
+
+    let mut out = Vec::with_capacity(HEADER_LEN + SALT_LEN + NONCE_LEN + body.len() + 16);
+    out.extend_from_slice(MAGIC);
+    out.push(ARCHIVE_VERSION);
+    out.push(flags);
+    out.extend_from_slice(&backup.schema_version.to_le_bytes());
+
+    match &options.password {
+        Some(password) => {
+            let mut salt = [0u8; SALT_LEN];
+            OsRng.fill_bytes(&mut salt);
+            let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+            let cipher = Aes256Gcm::new(&derive_key(password, &salt)?);
+            let encrypted = cipher
+                .encrypt(
+                    &nonce,
+                    Payload {
+                        msg: &body,
+                        aad: &out[..HEADER_LEN],
+                    },
+                )
+                .map_err(|_| BackupError::Broken)?;
+            out.extend_from_slice(&salt);
+            out.extend_from_slice(&nonce);
+            out.extend_from_slice(&encrypted);
+        }
+        None => out.extend_from_slice(&body),
+    }
+    Ok(out)
+}
+
+/// アーカイブを読み込む。
+/// データベースの構造の版は、復号の前に検査する。
+pub fn read_archive(data: &[u8], password: Option<&str>) -> Result<Backup, BackupError> {
+    if data.len() < HEADER_LEN || &data[..MAGIC.len()] != MAGIC {
+        return Err(BackupError::NotBackup);
+    }
+    let (header, rest) = data.split_at(HEADER_LEN);
+    let version = header[MAGIC.len()];
+    if version != ARCHIVE_VERSION {
+        return Err(BackupError::UnsupportedVersion(version.into()));
+    }
+    let flags = header[MAGIC.len() + 1];
+    let schema_version = u32::from_le_bytes(header[MAGIC.len() + 2..].try_into().unwrap());
+    if schema_version > SCHEMA_VERSION {
+        return Err(BackupError::NewerSchema(schema_version));
+    }
+
+    let mut body = if flags & FLAG_ENCRYPTED != 0 {
+        let Some(password) = password else {
+            return Err(BackupError::PasswordRequired);
+        };
+        if rest.len() < SALT_LEN + NONCE_LEN {
+            return Err(BackupError::Broken);
+        }
+        let (salt, rest) = rest.split_at(SALT_LEN);
+        let (nonce, encrypted) = rest.split_at(NONCE_LEN);
+        let cipher = Aes256Gcm::new(&derive_key(password, salt)?);
+        cipher
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: encrypted,
+                    aad: header,
+                },
+            )
+            .map_err(|_| BackupError::WrongPassword)?
+    } else {
+        rest.to_vec()
+    };
+    if flags & FLAG_COMPRESSED != 0 {
+        let mut decompressed = Vec::new();
+        ZlibDecoder::new(&body[..])
+            .read_to_end(&mut decompressed)
+            .map_err(|_| BackupError::Broken)?;
+        body = decompressed;
+    }
+
+    let backup = serde_json::from_slice::<Backup>(&body)?;
+    if backup.version != BACKUP_VERSION {
+        return Err(BackupError::UnsupportedVersion(backup.version));
+    }
+    if backup.schema_version != schema_version {
+        return Err(BackupError::Broken);
+    }
+    Ok(backup)
+}
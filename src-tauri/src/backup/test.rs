@@ -0,0 +1,210 @@
+use super::*;
+use crate::config::{ItemSortOrder, Theme};
+use crate::database::{
+    HistoryAction, SavedView, TodoHistory, TodoRecord, TodoSnapshot, UserSettings,
+};
+use chrono::NaiveDate;
+
+fn sample_backup() -> Backup {
+    let date = |m, d| NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+    let todos = (1..=20)
+        .map(|i| TodoRecord {
+            id: i,
+            title: format!("todo{i}件目"),
+            work: (i % 2 == 0).then(|| "内容".repeat(10)),
+            update_date: date(6, 1),
+            start_date: date(6, 1),
+            end_date: date(6, 30),
+            done: i % 3 == 0,
+            create_date: Some(date(5, 31)),
+            done_date: (i % 3 == 0).then(|| date(6, 2)),
+            tags: vec!["仕事".to_string()],
+            recurrence: (i == 1).then(|| "FREQ=WEEKLY".to_string()),
+        })
+        .collect();
+    let data = UserData {
+        todos,
+        views: vec![SavedView {
+            id: 1,
+            user_name: "美都".to_string(),
+            name: "仕事".to_string(),
+            filter: "tag:仕事".to_string(),
+            sort_order: ItemSortOrder::EndAsc,
+            only_incomplete: true,
+        }],
+        settings: Some(UserSettings {
+            user_name: "美都".to_string(),
+            only_incomplete: false,
+            item_sort_order: ItemSortOrder::UpdateDesc,
+            default_start_offset: 1,
+            default_end_offset: Some(7),
+            theme: Theme::Dark,
+        }),
+        history: vec![TodoHistory {
+            id: 1,
+            todo_id: 1,
+            action: HistoryAction::Create,
+            old_value: None,
+            new_value: Some(TodoSnapshot {
+                title: "todo1件目".to_string(),
+                work: None,
+                start_date: date(6, 1),
+                end_date: date(6, 30),
+                done: false,
+            }),
+            changed_at: date(5, 31).and_hms_micro_opt(9, 30, 0, 123456).unwrap(),
+            session: Some("01900000-0000-7000-8000-000000000000".to_string()),
+            client: "gui".to_string(),
+        }],
+    };
+    Backup::new("美都", data)
+}
+
+#[test]
+fn archive_roundtrip() {
+    let backup = sample_backup();
+    for compress in [false, true] {
+        for password in [None, Some("ひみつ".to_string())] {
+            let options = ArchiveOptions {
+                compress,
+                password: password.clone(),
+            };
+            let archive = write_archive(&backup, &options).unwrap();
+            assert!(archive.starts_with(b"NEKOBAK"));
+            let read = read_archive(&archive, password.as_deref()).unwrap();
+            assert_eq!(
+                read,
+                backup,
+                "圧縮:{compress} 暗号化:{}",
+                password.is_some()
+            );
+        }
+    }
+
+    let plain = write_archive(&backup, &ArchiveOptions::default()).unwrap();
+    let compressed = write_archive(
+        &backup,
+        &ArchiveOptions {
+            compress: true,
+            password: None,
+        },
+    )
+    .unwrap();
+    assert!(compressed.len() < plain.len(), "圧縮すると小さくなるはず");
+}
+
+#[test]
+fn archive_encryption_errors() {
+    let backup = sample_backup();
+    let options = ArchiveOptions {
+        compress: true,
+        password: Some("ひみつ".to_string()),
+    };
+    let mut archive = write_archive(&backup, &options).unwrap();
+    assert!(
+        !archive
+            .windows("todo1件目".len())
+            .any(|w| w == "todo1件目".as_bytes()),
+        "暗号化されていれば、平文は含まれない"
+    );
+    assert!(matches!(
+        read_archive(&archive, None),
+        Err(BackupError::PasswordRequired)
+    ));
+    assert!(matches!(
+        read_archive(&archive, Some("ちがう")),
+        Err(BackupError::WrongPassword)
+    ));
+
+    // 本体の改ざん
+    let last = archive.len() - 1;
+    archive[last] ^= 0xff;
+    assert!(matches!(
+        read_archive(&archive, Some("ひみつ")),
+        Err(BackupError::WrongPassword)
+    ));
+}
+
+#[test]
+fn archive_header_errors() {
+    let backup = sample_backup();
+    let archive = write_archive(&backup, &ArchiveOptions::default()).unwrap();
+
+    assert!(matches!(
+        read_archive(b"{\"version\":1}", None),
+        Err(BackupError::NotBackup)
+    ));
+    assert!(matches!(
+        read_archive(&archive[..5], None),
+        Err(BackupError::NotBackup)
+    ));
+
+    let mut newer_format = archive.clone();
+    newer_format[7] = 2;
+    assert!(matches!(
+        read_archive(&newer_format, None),
+        Err(BackupError::UnsupportedVersion(2))
+    ));
+
+    // 構造の版は、パスワードがなくても復号の前に検査する。
+    let mut newer_schema = write_archive(
+        &backup,
+        &ArchiveOptions {
+            compress: false,
+            password: Some("ひみつ".to_string()),
+        },
+    )
+    .unwrap();
+    newer_schema[9..13].copy_from_slice(&(SCHEMA_VERSION + 1).to_le_bytes());
+    assert!(matches!(
+        read_archive(&newer_schema, None),
+        Err(BackupError::NewerSchema(v)) if v == SCHEMA_VERSION + 1
+    ));
+
+    // 先頭部分と本体で、構造の版が異なる。
+    let mut mismatch = archive.clone();
+    mismatch[9..13].copy_from_slice(&(SCHEMA_VERSION - 1).to_le_bytes());
+    assert!(matches!(
+        read_archive(&mismatch, None),
+        Err(BackupError::Broken)
+    ));
+
+    let mut broken = archive;
+    broken.truncate(broken.len() / 2);
+    assert!(matches!(
+        read_archive(&broken, None),
+        Err(BackupError::Json(_))
+    ));
+}
+
+#[test]
+fn older_backup_is_readable() {
+    // 作成日・完了日を持たない版のバックアップも読み込める。
+    let json = r#"{"version":1,"schema_version":5,"created_at":"2024-06-01T10:00:00+09:00",
+        "user_name":"美都","todos":[{"id":1,"title":"古いtodo","work":null,
+        "update_date":"2024-05-01","start_date":"2024-05-01","end_date":"9999-12-31",
+        "done":false}]}"#;
+    let mut archive = b"NEKOBAK\x01\x00".to_vec();
+    archive.extend_from_slice(&5u32.to_le_bytes());
+    archive.extend_from_slice(json.as_bytes());
+    let backup = read_archive(&archive, None).unwrap();
+    assert_eq!(backup.data.todos.len(), 1);
+    assert_eq!(backup.data.todos[0].create_date, None);
+    assert!(backup.data.todos[0].tags.is_empty());
+    assert!(backup.data.views.is_empty());
+    assert_eq!(backup.data.settings, None);
+    assert!(backup.data.history.is_empty());
+}
+
+#[test]
+fn restore_mode_from_str() {
+    assert_eq!("merge".parse::<RestoreMode>().unwrap(), RestoreMode::Merge);
+    assert_eq!(
+        "Replace".parse::<RestoreMode>().unwrap(),
+        RestoreMode::Replace
+    );
+    assert!(matches!(
+        "overwrite".parse::<RestoreMode>(),
+        Err(BackupError::InvalidMode(_))
+    ));
+}
@@ -0,0 +1,115 @@
+//! todoの変更通知
+//!
+//! 変更履歴を一定間隔で確認し、新たな変更をTauriのイベントとして全ウィンドウへ通知する。
+//! 他のウィンドウ・CLI・todo.txtの同期などによる変更も、画面に即時に反映できる。
+//!
+//! | イベント名 | 変更の種類 |
+//! |---|---|
+//! | `todo-created` | 作成 |
+//! | `todo-updated` | 編集・完了状態の変更 |
+//! | `todo-deleted` | 削除 |
+#[cfg(test)]
+mod test;
+
+use crate::app_status::AppStatus;
+use crate::database::{HistoryAction, TodoHistory, TodoSnapshot};
+use crate::todo::{ChangeCursor, Todo, TodoError};
+use log::{error, info};
+use serde::Serialize;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// 変更履歴を確認する間隔
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// 変更通知のイベントの内容
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TodoChangeEvent {
+    /// 変更履歴のid
+    pub history_id: u64,
+    pub todo_id: u32,
+    pub action: HistoryAction,
+    /// 変更を行ったクライアント(gui, cliなど)
+    pub client: String,
+    /// 変更後の内容。削除時はNone。
+    pub todo: Option<TodoSnapshot>,
+}
+
+impl From<TodoHistory> for TodoChangeEvent {
+    fn from(value: TodoHistory) -> Self {
+        Self {
+            history_id: value.id,
+            todo_id: value.todo_id,
+            action: value.action,
+            client: value.client,
+            todo: value.new_value,
+        }
+    }
+}
+
+/// 変更の種類に対応するイベント名
+fn event_name(action: HistoryAction) -> &'static str {
+    match action {
+        HistoryAction::Create => "todo-created",
+        HistoryAction::Edit | HistoryAction::Done => "todo-updated",
+        HistoryAction::Delete => "todo-deleted",
+    }
+}
+
+/// 監視中のデータベースと、その監視位置
+struct Watch {
+    /// 監視位置を取得したTodo。入れ替えられたら、監視位置を破棄する。
+    todo: Weak<Todo>,
+    cursor: ChangeCursor,
+}
+
+/// 変更通知を、バックグラウンドで開始する。
+pub fn spawn_change_feed(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        info!("todoの変更通知開始");
+        let mut watch = None;
+        let mut ticker = tokio::time::interval(POLL_INTERVAL);
+        loop {
+            ticker.tick().await;
+            watch = poll_once(&app, watch).await;
+        }
+    });
+}
+
+/// 一回分の確認を行い、新しい監視位置を返す。ログインしていなければ、監視位置を破棄する。
+/// 接続情報の設定前やオフライン中は、確認しない。
+/// プロファイルの切り替えなどでTodoが入れ替えられたら、監視位置を取得し直す。
+async fn poll_once(app: &AppHandle, watch: Option<Watch>) -> Option<Watch> {
+    let state = app.state::<AppStatus>();
+    let sess = state.config().lock().unwrap().get_session_id()?;
+    let todo = state.todo();
+    if !state.is_configured() || !todo.is_online() {
+        return watch;
+    }
+    let cursor = watch
+        .filter(|w| std::ptr::eq(w.todo.as_ptr(), Arc::as_ptr(&todo)))
+        .map(|w| w.cursor);
+    match todo.poll_changes(sess, cursor.as_ref()).await {
+        Ok((cursor, changes)) => {
+            for change in changes {
+                let name = event_name(change.action);
+                if let Err(e) = app.emit(name, TodoChangeEvent::from(change)) {
+                    error!("変更通知({name})に失敗:{e}");
+                }
+            }
+            Some(Watch {
+                todo: Arc::downgrade(&todo),
+                cursor,
+            })
+        }
+        Err(TodoError::NotFoundSession) => None,
+        Err(e) => {
+            error!("変更履歴の確認に失敗:{e}");
+            cursor.map(|cursor| Watch {
+                todo: Arc::downgrade(&todo),
+                cursor,
+            })
+        }
+    }
+}
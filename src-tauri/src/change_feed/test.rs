@@ -0,0 +1,50 @@
+//! change_feedモジュールテスト
+
+use super::*;
+use chrono::NaiveDate;
+
+#[test]
+fn test_event_name() {
+    assert_eq!(event_name(HistoryAction::Create), "todo-created");
+    assert_eq!(event_name(HistoryAction::Edit), "todo-updated");
+    assert_eq!(event_name(HistoryAction::Done), "todo-updated");
+    assert_eq!(event_name(HistoryAction::Delete), "todo-deleted");
+}
+
+#[test]
+fn test_change_event() {
+    let snapshot = TodoSnapshot {
+        title: "タイトル".to_string(),
+        work: None,
+        start_date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+        end_date: NaiveDate::from_ymd_opt(2024, 1, 31).unwrap(),
+        done: true,
+    };
+    let history = TodoHistory {
+        id: 10,
+        todo_id: 3,
+        action: HistoryAction::Done,
+        old_value: None,
+        new_value: Some(snapshot.clone()),
+        changed_at: NaiveDate::from_ymd_opt(2024, 1, 2)
+            .unwrap()
+            .and_hms_opt(3, 4, 5)
+            .unwrap(),
+        session: None,
+        client: "cli".to_string(),
+    };
+    let event = TodoChangeEvent::from(history);
+    assert_eq!(
+        event,
+        TodoChangeEvent {
+            history_id: 10,
+            todo_id: 3,
+            action: HistoryAction::Done,
+            client: "cli".to_string(),
+            todo: Some(snapshot),
+        }
+    );
+    let json = serde_json::to_value(&event).unwrap();
+    assert_eq!(json["action"], "done");
+    assert_eq!(json["todo"]["end_date"], "2024-01-31");
+}
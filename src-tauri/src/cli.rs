@@ -0,0 +1,253 @@
+//! コマンドラインからの操作
+//!
+//! GUIを起動せずに、サブコマンドで指定された処理を行う。
+//! セッションは、GUIでログインした際に設定ファイルに保存されたものを使用する。
+
+use crate::{
+    backup::{ArchiveOptions, Backup, BackupError, RestoreMode},
+    config::NekoTodoConfig,
+    export::{ExportError, ExportFormat},
+    import::{parse, CsvMapping, ImportError, ImportFormat},
+    report::{parse_range, ReportError, ReportFormat},
+    todo::{Todo, TodoError},
+};
+use chrono::Local;
+use clap::Subcommand;
+use std::path::PathBuf;
+use thiserror::Error;
+
+/// サブコマンドの定義
+#[derive(Subcommand, Debug)]
+pub enum CliCommand {
+    /// todoをファイルにエクスポートする。
+    Export {
+        /// 出力先のファイル
+        path: PathBuf,
+        /// 出力形式(json, csv, md)
+        #[arg(short, long, default_value = "json")]
+        format: String,
+        /// CSVの先頭にBOMを付与する。
+        #[arg(long)]
+        bom: bool,
+    },
+    /// ファイルからtodoをインポートする。
+    Import {
+        /// 読み込むファイル
+        path: PathBuf,
+        /// 入力形式(json, csv)
+        #[arg(short, long, default_value = "json")]
+        format: String,
+        /// CSVの列の対応付け(例: --map title=件名 --map end_date=期限)
+        #[arg(short, long = "map")]
+        mapping: Vec<String>,
+        /// 検査のみ行い、登録しない。
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// 期間内の作業報告(日報・週報)を出力する。
+    Report {
+        /// 期間の初日(YYYY-MM-DD)。省略時は最終日と同じ日。
+        #[arg(long)]
+        from: Option<String>,
+        /// 期間の最終日(YYYY-MM-DD)。省略時は今日。
+        #[arg(long)]
+        to: Option<String>,
+        /// 出力形式(md, txt, tex)
+        #[arg(short, long, default_value = "md")]
+        format: String,
+        /// 出力先のファイル。省略時は標準出力。
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// ログイン中のユーザーのデータ一式をバックアップする。
+    Backup {
+        /// 出力先のファイル
+        path: PathBuf,
+        /// 圧縮しない。
+        #[arg(long)]
+        no_compress: bool,
+        /// 標準入力の一行目をパスワードとして、暗号化する。
+        #[arg(long)]
+        password_stdin: bool,
+    },
+    /// バックアップから、ログイン中のユーザーのデータを復元する。
+    Restore {
+        /// バックアップファイル
+        path: PathBuf,
+        /// 復元方法(merge: 追加, replace: 置き換え)
+        #[arg(short, long, default_value = "merge")]
+        mode: String,
+        /// 標準入力の一行目を、暗号化されたバックアップのパスワードとする。
+        #[arg(long)]
+        password_stdin: bool,
+    },
+}
+
+#[derive(Error, Debug)]
+pub enum CliError {
+    #[error("ログインしていません。アプリケーションからログインしてください。")]
+    NotLogin,
+    #[error(transparent)]
+    Todo(#[from] TodoError),
+    #[error(transparent)]
+    Export(#[from] ExportError),
+    #[error(transparent)]
+    Import(#[from] ImportError),
+    #[error(transparent)]
+    Report(#[from] ReportError),
+    #[error(transparent)]
+    Backup(#[from] BackupError),
+}
+
+impl CliCommand {
+    /// サブコマンドを実行する。
+    pub async fn run(&self, conf: &NekoTodoConfig, todo: &Todo) -> Result<(), CliError> {
+        let Some(sess) = conf.get_session_id() else {
+            return Err(CliError::NotLogin);
+        };
+        match self {
+            Self::Export { path, format, bom } => {
+                let format = format.parse::<ExportFormat>()?;
+                let doc = todo.export_todo(sess).await.map_err(|e| match e {
+                    TodoError::NotFoundSession => CliError::NotLogin,
+                    e => e.into(),
+                })?;
+                doc.write_to_file(path, format, *bom)?;
+                eprintln!(
+                    "{}件のtodoを出力しました。:{}",
+                    doc.items.len(),
+                    path.display()
+                );
+            }
+            Self::Import {
+                path,
+                format,
+                mapping,
+                dry_run,
+            } => {
+                let format = format.parse::<ImportFormat>()?;
+                let mut csv_mapping = CsvMapping::default();
+                for spec in mapping {
+                    csv_mapping.set(spec)?;
+                }
+                let data = std::fs::read_to_string(path).map_err(ImportError::from)?;
+                let parsed = parse(&data, format, &csv_mapping)?;
+                let report =
+                    todo.import_todo(sess, parsed, *dry_run)
+                        .await
+                        .map_err(|e| match e {
+                            TodoError::NotFoundSession => CliError::NotLogin,
+                            e => e.into(),
+                        })?;
+                for conflict in &report.conflicts {
+                    eprintln!(
+                        "{}行目: 重複のため追加しません。:{}",
+                        conflict.row, conflict.title
+                    );
+                }
+                for error in &report.errors {
+                    eprintln!("{}行目: {}", error.row, error.message);
+                }
+                eprintln!(
+                    "{}件中、{}件を{}。",
+                    report.total,
+                    report.imported,
+                    if report.dry_run {
+                        "登録できます"
+                    } else {
+                        "登録しました"
+                    }
+                );
+            }
+            Self::Report {
+                from,
+                to,
+                format,
+                output,
+            } => {
+                let format = format.parse::<ReportFormat>()?;
+                let (from, to) =
+                    parse_range(from.as_deref(), to.as_deref(), Local::now().date_naive())?;
+                let report = todo
+                    .make_report(sess, from, to)
+                    .await
+                    .map_err(|e| match e {
+                        TodoError::NotFoundSession => CliError::NotLogin,
+                        e => e.into(),
+                    })?;
+                let text = report.render_with_user_template(format)?;
+                match output {
+                    Some(path) => {
+                        std::fs::write(path, text).map_err(ReportError::from)?;
+                        eprintln!("作業報告を出力しました。:{}", path.display());
+                    }
+                    None => print!("{text}"),
+                }
+            }
+            Self::Backup {
+                path,
+                no_compress,
+                password_stdin,
+            } => {
+                let backup = todo.backup(sess).await.map_err(|e| match e {
+                    TodoError::NotFoundSession => CliError::NotLogin,
+                    e => e.into(),
+                })?;
+                let options = ArchiveOptions {
+                    compress: !no_compress,
+                    password: password_stdin
+                        .then(read_password)
+                        .transpose()
+                        .map_err(BackupError::from)?,
+                };
+                backup.write_to_file(path, &options)?;
+                eprintln!(
+                    "todo{}件、ビュー{}件をバックアップしました。:{}",
+                    backup.data.todos.len(),
+                    backup.data.views.len(),
+                    path.display()
+                );
+            }
+            Self::Restore {
+                path,
+                mode,
+                password_stdin,
+            } => {
+                let mode = mode.parse::<RestoreMode>()?;
+                let password = password_stdin
+                    .then(read_password)
+                    .transpose()
+                    .map_err(BackupError::from)?;
+                let backup = Backup::read_from_file(path, password.as_deref())?;
+                let count = todo
+                    .restore(sess, &backup, mode)
+                    .await
+                    .map_err(|e| match e {
+                        TodoError::NotFoundSession => CliError::NotLogin,
+                        e => e.into(),
+                    })?;
+                eprintln!(
+                    "todo{}件(重複{}件)、ビュー{}件(重複{}件)、変更履歴{}件を復元しました。{}",
+                    count.todos,
+                    count.skipped_todos,
+                    count.views,
+                    count.skipped_views,
+                    count.history,
+                    if count.settings {
+                        "設定も復元しました。"
+                    } else {
+                        ""
+                    }
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 標準入力の一行目を、パスワードとして読み込む。
+fn read_password() -> std::io::Result<String> {
+    let mut line = String::new();
+    std::io::stdin().read_line(&mut line)?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
@@ -1,5 +1,17 @@
 //! フロントエンドとのインターフェース　tauri::command
 pub mod app_state;
+pub mod backup;
+pub mod connection;
+pub mod export;
+pub mod feed;
+pub mod ical;
+pub mod import;
+pub mod offline;
+pub mod print;
+pub mod profile;
+pub mod report;
 pub mod session;
+pub mod setup;
 pub mod todo;
 pub mod user;
+pub mod view;
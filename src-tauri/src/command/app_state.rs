@@ -1,16 +1,27 @@
 //! アプリケーションの全体ステータスの取得・設定用インターフェース
 
+use super::session::get_curr_session;
 use crate::app_status::AppStatus;
 use crate::config::ItemSortOrder;
+use crate::database::UserSettings;
 use log::info;
 use tauri::{command, State};
+use uuid::Uuid;
 
 /// 完了済みのみを表示するかどうかを設定する。
 #[tauri::command]
-pub fn set_is_incomplete(app_status: State<'_, AppStatus>, is_incomplete: bool) {
-    let mut conf = app_status.config().lock().unwrap();
-    conf.set_is_incomplete(is_incomplete);
+pub async fn set_is_incomplete(
+    app_status: State<'_, AppStatus>,
+    is_incomplete: bool,
+) -> Result<(), String> {
+    app_status
+        .config()
+        .lock()
+        .unwrap()
+        .set_is_incomplete(is_incomplete);
+    update_user_settings(&app_status, |s| s.only_incomplete = is_incomplete).await?;
     info!("未完了のみ表示モードを{}にセット", is_incomplete);
+    Ok(())
 }
 
 /// 完了済みのみ表示モードの現在の値を取得する。
@@ -32,7 +43,7 @@ pub fn get_item_sort_order(app_status: State<'_, AppStatus>) -> String {
 
 /// アイテムリストのソート方法を設定する
 #[command]
-pub fn set_item_sort_order(
+pub async fn set_item_sort_order(
     app_status: State<'_, AppStatus>,
     sort_order: String,
 ) -> Result<(), String> {
@@ -44,6 +55,81 @@ pub fn set_item_sort_order(
         .lock()
         .unwrap()
         .set_item_sort_order(sort_order);
-    info!("ソートオーダー更新 => {}", sort_order.to_string());
+    update_user_settings(&app_status, |s| s.item_sort_order = sort_order).await?;
+    info!("ソートオーダー更新 => {}", sort_order);
+    Ok(())
+}
+
+/// ログイン中のユーザーの設定を取得する。
+#[command]
+pub async fn get_user_settings(app_status: State<'_, AppStatus>) -> Result<UserSettings, String> {
+    let Some(sess) = get_curr_session(&app_status) else {
+        return Err("NotLogin".to_string());
+    };
+    app_status
+        .todo()
+        .get_settings(sess)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// ログイン中のユーザーの設定を保存し、アプリケーションに反映する。
+#[command]
+pub async fn set_user_settings(
+    app_status: State<'_, AppStatus>,
+    settings: UserSettings,
+) -> Result<(), String> {
+    let Some(sess) = get_curr_session(&app_status) else {
+        return Err("NotLogin".to_string());
+    };
+    app_status
+        .todo()
+        .save_settings(sess, &settings)
+        .await
+        .map_err(|e| e.to_string())?;
+    apply_settings_to_config(&app_status, &settings);
+    info!("ユーザー設定を保存");
+    Ok(())
+}
+
+/// ユーザーの設定をデータベースから読み込み、アプリケーションに反映する。
+/// ログイン時に使用する。
+pub async fn load_user_settings(app_status: &AppStatus, sess: Uuid) -> Result<(), String> {
+    let settings = app_status
+        .todo()
+        .get_settings(sess)
+        .await
+        .map_err(|e| e.to_string())?;
+    apply_settings_to_config(app_status, &settings);
+    info!("ユーザー設定の読み込み完了:user->{}", settings.user_name);
     Ok(())
 }
+
+/// ユーザー設定のうち、アプリケーション設定で保持する項目を反映する。
+fn apply_settings_to_config(app_status: &AppStatus, settings: &UserSettings) {
+    let mut conf = app_status.config().lock().unwrap();
+    conf.set_is_incomplete(settings.only_incomplete);
+    conf.set_item_sort_order(settings.item_sort_order);
+}
+
+/// ログイン中であれば、ユーザー設定の一部を更新してデータベースに保存する。
+/// 未ログインの場合は、何もしない。
+async fn update_user_settings(
+    app_status: &AppStatus,
+    f: impl FnOnce(&mut UserSettings),
+) -> Result<(), String> {
+    let Some(sess) = get_curr_session(app_status) else {
+        return Ok(());
+    };
+    let mut settings = app_status
+        .todo()
+        .get_settings(sess)
+        .await
+        .map_err(|e| e.to_string())?;
+    f(&mut settings);
+    app_status
+        .todo()
+        .save_settings(sess, &settings)
+        .await
+        .map_err(|e| e.to_string())
+}
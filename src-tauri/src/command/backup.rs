@@ -0,0 +1,73 @@
+//! バックアップ・復元インターフェース
+
+use super::session::{get_cur_session_with_update, get_curr_session};
+use crate::app_status::AppStatus;
+use crate::backup::{ArchiveOptions, Backup, RestoreMode};
+use crate::database::RestoreCount;
+use log::info;
+use std::path::PathBuf;
+use tauri::{command, State};
+
+/// ログイン中のユーザーのデータ一式を、バックアップファイルに書き込む。
+/// compressを省略すると圧縮する。passwordを指定すると暗号化する。
+/// バックアップしたtodoの件数を返す。
+#[command]
+pub async fn backup_account(
+    app_status: State<'_, AppStatus>,
+    path: String,
+    compress: Option<bool>,
+    password: Option<String>,
+) -> Result<usize, String> {
+    let Some(sess) = get_curr_session(&app_status) else {
+        return Err("NotLogin".to_string());
+    };
+    let backup = app_status
+        .todo()
+        .backup(sess)
+        .await
+        .map_err(|e| e.to_string())?;
+    let options = ArchiveOptions {
+        compress: compress.unwrap_or(true),
+        password: password.filter(|p| !p.is_empty()),
+    };
+    backup
+        .write_to_file(&PathBuf::from(&path), &options)
+        .map_err(|e| e.to_string())?;
+    info!(
+        "バックアップ完了(todo{}件、暗号化:{}):{}",
+        backup.data.todos.len(),
+        options.password.is_some(),
+        path
+    );
+    Ok(backup.data.todos.len())
+}
+
+/// バックアップファイルから、ログイン中のユーザーのデータとして復元する。
+/// modeは、"merge"(追加)または"replace"(置き換え)。
+/// 暗号化されたバックアップには、passwordが必要。
+#[command]
+pub async fn restore_account(
+    app_status: State<'_, AppStatus>,
+    path: String,
+    mode: String,
+    password: Option<String>,
+) -> Result<RestoreCount, String> {
+    let sess = match get_cur_session_with_update(&app_status).await {
+        Ok(Some(u)) => u,
+        Ok(None) => return Err("NotLogin".to_string()),
+        Err(e) => return Err(e),
+    };
+    let mode = mode.parse::<RestoreMode>().map_err(|e| e.to_string())?;
+    let backup = Backup::read_from_file(&PathBuf::from(&path), password.as_deref())
+        .map_err(|e| e.to_string())?;
+    let count = app_status
+        .todo()
+        .restore(sess, &backup, mode)
+        .await
+        .map_err(|e| e.to_string())?;
+    info!(
+        "復元完了(todo{}件、ビュー{}件):{}",
+        count.todos, count.views, path
+    );
+    Ok(count)
+}
@@ -0,0 +1,13 @@
+//! データベース接続インターフェース
+
+use crate::app_status::AppStatus;
+use crate::todo::ConnectionStatus;
+use tauri::{command, State};
+
+/// データベースへの接続状況を取得する。
+#[command]
+pub async fn get_connection_status(
+    app_status: State<'_, AppStatus>,
+) -> Result<ConnectionStatus, String> {
+    Ok(app_status.todo().connection_status())
+}
@@ -0,0 +1,39 @@
+//! エクスポートインターフェース
+
+use super::session::get_curr_session;
+use crate::app_status::AppStatus;
+use crate::export::ExportFormat;
+use log::info;
+use std::path::PathBuf;
+use tauri::{command, State};
+
+/// todoを指定の形式でエクスポートし、ファイルに書き込む。
+/// formatは、"json", "csv", "md"のいずれか。
+/// bomがtrueの場合、CSVの先頭にBOMを付与する。
+#[command]
+pub async fn export_todo(
+    app_status: State<'_, AppStatus>,
+    path: String,
+    format: String,
+    bom: Option<bool>,
+) -> Result<usize, String> {
+    let Some(sess) = get_curr_session(&app_status) else {
+        return Err("NotLogin".to_string());
+    };
+    let format = format.parse::<ExportFormat>().map_err(|e| e.to_string())?;
+
+    let doc = app_status
+        .todo()
+        .export_todo(sess)
+        .await
+        .map_err(|e| e.to_string())?;
+    doc.write_to_file(&PathBuf::from(&path), format, bom.unwrap_or(false))
+        .map_err(|e| e.to_string())?;
+    info!(
+        "todo、{}件を{}形式でエクスポート完了:{}",
+        doc.items.len(),
+        format,
+        path
+    );
+    Ok(doc.items.len())
+}
@@ -0,0 +1,65 @@
+//! ICSフィードインターフェース
+
+use super::session::get_curr_session;
+use crate::app_status::AppStatus;
+use log::info;
+use tauri::{command, State};
+
+/// ICSフィードの購読用URLを取得する。
+/// フィードを提供していない(ポート未設定の)場合は、Noneを返す。
+#[command]
+pub async fn get_feed_url(app_status: State<'_, AppStatus>) -> Result<Option<String>, String> {
+    let Some(sess) = get_curr_session(&app_status) else {
+        return Err("NotLogin".to_string());
+    };
+    let Some(port) = app_status.config().lock().unwrap().get_ics_feed_port() else {
+        return Ok(None);
+    };
+    let token = app_status
+        .todo()
+        .get_feed_token(sess)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(Some(feed_url(port, &token)))
+}
+
+/// ICSフィードのトークンを再発行し、新しい購読用URLを返す。
+/// 以前のURLは使用できなくなる。
+#[command]
+pub async fn reset_feed_url(app_status: State<'_, AppStatus>) -> Result<Option<String>, String> {
+    let Some(sess) = get_curr_session(&app_status) else {
+        return Err("NotLogin".to_string());
+    };
+    let port = app_status.config().lock().unwrap().get_ics_feed_port();
+    let token = app_status
+        .todo()
+        .reset_feed_token(sess)
+        .await
+        .map_err(|e| e.to_string())?;
+    info!("ICSフィードのトークンを再発行");
+    Ok(port.map(|port| feed_url(port, &token)))
+}
+
+/// ICSフィードを、終了日の終日予定として出力するかを取得する。
+#[command]
+pub fn get_feed_all_day(app_status: State<'_, AppStatus>) -> bool {
+    app_status.config().lock().unwrap().get_ics_feed_all_day()
+}
+
+/// ICSフィードを、終了日の終日予定として出力するかを設定する。
+#[command]
+pub fn set_feed_all_day(app_status: State<'_, AppStatus>, all_day: bool) {
+    app_status
+        .config()
+        .lock()
+        .unwrap()
+        .set_ics_feed_all_day(all_day);
+    info!(
+        "ICSフィードの形式を変更:{}",
+        if all_day { "終日予定" } else { "VTODO" }
+    );
+}
+
+fn feed_url(port: u16, token: &str) -> String {
+    format!("http://127.0.0.1:{}/feed/{}.ics", port, token)
+}
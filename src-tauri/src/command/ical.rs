@@ -0,0 +1,61 @@
+//! iCalendar(VTODO)のエクスポート・インポートインターフェース
+
+use super::session::{get_cur_session_with_update, get_curr_session};
+use crate::app_status::AppStatus;
+use crate::ical;
+use crate::import::ImportReport;
+use log::info;
+use tauri::{command, State};
+
+/// todoをiCalendar形式でファイルに書き込む。
+#[command]
+pub async fn export_ical(app_status: State<'_, AppStatus>, path: String) -> Result<usize, String> {
+    let Some(sess) = get_curr_session(&app_status) else {
+        return Err("NotLogin".to_string());
+    };
+    let doc = app_status
+        .todo()
+        .export_todo(sess)
+        .await
+        .map_err(|e| e.to_string())?;
+    std::fs::write(&path, ical::render(&doc)).map_err(|e| e.to_string())?;
+    info!(
+        "todo、{}件をiCalendar形式でエクスポート完了:{}",
+        doc.items.len(),
+        path
+    );
+    Ok(doc.items.len())
+}
+
+/// iCalendar形式のファイルから、VTODOをインポートする。
+/// dry_runがtrueの場合は、検査結果の報告のみ行い、登録しない。
+#[command]
+pub async fn import_ical(
+    app_status: State<'_, AppStatus>,
+    path: String,
+    dry_run: bool,
+) -> Result<ImportReport, String> {
+    let sess = match get_cur_session_with_update(&app_status).await {
+        Ok(Some(u)) => u,
+        Ok(None) => return Err("NotLogin".to_string()),
+        Err(e) => return Err(e),
+    };
+    let data = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    let parsed = ical::parse(&data).map_err(|e| e.to_string())?;
+
+    let report = app_status
+        .todo()
+        .import_todo(sess, parsed, dry_run)
+        .await
+        .map_err(|e| e.to_string())?;
+    info!(
+        "iCalendarインポート{}:{} 読込{}件、登録{}件、重複{}件、エラー{}件",
+        if dry_run { "(試行)" } else { "" },
+        path,
+        report.total,
+        report.imported,
+        report.conflicts.len(),
+        report.errors.len()
+    );
+    Ok(report)
+}
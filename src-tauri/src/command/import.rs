@@ -0,0 +1,45 @@
+//! インポートインターフェース
+
+use super::session::get_cur_session_with_update;
+use crate::app_status::AppStatus;
+use crate::import::{parse, CsvMapping, ImportFormat, ImportReport};
+use log::info;
+use tauri::{command, State};
+
+/// ファイルからtodoをインポートする。
+/// formatは、"json", "csv"のいずれか。
+/// mappingには、CSVの列の対応付けを指定する。省略時は、エクスポートした時の見出し。
+/// dry_runがtrueの場合は、検査結果の報告のみ行い、登録しない。
+#[command]
+pub async fn import_todo(
+    app_status: State<'_, AppStatus>,
+    path: String,
+    format: String,
+    mapping: Option<CsvMapping>,
+    dry_run: bool,
+) -> Result<ImportReport, String> {
+    let sess = match get_cur_session_with_update(&app_status).await {
+        Ok(Some(u)) => u,
+        Ok(None) => return Err("NotLogin".to_string()),
+        Err(e) => return Err(e),
+    };
+    let format = format.parse::<ImportFormat>().map_err(|e| e.to_string())?;
+    let data = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    let parsed = parse(&data, format, &mapping.unwrap_or_default()).map_err(|e| e.to_string())?;
+
+    let report = app_status
+        .todo()
+        .import_todo(sess, parsed, dry_run)
+        .await
+        .map_err(|e| e.to_string())?;
+    info!(
+        "インポート{}:{} 読込{}件、登録{}件、重複{}件、エラー{}件",
+        if dry_run { "(試行)" } else { "" },
+        path,
+        report.total,
+        report.imported,
+        report.conflicts.len(),
+        report.errors.len()
+    );
+    Ok(report)
+}
@@ -0,0 +1,20 @@
+//! オフライン動作インターフェース
+
+use crate::app_status::AppStatus;
+use crate::offline::OfflineStatus;
+use log::info;
+use tauri::{command, State};
+
+/// データベースへの接続状態と、オフライン中の変更の送信状況を取得する。
+#[command]
+pub async fn get_offline_status(app_status: State<'_, AppStatus>) -> Result<OfflineStatus, String> {
+    Ok(app_status.todo().offline_status())
+}
+
+/// オフライン中の変更のうち、反映できなかったものの記録を破棄する。
+#[command]
+pub async fn clear_offline_conflicts(app_status: State<'_, AppStatus>) -> Result<(), String> {
+    app_status.todo().clear_offline_conflicts();
+    info!("オフライン中の競合の記録を破棄");
+    Ok(())
+}
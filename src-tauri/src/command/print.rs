@@ -0,0 +1,58 @@
+//! 印刷用のtodo一覧(PDF)の出力インターフェース
+
+use super::session::get_curr_session;
+use crate::app_status::AppStatus;
+use crate::pdf::{Orientation, PageSetup, PaperSize};
+use log::info;
+use tauri::{command, State};
+
+/// 現在の絞り込み・並び順のtodo一覧を、PDFファイルに書き込む。
+/// filterは、get_todo_listと同じ形式。
+/// paperは"a4"または"letter"、orientationは"portrait"または"landscape"。省略時はA4縦。
+#[command]
+pub async fn export_todo_pdf(
+    app_status: State<'_, AppStatus>,
+    path: String,
+    filter: Option<String>,
+    paper: Option<String>,
+    orientation: Option<String>,
+) -> Result<usize, String> {
+    let Some(sess) = get_curr_session(&app_status) else {
+        return Err("NotLogin".to_string());
+    };
+    let setup = PageSetup {
+        paper: paper
+            .map(|s| s.parse::<PaperSize>())
+            .transpose()
+            .map_err(|e| e.to_string())?
+            .unwrap_or_default(),
+        orientation: orientation
+            .map(|s| s.parse::<Orientation>())
+            .transpose()
+            .map_err(|e| e.to_string())?
+            .unwrap_or_default(),
+    };
+
+    let is_incomplete;
+    let sort_order;
+    {
+        let conf = app_status.config().lock().unwrap();
+        is_incomplete = conf.get_is_incomplete();
+        sort_order = conf.get_item_sort_order();
+    }
+
+    let (count, pdf) = app_status
+        .todo()
+        .render_todo_pdf(
+            sess,
+            is_incomplete,
+            sort_order,
+            filter.as_deref().unwrap_or_default(),
+            setup,
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+    std::fs::write(&path, pdf).map_err(|e| e.to_string())?;
+    info!("todo一覧、{count}件をPDFに出力完了:{path}");
+    Ok(count)
+}
@@ -0,0 +1,70 @@
+//! 接続プロファイルインターフェース
+
+use crate::app_status::AppStatus;
+use crate::setup::open_todo;
+use log::{error, info};
+use serde::Serialize;
+use tauri::{command, State};
+
+/// プロファイルの一覧
+#[derive(Serialize, Debug)]
+pub struct ProfileList {
+    /// 使用中のプロファイル名
+    current: String,
+    /// 起動時に使用するプロファイル名
+    default: String,
+    profiles: Vec<String>,
+}
+
+/// プロファイルの一覧を取得する。
+#[command]
+pub async fn get_profiles(app_status: State<'_, AppStatus>) -> Result<ProfileList, String> {
+    let conf = app_status.config().lock().unwrap();
+    Ok(ProfileList {
+        current: conf.get_profile_name().to_string(),
+        default: conf.get_default_profile().to_string(),
+        profiles: conf.get_profile_names(),
+    })
+}
+
+/// 使用するプロファイルを切り替え、データベースに接続し直す。
+/// 接続できなければ、元のプロファイルに戻す。
+#[command]
+pub async fn switch_profile(app_status: State<'_, AppStatus>, name: String) -> Result<(), String> {
+    // 接続を試す間は、設定をロックしないよう、切り替え後の接続情報を取り出しておく。
+    let (prev, conn, pool, cache_path) = {
+        let mut conf = app_status.config().lock().unwrap();
+        let prev = conf.get_profile_name().to_string();
+        if prev == name {
+            return Ok(());
+        }
+        conf.select_profile(&name, false)
+            .map_err(|e| e.to_string())?;
+        let cache_path = conf.get_offline_cache_path();
+        let (conn, pool) = (conf.get_connect_config(), conf.get_pool_config());
+        // 切り替え先の設定を取り出したら、接続できるまでは元に戻しておく。
+        conf.select_profile(&prev, false)
+            .map_err(|e| e.to_string())?;
+        (prev, conn, pool, cache_path)
+    };
+    match open_todo(&conn, &pool, &cache_path).await {
+        Ok(todo) => {
+            app_status
+                .config()
+                .lock()
+                .unwrap()
+                .select_profile(&name, true)
+                .map_err(|e| e.to_string())?;
+            app_status.set_todo(todo);
+            info!("プロファイルを切り替えました。{} -> {}", prev, name);
+            Ok(())
+        }
+        Err(e) => {
+            error!(
+                "プロファイル[{}]のデータベースに接続できません。:{}",
+                name, e
+            );
+            Err(e.to_string())
+        }
+    }
+}
@@ -0,0 +1,42 @@
+//! 作業報告(日報・週報)の生成インターフェース
+
+use super::session::get_curr_session;
+use crate::app_status::AppStatus;
+use crate::report::{parse_range, ReportFormat};
+use chrono::Local;
+use log::info;
+use tauri::{command, State};
+
+/// 期間内の作業報告を、ユーザーのテンプレートで生成して返す。
+/// from, toは"YYYY-MM-DD"形式。toを省略すると今日、fromを省略するとtoと同じ日とする。
+/// formatは、"md", "txt", "tex"のいずれか。
+/// pathを指定した場合は、ファイルにも書き込む。
+#[command]
+pub async fn generate_report(
+    app_status: State<'_, AppStatus>,
+    from: Option<String>,
+    to: Option<String>,
+    format: String,
+    path: Option<String>,
+) -> Result<String, String> {
+    let Some(sess) = get_curr_session(&app_status) else {
+        return Err("NotLogin".to_string());
+    };
+    let format = format.parse::<ReportFormat>().map_err(|e| e.to_string())?;
+    let (from, to) = parse_range(from.as_deref(), to.as_deref(), Local::now().date_naive())
+        .map_err(|e| e.to_string())?;
+
+    let report = app_status
+        .todo()
+        .make_report(sess, from, to)
+        .await
+        .map_err(|e| e.to_string())?;
+    let text = report
+        .render_with_user_template(format)
+        .map_err(|e| e.to_string())?;
+    if let Some(path) = path {
+        std::fs::write(&path, &text).map_err(|e| e.to_string())?;
+        info!("作業報告({from}〜{to})を{format}形式で出力:{path}");
+    }
+    Ok(text)
+}
@@ -1,5 +1,6 @@
 //! セッション関係の関数及びインターフェース
 
+use super::app_state::load_user_settings;
 use crate::app_status::AppStatus;
 use log::info;
 use tauri::{command, State};
@@ -8,9 +9,18 @@ use uuid::Uuid;
 /// 現在、有効なセッションが存在するかどうか確認。(ユーザI/F用)
 #[command]
 pub async fn is_valid_session(app_status: State<'_, AppStatus>) -> Result<bool, String> {
-    let sess = get_cur_session_with_update(&app_status)
-        .await
-        .map(|i| i.is_some());
+    // 接続情報が未設定なら、設定画面へ誘導する。
+    if !app_status.is_configured() {
+        info!("セッション確認(接続情報未設定)");
+        return Err("NotConfigured".to_string());
+    }
+    let sess = match get_cur_session_with_update(&app_status).await {
+        // オフライン中は、設定ファイルに保存済みのユーザー設定を使用する。
+        Ok(Some(_)) if !app_status.todo().is_online() => Ok(true),
+        Ok(Some(s)) => load_user_settings(&app_status, s).await.map(|_| true),
+        Ok(None) => Ok(false),
+        Err(e) => Err(e),
+    };
     match sess {
         Ok(sess) => info!("セッション確認({})", if sess { "有効" } else { "無効" }),
         Err(ref e) => info!("セション確認エラー({})", e),
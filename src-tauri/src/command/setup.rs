@@ -0,0 +1,148 @@
+//! データベース接続の初期設定インターフェース
+
+use crate::app_status::AppStatus;
+use crate::credential::Secret;
+use crate::database::{ConnectConfig, DbCheck, SslMode};
+use crate::setup::open_todo;
+use crate::todo::Todo;
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use tauri::{command, State};
+
+/// 使用中のプロファイルの接続情報を取得する。パスワードは返さない。
+#[command]
+pub async fn get_connection_settings(
+    app_status: State<'_, AppStatus>,
+) -> Result<FormConnection, String> {
+    let conn = app_status.config().lock().unwrap().get_connect_config();
+    Ok(conn.into())
+}
+
+/// 接続情報でサーバーへの接続を試し、データベースとスキーマの状態を返す。
+#[command]
+pub async fn test_connection(
+    app_status: State<'_, AppStatus>,
+    settings: FormConnection,
+) -> Result<DbCheck, String> {
+    let conn = to_connect_config(&app_status, settings)?;
+    let check = Todo::inspect_database(&conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    info!("接続の試行に成功:{:?}", check);
+    Ok(check)
+}
+
+/// データベースがなければ作成し、スキーマのマイグレーションを適用する。
+#[command]
+pub async fn apply_migrations(
+    app_status: State<'_, AppStatus>,
+    settings: FormConnection,
+) -> Result<DbCheck, String> {
+    let conn = to_connect_config(&app_status, settings)?;
+    let check = Todo::setup_database(&conn).await.map_err(|e| {
+        error!("マイグレーションの適用に失敗:{e}");
+        e.to_string()
+    })?;
+    info!("マイグレーションの適用完了:{:?}", check);
+    Ok(check)
+}
+
+/// 接続情報を使用中のプロファイルに保存し、データベースに接続し直す。
+/// 接続できなければ、保存しない。
+#[command]
+pub async fn save_connection_settings(
+    app_status: State<'_, AppStatus>,
+    settings: FormConnection,
+) -> Result<(), String> {
+    let conn = to_connect_config(&app_status, settings)?;
+    let (pool, cache_path) = {
+        let conf = app_status.config().lock().unwrap();
+        (conf.get_pool_config(), conf.get_offline_cache_path())
+    };
+    let todo = open_todo(&conn, &pool, &cache_path)
+        .await
+        .map_err(|e| e.to_string())?;
+    {
+        let mut conf = app_status.config().lock().unwrap();
+        conf.set_connect_config(&conn);
+        conf.save().map_err(|e| e.to_string())?;
+    }
+    app_status.set_todo(todo);
+    info!("接続情報を保存");
+    Ok(())
+}
+
+/// 画面の入力を接続情報にする。
+/// パスワードが未入力なら、使用中のプロファイルに保存済みのものを使用する。
+fn to_connect_config(
+    app_status: &AppStatus,
+    settings: FormConnection,
+) -> Result<ConnectConfig, String> {
+    let mut conn: ConnectConfig = settings.try_into()?;
+    if conn.pass.is_empty() {
+        conn.pass = app_status
+            .config()
+            .lock()
+            .unwrap()
+            .get_connect_config()
+            .pass;
+    }
+    Ok(conn)
+}
+
+/// 接続情報の設定画面データ
+/// パスワードを含むため、Debugは実装しない。
+#[derive(Serialize, Deserialize, Clone)]
+pub struct FormConnection {
+    host: String,
+    port: Option<u16>,
+    user: String,
+    pass: String,
+    database: String,
+    ssl_mode: String,
+    ssl_ca: String,
+    socket: String,
+}
+
+impl TryFrom<FormConnection> for ConnectConfig {
+    type Error = String;
+
+    fn try_from(val: FormConnection) -> Result<Self, Self::Error> {
+        let ssl_mode = val.ssl_mode.parse::<SslMode>().map_err(|e| e.to_string())?;
+        let non_empty = |s: String| (!s.trim().is_empty()).then(|| s.trim().to_string());
+        let conn = ConnectConfig {
+            host: val.host.trim().to_string(),
+            port: val.port,
+            user: val.user.trim().to_string(),
+            pass: Secret::new(val.pass),
+            database: non_empty(val.database),
+            ssl_mode,
+            ssl_ca: non_empty(val.ssl_ca).map(Into::into),
+            socket: non_empty(val.socket).map(Into::into),
+        };
+        if conn.host.is_empty() && conn.socket.is_none() {
+            return Err("ホスト名かソケットを入力してください。".to_string());
+        }
+        if conn.user.is_empty() {
+            return Err("ユーザー名を入力してください。".to_string());
+        }
+        Ok(conn)
+    }
+}
+
+impl From<ConnectConfig> for FormConnection {
+    fn from(val: ConnectConfig) -> Self {
+        let path =
+            |p: Option<std::path::PathBuf>| p.map(|p| p.display().to_string()).unwrap_or_default();
+        Self {
+            host: val.host,
+            port: val.port,
+            user: val.user,
+            pass: String::new(),
+            database: val.database.unwrap_or_default(),
+            ssl_mode: val.ssl_mode.to_string(),
+            ssl_ca: path(val.ssl_ca),
+            socket: path(val.socket),
+        }
+    }
+}
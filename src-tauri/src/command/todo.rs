@@ -2,18 +2,34 @@
 
 use super::session::{get_cur_session_with_update, get_curr_session};
 use crate::app_status::AppStatus;
-use crate::database::ItemTodo;
+use crate::database::{ItemTodo, PageRequest, TodoCursor, TodoHistory, TodoPage};
+use crate::filter::TodoFilter;
+use crate::todo::UndoState;
 use log::{debug, info};
 use serde::Deserialize;
 use tauri::State;
 
 /// todoのリストを取得する。
+/// filterには、絞り込み条件(`due<7d tag:work -done`など)を指定できる。
+/// cursorとlimitを指定すると、ページ単位で取得する。
+/// cursorには、前回の結果のnext_cursorを指定する。
 #[tauri::command]
-pub async fn get_todo_list(app_status: State<'_, AppStatus>) -> Result<Vec<ItemTodo>, String> {
+pub async fn get_todo_list(
+    app_status: State<'_, AppStatus>,
+    filter: Option<String>,
+    cursor: Option<String>,
+    limit: Option<u32>,
+) -> Result<TodoPage, String> {
     let sess = match get_curr_session(&app_status) {
         Some(u) => u,
         None => return Err("NotLogin".to_string()),
     };
+    let filter = filter
+        .as_deref()
+        .unwrap_or_default()
+        .parse::<TodoFilter>()
+        .map_err(|e| e.to_string())?;
+    let page = page_request(cursor, limit)?;
 
     let is_incomplete;
     let sort_order;
@@ -25,13 +41,26 @@ pub async fn get_todo_list(app_status: State<'_, AppStatus>) -> Result<Vec<ItemT
 
     let ret = app_status
         .todo()
-        .get_todo_list(sess, is_incomplete, sort_order)
+        .get_todo_list(sess, is_incomplete, sort_order, &filter, &page)
         .await
         .map_err(|e| e.to_string())?;
-    info!("todoリスト、{}件、取得完了", ret.len());
+    info!(
+        "todoリスト、{}件(全{}件)、取得完了",
+        ret.items.len(),
+        ret.total
+    );
     Ok(ret)
 }
 
+/// ページングの引数から、取得範囲を生成する。
+pub fn page_request(cursor: Option<String>, limit: Option<u32>) -> Result<PageRequest, String> {
+    let cursor = cursor
+        .map(|c| c.parse::<TodoCursor>())
+        .transpose()
+        .map_err(|e| e.to_string())?;
+    Ok(PageRequest { cursor, limit })
+}
+
 /// todoアイテムを取得する
 #[tauri::command]
 pub async fn get_todo_with_id(
@@ -71,20 +100,23 @@ pub async fn add_todo(app_status: State<'_, AppStatus>, item: FormTodo) -> Resul
 }
 
 /// todoの完了状態を変更する。
+/// revisionには、表示中のtodoの版番号を指定する。変更後の版番号を返す。
+/// 他で更新されていた場合は、"Conflict:"に続けて最新のtodoをJSONで返す。
 #[tauri::command]
 pub async fn update_done(
     app_status: State<'_, AppStatus>,
     id: u32,
     done: bool,
-) -> Result<(), String> {
+    revision: u32,
+) -> Result<u32, String> {
     let sess = match get_cur_session_with_update(&app_status).await {
         Ok(Some(s)) => s,
         Ok(None) => return Err("NotLogin".to_string()),
         Err(e) => return Err(e),
     };
-    app_status
+    let revision = app_status
         .todo()
-        .change_done(id, sess, done)
+        .change_done(id, sess, done, revision)
         .await
         .map_err(|e| e.to_string())?;
     info!(
@@ -92,34 +124,123 @@ pub async fn update_done(
         id,
         if done { "完了" } else { "未完了" }
     );
-    Ok(())
+    Ok(revision)
 }
 
 /// todoの編集を行う。
+/// revisionには、編集を始めたときのtodoの版番号を指定する。変更後の版番号を返す。
+/// 他で更新されていた場合は、"Conflict:"に続けて最新のtodoをJSONで返す。
 #[tauri::command]
 pub async fn edit_todo(
     app_status: State<'_, AppStatus>,
     id: u32,
     item: FormTodo,
-) -> Result<(), String> {
+    revision: u32,
+) -> Result<u32, String> {
     let sess = match get_cur_session_with_update(&app_status).await {
         Ok(Some(u)) => u,
         Ok(None) => return Err("NotLogin".to_string()),
         Err(e) => return Err(e),
     };
 
-    debug!("input => id: {},  item: {:?}", id, &item);
+    debug!(
+        "input => id: {},  item: {:?}, revision: {}",
+        id, &item, revision
+    );
     let mut item: ItemTodo = item.into();
     item.id = id;
-    app_status
+    item.revision = revision;
+    let revision = app_status
         .todo()
         .edit_todo(&item, sess)
         .await
         .map_err(|e| e.to_string())?;
     info!("アイテム編集完了 id=>{}", id);
+    Ok(revision)
+}
+
+/// todoを削除する。
+#[tauri::command]
+pub async fn delete_todo(app_status: State<'_, AppStatus>, id: u32) -> Result<(), String> {
+    let sess = match get_cur_session_with_update(&app_status).await {
+        Ok(Some(u)) => u,
+        Ok(None) => return Err("NotLogin".to_string()),
+        Err(e) => return Err(e),
+    };
+    app_status
+        .todo()
+        .delete_todo(id, sess)
+        .await
+        .map_err(|e| e.to_string())?;
+    info!("todoを削除 id=>{}", id);
     Ok(())
 }
 
+/// todoの変更履歴を、古い順に取得する。削除済みのtodoの履歴も取得できる。
+#[tauri::command]
+pub async fn get_todo_history(
+    app_status: State<'_, AppStatus>,
+    id: u32,
+) -> Result<Vec<TodoHistory>, String> {
+    let Some(sess) = get_curr_session(&app_status) else {
+        return Err("NotLogin".to_string());
+    };
+    let ret = app_status
+        .todo()
+        .get_todo_history(id, sess)
+        .await
+        .map_err(|e| e.to_string())?;
+    info!("todoの変更履歴、{}件取得 id=>{}", ret.len(), id);
+    Ok(ret)
+}
+
+/// 直前のtodoの操作(追加・編集・完了状態の変更・削除)を取り消す。
+#[tauri::command]
+pub async fn undo(app_status: State<'_, AppStatus>) -> Result<UndoState, String> {
+    let sess = match get_cur_session_with_update(&app_status).await {
+        Ok(Some(u)) => u,
+        Ok(None) => return Err("NotLogin".to_string()),
+        Err(e) => return Err(e),
+    };
+    let ret = app_status
+        .todo()
+        .undo(sess)
+        .await
+        .map_err(|e| e.to_string())?;
+    info!("操作の取り消し完了 残り=>{}", ret.undo);
+    Ok(ret)
+}
+
+/// 取り消したtodoの操作をやり直す。
+#[tauri::command]
+pub async fn redo(app_status: State<'_, AppStatus>) -> Result<UndoState, String> {
+    let sess = match get_cur_session_with_update(&app_status).await {
+        Ok(Some(u)) => u,
+        Ok(None) => return Err("NotLogin".to_string()),
+        Err(e) => return Err(e),
+    };
+    let ret = app_status
+        .todo()
+        .redo(sess)
+        .await
+        .map_err(|e| e.to_string())?;
+    info!("操作のやり直し完了 残り=>{}", ret.redo);
+    Ok(ret)
+}
+
+/// 取り消し・やり直しができる件数を取得する。
+#[tauri::command]
+pub async fn get_undo_state(app_status: State<'_, AppStatus>) -> Result<UndoState, String> {
+    let Some(sess) = get_curr_session(&app_status) else {
+        return Err("NotLogin".to_string());
+    };
+    app_status
+        .todo()
+        .get_undo_state(sess)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 /// Todo項目追加画面データ取得用
 #[derive(Deserialize, Debug, Clone)]
 pub struct FormTodo {
@@ -142,6 +263,7 @@ impl From<FormTodo> for ItemTodo {
             start_date: start,
             end_date: end,
             done: false,
+            revision: 0,
         }
     }
 }
@@ -1,5 +1,6 @@
 //! ユーザー操作インターフェース
 
+use super::app_state::load_user_settings;
 use crate::app_status::AppStatus;
 use log::info;
 use tauri::{command, State};
@@ -29,9 +30,12 @@ pub async fn login(
 ) -> Result<String, String> {
     let session = app_status.todo().login(&name, &password).await?;
 
-    let mut cnf = app_status.config().lock().unwrap();
-    cnf.set_session_id(&session);
-    //cnf.save().map_err(|e| format!("OtherError:{}", e))?;
+    {
+        let mut cnf = app_status.config().lock().unwrap();
+        cnf.set_session_id(&session);
+        //cnf.save().map_err(|e| format!("OtherError:{}", e))?;
+    }
+    load_user_settings(&app_status, session).await?;
     info!("ログイン完了:user->{}", &name);
     Ok(session.to_string())
 }
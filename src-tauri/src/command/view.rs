@@ -0,0 +1,142 @@
+//! 保存済みビュー操作インターフェース
+
+use super::session::{get_cur_session_with_update, get_curr_session};
+use super::todo::page_request;
+use crate::app_status::AppStatus;
+use crate::config::ItemSortOrder;
+use crate::database::{SavedView, TodoPage};
+use log::{debug, info};
+use serde::Deserialize;
+use tauri::{command, State};
+
+/// ビューの一覧を取得する。
+#[command]
+pub async fn get_views(app_status: State<'_, AppStatus>) -> Result<Vec<SavedView>, String> {
+    let Some(sess) = get_curr_session(&app_status) else {
+        return Err("NotLogin".to_string());
+    };
+    let ret = app_status
+        .todo()
+        .get_views(sess)
+        .await
+        .map_err(|e| e.to_string())?;
+    info!("ビュー一覧、{}件、取得完了", ret.len());
+    Ok(ret)
+}
+
+/// ビューを追加する。生成されたビューのidを返す。
+#[command]
+pub async fn add_view(app_status: State<'_, AppStatus>, view: FormView) -> Result<u32, String> {
+    let sess = match get_cur_session_with_update(&app_status).await {
+        Ok(Some(u)) => u,
+        Ok(None) => return Err("NotLogin".to_string()),
+        Err(e) => return Err(e),
+    };
+
+    debug!("input = {:?}", &view);
+    let view: SavedView = view.try_into()?;
+    let id = app_status
+        .todo()
+        .add_view(sess, &view)
+        .await
+        .map_err(|e| e.to_string())?;
+    info!("ビューの追加完了 id=>{}", id);
+    Ok(id)
+}
+
+/// ビューの編集を行う。
+#[command]
+pub async fn edit_view(
+    app_status: State<'_, AppStatus>,
+    id: u32,
+    view: FormView,
+) -> Result<(), String> {
+    let sess = match get_cur_session_with_update(&app_status).await {
+        Ok(Some(u)) => u,
+        Ok(None) => return Err("NotLogin".to_string()),
+        Err(e) => return Err(e),
+    };
+
+    debug!("input => id: {}, view: {:?}", id, &view);
+    let mut view: SavedView = view.try_into()?;
+    view.id = id;
+    app_status
+        .todo()
+        .edit_view(sess, &view)
+        .await
+        .map_err(|e| e.to_string())?;
+    info!("ビュー編集完了 id=>{}", id);
+    Ok(())
+}
+
+/// ビューを削除する。
+#[command]
+pub async fn delete_view(app_status: State<'_, AppStatus>, id: u32) -> Result<(), String> {
+    let sess = match get_cur_session_with_update(&app_status).await {
+        Ok(Some(u)) => u,
+        Ok(None) => return Err("NotLogin".to_string()),
+        Err(e) => return Err(e),
+    };
+    app_status
+        .todo()
+        .delete_view(id, sess)
+        .await
+        .map_err(|e| e.to_string())?;
+    info!("ビュー削除完了 id=>{}", id);
+    Ok(())
+}
+
+/// 指定したビューの条件で、todoのリストを取得する。
+/// ページングの指定は、get_todo_listと同じ。
+#[command]
+pub async fn get_todo_list_for_view(
+    app_status: State<'_, AppStatus>,
+    id: u32,
+    cursor: Option<String>,
+    limit: Option<u32>,
+) -> Result<TodoPage, String> {
+    let Some(sess) = get_curr_session(&app_status) else {
+        return Err("NotLogin".to_string());
+    };
+    let page = page_request(cursor, limit)?;
+    let ret = app_status
+        .todo()
+        .get_todo_list_for_view(sess, id, &page)
+        .await
+        .map_err(|e| e.to_string())?;
+    info!(
+        "ビュー(id=>{})のtodoリスト、{}件(全{}件)、取得完了",
+        id,
+        ret.items.len(),
+        ret.total
+    );
+    Ok(ret)
+}
+
+/// ビュー編集画面データ取得用
+#[derive(Deserialize, Debug, Clone)]
+pub struct FormView {
+    name: String,
+    filter: String,
+    sort_order: String,
+    only_incomplete: bool,
+}
+
+impl TryFrom<FormView> for SavedView {
+    type Error = String;
+
+    fn try_from(val: FormView) -> Result<Self, Self::Error> {
+        let sort_order = val
+            .sort_order
+            .parse::<ItemSortOrder>()
+            .map_err(|e| e.to_string())?;
+        Ok(SavedView {
+            id: 0,
+            user_name: "".to_string(),
+            name: val.name,
+            filter: val.filter,
+            sort_order,
+            only_incomplete: val.only_incomplete,
+        })
+    }
+}
@@ -1,37 +1,58 @@
 //! アプリケーション設定の取得関係
 
+mod file;
 mod impl_neko_todo_config;
+mod legacy;
+mod profile;
+mod sync;
 #[cfg(test)]
 mod test;
+mod watcher;
 
-use uuid::Uuid;
+use crate::credential::{
+    CredentialError, CredentialStore, Secret, CREDENTIAL_FILE_NAME, DB_PASS_ACCOUNT,
+};
+use crate::database::{ConnectConfig, PoolConfig, SslMode};
+use file::*;
+use profile::{pass_account, profile_key};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use sync::{ConfigLock, FileStamp};
+use thiserror::Error;
 
-const CONF_FILE_NAME: &str = "neko_todo.conf";
-const DB_HOST: &str = "NEKO_DB_DB_HOST";
-const DB_USER: &str = "NEKO_DB_DB_USER";
-const DB_PASS: &str = "NEKO_DB_DB_PASS";
-const SESSION: &str = "NEKO_DB_SESSION_ID";
-const WIN_POS_X: &str = "NEKO_DB_INIT_WINDOW_POS_X";
-const WIN_POS_Y: &str = "NEKO_DB_INIT_WINDOW_POS_Y";
-const WIN_SIZE_W: &str = "NEKO_DB_INIT_WINDOW_SIZE_W";
-const WIN_SIZE_H: &str = "NEKO_DB_INIT_WINDOW_SIZE_H";
+pub use profile::is_valid_profile_name;
+pub use watcher::spawn_config_watcher;
+
+/// 既定のプロファイル名
+pub const DEFAULT_PROFILE: &str = "default";
+
+const CONF_FILE_NAME: &str = "neko_todo.toml";
+/// 旧形式の設定ファイル。移行後は、拡張子に.oldを付けて残す。
+const LEGACY_CONF_FILE_NAME: &str = "neko_todo.conf";
 
 /// アプリケーション全体の状態設定
 #[derive(Debug)]
 pub struct NekoTodoConfig {
-    db_host: String,
-    db_user: String,
-    db_pass: String,
-    session_id: Option<Uuid>,
+    /// 設定ファイルのパス
+    path: PathBuf,
+    /// 設定ファイルの内容
+    file: ConfigFile,
+    /// 最後に読み書きした時点の設定ファイルの内容。保存時に、変更した項目を求めるのに使用する。
+    base: ConfigFile,
+    /// 最後に読み書きした時点の設定ファイルの状態
+    stamp: Option<FileStamp>,
+    /// 使用中のプロファイル名
+    profile: String,
+    /// プロファイルごとのデータベースのパスワード。設定ファイルには保存しない。
+    passwords: BTreeMap<String, Secret>,
+    /// パスワードを変更し、まだ保存していないプロファイル
+    pass_dirty: BTreeSet<String>,
+    credentials: CredentialStore,
     dirty: bool,
-    is_incomplete: bool,
-    item_sort_order: ItemSortOrder,
-    window_pos: Option<tauri::PhysicalPosition<i32>>,
-    window_size: Option<tauri::PhysicalSize<u32>>,
 }
 
 /// アイテムリストのソート順位を表す。
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum ItemSortOrder {
     StartAsc,
     StartDesc,
@@ -70,8 +91,96 @@ impl std::str::FromStr for ItemSortOrder {
     }
 }
 
+impl TryFrom<String> for ItemSortOrder {
+    type Error = ItemSortOrderParseError;
+
+    fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum ItemSortOrderParseError {
     #[error("Invalid Argument")]
     InvalidArgument,
 }
+
+/// 画面のテーマ
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum Theme {
+    System,
+    Light,
+    Dark,
+}
+
+impl std::fmt::Display for Theme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::System => write!(f, "System"),
+            Self::Light => write!(f, "Light"),
+            Self::Dark => write!(f, "Dark"),
+        }
+    }
+}
+
+impl std::str::FromStr for Theme {
+    type Err = ThemeParseError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "System" => Ok(Self::System),
+            "Light" => Ok(Self::Light),
+            "Dark" => Ok(Self::Dark),
+            _ => Err(ThemeParseError::InvalidArgument),
+        }
+    }
+}
+
+impl TryFrom<String> for Theme {
+    type Error = ThemeParseError;
+
+    fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ThemeParseError {
+    #[error("Invalid Argument")]
+    InvalidArgument,
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ProfileError {
+    #[error("プロファイル名には、英数字と'_'のみ使用できます。:{0}")]
+    InvalidName(String),
+    #[error("プロファイルがありません。:{0}")]
+    NotFound(String),
+}
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("設定ファイルへのアクセスに失敗:{0}")]
+    Io(#[from] std::io::Error),
+    #[error("設定ファイルの誤り({}:{line}行{column}桁目):{message}", path.display())]
+    Parse {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    #[error(
+        "新しい版のアプリケーションの設定ファイルです。(版:{}、対応する版:{}まで)",
+        .0,
+        file::CONFIG_VERSION
+    )]
+    NewerVersion(i64),
+    #[error("設定ファイルの誤り:{0}")]
+    Profile(#[from] ProfileError),
+    #[error("設定ファイルの誤り({key}):{message}")]
+    Invalid { key: String, message: String },
+    #[error("旧形式の設定ファイルの読み込みに失敗:{0}")]
+    Legacy(#[from] dotenvy::Error),
+    #[error("データベースのパスワードの保存に失敗:{0}")]
+    Credential(#[from] CredentialError),
+}
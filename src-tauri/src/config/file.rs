@@ -0,0 +1,268 @@
+//! 設定ファイル(TOML)の形式と読み書き
+
+use super::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+use uuid::Uuid;
+
+/// 設定ファイルの形式の版。形式を変えたら増やす。
+/// 旧形式(dotenv形式のneko_todo.conf)は、版0として扱う。
+pub(super) const CONFIG_VERSION: u32 = 1;
+
+/// 設定ファイルの内容
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub(super) struct ConfigFile {
+    pub version: u32,
+    /// 起動時に使用するプロファイル名
+    #[serde(default = "default_profile_name")]
+    pub profile: String,
+    /// falseなら、資格情報ストアを使用せず、パスワードをファイルに保存する。
+    #[serde(default = "default_true")]
+    pub keyring: bool,
+    #[serde(default)]
+    pub view: ViewSection,
+    #[serde(default)]
+    pub window: WindowSection,
+    #[serde(default)]
+    pub ics_feed: IcsFeedSection,
+    #[serde(default)]
+    pub todotxt: TodotxtSection,
+    #[serde(default)]
+    pub database: DatabaseSection,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, ProfileSection>,
+}
+
+/// 一覧の表示
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub(super) struct ViewSection {
+    /// 未完了のtodoのみ表示するか
+    #[serde(default = "default_true")]
+    pub is_incomplete: bool,
+    #[serde(default = "default_sort_order")]
+    pub item_sort_order: ItemSortOrder,
+}
+
+/// 起動時のwindowの位置と大きさ
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub(super) struct WindowSection {
+    /// [x, y]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<[i32; 2]>,
+    /// [幅, 高さ]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<[u32; 2]>,
+}
+
+/// ICSフィード
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub(super) struct IcsFeedSection {
+    /// 提供するポート番号。なければ提供しない。
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    /// todoを終了日の終日予定として出力するか
+    #[serde(default)]
+    pub all_day: bool,
+}
+
+/// todo.txtファイルとの同期
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub(super) struct TodotxtSection {
+    /// 同期するファイル。なければ同期しない。
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+    /// データベースの変更を反映する間隔(秒)
+    #[serde(default = "default_todotxt_interval")]
+    pub interval: u64,
+}
+
+/// データベースへの接続の管理
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub(super) struct DatabaseSection {
+    pub pool_max: u32,
+    pub pool_min: u32,
+    /// 接続を取得するまで待つ時間(秒)
+    pub acquire_timeout: u64,
+    /// 接続を確認する間隔(秒)
+    pub health_interval: u64,
+}
+
+/// プロファイルごとのデータベースへの接続情報とセッション。
+/// パスワードは、資格情報ストアに保存する。
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub(super) struct ProfileSection {
+    #[serde(default)]
+    pub host: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub user: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub database: Option<String>,
+    #[serde(default)]
+    pub ssl_mode: SslMode,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssl_ca: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub socket: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<Uuid>,
+}
+
+impl Default for ConfigFile {
+    fn default() -> Self {
+        Self {
+            version: CONFIG_VERSION,
+            profile: default_profile_name(),
+            keyring: true,
+            view: ViewSection::default(),
+            window: WindowSection::default(),
+            ics_feed: IcsFeedSection::default(),
+            todotxt: TodotxtSection::default(),
+            database: DatabaseSection::default(),
+            profiles: BTreeMap::from([(DEFAULT_PROFILE.to_string(), ProfileSection::default())]),
+        }
+    }
+}
+
+impl Default for ViewSection {
+    fn default() -> Self {
+        Self {
+            is_incomplete: true,
+            item_sort_order: default_sort_order(),
+        }
+    }
+}
+
+impl Default for TodotxtSection {
+    fn default() -> Self {
+        Self {
+            path: None,
+            interval: default_todotxt_interval(),
+        }
+    }
+}
+
+impl Default for DatabaseSection {
+    fn default() -> Self {
+        let pool = PoolConfig::default();
+        Self {
+            pool_max: pool.max_connections,
+            pool_min: pool.min_connections,
+            acquire_timeout: pool.acquire_timeout.as_secs(),
+            health_interval: 10,
+        }
+    }
+}
+
+impl DatabaseSection {
+    /// 接続プールを生成できない値や、接続を取得できない値を拒否する。
+    fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |key: &str, message: &str| ConfigError::Invalid {
+            key: format!("database.{key}"),
+            message: message.to_string(),
+        };
+        if self.pool_max == 0 {
+            return Err(invalid("pool_max", "1以上を指定してください。"));
+        }
+        if self.pool_min > self.pool_max {
+            return Err(invalid("pool_min", "pool_max以下を指定してください。"));
+        }
+        if self.acquire_timeout == 0 {
+            return Err(invalid("acquire_timeout", "1以上を指定してください。"));
+        }
+        Ok(())
+    }
+}
+
+fn default_profile_name() -> String {
+    DEFAULT_PROFILE.to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_sort_order() -> ItemSortOrder {
+    ItemSortOrder::EndAsc
+}
+
+fn default_todotxt_interval() -> u64 {
+    60
+}
+
+impl ConfigFile {
+    /// 設定ファイルの内容を解析する。
+    /// 誤りがあれば、その位置(行・桁)をエラーに含める。
+    pub(super) fn parse(src: &str, path: &Path) -> Result<Self, ConfigError> {
+        let located = |e: toml::de::Error| {
+            let (line, column) = e
+                .span()
+                .map(|span| line_column(src, span.start))
+                .unwrap_or((1, 1));
+            ConfigError::Parse {
+                path: path.to_path_buf(),
+                line,
+                column,
+                message: e.message().to_string(),
+            }
+        };
+        // 新しい版の設定ファイルは、項目の誤りより先に、版の違いとして報告する。
+        let table: toml::Table = toml::from_str(src).map_err(located)?;
+        if let Some(version) = table.get("version").and_then(|v| v.as_integer()) {
+            if version > CONFIG_VERSION as i64 {
+                return Err(ConfigError::NewerVersion(version));
+            }
+        }
+        let file: Self = toml::from_str(src).map_err(located)?;
+        file.normalize()
+    }
+
+    /// 既定のプロファイルを補い、プロファイル名と接続プールの設定を検査する。
+    pub(super) fn normalize(mut self) -> Result<Self, ConfigError> {
+        if !self.profiles.contains_key(DEFAULT_PROFILE) {
+            self.profiles
+                .insert(DEFAULT_PROFILE.to_string(), ProfileSection::default());
+        }
+        if let Some(name) = self.profiles.keys().find(|n| !is_valid_profile_name(n)) {
+            return Err(ConfigError::Profile(ProfileError::InvalidName(
+                name.clone(),
+            )));
+        }
+        if !self.profiles.contains_key(&self.profile) {
+            self.profile = default_profile_name();
+        }
+        self.database.validate()?;
+        Ok(self)
+    }
+
+    /// 設定ファイルに書き込む。
+    /// 一時ファイルに書き込んでから置き換え、書き込み途中の内容が残らないようにする。
+    pub(super) fn write(&self, path: &Path) -> Result<(), ConfigError> {
+        let src = toml::to_string_pretty(self).map_err(std::io::Error::other)?;
+        let tmp = path.with_extension("toml.tmp");
+        let mut file = std::fs::File::create(&tmp)?;
+        file.write_all(src.as_bytes())?;
+        file.sync_all()?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+/// 文字列中の位置を、1から始まる行・桁にする。
+fn line_column(src: &str, offset: usize) -> (usize, usize) {
+    let before = &src[..offset.min(src.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().map_or(0, |s| s.chars().count()) + 1;
+    (line, column)
+}
@@ -1,171 +1,340 @@
 //! NetoTodoConfig実装
 
 use super::*;
+use crate::offline::CACHE_FILE_NAME;
 use directories::ProjectDirs;
+use log::{error, info, warn};
 use std::{
-    fs::OpenOptions,
-    io::{BufWriter, ErrorKind, Result, Write},
-    path::PathBuf,
+    io::{ErrorKind, Result},
+    path::Path,
+    time::Duration,
 };
 use uuid::Uuid;
 
 impl NekoTodoConfig {
-    pub fn new() -> dotenvy::Result<Self> {
-        let file = Self::get_config_file_path().map_err(dotenvy::Error::Io)?;
-        dotenvy::from_path(file)?;
-        let session_id = std::env::var(SESSION)
-            .ok()
-            .map(|s| Uuid::parse_str(&s).expect("環境ファイル異常:SESSION_ID不正"));
-
-        Ok(Self {
-            db_host: std::env::var(DB_HOST).unwrap_or_default(),
-            db_user: std::env::var(DB_USER).unwrap_or_default(),
-            db_pass: std::env::var(DB_PASS).unwrap_or_default(),
-            session_id,
-            dirty: false,
-            is_incomplete: true,
-            item_sort_order: ItemSortOrder::EndAsc,
-            window_pos: Self::win_pos_from_env(),
-            window_size: Self::win_size_from_env(),
-        })
-    }
-
-    fn win_pos_from_env() -> Option<tauri::PhysicalPosition<i32>> {
-        let x_env = std::env::var(WIN_POS_X);
-        let y_env = std::env::var(WIN_POS_Y);
-        if let (Ok(x_env), Ok(y_env)) = (x_env, y_env) {
-            let x = x_env.parse::<i32>();
-            let y = y_env.parse::<i32>();
-            if let (Ok(x), Ok(y)) = (x, y) {
-                Some(tauri::PhysicalPosition::new(x, y))
-            } else {
-                None
-            }
-        } else {
-            None
-        }
+    pub fn new() -> std::result::Result<Self, ConfigError> {
+        Self::open(&Self::get_config_dir()?)
     }
 
-    fn win_size_from_env() -> Option<tauri::PhysicalSize<u32>> {
-        let Ok(w_env) = std::env::var(WIN_SIZE_W) else {
-            return None;
-        };
-        let Ok(h_env) = std::env::var(WIN_SIZE_H) else {
-            return None;
-        };
-        let Ok(w) = w_env.parse::<u32>() else {
-            return None;
+    /// 設定ディレクトリの設定ファイルを読み込む。
+    /// 設定ファイルがなく、旧形式の設定ファイルがあれば、新しい形式に移行する。
+    pub(super) fn open(dir: &Path) -> std::result::Result<Self, ConfigError> {
+        let path = dir.join(CONF_FILE_NAME);
+        let legacy_path = dir.join(LEGACY_CONF_FILE_NAME);
+        let lock = ConfigLock::acquire(&path)?;
+        let (file, plain, migrating) = match std::fs::read_to_string(&path) {
+            Ok(src) => (ConfigFile::parse(&src, &path)?, vec![], false),
+            Err(e) if e.kind() == ErrorKind::NotFound && legacy_path.exists() => {
+                info!(
+                    "旧形式の設定ファイルを移行します。:{}",
+                    legacy_path.display()
+                );
+                let (file, plain) = legacy::convert(&legacy::read(&legacy_path)?);
+                (file.normalize()?, plain, true)
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => (ConfigFile::default(), vec![], false),
+            Err(e) => return Err(e.into()),
         };
-        let Ok(h) = h_env.parse::<u32>() else {
-            return None;
+        let stamp = FileStamp::of(&path);
+        drop(lock);
+
+        let mut conf = Self {
+            path,
+            profile: file.profile.clone(),
+            base: file.clone(),
+            credentials: CredentialStore::new(&dir.join(CREDENTIAL_FILE_NAME), file.keyring),
+            file,
+            stamp,
+            passwords: BTreeMap::new(),
+            pass_dirty: BTreeSet::new(),
+            dirty: migrating,
         };
-        Some(tauri::PhysicalSize::new(w, h))
+        conf.load_passwords();
+
+        if migrating {
+            // 平文で保存されていたパスワードは、資格情報ストアへ移す。
+            for (name, pass) in plain {
+                conf.passwords.insert(name.clone(), pass);
+                conf.pass_dirty.insert(name);
+            }
+            conf.save()?;
+            legacy::retire(&legacy_path)?;
+            info!("設定ファイルを移行しました。:{}", conf.path.display());
+        }
+        Ok(conf)
     }
 
-    pub fn get_db_host(&self) -> &str {
-        &self.db_host
+    /// 変更していないプロファイルのパスワードを、資格情報ストアから読み込む。
+    fn load_passwords(&mut self) {
+        for name in self.file.profiles.keys() {
+            if self.pass_dirty.contains(name) {
+                continue;
+            }
+            let pass = self
+                .credentials
+                .get(&pass_account(name))
+                .unwrap_or_else(|e| {
+                    warn!("データベースのパスワードを取得できません。[{name}]:{e}");
+                    None
+                })
+                .unwrap_or_default();
+            self.passwords.insert(name.clone(), pass);
+        }
     }
 
-    pub fn get_db_user(&self) -> &str {
-        &self.db_user
+    /// 他のプロセスが設定ファイルを変更していれば、読み直す。
+    /// このプロセスでの未保存の変更は、読み直した内容に反映して残す。
+    /// 読み直したら、trueを返す。
+    pub fn reload(&mut self) -> std::result::Result<bool, ConfigError> {
+        let _lock = ConfigLock::acquire(&self.path)?;
+        let stamp = FileStamp::of(&self.path);
+        if stamp.is_none() || stamp == self.stamp {
+            return Ok(false);
+        }
+        let disk = ConfigFile::parse(&std::fs::read_to_string(&self.path)?, &self.path)?;
+        self.adopt(disk)?;
+        self.stamp = stamp;
+        if let Some(dir) = self.path.parent() {
+            self.credentials =
+                CredentialStore::new(&dir.join(CREDENTIAL_FILE_NAME), self.file.keyring);
+        }
+        self.load_passwords();
+        Ok(true)
     }
 
-    pub fn get_db_pass(&self) -> &str {
-        &self.db_pass
+    /// 設定ファイルから読み込んだ内容に、未保存の変更を反映したものを、現在の設定とする。
+    fn adopt(&mut self, disk: ConfigFile) -> std::result::Result<(), ConfigError> {
+        let mut merged = self.file.merge_into(&self.base, &disk)?;
+        // 使用中のプロファイルが他のプロセスで削除されても、このプロセスでは使い続ける。
+        if !merged.profiles.contains_key(&self.profile) {
+            warn!(
+                "使用中のプロファイル[{}]が、他のプロセスで削除されました。",
+                self.profile
+            );
+            merged
+                .profiles
+                .insert(self.profile.clone(), self.current().clone());
+        }
+        self.base = disk;
+        self.file = merged;
+        Ok(())
+    }
+
+    /// 使用中のプロファイル
+    fn current(&self) -> &ProfileSection {
+        &self.file.profiles[&self.profile]
+    }
+
+    fn current_mut(&mut self) -> &mut ProfileSection {
+        self.file.profiles.get_mut(&self.profile).unwrap()
+    }
+
+    /// データベースへの接続情報を取得する。
+    pub fn get_connect_config(&self) -> ConnectConfig {
+        let pass = self
+            .passwords
+            .get(&self.profile)
+            .cloned()
+            .unwrap_or_default();
+        self.current().connect_config(pass)
+    }
+
+    /// データベースへの接続情報を設定する。
+    pub fn set_connect_config(&mut self, conn: &ConnectConfig) {
+        self.current_mut().set_connect_config(conn);
+        self.set_db_pass(conn.pass.expose());
     }
 
     pub fn get_session_id(&self) -> Option<Uuid> {
-        self.session_id
+        self.current().session_id
+    }
+
+    /// 使用中のプロファイル名
+    pub fn get_profile_name(&self) -> &str {
+        &self.profile
+    }
+
+    /// 起動時に使用するプロファイル名
+    pub fn get_default_profile(&self) -> &str {
+        &self.file.profile
+    }
+
+    /// プロファイル名の一覧
+    pub fn get_profile_names(&self) -> Vec<String> {
+        self.file.profiles.keys().cloned().collect()
+    }
+
+    /// 使用するプロファイルを切り替える。
+    /// persistがtrueなら、次回の起動時にも使用する。
+    pub fn select_profile(
+        &mut self,
+        name: &str,
+        persist: bool,
+    ) -> std::result::Result<(), ProfileError> {
+        if !self.file.profiles.contains_key(name) {
+            return Err(ProfileError::NotFound(name.to_string()));
+        }
+        self.profile = name.to_string();
+        if persist && self.file.profile != name {
+            self.file.profile = name.to_string();
+            self.dirty = true;
+        }
+        Ok(())
+    }
+
+    /// プロファイルを追加し、使用するプロファイルに切り替える。
+    /// 既にあれば、切り替えのみ行う。
+    pub fn add_profile(&mut self, name: &str) -> std::result::Result<(), ProfileError> {
+        if !is_valid_profile_name(name) {
+            return Err(ProfileError::InvalidName(name.to_string()));
+        }
+        if !self.file.profiles.contains_key(name) {
+            self.file
+                .profiles
+                .insert(name.to_string(), ProfileSection::default());
+            self.passwords.insert(name.to_string(), Secret::default());
+            self.dirty = true;
+        }
+        self.select_profile(name, false)
+    }
+
+    /// 使用中のプロファイルの、オフラインキャッシュのファイル
+    pub fn get_offline_cache_path(&self) -> PathBuf {
+        let path = self.path.with_file_name(CACHE_FILE_NAME);
+        if self.profile == DEFAULT_PROFILE {
+            path
+        } else {
+            path.with_extension(format!("{}.json", self.profile))
+        }
     }
 
     pub fn get_is_incomplete(&self) -> bool {
-        self.is_incomplete
+        self.file.view.is_incomplete
     }
 
     pub fn get_item_sort_order(&self) -> ItemSortOrder {
-        self.item_sort_order
+        self.file.view.item_sort_order
     }
 
-    pub fn get_win_pos(&self) -> Option<tauri::PhysicalPosition<i32>> {
-        self.window_pos
+    pub fn get_ics_feed_port(&self) -> Option<u16> {
+        self.file.ics_feed.port
     }
 
-    pub fn get_win_size(&self) -> Option<tauri::PhysicalSize<u32>> {
-        self.window_size
+    pub fn get_ics_feed_all_day(&self) -> bool {
+        self.file.ics_feed.all_day
     }
 
-    pub fn set_db_host(&mut self, val: &str) {
-        self.db_host = val.to_string();
-        self.dirty = true;
+    pub fn get_todotxt_path(&self) -> Option<&std::path::Path> {
+        self.file.todotxt.path.as_deref()
     }
 
-    pub fn set_db_user(&mut self, val: &str) {
-        self.db_user = val.to_string();
-        self.dirty = true;
+    pub fn get_todotxt_interval(&self) -> u64 {
+        self.file.todotxt.interval
+    }
+
+    pub fn get_pool_config(&self) -> PoolConfig {
+        let db = &self.file.database;
+        PoolConfig {
+            max_connections: db.pool_max,
+            min_connections: db.pool_min,
+            acquire_timeout: Duration::from_secs(db.acquire_timeout),
+        }
+    }
+
+    pub fn get_health_interval(&self) -> u64 {
+        self.file.database.health_interval
+    }
+
+    pub fn get_win_pos(&self) -> Option<tauri::PhysicalPosition<i32>> {
+        self.file
+            .window
+            .position
+            .map(|[x, y]| tauri::PhysicalPosition::new(x, y))
+    }
+
+    pub fn get_win_size(&self) -> Option<tauri::PhysicalSize<u32>> {
+        self.file
+            .window
+            .size
+            .map(|[w, h]| tauri::PhysicalSize::new(w, h))
     }
 
     pub fn set_db_pass(&mut self, val: &str) {
-        self.db_pass = val.to_string();
+        self.passwords
+            .insert(self.profile.clone(), Secret::new(val));
+        self.pass_dirty.insert(self.profile.clone());
         self.dirty = true;
     }
 
     pub fn set_session_id(&mut self, uuid: &Uuid) {
-        self.session_id = Some(*uuid);
+        self.current_mut().session_id = Some(*uuid);
         self.dirty = true;
     }
 
     pub fn set_is_incomplete(&mut self, is_incomplete: bool) {
-        self.is_incomplete = is_incomplete;
+        self.file.view.is_incomplete = is_incomplete;
+        self.dirty = true;
     }
 
     pub fn set_item_sort_order(&mut self, item_sort_order: ItemSortOrder) {
-        self.item_sort_order = item_sort_order;
+        self.file.view.item_sort_order = item_sort_order;
+        self.dirty = true;
+    }
+
+    pub fn set_ics_feed_all_day(&mut self, all_day: bool) {
+        self.file.ics_feed.all_day = all_day;
+        self.dirty = true;
     }
 
     pub fn set_win_pos(&mut self, pos: tauri::PhysicalPosition<i32>) {
-        self.window_pos = Some(pos);
+        self.file.window.position = Some([pos.x, pos.y]);
+        self.dirty = true;
     }
 
     pub fn set_win_size(&mut self, size: tauri::PhysicalSize<u32>) {
-        self.window_size = Some(size);
+        self.file.window.size = Some([size.width, size.height]);
+        self.dirty = true;
     }
 
-    pub fn save(&mut self) -> Result<()> {
+    /// 変更した項目を、設定ファイルに保存する。
+    /// 他のプロセスが保存した内容は、変更していない項目について残す。
+    pub fn save(&mut self) -> std::result::Result<(), ConfigError> {
         if !self.dirty {
             return Ok(());
         }
-        let path = Self::get_config_file_path()?;
-        let file = OpenOptions::new().write(true).truncate(true).open(&path)?;
-        let mut buffer = BufWriter::new(file);
-        writeln!(buffer, "{}={}", DB_HOST, self.get_db_host())?;
-        writeln!(buffer, "{}={}", DB_USER, self.get_db_user())?;
-        writeln!(buffer, "{}={}", DB_PASS, self.get_db_pass())?;
-        if let Some(s) = self.session_id {
-            writeln!(buffer, "{}={}", SESSION, s)?;
-        }
-        if let Some(pos) = self.get_win_pos() {
-            writeln!(buffer, "{}={}", WIN_POS_X, pos.x)?;
-            writeln!(buffer, "{}={}", WIN_POS_Y, pos.y)?;
+        let _lock = ConfigLock::acquire(&self.path)?;
+        // パスワードは、設定ファイルより先に保存する。
+        while let Some(name) = self.pass_dirty.first().cloned() {
+            let account = pass_account(&name);
+            match self.passwords.get(&name).filter(|p| !p.is_empty()) {
+                Some(pass) => {
+                    self.credentials.set(&account, pass)?;
+                }
+                None => self.credentials.delete(&account)?,
+            }
+            self.pass_dirty.remove(&name);
         }
-        if let Some(size) = self.get_win_size() {
-            writeln!(buffer, "{}={}", WIN_SIZE_W, size.width)?;
-            writeln!(buffer, "{}={}", WIN_SIZE_H, size.height)?;
+        match std::fs::read_to_string(&self.path) {
+            Ok(src) => self.adopt(ConfigFile::parse(&src, &self.path)?)?,
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
         }
-
+        self.file.write(&self.path)?;
+        self.base = self.file.clone();
+        self.stamp = FileStamp::of(&self.path);
         self.dirty = false;
         Ok(())
     }
 
-    /// コンフィグファイルのファイル名を生成する
-    /// 必要に応じて、コンフィグファイル用のディレクトリ("neko_todo")を生成し
-    /// さらに、存在しなければ、空のコンフィグファイル("neko_todo.conf")を生成する。
-    pub(super) fn get_config_file_path() -> Result<PathBuf> {
+    /// コンフィグ用のディレクトリを取得する。
+    /// 存在しなければ、生成する。
+    pub fn get_config_dir() -> Result<PathBuf> {
         use std::io;
         // 環境依存コンフィグ用ディレクトリの取得
         // 必要であれば、自分用のディレクトリを生成する。
         // ここでエラーになるのは、OSシステムに問題がある。
-        let mut path: PathBuf = ProjectDirs::from("jp", "laki", "nekotodo")
-            .ok_or(io::Error::new(ErrorKind::Other, "Not Found Home"))?
+        let path: PathBuf = ProjectDirs::from("jp", "laki", "nekotodo")
+            .ok_or(io::Error::other("Not Found Home"))?
             .config_dir()
             .into();
         if let Err(e) = std::fs::create_dir(&path) {
@@ -173,22 +342,14 @@ impl NekoTodoConfig {
                 return Err(e);
             }
         }
-
-        // コンフィグファイルがなければ、空のファイルを生成する。
-        path.push(CONF_FILE_NAME);
-        if let Err(e) = std::fs::File::create_new(&path) {
-            if e.kind() != ErrorKind::AlreadyExists {
-                return Err(e);
-            }
-        }
         Ok(path)
     }
 }
 
 impl Drop for NekoTodoConfig {
     fn drop(&mut self) {
-        if self.dirty {
-            self.save().unwrap();
+        if let Err(e) = self.save() {
+            error!("設定ファイルの保存に失敗:{e}");
         }
     }
 }
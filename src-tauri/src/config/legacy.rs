@@ -0,0 +1,134 @@
+//! 旧形式の設定ファイル(dotenv形式のneko_todo.conf)からの移行
+//!
+//! 既定のプロファイルは「キー」、それ以外のプロファイルは「キー.プロファイル名」で保存していた。
+//! 読み込みには、環境変数を変更しないdotenvy::from_path_iterを使用する。
+
+use super::*;
+use std::{collections::HashMap, path::Path};
+
+const DB_HOST: &str = "NEKO_DB_DB_HOST";
+const DB_USER: &str = "NEKO_DB_DB_USER";
+const DB_PASS: &str = "NEKO_DB_DB_PASS";
+const DB_PORT: &str = "NEKO_DB_DB_PORT";
+const DB_NAME: &str = "NEKO_DB_DB_NAME";
+const DB_SSL_MODE: &str = "NEKO_DB_DB_SSL_MODE";
+const DB_SSL_CA: &str = "NEKO_DB_DB_SSL_CA";
+const DB_SOCKET: &str = "NEKO_DB_DB_SOCKET";
+const SESSION: &str = "NEKO_DB_SESSION_ID";
+const WIN_POS_X: &str = "NEKO_DB_INIT_WINDOW_POS_X";
+const WIN_POS_Y: &str = "NEKO_DB_INIT_WINDOW_POS_Y";
+const WIN_SIZE_W: &str = "NEKO_DB_INIT_WINDOW_SIZE_W";
+const WIN_SIZE_H: &str = "NEKO_DB_INIT_WINDOW_SIZE_H";
+const IS_INCOMPLETE: &str = "NEKO_DB_IS_INCOMPLETE";
+const ITEM_SORT_ORDER: &str = "NEKO_DB_ITEM_SORT_ORDER";
+const ICS_FEED_PORT: &str = "NEKO_DB_ICS_FEED_PORT";
+const ICS_FEED_ALL_DAY: &str = "NEKO_DB_ICS_FEED_ALL_DAY";
+const TODOTXT_PATH: &str = "NEKO_DB_TODOTXT_PATH";
+const TODOTXT_INTERVAL: &str = "NEKO_DB_TODOTXT_INTERVAL";
+const POOL_MAX: &str = "NEKO_DB_POOL_MAX";
+const POOL_MIN: &str = "NEKO_DB_POOL_MIN";
+const ACQUIRE_TIMEOUT: &str = "NEKO_DB_ACQUIRE_TIMEOUT";
+const HEALTH_INTERVAL: &str = "NEKO_DB_HEALTH_INTERVAL";
+const CREDENTIAL_STORE: &str = "NEKO_DB_CREDENTIAL_STORE";
+const PROFILE: &str = "NEKO_DB_PROFILE";
+const PROFILES: &str = "NEKO_DB_PROFILES";
+
+/// 旧形式の設定ファイルを読み込む。
+pub(super) fn read(path: &Path) -> Result<HashMap<String, String>, ConfigError> {
+    Ok(dotenvy::from_path_iter(path)?.collect::<Result<_, _>>()?)
+}
+
+/// 旧形式の設定を変換する。
+/// 平文で保存されていたパスワードは、プロファイル名とともに別に返す。
+pub(super) fn convert(vars: &HashMap<String, String>) -> (ConfigFile, Vec<(String, Secret)>) {
+    fn parse<T: std::str::FromStr>(vars: &HashMap<String, String>, key: &str) -> Option<T> {
+        vars.get(key).and_then(|s| s.parse().ok())
+    }
+    let text = |key: &str| vars.get(key).filter(|s| !s.is_empty()).cloned();
+
+    let mut names = text(PROFILES)
+        .map(|s| {
+            s.split(',')
+                .map(str::trim)
+                .filter(|s| is_valid_profile_name(s))
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    if !names.iter().any(|n| n == DEFAULT_PROFILE) {
+        names.push(DEFAULT_PROFILE.to_string());
+    }
+    let mut profiles = BTreeMap::new();
+    let mut passwords = vec![];
+    for name in names {
+        let key = |key: &str| profile_key(key, &name);
+        profiles.insert(
+            name.clone(),
+            ProfileSection {
+                host: text(&key(DB_HOST)).unwrap_or_default(),
+                port: parse(vars, &key(DB_PORT)),
+                user: text(&key(DB_USER)).unwrap_or_default(),
+                database: text(&key(DB_NAME)),
+                ssl_mode: parse(vars, &key(DB_SSL_MODE)).unwrap_or_default(),
+                ssl_ca: text(&key(DB_SSL_CA)).map(Into::into),
+                socket: text(&key(DB_SOCKET)).map(Into::into),
+                session_id: parse(vars, &key(SESSION)),
+            },
+        );
+        if let Some(pass) = text(&key(DB_PASS)) {
+            passwords.push((name, Secret::new(pass)));
+        }
+    }
+
+    let default = ConfigFile::default();
+    let file = ConfigFile {
+        version: CONFIG_VERSION,
+        profile: text(PROFILE)
+            .filter(|p| profiles.contains_key(p))
+            .unwrap_or(default.profile),
+        keyring: text(CREDENTIAL_STORE).as_deref() != Some("file"),
+        view: ViewSection {
+            is_incomplete: parse(vars, IS_INCOMPLETE).unwrap_or(default.view.is_incomplete),
+            item_sort_order: parse(vars, ITEM_SORT_ORDER).unwrap_or(default.view.item_sort_order),
+        },
+        window: WindowSection {
+            position: parse(vars, WIN_POS_X)
+                .zip(parse(vars, WIN_POS_Y))
+                .map(|(x, y)| [x, y]),
+            size: parse(vars, WIN_SIZE_W)
+                .zip(parse(vars, WIN_SIZE_H))
+                .map(|(w, h)| [w, h]),
+        },
+        ics_feed: IcsFeedSection {
+            port: parse(vars, ICS_FEED_PORT),
+            all_day: parse(vars, ICS_FEED_ALL_DAY).unwrap_or(default.ics_feed.all_day),
+        },
+        todotxt: TodotxtSection {
+            path: text(TODOTXT_PATH).map(Into::into),
+            interval: parse(vars, TODOTXT_INTERVAL).unwrap_or(default.todotxt.interval),
+        },
+        database: DatabaseSection {
+            pool_max: parse(vars, POOL_MAX).unwrap_or(default.database.pool_max),
+            pool_min: parse(vars, POOL_MIN).unwrap_or(default.database.pool_min),
+            acquire_timeout: parse(vars, ACQUIRE_TIMEOUT)
+                .unwrap_or(default.database.acquire_timeout),
+            health_interval: parse(vars, HEALTH_INTERVAL)
+                .unwrap_or(default.database.health_interval),
+        },
+        profiles,
+    };
+    (file, passwords)
+}
+
+/// 移行済みの旧形式の設定ファイルを、拡張子に.oldを付けて残す。
+/// 平文のパスワードは、残さない。
+pub(super) fn retire(path: &Path) -> std::io::Result<()> {
+    let src = std::fs::read_to_string(path)?;
+    let kept = src
+        .lines()
+        .filter(|l| !l.trim_start().starts_with(DB_PASS))
+        .map(|l| format!("{l}\n"))
+        .collect::<String>();
+    std::fs::write(path.with_extension("conf.old"), kept)?;
+    std::fs::remove_file(path)
+}
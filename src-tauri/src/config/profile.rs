@@ -0,0 +1,52 @@
+//! データベース接続のプロファイル
+//!
+//! プロファイルごとに、接続情報とセッションを持つ。
+//! パスワードは、「database.プロファイル名」(既定のプロファイルは「database」)の名前で、
+//! 資格情報ストアに保存する。
+
+use super::*;
+
+impl ProfileSection {
+    pub(super) fn connect_config(&self, pass: Secret) -> ConnectConfig {
+        ConnectConfig {
+            host: self.host.clone(),
+            port: self.port,
+            user: self.user.clone(),
+            pass,
+            database: self.database.clone(),
+            ssl_mode: self.ssl_mode,
+            ssl_ca: self.ssl_ca.clone(),
+            socket: self.socket.clone(),
+        }
+    }
+
+    pub(super) fn set_connect_config(&mut self, conn: &ConnectConfig) {
+        self.host = conn.host.clone();
+        self.port = conn.port;
+        self.user = conn.user.clone();
+        self.database = conn.database.clone();
+        self.ssl_mode = conn.ssl_mode;
+        self.ssl_ca = conn.ssl_ca.clone();
+        self.socket = conn.socket.clone();
+    }
+}
+
+/// プロファイルごとの名前。
+/// 既定のプロファイルは、プロファイル導入前と同じ名前とする。
+pub(super) fn profile_key(key: &str, name: &str) -> String {
+    if name == DEFAULT_PROFILE {
+        key.to_string()
+    } else {
+        format!("{key}.{name}")
+    }
+}
+
+/// プロファイルのパスワードの、資格情報ストア上の保存名
+pub(super) fn pass_account(name: &str) -> String {
+    profile_key(DB_PASS_ACCOUNT, name)
+}
+
+/// プロファイル名として使用できるか。英数字と'_'のみとする。
+pub fn is_valid_profile_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
@@ -0,0 +1,81 @@
+//! 複数のプロセスでの設定ファイルの共有
+//!
+//! 保存時は、ロックファイルで他のプロセスと排他し、設定ファイルを読み直してから、
+//! このプロセスで変更した項目のみを反映して書き込む。
+//! 他のプロセスによる変更は、設定ファイルの更新日時と大きさで検知する。
+
+use super::*;
+use std::{fs::File, path::Path, time::SystemTime};
+use toml::Table;
+
+/// 設定ファイルのロック。破棄時に解除する。
+pub(super) struct ConfigLock(File);
+
+impl ConfigLock {
+    /// 設定ファイルのロックを取得する。他のプロセスが保持していれば、解除を待つ。
+    /// 設定ファイルは置き換えて保存するため、別のロックファイルを使用する。
+    pub(super) fn acquire(path: &Path) -> std::io::Result<Self> {
+        let file = File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path.with_extension("toml.lock"))?;
+        file.lock()?;
+        Ok(Self(file))
+    }
+}
+
+impl Drop for ConfigLock {
+    fn drop(&mut self) {
+        let _ = self.0.unlock();
+    }
+}
+
+/// 変更の検知に使用する、設定ファイルの更新日時と大きさ
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) struct FileStamp {
+    modified: SystemTime,
+    len: u64,
+}
+
+impl FileStamp {
+    /// ファイルがなければ、Noneを返す。
+    pub(super) fn of(path: &Path) -> Option<Self> {
+        let meta = std::fs::metadata(path).ok()?;
+        Some(Self {
+            modified: meta.modified().ok()?,
+            len: meta.len(),
+        })
+    }
+}
+
+impl ConfigFile {
+    /// baseからselfへの変更のみを、他のプロセスが保存した内容(disk)に反映する。
+    pub(super) fn merge_into(&self, base: &Self, disk: &Self) -> Result<Self, ConfigError> {
+        let to_table = |file: &Self| Table::try_from(file).map_err(std::io::Error::other);
+        let mut merged = to_table(disk)?;
+        apply_changes(&to_table(base)?, &to_table(self)?, &mut merged);
+        let file: Self = merged.try_into().map_err(std::io::Error::other)?;
+        file.normalize()
+    }
+}
+
+/// baseからlocalへの変更を、キー単位でdiskに反映する。
+fn apply_changes(base: &Table, local: &Table, disk: &mut Table) {
+    for (key, value) in local {
+        match (base.get(key), value, disk.get_mut(key)) {
+            (Some(b), _, _) if b == value => {}
+            (Some(toml::Value::Table(b)), toml::Value::Table(l), Some(toml::Value::Table(d))) => {
+                apply_changes(b, l, d)
+            }
+            _ => {
+                disk.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    for key in base.keys() {
+        if !local.contains_key(key) {
+            disk.remove(key);
+        }
+    }
+}
@@ -1,75 +1,292 @@
 //! configモジュールテスト
 
 use super::*;
+use std::path::Path;
+use uuid::Uuid;
 
-/// 環境設定の挙動テスト
+/// テスト用の設定ディレクトリ。テスト終了時に削除する。
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("neko_todo_{name}_{}", Uuid::now_v7()));
+        std::fs::create_dir_all(&path).unwrap();
+        // 資格情報ストアを使用せず、ファイルにパスワードを保存する。
+        std::fs::write(
+            path.join(CONF_FILE_NAME),
+            format!("version = {CONFIG_VERSION}\nkeyring = false\n"),
+        )
+        .unwrap();
+        Self(path)
+    }
+
+    fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// 設定の保存と読み込みの確認
 #[test]
-#[ignore]
-fn test_env_val() {
+fn test_save_and_load() {
+    let dir = TempDir::new("save");
     let val_db_host = "test_host";
     let val_db_user = "test_user";
     let val_db_pass = "test_pass";
-    save_curr_conf_file();
     {
-        let mut conf = NekoTodoConfig::new().unwrap();
+        let mut conf = NekoTodoConfig::open(dir.path()).unwrap();
         // 初期状態では空文字列が返るはず
-        assert_eq!(conf.get_db_host(), "");
-        assert_eq!(conf.get_db_user(), "");
-        assert_eq!(conf.get_db_pass(), "");
+        assert_eq!(conf.get_connect_config().host, "");
+        assert_eq!(conf.get_connect_config().user, "");
+        assert_eq!(conf.get_connect_config().pass.expose(), "");
         // test_hostをセットしてセットされているか確認。
-        conf.set_db_host(val_db_host);
-        conf.set_db_user(val_db_user);
-        conf.set_db_pass(val_db_pass);
-        assert_eq!(conf.get_db_host(), val_db_host);
-        assert_eq!(conf.get_db_user(), val_db_user);
-        assert_eq!(conf.get_db_pass(), val_db_pass);
-    } // この時点で一旦環境ファイルを保存してみる。
-      // 環境ファイルをもう一度ロードして、環境を確認
-    delete_env_val();
-    let conf = NekoTodoConfig::new().unwrap();
-    assert_eq!(conf.get_db_host(), val_db_host);
-    assert_eq!(conf.get_db_user(), val_db_user);
-    assert_eq!(conf.get_db_pass(), val_db_pass);
-    restore_curr_conf_file();
+        conf.set_connect_config(&ConnectConfig {
+            host: val_db_host.to_string(),
+            user: val_db_user.to_string(),
+            pass: Secret::new(val_db_pass),
+            ..Default::default()
+        });
+        conf.set_item_sort_order(ItemSortOrder::UpdateDesc);
+        assert_eq!(conf.get_connect_config().host, val_db_host);
+        assert_eq!(conf.get_connect_config().user, val_db_user);
+        assert_eq!(conf.get_connect_config().pass.expose(), val_db_pass);
+    } // この時点で設定ファイルを保存する。
+
+    let written = std::fs::read_to_string(dir.path().join(CONF_FILE_NAME)).unwrap();
+    assert!(!written.contains(val_db_pass));
+    assert!(!dir
+        .path()
+        .join(CONF_FILE_NAME)
+        .with_extension("toml.tmp")
+        .exists());
+
+    let conf = NekoTodoConfig::open(dir.path()).unwrap();
+    assert_eq!(conf.get_connect_config().host, val_db_host);
+    assert_eq!(conf.get_connect_config().user, val_db_user);
+    assert_eq!(conf.get_connect_config().pass.expose(), val_db_pass);
+    assert_eq!(conf.get_item_sort_order(), ItemSortOrder::UpdateDesc);
 }
 
-/// テスト環境のため、元のconfファイルを退避
-fn save_curr_conf_file() {
-    let file = NekoTodoConfig::get_config_file_path().unwrap();
-    let mut save_file = file.clone();
-    save_file.set_extension("save");
-    if file.exists() {
-        println!(
-            "現在の環境ファイル[{:?}]を[{:?}]に退避します。",
-            &file, &save_file
-        );
-        std::fs::rename(file, save_file).unwrap();
-    }
+#[test]
+fn test_profile_name() {
+    assert!(is_valid_profile_name("default"));
+    assert!(is_valid_profile_name("work_2"));
+    assert!(!is_valid_profile_name(""));
+    assert!(!is_valid_profile_name("home.db"));
+    assert!(!is_valid_profile_name("チーム"));
+
+    assert_eq!(pass_account(DEFAULT_PROFILE), DB_PASS_ACCOUNT);
+    assert_eq!(pass_account("work"), "database.work");
+}
+
+/// 設定ファイルの書き出しと読み込みの確認
+#[test]
+fn test_file_round_trip() {
+    let session = Uuid::now_v7();
+    let mut file = ConfigFile::default();
+    file.profiles.insert(
+        "work".to_string(),
+        ProfileSection {
+            host: "db.example.com".to_string(),
+            user: "user".to_string(),
+            port: Some(3307),
+            ssl_mode: SslMode::VerifyCa,
+            session_id: Some(session),
+            ..Default::default()
+        },
+    );
+    file.profile = "work".to_string();
+    file.window.position = Some([10, -20]);
+
+    let src = toml::to_string_pretty(&file).unwrap();
+    assert!(src.contains("ssl_mode = \"verify-ca\""));
+    let read = ConfigFile::parse(&src, Path::new(CONF_FILE_NAME)).unwrap();
+    assert_eq!(read, file);
 }
 
-/// テスト環境のための一時ファイルを抹消し、元のファイルを復旧
-fn restore_curr_conf_file() {
-    let file = NekoTodoConfig::get_config_file_path().unwrap();
-    let mut save_file = file.clone();
-    save_file.set_extension("save");
-    if save_file.exists() {
-        if file.exists() {
-            println!("テスト用環境ファイル{:?}を削除します。", &file);
-            std::fs::remove_file(&file).unwrap();
+/// 誤りのある設定ファイルは、その位置を示すエラーとなる。
+#[test]
+fn test_parse_error() {
+    let path = Path::new(CONF_FILE_NAME);
+    let src = "version = 1\n\n[view]\nis_incomplete = true\nsort = \"EndAsc\"\n";
+    match ConfigFile::parse(src, path) {
+        Err(ConfigError::Parse { line, column, .. }) => assert_eq!((line, column), (5, 1)),
+        e => panic!("unexpected result: {e:?}"),
+    }
+
+    let src = "version = 1\n[database]\npool_max = \"many\"\n";
+    match ConfigFile::parse(src, path) {
+        Err(ConfigError::Parse { line, .. }) => assert_eq!(line, 3),
+        e => panic!("unexpected result: {e:?}"),
+    }
+
+    let src = "version = 1\n[profiles.\"home.db\"]\n";
+    assert!(matches!(
+        ConfigFile::parse(src, path),
+        Err(ConfigError::Profile(ProfileError::InvalidName(_)))
+    ));
+
+    // 接続プールを生成できない値は、項目名を示すエラーとなる。
+    for (src, key) in [
+        ("pool_max = 0", "database.pool_max"),
+        ("pool_max = 2\npool_min = 3", "database.pool_min"),
+        ("acquire_timeout = 0", "database.acquire_timeout"),
+    ] {
+        let src = format!("version = 1\n[database]\n{src}\n");
+        match ConfigFile::parse(&src, path) {
+            Err(ConfigError::Invalid { key: k, .. }) => assert_eq!(k, key),
+            e => panic!("unexpected result: {e:?}"),
         }
-        println!(
-            "元の環境ファイル[{:?}]を[{:?}]に復元します。",
-            &save_file, &file
-        );
-        std::fs::rename(save_file, file).unwrap();
     }
+
+    // 新しい版の設定ファイルは、未知の項目があっても版の違いとする。
+    let src = "version = 2\nnew_item = 1\n";
+    assert!(matches!(
+        ConfigFile::parse(src, path),
+        Err(ConfigError::NewerVersion(2))
+    ));
 }
 
-/// テスト環境のため、環境変数をすべて消去する。
-fn delete_env_val() {
-    unsafe {
-        std::env::remove_var(DB_HOST);
-        std::env::remove_var(DB_USER);
-        std::env::remove_var(DB_USER);
-    }
+/// 設定ファイルの既定値と、存在しないプロファイルの指定の確認
+#[test]
+fn test_parse_default() {
+    let src = "version = 1\nprofile = \"none\"\n";
+    let file = ConfigFile::parse(src, Path::new(CONF_FILE_NAME)).unwrap();
+    assert_eq!(file, ConfigFile::default());
+
+    // 省略した項目は、既定値とする。
+    let src = "version = 1\n[database]\npool_max = 5\n";
+    let file = ConfigFile::parse(src, Path::new(CONF_FILE_NAME)).unwrap();
+    assert_eq!(file.database.pool_max, 5);
+    assert_eq!(file.database.health_interval, 10);
+}
+
+/// 旧形式の設定ファイルからの移行の確認
+#[test]
+fn test_legacy_migration() {
+    let dir = TempDir::new("legacy");
+    std::fs::remove_file(dir.path().join(CONF_FILE_NAME)).unwrap();
+    let session = Uuid::now_v7();
+    let legacy_src = format!(
+        "NEKO_DB_DB_HOST=localhost\n\
+         NEKO_DB_DB_USER=neko\n\
+         NEKO_DB_DB_PASS=old-secret\n\
+         NEKO_DB_DB_HOST.work=db.example.com\n\
+         NEKO_DB_DB_PORT.work=3307\n\
+         NEKO_DB_DB_SSL_MODE.work=required\n\
+         NEKO_DB_SESSION_ID.work={session}\n\
+         NEKO_DB_PROFILES=work\n\
+         NEKO_DB_PROFILE=work\n\
+         NEKO_DB_CREDENTIAL_STORE=file\n\
+         NEKO_DB_ITEM_SORT_ORDER=StartDesc\n\
+         NEKO_DB_INIT_WINDOW_POS_X=100\n\
+         NEKO_DB_INIT_WINDOW_POS_Y=200\n"
+    );
+    std::fs::write(dir.path().join(LEGACY_CONF_FILE_NAME), legacy_src).unwrap();
+
+    let mut conf = NekoTodoConfig::open(dir.path()).unwrap();
+    assert_eq!(conf.get_profile_name(), "work");
+    assert_eq!(conf.get_profile_names(), vec!["default", "work"]);
+    assert_eq!(conf.get_item_sort_order(), ItemSortOrder::StartDesc);
+    assert_eq!(
+        conf.get_win_pos(),
+        Some(tauri::PhysicalPosition::new(100, 200))
+    );
+    let conn = conf.get_connect_config();
+    assert_eq!(conn.host, "db.example.com");
+    assert_eq!(conn.port, Some(3307));
+    assert_eq!(conn.ssl_mode, SslMode::Required);
+    assert_eq!(conf.get_session_id(), Some(session));
+    conf.select_profile(DEFAULT_PROFILE, false).unwrap();
+    let conn = conf.get_connect_config();
+    assert_eq!(conn.host, "localhost");
+    assert_eq!(conn.pass.expose(), "old-secret");
+    drop(conf);
+
+    // 旧形式のファイルは、パスワードを除いて.oldとして残る。
+    assert!(!dir.path().join(LEGACY_CONF_FILE_NAME).exists());
+    let old = std::fs::read_to_string(dir.path().join("neko_todo.conf.old")).unwrap();
+    assert!(old.contains("NEKO_DB_DB_HOST=localhost"));
+    assert!(!old.contains("old-secret"));
+    let toml = std::fs::read_to_string(dir.path().join(CONF_FILE_NAME)).unwrap();
+    assert!(!toml.contains("old-secret"));
+
+    let conf = NekoTodoConfig::open(dir.path()).unwrap();
+    assert_eq!(conf.get_profile_name(), "work");
+}
+
+/// 複数のプロセスで保存しても、互いの変更を上書きしないことの確認
+#[test]
+fn test_merge_on_save() {
+    let dir = TempDir::new("merge");
+    let session = Uuid::now_v7();
+    let mut first = NekoTodoConfig::open(dir.path()).unwrap();
+    let mut second = NekoTodoConfig::open(dir.path()).unwrap();
+
+    first.set_session_id(&session);
+    first.save().unwrap();
+    second.set_win_pos(tauri::PhysicalPosition::new(30, 40));
+    second.set_item_sort_order(ItemSortOrder::StartAsc);
+    second.save().unwrap();
+    // 保存時に、他のプロセスの変更を取り込む。
+    assert_eq!(second.get_session_id(), Some(session));
+
+    let conf = NekoTodoConfig::open(dir.path()).unwrap();
+    assert_eq!(conf.get_session_id(), Some(session));
+    assert_eq!(
+        conf.get_win_pos(),
+        Some(tauri::PhysicalPosition::new(30, 40))
+    );
+    assert_eq!(conf.get_item_sort_order(), ItemSortOrder::StartAsc);
+}
+
+/// 他のプロセスの変更の読み直しの確認
+#[test]
+fn test_reload() {
+    let dir = TempDir::new("reload");
+    let mut first = NekoTodoConfig::open(dir.path()).unwrap();
+    assert!(!first.reload().unwrap());
+    first.set_is_incomplete(false);
+
+    {
+        let mut other = NekoTodoConfig::open(dir.path()).unwrap();
+        other.add_profile("work").unwrap();
+        other.set_ics_feed_all_day(true);
+        other.set_is_incomplete(true);
+    } // 保存する。
+
+    assert!(first.reload().unwrap());
+    assert_eq!(first.get_profile_names(), vec!["default", "work"]);
+    assert!(first.get_ics_feed_all_day());
+    // 未保存の変更は残る。
+    assert!(!first.get_is_incomplete());
+    assert!(!first.reload().unwrap());
+}
+
+/// 変更した項目のみを反映することの確認
+#[test]
+fn test_merge_into() {
+    let base = ConfigFile::default();
+    let mut local = base.clone();
+    local.view.is_incomplete = false;
+    local.profiles.remove(DEFAULT_PROFILE);
+    local
+        .profiles
+        .insert("work".to_string(), ProfileSection::default());
+    let mut disk = base.clone();
+    disk.view.item_sort_order = ItemSortOrder::UpdateAsc;
+    disk.database.pool_max = 3;
+
+    let merged = local.merge_into(&base, &disk).unwrap();
+    assert!(!merged.view.is_incomplete);
+    assert_eq!(merged.view.item_sort_order, ItemSortOrder::UpdateAsc);
+    assert_eq!(merged.database.pool_max, 3);
+    assert!(merged.profiles.contains_key("work"));
+    // 既定のプロファイルは、削除しても補われる。
+    assert!(merged.profiles.contains_key(DEFAULT_PROFILE));
 }
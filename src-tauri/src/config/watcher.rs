@@ -0,0 +1,67 @@
+//! 設定ファイルの監視
+//!
+//! 他のプロセスが設定ファイルを変更したら読み直し、画面に`config-changed`イベントで通知する。
+
+use crate::app_status::AppStatus;
+use log::{error, info, warn};
+use notify::{RecursiveMode, Watcher};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// 変更を検知してから、読み直すまでの待ち時間
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// 設定ファイルの監視を、バックグラウンドで開始する。
+pub fn spawn_config_watcher(app: AppHandle) {
+    let path = app
+        .state::<AppStatus>()
+        .config()
+        .lock()
+        .unwrap()
+        .path
+        .clone();
+    tauri::async_runtime::spawn(async move {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let file_name = path.file_name().map(|n| n.to_os_string());
+        let watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            if let Ok(event) = res {
+                if event
+                    .paths
+                    .iter()
+                    .any(|p| p.file_name().map(|n| n.to_os_string()) == file_name)
+                {
+                    let _ = tx.send(());
+                }
+            }
+        });
+        // 設定ファイルは置き換えて保存されるため、ディレクトリを監視する。
+        let Some(dir) = path.parent() else {
+            return;
+        };
+        let _watcher = match watcher.and_then(|mut w| {
+            w.watch(dir, RecursiveMode::NonRecursive)?;
+            Ok(w)
+        }) {
+            Ok(w) => w,
+            Err(e) => {
+                warn!("設定ファイルの監視を開始できません。:{e}");
+                return;
+            }
+        };
+
+        while rx.recv().await.is_some() {
+            tokio::time::sleep(DEBOUNCE).await;
+            while rx.try_recv().is_ok() {}
+            let state = app.state::<AppStatus>();
+            let reloaded = state.config().lock().unwrap().reload();
+            match reloaded {
+                Ok(true) => {
+                    info!("他のプロセスが変更した設定ファイルを読み直しました。");
+                    let _ = app.emit("config-changed", ());
+                }
+                Ok(false) => {}
+                Err(e) => error!("設定ファイルの読み直しに失敗:{e}"),
+            }
+        }
+    });
+}
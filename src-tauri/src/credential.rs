@@ -0,0 +1,188 @@
+//! 認証情報の保存
+//!
+//! データベースのパスワード等を、OSの資格情報ストア(LinuxではSecret Service)に保存する。
+//! 資格情報ストアを使用できない環境では、所有者のみ読み書きできるファイルに保存する。
+
+use log::warn;
+use std::{
+    collections::BTreeMap,
+    fmt,
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+#[cfg(test)]
+mod test;
+
+/// 資格情報ストアを使用できない場合に、認証情報を保存するファイル名
+pub const CREDENTIAL_FILE_NAME: &str = "credentials.json";
+/// データベースのパスワードの保存名
+pub const DB_PASS_ACCOUNT: &str = "database";
+/// 資格情報ストアに登録するサービス名
+const SERVICE: &str = "jp.laki.nekotodo";
+
+/// パスワード等の秘密の値。
+/// ログ等に出力されないよう、Debugでは値を表示しない。
+#[derive(Clone, PartialEq, Default)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    /// 値を取り出す。ログには出力しないこと。
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret(****)")
+    }
+}
+
+/// 認証情報の保存先
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CredentialBackend {
+    /// OSの資格情報ストア
+    Keyring,
+    /// 所有者のみ読み書きできるファイル
+    File,
+}
+
+/// 認証情報の保存・取得を行う。
+#[derive(Debug)]
+pub struct CredentialStore {
+    /// 資格情報ストアを使用できない場合の保存先ファイル
+    file: PathBuf,
+    /// 資格情報ストアを使用するか
+    use_keyring: bool,
+}
+
+impl CredentialStore {
+    /// use_keyringがfalseなら、資格情報ストアを使用せず、常にファイルに保存する。
+    pub fn new(file: &Path, use_keyring: bool) -> Self {
+        Self {
+            file: file.to_path_buf(),
+            use_keyring,
+        }
+    }
+
+    /// 認証情報を取得する。保存されていなければNoneを返す。
+    pub fn get(&self, account: &str) -> Result<Option<Secret>, CredentialError> {
+        if self.use_keyring {
+            match keyring_entry(account).and_then(|e| e.get_password()) {
+                Ok(pass) => return Ok(Some(Secret(pass))),
+                Err(keyring::Error::NoEntry) => {}
+                Err(e) => warn!("資格情報ストアから取得できません。ファイルを使用します。:{e}"),
+            }
+        }
+        Ok(self.read_file()?.remove(account).map(Secret))
+    }
+
+    /// 認証情報を保存し、保存先を返す。
+    /// 資格情報ストアに保存できなければ、ファイルに保存する。
+    pub fn set(
+        &self,
+        account: &str,
+        secret: &Secret,
+    ) -> Result<CredentialBackend, CredentialError> {
+        if self.use_keyring {
+            match keyring_entry(account).and_then(|e| e.set_password(secret.expose())) {
+                Ok(()) => {
+                    // 以前にファイルへ保存したものが残らないようにする。
+                    self.remove_from_file(account)?;
+                    return Ok(CredentialBackend::Keyring);
+                }
+                Err(e) => warn!("資格情報ストアに保存できません。ファイルを使用します。:{e}"),
+            }
+        }
+        let mut map = self.read_file()?;
+        map.insert(account.to_string(), secret.expose().to_string());
+        self.write_file(&map)?;
+        Ok(CredentialBackend::File)
+    }
+
+    /// 認証情報を削除する。
+    pub fn delete(&self, account: &str) -> Result<(), CredentialError> {
+        if self.use_keyring {
+            match keyring_entry(account).and_then(|e| e.delete_credential()) {
+                Ok(()) | Err(keyring::Error::NoEntry) => {}
+                Err(e) => warn!("資格情報ストアから削除できません。:{e}"),
+            }
+        }
+        self.remove_from_file(account)
+    }
+
+    fn remove_from_file(&self, account: &str) -> Result<(), CredentialError> {
+        let mut map = self.read_file()?;
+        if map.remove(account).is_some() {
+            self.write_file(&map)?;
+        }
+        Ok(())
+    }
+
+    /// ファイルから認証情報を読み込む。ファイルがなければ空とする。
+    fn read_file(&self) -> Result<BTreeMap<String, String>, CredentialError> {
+        let s = match std::fs::read_to_string(&self.file) {
+            Ok(s) => s,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+            Err(e) => return Err(e.into()),
+        };
+        restrict_permissions(&self.file)?;
+        Ok(serde_json::from_str(&s)?)
+    }
+
+    /// ファイルに認証情報を書き込む。
+    /// 所有者のみ読み書きできる一時ファイルに書き込んでから、置き換える。
+    fn write_file(&self, map: &BTreeMap<String, String>) -> Result<(), CredentialError> {
+        let tmp = self.file.with_extension("json.tmp");
+        let json = serde_json::to_vec(map)?;
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(&tmp)?;
+        restrict_permissions(&tmp)?;
+        file.write_all(&json)?;
+        file.sync_all()?;
+        std::fs::rename(&tmp, &self.file)?;
+        Ok(())
+    }
+}
+
+fn keyring_entry(account: &str) -> keyring::Result<keyring::Entry> {
+    keyring::Entry::new(SERVICE, account)
+}
+
+/// 所有者以外が読み書きできないようにする。
+#[cfg(unix)]
+fn restrict_permissions(path: &Path) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = std::fs::metadata(path)?.permissions().mode();
+    if mode & 0o077 != 0 {
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn restrict_permissions(_path: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+#[derive(Error, Debug)]
+pub enum CredentialError {
+    #[error("認証情報ファイルへのアクセスに失敗:{0}")]
+    Io(#[from] std::io::Error),
+    #[error("認証情報ファイルの形式が不正:{0}")]
+    Format(#[from] serde_json::Error),
+}
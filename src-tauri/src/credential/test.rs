@@ -0,0 +1,60 @@
+//! credentialモジュールテスト
+
+use super::*;
+
+/// テスト用の一時ディレクトリ内の認証情報ファイル
+fn temp_file(name: &str) -> PathBuf {
+    let dir =
+        std::env::temp_dir().join(format!("nekotodo_credential_{name}_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir.join(CREDENTIAL_FILE_NAME)
+}
+
+#[test]
+fn file_store_test() {
+    let file = temp_file("store");
+    let store = CredentialStore::new(&file, false);
+    assert_eq!(store.get(DB_PASS_ACCOUNT).unwrap(), None);
+
+    let secret = Secret::new("p@ss=word");
+    assert_eq!(
+        store.set(DB_PASS_ACCOUNT, &secret).unwrap(),
+        CredentialBackend::File
+    );
+    assert_eq!(store.get(DB_PASS_ACCOUNT).unwrap(), Some(secret.clone()));
+    assert_eq!(store.get("other").unwrap(), None);
+
+    // 作り直しても読み込める。
+    let store = CredentialStore::new(&file, false);
+    assert_eq!(store.get(DB_PASS_ACCOUNT).unwrap(), Some(secret));
+
+    store.delete(DB_PASS_ACCOUNT).unwrap();
+    assert_eq!(store.get(DB_PASS_ACCOUNT).unwrap(), None);
+    std::fs::remove_dir_all(file.parent().unwrap()).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn file_permission_test() {
+    use std::os::unix::fs::PermissionsExt;
+    let file = temp_file("permission");
+    let store = CredentialStore::new(&file, false);
+    store.set(DB_PASS_ACCOUNT, &Secret::new("pass")).unwrap();
+    let mode = std::fs::metadata(&file).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+
+    // 他人が読めるファイルは、読み込み時に制限し直す。
+    std::fs::set_permissions(&file, std::fs::Permissions::from_mode(0o644)).unwrap();
+    store.get(DB_PASS_ACCOUNT).unwrap();
+    let mode = std::fs::metadata(&file).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+    std::fs::remove_dir_all(file.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn secret_debug_test() {
+    let secret = Secret::new("top-secret");
+    assert!(!format!("{secret:?}").contains("top-secret"));
+    assert_eq!(secret.expose(), "top-secret");
+}
@@ -1,19 +1,105 @@
 //! データベースの操作を司る
+mod backup;
+#[cfg(test)]
+mod bench;
+mod feed;
+mod history;
+mod migrate;
 mod new;
+mod page;
+mod recurrence;
+mod report;
 mod session;
+mod settings;
+mod tag;
 #[cfg(test)]
 mod test;
 mod todo;
 mod user;
+mod view;
 
-use chrono::NaiveDate;
-use log::error;
+use crate::config::{ItemSortOrder, Theme};
+use crate::credential::Secret;
+use chrono::{NaiveDate, NaiveDateTime};
 use serde::{Deserialize, Serialize};
 use sqlx::{
     mysql::{MySqlPool, MySqlPoolOptions},
     prelude::*,
 };
+use std::path::PathBuf;
+use std::time::Duration;
 use thiserror::Error;
+use uuid::Uuid;
+
+/// データベースの構造の版。migrationsの最新の番号と一致させる。
+pub const SCHEMA_VERSION: u32 = 9;
+
+/// データベースへの接続情報
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ConnectConfig {
+    pub host: String,
+    /// ポート番号。Noneなら既定(3306)。
+    pub port: Option<u16>,
+    pub user: String,
+    pub pass: Secret,
+    /// データベース名。Noneなら既定(nekotodo)。
+    pub database: Option<String>,
+    pub ssl_mode: SslMode,
+    /// サーバー証明書の検証に使用するCA証明書
+    pub ssl_ca: Option<PathBuf>,
+    /// Unixドメインソケット。指定した場合は、ホスト名・ポート番号は使用しない。
+    pub socket: Option<PathBuf>,
+}
+
+/// TLSの使用方法
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SslMode {
+    /// TLSを使用しない
+    Disabled,
+    /// サーバーが対応していればTLSを使用する
+    #[default]
+    Preferred,
+    /// TLSを必須とする。証明書は検証しない。
+    Required,
+    /// TLSを必須とし、証明書をCA証明書で検証する。
+    VerifyCa,
+    /// TLSを必須とし、証明書とホスト名を検証する。
+    VerifyIdentity,
+}
+
+/// 接続先のデータベースとスキーマの状態
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DbCheck {
+    pub database_exists: bool,
+    /// 適用済みのマイグレーションの最新の番号。未適用ならNone。
+    pub schema_version: Option<i64>,
+    /// アプリケーションが必要とするスキーマの版
+    pub required_version: u32,
+}
+
+/// データベース名の既定値
+pub const DEFAULT_DATABASE: &str = "nekotodo";
+
+/// コネクションプールの設定
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PoolConfig {
+    pub max_connections: u32,
+    pub min_connections: u32,
+    /// 接続の取得を待つ上限時間。
+    /// データベースに接続できない場合に、オフライン動作へ早く切り替えるため、短めにする。
+    pub acquire_timeout: Duration,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: 10,
+            min_connections: 3,
+            acquire_timeout: Duration::from_secs(5),
+        }
+    }
+}
 
 /// neko_dbデータベース操作関数郡
 #[derive(Clone, Debug)]
@@ -37,12 +123,220 @@ pub struct ItemTodo {
     pub start_date: Option<NaiveDate>,
     pub end_date: Option<NaiveDate>,
     pub done: bool,
+    /// 版番号。編集・完了状態の変更のたびに増える。
+    #[serde(default)]
+    pub revision: u32,
+}
+
+/// 一括追加するtodo。タグ・繰り返し規則を合わせて持つ。
+#[derive(Debug, PartialEq, Clone)]
+pub struct NewTodoItem {
+    pub item: ItemTodo,
+    pub tags: Vec<String>,
+    /// iCalendarのRRULEの値
+    pub recurrence: Option<String>,
+}
+
+/// 作業報告用のtodo。作成日・完了日を合わせて持つ。
+#[derive(FromRow, Debug, PartialEq, Clone)]
+pub struct ReportTodoItem {
+    pub id: u32,
+    pub title: String,
+    pub work: Option<String>,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    /// 作成日。記録されていなければNone。
+    pub create_date: Option<NaiveDate>,
+    /// 完了日。未完了ならNone。
+    pub done_date: Option<NaiveDate>,
+}
+
+/// バックアップ・復元用のtodo。すべての列と、タグ・繰り返し規則を持つ。
+#[derive(FromRow, Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct TodoRecord {
+    pub id: u32,
+    pub title: String,
+    pub work: Option<String>,
+    pub update_date: NaiveDate,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub done: bool,
+    #[serde(default)]
+    pub create_date: Option<NaiveDate>,
+    #[serde(default)]
+    pub done_date: Option<NaiveDate>,
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// iCalendarのRRULEの値
+    #[sqlx(skip)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recurrence: Option<String>,
+}
+
+/// 編集・完了状態の変更の結果。
+/// 変更前後の内容は、行をロックした同じトランザクション内で取得したもの。
+#[derive(Debug, PartialEq, Clone)]
+pub struct TodoChange {
+    /// 変更後の版番号
+    pub revision: u32,
+    pub before: TodoRecord,
+    pub after: TodoRecord,
+}
+
+/// 変更履歴に記録する、todoの内容
+#[derive(FromRow, Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct TodoSnapshot {
+    pub title: String,
+    pub work: Option<String>,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub done: bool,
+}
+
+impl From<&TodoRecord> for TodoSnapshot {
+    fn from(value: &TodoRecord) -> Self {
+        Self {
+            title: value.title.clone(),
+            work: value.work.clone(),
+            start_date: value.start_date,
+            end_date: value.end_date,
+            done: value.done,
+        }
+    }
+}
+
+/// todoの変更の種類
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryAction {
+    Create,
+    Edit,
+    /// 完了状態の変更
+    Done,
+    Delete,
+}
+
+/// todoの変更履歴の一件
+#[derive(FromRow, Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct TodoHistory {
+    pub id: u64,
+    pub todo_id: u32,
+    #[sqlx(try_from = "String")]
+    pub action: HistoryAction,
+    /// 変更前の内容。作成時はNone。
+    #[sqlx(skip)]
+    pub old_value: Option<TodoSnapshot>,
+    /// 変更後の内容。削除時はNone。
+    #[sqlx(skip)]
+    pub new_value: Option<TodoSnapshot>,
+    pub changed_at: NaiveDateTime,
+    /// 変更を行ったセッション
+    pub session: Option<String>,
+    /// 変更を行ったクライアント(gui, cliなど)
+    pub client: String,
+}
+
+/// 変更履歴に記録する、変更元の情報
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangeSource {
+    pub session: Uuid,
+    pub client: String,
+}
+
+/// ユーザーごとのデータ一式(todo・タグ・ビュー・設定・変更履歴)
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct UserData {
+    pub todos: Vec<TodoRecord>,
+    #[serde(default)]
+    pub views: Vec<SavedView>,
+    /// 表示設定。保存されていなければNone。
+    #[serde(default)]
+    pub settings: Option<UserSettings>,
+    /// todoの変更履歴
+    #[serde(default)]
+    pub history: Vec<TodoHistory>,
+}
+
+/// 復元した件数
+#[derive(Serialize, Debug, PartialEq, Clone, Default)]
+pub struct RestoreCount {
+    pub todos: usize,
+    /// 既存のtodoと重複したため、復元しなかった件数
+    pub skipped_todos: usize,
+    pub views: usize,
+    /// 既存のビューと名前が重複したため、復元しなかった件数
+    pub skipped_views: usize,
+    /// 設定を復元したか
+    pub settings: bool,
+    /// 復元した変更履歴の件数
+    pub history: usize,
+}
+
+/// todo一覧の取得範囲(キーセット方式のページング)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PageRequest {
+    /// 前のページの最後の位置。Noneなら先頭から。
+    pub cursor: Option<TodoCursor>,
+    /// 取得する最大件数。Noneなら全件。
+    pub limit: Option<u32>,
+}
+
+/// ページの位置。ソートキー2つとidの組。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TodoCursor {
+    pub key1: NaiveDate,
+    pub key2: NaiveDate,
+    pub id: u32,
+}
+
+/// ページ単位で取得したtodo一覧
+#[derive(Serialize, Debug, PartialEq, Clone)]
+pub struct TodoPage {
+    pub items: Vec<ItemTodo>,
+    /// 次のページを取得するためのカーソル文字列。最終ページならNone。
+    pub next_cursor: Option<String>,
+    /// 条件に一致する全件数
+    pub total: i64,
+}
+
+/// 保存済みビュー(名前付きの絞り込み条件・ソート順・表示設定)
+#[derive(FromRow, Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct SavedView {
+    pub id: u32,
+    pub user_name: String,
+    pub name: String,
+    pub filter: String,
+    #[sqlx(try_from = "String")]
+    pub sort_order: ItemSortOrder,
+    pub only_incomplete: bool,
+}
+
+/// ユーザーごとの表示設定
+#[derive(FromRow, Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UserSettings {
+    #[serde(default)]
+    pub user_name: String,
+    /// 未完了のみ表示するか
+    pub only_incomplete: bool,
+    #[sqlx(try_from = "String")]
+    pub item_sort_order: ItemSortOrder,
+    /// todo追加時の開始日の初期値(今日からの日数)
+    pub default_start_offset: i32,
+    /// todo追加時の終了日の初期値(今日からの日数)。Noneなら期限なし。
+    pub default_end_offset: Option<i32>,
+    #[sqlx(try_from = "String")]
+    pub theme: Theme,
 }
 
 #[derive(Error, Debug)]
 pub enum DbError {
+    /// 接続情報の誤りなど、再試行しても解消しない接続の失敗
     #[error("データベースへの接続に失敗。")]
     FailConnect(sqlx::Error),
+    /// ネットワークの切断・サーバーの停止など、再試行で解消しうる接続の失敗
+    #[error("データベースに一時的に接続できません。")]
+    Unavailable(sqlx::Error),
     #[error("データベース操作失敗(一般)")]
     FailDbAccess(sqlx::Error),
     #[error("User挿入失敗(name重複)")]
@@ -53,4 +347,80 @@ pub enum DbError {
     NotFoundSession,
     #[error("指定されたidのtodoが見つかりません。")]
     NotFoundTodo,
+    #[error("指定されたidのビューが見つかりません。")]
+    NotFoundView,
+    #[error("ビュー挿入失敗(name重複)")]
+    DuplicateViewName(sqlx::Error),
+    #[error("todoが他で更新されています。")]
+    Conflict,
+    #[error("マイグレーションの適用に失敗。")]
+    Migrate(sqlx::migrate::MigrateError),
+}
+
+impl DbError {
+    /// 再試行で解消しうる失敗か。
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::Unavailable(_) => true,
+            Self::FailDbAccess(e) => is_transient(e),
+            _ => false,
+        }
+    }
+
+    /// 接続時のエラーを、一時的なものかどうかで分類する。
+    fn connect(e: sqlx::Error) -> Self {
+        if is_transient(&e) {
+            Self::Unavailable(e)
+        } else {
+            Self::FailConnect(e)
+        }
+    }
+}
+
+/// 再試行で解消しうるエラーか。
+/// 接続に関するエラーのほか、ロックの競合も一時的なものとする。
+pub fn is_transient(e: &sqlx::Error) -> bool {
+    // 1205:ロック待ちタイムアウト 1213:デッドロック
+    is_connection_error(e) || has_error_number(e, &[1205, 1213])
+}
+
+/// データベースに接続できない・接続が切れたことによるエラーか。
+/// TLSのエラーは、証明書の誤りなど設定によるものであり、再試行しても直らないため含めない。
+pub fn is_connection_error(e: &sqlx::Error) -> bool {
+    // 1040:接続数超過 2002,2003:接続不可 2006:サーバー切断 2013:通信中の切断
+    matches!(
+        e,
+        sqlx::Error::Io(_)
+            | sqlx::Error::PoolTimedOut
+            | sqlx::Error::PoolClosed
+            | sqlx::Error::WorkerCrashed
+    ) || has_error_number(e, &[1040, 2002, 2003, 2006, 2013])
+}
+
+/// MariaDBのエラー番号が、指定のいずれかであるか。
+fn has_error_number(e: &sqlx::Error, numbers: &[u16]) -> bool {
+    match e {
+        sqlx::Error::Database(db) => db
+            .try_downcast_ref::<sqlx::mysql::MySqlDatabaseError>()
+            .is_some_and(|e| numbers.contains(&e.number())),
+        _ => false,
+    }
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum SslModeParseError {
+    #[error("ssl-modeには、disabled, preferred, required, verify-ca, verify-identityのいずれかを指定してください。")]
+    InvalidArgument,
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum HistoryActionParseError {
+    #[error("変更の種類が不正です。")]
+    InvalidArgument,
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum CursorParseError {
+    #[error("カーソルの形式が不正です。")]
+    InvalidFormat,
 }
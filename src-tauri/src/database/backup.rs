@@ -0,0 +1,274 @@
+//! ユーザーデータ一式の取得と復元
+use super::history::{record_history, restore_history};
+use super::*;
+use sqlx::{query, query_as, MySql, Transaction};
+use std::collections::{HashMap, HashSet};
+
+impl Database {
+    /// ユーザーのtodo(タグ・繰り返し規則を含む)、ビュー、設定をすべて取得する。
+    pub async fn get_user_data(&self, user_name: &str) -> Result<UserData, DbError> {
+        let todo_sql = r#"
+            select id, title, work, update_date, start_date, end_date, done, create_date, done_date
+            from todo where user_name = ?
+            order by id;
+            "#;
+        let mut todos = query_as::<_, TodoRecord>(todo_sql)
+            .bind(user_name)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(DbError::FailDbAccess)?;
+
+        let tag_sql = r#"
+            select tt.todo_id, tt.tag_name
+            from todo_tag tt join todo t on t.id = tt.todo_id
+            where t.user_name = ?
+            order by tt.todo_id, tt.tag_name;
+            "#;
+        let mut tags = HashMap::<u32, Vec<String>>::new();
+        for (id, tag) in query_as::<_, (u32, String)>(tag_sql)
+            .bind(user_name)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(DbError::FailDbAccess)?
+        {
+            tags.entry(id).or_default().push(tag);
+        }
+
+        let recurrence_sql = r#"
+            select r.todo_id, r.rrule
+            from todo_recurrence r join todo t on t.id = r.todo_id
+            where t.user_name = ?;
+            "#;
+        let mut recurrences = query_as::<_, (u32, String)>(recurrence_sql)
+            .bind(user_name)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(DbError::FailDbAccess)?
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+        for todo in todos.iter_mut() {
+            todo.tags = tags.remove(&todo.id).unwrap_or_default();
+            todo.recurrence = recurrences.remove(&todo.id);
+        }
+
+        let view_sql = r#"
+            select id, user_name, name, filter, sort_order, only_incomplete
+            from saved_views where user_name = ?
+            order by name;
+            "#;
+        let views = query_as::<_, SavedView>(view_sql)
+            .bind(user_name)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(DbError::FailDbAccess)?;
+
+        let settings_sql = r#"
+            select user_name, only_incomplete, item_sort_order,
+                default_start_offset, default_end_offset, theme
+            from user_settings where user_name = ?;
+            "#;
+        let settings = query_as::<_, UserSettings>(settings_sql)
+            .bind(user_name)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(DbError::FailDbAccess)?;
+
+        let history = self.get_user_history(user_name).await?;
+
+        Ok(UserData {
+            todos,
+            views,
+            settings,
+            history,
+        })
+    }
+
+    /// ユーザーデータ一式を、指定ユーザーのデータとして一つのトランザクションで復元する。
+    /// replaceがtrueなら、既存のtodo・ビュー・設定をすべて削除してから復元する。
+    /// falseなら既存のデータに追加する。このとき、タイトル・開始日・終了日が同じtodoと、
+    /// 名前が同じビューは復元せず、設定は保存されていない場合のみ復元する。
+    /// todoのidは新たに採番され、変更履歴は復元したtodoの分のみ、新たなidで復元する。
+    /// 復元したtodoには、作成の変更履歴を記録する。
+    pub async fn restore_user_data(
+        &self,
+        user_name: &str,
+        data: &UserData,
+        replace: bool,
+        source: &ChangeSource,
+    ) -> Result<RestoreCount, DbError> {
+        let mut count = RestoreCount::default();
+        let mut tx = self.pool.begin().await.map_err(DbError::FailDbAccess)?;
+
+        let mut known_todos = HashSet::new();
+        let mut known_views = HashSet::new();
+        if replace {
+            for sql in [
+                "delete from todo where user_name = ?;",
+                "delete from saved_views where user_name = ?;",
+                "delete from user_settings where user_name = ?;",
+                "delete from todo_history where user_name = ?;",
+            ] {
+                query(sql)
+                    .bind(user_name)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(DbError::FailDbAccess)?;
+            }
+        } else {
+            known_todos = query_as::<_, (String, NaiveDate, NaiveDate)>(
+                "select title, start_date, end_date from todo where user_name = ?;",
+            )
+            .bind(user_name)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(DbError::FailDbAccess)?
+            .into_iter()
+            .collect();
+            known_views =
+                query_as::<_, (String,)>("select name from saved_views where user_name = ?;")
+                    .bind(user_name)
+                    .fetch_all(&mut *tx)
+                    .await
+                    .map_err(DbError::FailDbAccess)?
+                    .into_iter()
+                    .map(|(name,)| name)
+                    .collect();
+        }
+
+        let has_history = data
+            .history
+            .iter()
+            .map(|h| h.todo_id)
+            .collect::<HashSet<_>>();
+        let mut new_ids = HashMap::new();
+        for todo in &data.todos {
+            // 置き換えの場合は、バックアップ内で重複していてもそのまま復元する。
+            if !replace && !known_todos.insert((todo.title.clone(), todo.start_date, todo.end_date))
+            {
+                count.skipped_todos += 1;
+                continue;
+            }
+            // 作成の履歴もバックアップにあれば、それを復元するため、新たには記録しない。
+            let source = (!has_history.contains(&todo.id)).then_some(source);
+            let id = insert_todo_record(&mut tx, user_name, todo, source).await?;
+            new_ids.insert(todo.id, id);
+            count.todos += 1;
+        }
+
+        for history in &data.history {
+            let Some(&id) = new_ids.get(&history.todo_id) else {
+                continue;
+            };
+            restore_history(&mut tx, id, user_name, history).await?;
+            count.history += 1;
+        }
+
+        let view_sql = r#"
+            insert into saved_views(user_name, name, filter, sort_order, only_incomplete)
+            values (?, ?, ?, ?, ?);
+            "#;
+        for view in &data.views {
+            if !known_views.insert(view.name.clone()) {
+                count.skipped_views += 1;
+                continue;
+            }
+            query(view_sql)
+                .bind(user_name)
+                .bind(&view.name)
+                .bind(&view.filter)
+                .bind(view.sort_order.to_string())
+                .bind(view.only_incomplete)
+                .execute(&mut *tx)
+                .await
+                .map_err(DbError::FailDbAccess)?;
+            count.views += 1;
+        }
+
+        if let Some(settings) = &data.settings {
+            let settings_sql = r#"
+                insert ignore into user_settings(user_name, only_incomplete, item_sort_order,
+                    default_start_offset, default_end_offset, theme)
+                values (?, ?, ?, ?, ?, ?);
+                "#;
+            let res = query(settings_sql)
+                .bind(user_name)
+                .bind(settings.only_incomplete)
+                .bind(settings.item_sort_order.to_string())
+                .bind(settings.default_start_offset)
+                .bind(settings.default_end_offset)
+                .bind(settings.theme.to_string())
+                .execute(&mut *tx)
+                .await
+                .map_err(DbError::FailDbAccess)?;
+            count.settings = res.rows_affected() > 0;
+        }
+
+        tx.commit().await.map_err(DbError::FailDbAccess)?;
+        Ok(count)
+    }
+}
+
+/// todoを、タグ・繰り返し規則と合わせて追加し、作成の変更履歴を記録する。
+/// sourceがNoneなら、変更履歴は記録しない。
+/// 新たに採番したidを返す。
+pub(super) async fn insert_todo_record(
+    tx: &mut Transaction<'_, MySql>,
+    user_name: &str,
+    todo: &TodoRecord,
+    source: Option<&ChangeSource>,
+) -> Result<u32, DbError> {
+    let todo_sql = r#"
+        insert into todo(user_name, title, work, update_date, start_date, end_date, done,
+            create_date, done_date)
+        values (?, ?, ?, ?, ?, ?, ?, ?, ?);
+        "#;
+    let id = query(todo_sql)
+        .bind(user_name)
+        .bind(&todo.title)
+        .bind(&todo.work)
+        .bind(todo.update_date)
+        .bind(todo.start_date)
+        .bind(todo.end_date)
+        .bind(todo.done)
+        .bind(todo.create_date)
+        .bind(todo.done_date)
+        .execute(&mut **tx)
+        .await
+        .map_err(DbError::FailDbAccess)?
+        .last_insert_id() as u32;
+    if let Some(source) = source {
+        let snapshot = TodoSnapshot::from(todo);
+        record_history(
+            tx,
+            id,
+            user_name,
+            HistoryAction::Create,
+            None,
+            Some(&snapshot),
+            source,
+        )
+        .await?;
+    }
+    for tag in &todo.tags {
+        query("insert ignore into tag(name) values (?);")
+            .bind(tag)
+            .execute(&mut **tx)
+            .await
+            .map_err(DbError::FailDbAccess)?;
+        query("insert ignore into todo_tag(todo_id, tag_name) values (?, ?);")
+            .bind(id)
+            .bind(tag)
+            .execute(&mut **tx)
+            .await
+            .map_err(DbError::FailDbAccess)?;
+    }
+    if let Some(rrule) = &todo.recurrence {
+        query("insert into todo_recurrence(todo_id, rrule) values (?, ?);")
+            .bind(id)
+            .bind(rrule)
+            .execute(&mut **tx)
+            .await
+            .map_err(DbError::FailDbAccess)?;
+    }
+    Ok(id)
+}
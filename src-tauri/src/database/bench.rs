@@ -0,0 +1,167 @@
+//! databaseモジュールのベンチマーク
+//!
+//! 大量のデータを投入し、一覧取得・検索の所要時間を計測する。
+//! 通常のテストでは実行しない。次のように実行する。
+//! `cargo test bench_ -- --ignored --nocapture`
+//! 投入件数は、環境変数NEKO_BENCH_ROWS(既定値:10000)で変更できる。
+
+use super::*;
+use crate::filter::TodoFilter;
+use chrono::{Days, Local};
+use sqlx::{MySql, QueryBuilder};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// 一つの計測の繰り返し回数
+const ITERATIONS: u32 = 10;
+/// 一度のinsert文で投入する件数
+const CHUNK_SIZE: usize = 1000;
+
+#[sqlx::test]
+#[ignore]
+async fn bench_get_todo_list(pool: MySqlPool) {
+    let db = Database::new_test(pool);
+    let rows = bench_rows();
+    let sess = seed(&db, rows).await;
+    let today = Local::now().date_naive();
+    println!("投入件数:{}", rows);
+
+    for (name, only_incomplete, sort_order) in [
+        ("未完了のみ・終了日順", true, ItemSortOrder::EndAsc),
+        ("全件・終了日順", false, ItemSortOrder::EndAsc),
+        ("全件・更新日逆順", false, ItemSortOrder::UpdateDesc),
+    ] {
+        for (page_name, limit) in [("全件", None), ("先頭100件", Some(100))] {
+            let page = PageRequest {
+                cursor: None,
+                limit,
+            };
+            measure(&format!("一覧[{}][{}]", name, page_name), || async {
+                db.get_todo_item(
+                    sess,
+                    today,
+                    only_incomplete,
+                    sort_order,
+                    &TodoFilter::default(),
+                    &page,
+                )
+                .await
+                .unwrap()
+                .items
+                .len()
+            })
+            .await;
+        }
+    }
+}
+
+#[sqlx::test]
+#[ignore]
+async fn bench_search_todo(pool: MySqlPool) {
+    let db = Database::new_test(pool);
+    let rows = bench_rows();
+    let sess = seed(&db, rows).await;
+    let today = Local::now().date_naive();
+    println!("投入件数:{}", rows);
+
+    for filter in [
+        "due<7d",
+        "tag:tag3",
+        "-done tag:tag5 due<30d",
+        "title:\"item 12\"",
+        "報告",
+    ] {
+        let parsed = filter.parse::<TodoFilter>().unwrap();
+        let page = PageRequest {
+            cursor: None,
+            limit: Some(100),
+        };
+        measure(&format!("検索[{}]", filter), || async {
+            db.get_todo_item(sess, today, false, ItemSortOrder::EndAsc, &parsed, &page)
+                .await
+                .unwrap()
+                .total as usize
+        })
+        .await;
+    }
+}
+
+/// 投入件数を環境変数から取得する。
+fn bench_rows() -> usize {
+    std::env::var("NEKO_BENCH_ROWS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(10000)
+}
+
+/// 処理を繰り返し実行し、所要時間を表示する。
+/// 処理の戻り値は、取得件数として表示する。
+async fn measure<F, Fut>(name: &str, f: F)
+where
+    F: Fn() -> Fut,
+    Fut: std::future::Future<Output = usize>,
+{
+    let count = f().await; // 初回はキャッシュ暖機のため計測しない。
+    let mut times = Vec::new();
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        f().await;
+        times.push(start.elapsed());
+    }
+    let total: Duration = times.iter().sum();
+    println!(
+        "{}: 件数={} 平均={:?} 最小={:?} 最大={:?}",
+        name,
+        count,
+        total / ITERATIONS,
+        times.iter().min().unwrap(),
+        times.iter().max().unwrap(),
+    );
+}
+
+/// ベンチマーク用のユーザー・todo・タグを投入し、セッションを返す。
+/// 他のユーザーのデータも同数投入し、ユーザーでの絞り込みの効果も計測対象とする。
+async fn seed(db: &Database, rows: usize) -> Uuid {
+    let start = Instant::now();
+    let today = Local::now().date_naive();
+    let users = ["bench", "other"];
+    for user in users {
+        db.add_user(user, "bench").await.unwrap();
+    }
+    let mut qb = QueryBuilder::<MySql>::new("insert into tag(name) ");
+    qb.push_values(0..10, |mut b, i| {
+        b.push_bind(format!("tag{}", i));
+    });
+    qb.build().execute(&db.pool).await.unwrap();
+
+    for user in users {
+        let ids = (0..rows).collect::<Vec<_>>();
+        for chunk in ids.chunks(CHUNK_SIZE) {
+            let mut qb = QueryBuilder::<MySql>::new(
+                "insert into todo(user_name, title, work, update_date, start_date, end_date, done) ",
+            );
+            qb.push_values(chunk, |mut b, &i| {
+                let start_date = today - Days::new((i % 365) as u64);
+                b.push_bind(user)
+                    .push_bind(format!("item {}", i))
+                    .push_bind(if i % 3 == 0 {
+                        Some("週次報告")
+                    } else {
+                        None
+                    })
+                    .push_bind(start_date)
+                    .push_bind(start_date)
+                    .push_bind(start_date + Days::new((i % 60) as u64))
+                    .push_bind(i % 4 != 0);
+            });
+            qb.build().execute(&db.pool).await.unwrap();
+        }
+    }
+
+    let sql =
+        "insert into todo_tag(todo_id, tag_name) select id, concat('tag', id % 10) from todo;";
+    sqlx::query(sql).execute(&db.pool).await.unwrap();
+
+    println!("データ投入時間:{:?}", start.elapsed());
+    db.make_new_session(users[0]).await.unwrap()
+}
@@ -0,0 +1,80 @@
+//! ICSフィード用の操作
+//! フィードの要求にはセッションがないため、ユーザー名で検索する。
+use super::*;
+use sqlx::{query, query_as, query_scalar};
+
+impl Database {
+    /// ユーザーのフィード用トークンを取得する。未発行ならNoneを返す。
+    pub async fn get_feed_token(&self, user_name: &str) -> Result<Option<String>, DbError> {
+        let sql = "select token from feed_tokens where user_name = ?;";
+        query_scalar::<_, String>(sql)
+            .bind(user_name)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(DbError::FailDbAccess)
+    }
+
+    /// ユーザーのフィード用トークンを設定する。既存のトークンは置き換えられる。
+    pub async fn set_feed_token(&self, user_name: &str, token: &str) -> Result<(), DbError> {
+        let sql = r#"
+            insert into feed_tokens(user_name, token) values (?, ?)
+            on duplicate key update token = values(token);
+            "#;
+        query(sql)
+            .bind(user_name)
+            .bind(token)
+            .execute(&self.pool)
+            .await
+            .map_err(DbError::FailDbAccess)?;
+        Ok(())
+    }
+
+    /// トークンから、ユーザー名を取得する。一致しなければNoneを返す。
+    pub async fn get_user_name_from_feed_token(
+        &self,
+        token: &str,
+    ) -> Result<Option<String>, DbError> {
+        let sql = "select user_name from feed_tokens where token = ?;";
+        query_scalar::<_, String>(sql)
+            .bind(token)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(DbError::FailDbAccess)
+    }
+
+    /// ユーザーの未完了のtodoを、終了日順に取得する。
+    pub async fn get_open_todo_item_of_user(
+        &self,
+        user_name: &str,
+    ) -> Result<Vec<ItemTodo>, DbError> {
+        let sql = r#"
+            select id, user_name, title, work, update_date, start_date, end_date, done, revision
+            from todo
+            where user_name = ? and done = false
+            order by end_date, id;
+            "#;
+        query_as::<_, ItemTodo>(sql)
+            .bind(user_name)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(DbError::FailDbAccess)
+    }
+
+    /// ユーザーの未完了のtodoに付与されたタグを、(todoのid, タグ名)の組で取得する。
+    pub async fn get_open_todo_tags_of_user(
+        &self,
+        user_name: &str,
+    ) -> Result<Vec<(u32, String)>, DbError> {
+        let sql = r#"
+            select tt.todo_id, tt.tag_name
+            from todo_tag tt join todo t on t.id = tt.todo_id
+            where t.user_name = ? and t.done = false
+            order by tt.todo_id, tt.tag_name;
+            "#;
+        query_as::<_, (u32, String)>(sql)
+            .bind(user_name)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(DbError::FailDbAccess)
+    }
+}
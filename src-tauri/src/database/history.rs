@@ -0,0 +1,257 @@
+//! todoの変更履歴
+use super::*;
+use sqlx::{query, query_as, query_scalar, MySql, Transaction};
+
+impl Database {
+    /// 指定ユーザーの、指定todoの変更履歴を古い順に取得する。
+    /// 削除済みのtodoの履歴も取得できる。
+    pub async fn get_todo_history(
+        &self,
+        id: u32,
+        user_name: &str,
+    ) -> Result<Vec<TodoHistory>, DbError> {
+        let sql = r#"
+            select id, todo_id, action, old_value, new_value, changed_at, session, client
+            from todo_history
+            where todo_id = ? and user_name = ?
+            order by changed_at, id;
+            "#;
+        query_as::<_, HistoryRow>(sql)
+            .bind(id)
+            .bind(user_name)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(DbError::FailDbAccess)?
+            .into_iter()
+            .map(HistoryRow::into_history)
+            .collect()
+    }
+
+    /// 指定ユーザーの変更履歴を、すべて古い順に取得する。
+    pub async fn get_user_history(&self, user_name: &str) -> Result<Vec<TodoHistory>, DbError> {
+        let sql = r#"
+            select id, todo_id, action, old_value, new_value, changed_at, session, client
+            from todo_history
+            where user_name = ?
+            order by changed_at, id;
+            "#;
+        query_as::<_, HistoryRow>(sql)
+            .bind(user_name)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(DbError::FailDbAccess)?
+            .into_iter()
+            .map(HistoryRow::into_history)
+            .collect()
+    }
+
+    /// 指定ユーザーの変更履歴のうち、idがafter_idより大きいものを、記録した順に取得する。
+    pub async fn get_history_since(
+        &self,
+        user_name: &str,
+        after_id: u64,
+    ) -> Result<Vec<TodoHistory>, DbError> {
+        let sql = r#"
+            select id, todo_id, action, old_value, new_value, changed_at, session, client
+            from todo_history
+            where user_name = ? and id > ?
+            order by id;
+            "#;
+        query_as::<_, HistoryRow>(sql)
+            .bind(user_name)
+            .bind(after_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(DbError::FailDbAccess)?
+            .into_iter()
+            .map(HistoryRow::into_history)
+            .collect()
+    }
+
+    /// 指定ユーザーの、変更履歴の件数と最新のidを取得する。
+    /// 他のプロセスによる変更の有無の確認に使用する。
+    pub async fn get_history_stamp(&self, user_name: &str) -> Result<(u64, u64), DbError> {
+        let sql = r#"
+            select cast(count(*) as unsigned), cast(coalesce(max(id), 0) as unsigned)
+            from todo_history
+            where user_name = ?;
+            "#;
+        query_as::<_, (u64, u64)>(sql)
+            .bind(user_name)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(DbError::FailDbAccess)
+    }
+
+    /// 指定ユーザーの、最新の変更履歴のidを取得する。履歴がなければ0。
+    pub async fn get_last_history_id(&self, user_name: &str) -> Result<u64, DbError> {
+        let sql =
+            "select cast(coalesce(max(id), 0) as unsigned) from todo_history where user_name = ?;";
+        query_scalar::<_, u64>(sql)
+            .bind(user_name)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(DbError::FailDbAccess)
+    }
+}
+
+/// 変更履歴の行。変更前後の内容はJSONで保存している。
+#[derive(FromRow)]
+struct HistoryRow {
+    #[sqlx(flatten)]
+    history: TodoHistory,
+    old_value: Option<String>,
+    new_value: Option<String>,
+}
+
+impl HistoryRow {
+    fn into_history(self) -> Result<TodoHistory, DbError> {
+        let decode = |json: Option<String>| {
+            json.map(|j| serde_json::from_str::<TodoSnapshot>(&j))
+                .transpose()
+                .map_err(|e| DbError::FailDbAccess(sqlx::Error::Decode(Box::new(e))))
+        };
+        Ok(TodoHistory {
+            old_value: decode(self.old_value)?,
+            new_value: decode(self.new_value)?,
+            ..self.history
+        })
+    }
+}
+
+/// 変更前の内容を取得する。更新が終わるまで、行をロックする。
+/// 戻り値は、todoの持ち主・版番号と内容。todoがなければNone。
+pub(super) async fn lock_snapshot(
+    tx: &mut Transaction<'_, MySql>,
+    id: u32,
+) -> Result<Option<(String, u32, TodoSnapshot)>, DbError> {
+    let sql = r#"
+        select user_name, revision, title, work, start_date, end_date, done
+        from todo where id = ?
+        for update;
+        "#;
+    let row = query_as::<
+        _,
+        (
+            String,
+            u32,
+            String,
+            Option<String>,
+            NaiveDate,
+            NaiveDate,
+            bool,
+        ),
+    >(sql)
+    .bind(id)
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(DbError::FailDbAccess)?;
+    Ok(row.map(
+        |(user_name, revision, title, work, start_date, end_date, done)| {
+            (
+                user_name,
+                revision,
+                TodoSnapshot {
+                    title,
+                    work,
+                    start_date,
+                    end_date,
+                    done,
+                },
+            )
+        },
+    ))
+}
+
+/// 変更履歴を一件記録する。
+pub(super) async fn record_history(
+    tx: &mut Transaction<'_, MySql>,
+    todo_id: u32,
+    user_name: &str,
+    action: HistoryAction,
+    old: Option<&TodoSnapshot>,
+    new: Option<&TodoSnapshot>,
+    source: &ChangeSource,
+) -> Result<(), DbError> {
+    let sql = r#"
+        insert into todo_history(todo_id, user_name, action, old_value, new_value, session, client)
+        values (?, ?, ?, ?, ?, ?, ?);
+        "#;
+    query(sql)
+        .bind(todo_id)
+        .bind(user_name)
+        .bind(action.to_string())
+        .bind(encode_snapshot(old))
+        .bind(encode_snapshot(new))
+        .bind(source.session.to_string())
+        .bind(&source.client)
+        .execute(&mut **tx)
+        .await
+        .map_err(DbError::FailDbAccess)?;
+    Ok(())
+}
+
+/// バックアップの変更履歴を、日時・変更元を保ったまま、指定todoの履歴として復元する。
+pub(super) async fn restore_history(
+    tx: &mut Transaction<'_, MySql>,
+    todo_id: u32,
+    user_name: &str,
+    history: &TodoHistory,
+) -> Result<(), DbError> {
+    let sql = r#"
+        insert into todo_history(todo_id, user_name, action, old_value, new_value,
+            changed_at, session, client)
+        values (?, ?, ?, ?, ?, ?, ?, ?);
+        "#;
+    query(sql)
+        .bind(todo_id)
+        .bind(user_name)
+        .bind(history.action.to_string())
+        .bind(encode_snapshot(history.old_value.as_ref()))
+        .bind(encode_snapshot(history.new_value.as_ref()))
+        .bind(history.changed_at)
+        .bind(&history.session)
+        .bind(&history.client)
+        .execute(&mut **tx)
+        .await
+        .map_err(DbError::FailDbAccess)?;
+    Ok(())
+}
+
+/// 変更前後の内容を、保存用のJSONにする。
+fn encode_snapshot(snapshot: Option<&TodoSnapshot>) -> Option<String> {
+    snapshot.map(|s| serde_json::to_string(s).expect("TodoSnapshotは必ずJSONにできる"))
+}
+
+impl std::fmt::Display for HistoryAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Create => write!(f, "create"),
+            Self::Edit => write!(f, "edit"),
+            Self::Done => write!(f, "done"),
+            Self::Delete => write!(f, "delete"),
+        }
+    }
+}
+
+impl std::str::FromStr for HistoryAction {
+    type Err = HistoryActionParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "create" => Ok(Self::Create),
+            "edit" => Ok(Self::Edit),
+            "done" => Ok(Self::Done),
+            "delete" => Ok(Self::Delete),
+            _ => Err(HistoryActionParseError::InvalidArgument),
+        }
+    }
+}
+
+impl TryFrom<String> for HistoryAction {
+    type Error = HistoryActionParseError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
@@ -0,0 +1,81 @@
+//! データベースの作成と、スキーマのマイグレーション
+
+use super::*;
+use sqlx::{migrate::Migrator, query, query_scalar, Connection, MySqlConnection};
+
+/// migrationsディレクトリのマイグレーション
+static MIGRATOR: Migrator = sqlx::migrate!();
+
+impl ConnectConfig {
+    /// データベースを選択せずにサーバーへ接続し、データベースとスキーマの状態を調べる。
+    pub async fn inspect(&self) -> Result<DbCheck, DbError> {
+        let mut conn = self.connect_server().await?;
+        let database = self.database_name();
+        let exists: i64 =
+            query_scalar("select count(*) from information_schema.schemata where schema_name = ?;")
+                .bind(database)
+                .fetch_one(&mut conn)
+                .await
+                .map_err(DbError::FailDbAccess)?;
+        let migrated: i64 = query_scalar(
+            r#"
+            select count(*) from information_schema.tables
+            where table_schema = ? and table_name = '_sqlx_migrations';"#,
+        )
+        .bind(database)
+        .fetch_one(&mut conn)
+        .await
+        .map_err(DbError::FailDbAccess)?;
+        let schema_version = if migrated > 0 {
+            let sql = format!(
+                "select max(version) from {}._sqlx_migrations where success;",
+                quote_ident(database)
+            );
+            query_scalar(&sql)
+                .fetch_one(&mut conn)
+                .await
+                .map_err(DbError::FailDbAccess)?
+        } else {
+            None
+        };
+        let _ = conn.close().await;
+        Ok(DbCheck {
+            database_exists: exists > 0,
+            schema_version,
+            required_version: SCHEMA_VERSION,
+        })
+    }
+
+    /// データベースがなければ作成する。
+    pub async fn create_database(&self) -> Result<(), DbError> {
+        let mut conn = self.connect_server().await?;
+        let sql = format!(
+            "create database if not exists {};",
+            quote_ident(self.database_name())
+        );
+        query(&sql)
+            .execute(&mut conn)
+            .await
+            .map_err(DbError::FailDbAccess)?;
+        let _ = conn.close().await;
+        Ok(())
+    }
+
+    async fn connect_server(&self) -> Result<MySqlConnection, DbError> {
+        MySqlConnection::connect_with(&self.server_options())
+            .await
+            .map_err(DbError::connect)
+    }
+}
+
+impl Database {
+    /// 未適用のマイグレーションを適用する。
+    pub async fn migrate(&self) -> Result<(), DbError> {
+        MIGRATOR.run(&self.pool).await.map_err(DbError::Migrate)
+    }
+}
+
+/// 識別子をバッククォートで囲む。
+fn quote_ident(name: &str) -> String {
+    format!("`{}`", name.replace('`', "``"))
+}
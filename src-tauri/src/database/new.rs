@@ -1,17 +1,121 @@
 //! database構造体新規作成
 
 use super::*;
+use sqlx::mysql::{MySqlConnectOptions, MySqlSslMode};
+use sqlx::query;
 
 impl Database {
     /// 新規生成。
-    pub async fn new(host: &str, user: &str, pass: &str) -> Result<Self, DbError> {
-        let db_url = format!("mariadb://{}:{}@{}/nekotodo", user, pass, host);
-        let pool = MySqlPoolOptions::new()
-            .max_connections(10)
-            .min_connections(3)
-            .connect(&db_url)
+    pub async fn new(conn: &ConnectConfig, config: &PoolConfig) -> Result<Self, DbError> {
+        let pool = pool_options(config)
+            .connect_with(conn.connect_options())
             .await
-            .map_err(DbError::FailConnect)?;
+            .map_err(DbError::connect)?;
         Ok(Self { pool })
     }
+
+    /// 接続を行わずに生成する。最初に使用するときに接続する。
+    /// データベースに接続できない状態で、起動する場合に使用する。
+    pub fn new_lazy(conn: &ConnectConfig, config: &PoolConfig) -> Self {
+        let pool = pool_options(config).connect_lazy_with(conn.connect_options());
+        Self { pool }
+    }
+
+    /// データベースに接続できるか確認する。
+    /// 切れた接続は、プールが破棄して接続し直す。
+    pub async fn health_check(&self) -> Result<(), DbError> {
+        query("select 1;")
+            .execute(&self.pool)
+            .await
+            .map_err(DbError::connect)?;
+        Ok(())
+    }
+}
+
+impl ConnectConfig {
+    /// sqlxの接続オプションを生成する。
+    /// パスワード等に記号が含まれていても、そのまま使用できる。
+    pub fn connect_options(&self) -> MySqlConnectOptions {
+        self.server_options().database(self.database_name())
+    }
+
+    /// 接続するデータベース名
+    pub fn database_name(&self) -> &str {
+        self.database.as_deref().unwrap_or(DEFAULT_DATABASE)
+    }
+
+    /// 接続に必要な情報がそろっているか。
+    /// パスワードは、ソケット接続(unix_socket認証)では省略できる。
+    pub fn is_complete(&self) -> bool {
+        (!self.host.is_empty() || self.socket.is_some())
+            && !self.user.is_empty()
+            && (!self.pass.is_empty() || self.socket.is_some())
+    }
+
+    /// データベースを選択せずに、サーバーへ接続する接続オプションを生成する。
+    /// データベースの作成前に使用する。
+    pub(super) fn server_options(&self) -> MySqlConnectOptions {
+        let mut options = MySqlConnectOptions::new()
+            .username(&self.user)
+            .password(self.pass.expose())
+            .ssl_mode(self.ssl_mode.into());
+        options = match self.socket {
+            Some(ref socket) => options.socket(socket),
+            None => options.host(&self.host),
+        };
+        if let Some(port) = self.port {
+            options = options.port(port);
+        }
+        if let Some(ref ca) = self.ssl_ca {
+            options = options.ssl_ca(ca);
+        }
+        options
+    }
+}
+
+impl From<SslMode> for MySqlSslMode {
+    fn from(value: SslMode) -> Self {
+        match value {
+            SslMode::Disabled => Self::Disabled,
+            SslMode::Preferred => Self::Preferred,
+            SslMode::Required => Self::Required,
+            SslMode::VerifyCa => Self::VerifyCa,
+            SslMode::VerifyIdentity => Self::VerifyIdentity,
+        }
+    }
+}
+
+impl std::fmt::Display for SslMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Disabled => write!(f, "disabled"),
+            Self::Preferred => write!(f, "preferred"),
+            Self::Required => write!(f, "required"),
+            Self::VerifyCa => write!(f, "verify-ca"),
+            Self::VerifyIdentity => write!(f, "verify-identity"),
+        }
+    }
+}
+
+impl std::str::FromStr for SslMode {
+    type Err = SslModeParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "disabled" => Ok(Self::Disabled),
+            "preferred" => Ok(Self::Preferred),
+            "required" => Ok(Self::Required),
+            "verify-ca" => Ok(Self::VerifyCa),
+            "verify-identity" => Ok(Self::VerifyIdentity),
+            _ => Err(SslModeParseError::InvalidArgument),
+        }
+    }
+}
+
+fn pool_options(config: &PoolConfig) -> MySqlPoolOptions {
+    MySqlPoolOptions::new()
+        .max_connections(config.max_connections)
+        .min_connections(config.min_connections.min(config.max_connections))
+        .acquire_timeout(config.acquire_timeout)
+        .test_before_acquire(true)
 }
@@ -0,0 +1,99 @@
+//! todo一覧のページング(キーセット方式)
+use super::*;
+
+/// カーソル文字列中の日付の書式
+const CURSOR_DATE_FORMAT: &str = "%Y-%m-%d";
+
+impl TodoCursor {
+    /// アイテムの位置を示すカーソルを生成する。
+    pub fn from_item(item: &ItemTodo, sort_order: ItemSortOrder) -> Self {
+        let [(col1, _), (col2, _)] = sort_columns(sort_order);
+        Self {
+            key1: sort_value(item, col1),
+            key2: sort_value(item, col2),
+            id: item.id,
+        }
+    }
+}
+
+impl std::fmt::Display for TodoCursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}|{}|{}",
+            self.key1.format(CURSOR_DATE_FORMAT),
+            self.key2.format(CURSOR_DATE_FORMAT),
+            self.id
+        )
+    }
+}
+
+impl std::str::FromStr for TodoCursor {
+    type Err = CursorParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split('|');
+        let (Some(key1), Some(key2), Some(id), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(CursorParseError::InvalidFormat);
+        };
+        let parse_date = |d| {
+            NaiveDate::parse_from_str(d, CURSOR_DATE_FORMAT)
+                .map_err(|_| CursorParseError::InvalidFormat)
+        };
+        Ok(Self {
+            key1: parse_date(key1)?,
+            key2: parse_date(key2)?,
+            id: id.parse().map_err(|_| CursorParseError::InvalidFormat)?,
+        })
+    }
+}
+
+/// ソート順に対応する、ソートキーの列名と降順かどうかの組。
+/// 同じキーの中では、idの昇順とする。
+pub(super) fn sort_columns(sort_order: ItemSortOrder) -> [(&'static str, bool); 2] {
+    match sort_order {
+        ItemSortOrder::EndAsc => [("end_date", false), ("update_date", false)],
+        ItemSortOrder::EndDesc => [("end_date", true), ("update_date", false)],
+        ItemSortOrder::StartAsc => [("start_date", false), ("update_date", false)],
+        ItemSortOrder::StartDesc => [("start_date", true), ("update_date", false)],
+        ItemSortOrder::UpdateAsc => [("update_date", false), ("end_date", false)],
+        ItemSortOrder::UpdateDesc => [("update_date", true), ("end_date", false)],
+    }
+}
+
+/// ソート順に対応するorder by句
+pub(super) fn order_by_clause(sort_order: ItemSortOrder) -> String {
+    let [(col1, desc1), (col2, desc2)] = sort_columns(sort_order);
+    format!(
+        " order by t.{}{}, t.{}{}, t.id",
+        col1,
+        if desc1 { " desc" } else { "" },
+        col2,
+        if desc2 { " desc" } else { "" },
+    )
+}
+
+/// カーソルより後ろのアイテムを選択する条件式。
+/// パラメータは、key1, key1, key2, key2, idの順にバインドする。
+pub(super) fn after_cursor_clause(sort_order: ItemSortOrder) -> String {
+    let [(col1, desc1), (col2, desc2)] = sort_columns(sort_order);
+    let op = |desc| if desc { "<" } else { ">" };
+    format!(
+        "(t.{col1} {} ? or (t.{col1} = ? and (t.{col2} {} ? or (t.{col2} = ? and t.id > ?))))",
+        op(desc1),
+        op(desc2),
+    )
+}
+
+/// 列名に対応するアイテムの日付
+fn sort_value(item: &ItemTodo, column: &str) -> NaiveDate {
+    let value = match column {
+        "start_date" => item.start_date,
+        "end_date" => item.end_date,
+        "update_date" => item.update_date,
+        _ => unreachable!("[sort_value]不明な列名[{column}]"),
+    };
+    value.unwrap_or_default()
+}
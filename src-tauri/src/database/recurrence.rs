@@ -0,0 +1,23 @@
+//! todoの繰り返し規則の操作
+use super::*;
+use sqlx::query_as;
+use uuid::Uuid;
+
+impl Database {
+    /// セッションの持ち主のtodoの繰り返し規則を、(todoのid, 規則)の組で、id順に取得する。
+    pub async fn get_todo_recurrences(&self, sess: Uuid) -> Result<Vec<(u32, String)>, DbError> {
+        let sql = r#"
+            select r.todo_id, r.rrule
+            from todo_recurrence r
+            join todo t on t.id = r.todo_id
+            join sessions s on s.user_name = t.user_name
+            where s.id = ?
+            order by r.todo_id;
+            "#;
+        query_as::<_, (u32, String)>(sql)
+            .bind(sess.to_string())
+            .fetch_all(&self.pool)
+            .await
+            .map_err(DbError::FailDbAccess)
+    }
+}
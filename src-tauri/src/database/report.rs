@@ -0,0 +1,70 @@
+//! 作業報告用のtodoの取得
+use super::*;
+use sqlx::query_as;
+use uuid::Uuid;
+
+/// 作業報告用のtodoの取得列
+const REPORT_SELECT: &str = r#"
+    select t.id, t.title, t.work, t.start_date, t.end_date, t.create_date, t.done_date
+    from todo t join sessions s on s.user_name = t.user_name
+    "#;
+
+impl Database {
+    /// セッションの持ち主のtodoのうち、期間内(両端を含む)に完了したものを、完了日順に取得する。
+    pub async fn get_completed_todo_item(
+        &self,
+        sess: Uuid,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<ReportTodoItem>, DbError> {
+        let sql = format!(
+            "{REPORT_SELECT} where s.id = ? and t.done and t.done_date between ? and ? \
+             order by t.done_date, t.id;"
+        );
+        query_as::<_, ReportTodoItem>(&sql)
+            .bind(sess.to_string())
+            .bind(from)
+            .bind(to)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(DbError::FailDbAccess)
+    }
+
+    /// セッションの持ち主のtodoのうち、期間内(両端を含む)に作成したものを、作成日順に取得する。
+    pub async fn get_created_todo_item(
+        &self,
+        sess: Uuid,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<ReportTodoItem>, DbError> {
+        let sql = format!(
+            "{REPORT_SELECT} where s.id = ? and t.create_date between ? and ? \
+             order by t.create_date, t.id;"
+        );
+        query_as::<_, ReportTodoItem>(&sql)
+            .bind(sess.to_string())
+            .bind(from)
+            .bind(to)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(DbError::FailDbAccess)
+    }
+
+    /// セッションの持ち主の未完了のtodoのうち、終了日がuntilより前のものを、終了日順に取得する。
+    pub async fn get_overdue_todo_item(
+        &self,
+        sess: Uuid,
+        until: NaiveDate,
+    ) -> Result<Vec<ReportTodoItem>, DbError> {
+        let sql = format!(
+            "{REPORT_SELECT} where s.id = ? and not t.done and t.end_date < ? \
+             order by t.end_date, t.id;"
+        );
+        query_as::<_, ReportTodoItem>(&sql)
+            .bind(sess.to_string())
+            .bind(until)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(DbError::FailDbAccess)
+    }
+}
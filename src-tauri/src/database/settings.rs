@@ -0,0 +1,61 @@
+//! ユーザー設定の操作
+use super::*;
+use sqlx::{query, query_as};
+
+impl UserSettings {
+    /// 設定が未保存のユーザーに適用する初期値
+    pub fn new(user_name: &str) -> Self {
+        Self {
+            user_name: user_name.to_string(),
+            only_incomplete: true,
+            item_sort_order: ItemSortOrder::EndAsc,
+            default_start_offset: 0,
+            default_end_offset: None,
+            theme: Theme::System,
+        }
+    }
+}
+
+impl Database {
+    /// ユーザーの設定を取得する。
+    /// 設定が保存されていなければ、初期値を返す。
+    pub async fn get_user_settings(&self, user_name: &str) -> Result<UserSettings, DbError> {
+        let sql = r#"
+            select user_name, only_incomplete, item_sort_order,
+                default_start_offset, default_end_offset, theme
+            from user_settings where user_name = ?;
+            "#;
+        let settings = query_as::<_, UserSettings>(sql)
+            .bind(user_name)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(DbError::FailDbAccess)?;
+        Ok(settings.unwrap_or_else(|| UserSettings::new(user_name)))
+    }
+
+    /// ユーザーの設定を保存する。
+    pub async fn save_user_settings(&self, settings: &UserSettings) -> Result<(), DbError> {
+        let sql = r#"
+            insert into user_settings(user_name, only_incomplete, item_sort_order,
+                default_start_offset, default_end_offset, theme)
+            values (?, ?, ?, ?, ?, ?)
+            on duplicate key update
+                only_incomplete = values(only_incomplete),
+                item_sort_order = values(item_sort_order),
+                default_start_offset = values(default_start_offset),
+                default_end_offset = values(default_end_offset),
+                theme = values(theme);
+            "#;
+        query(sql)
+            .bind(&settings.user_name)
+            .bind(settings.only_incomplete)
+            .bind(settings.item_sort_order.to_string())
+            .bind(settings.default_start_offset)
+            .bind(settings.default_end_offset)
+            .bind(settings.theme.to_string())
+            .execute(&self.pool)
+            .await
+            .map_err(DbError::FailDbAccess)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,49 @@
+//! タグの操作
+use super::*;
+use sqlx::{query, query_as};
+use uuid::Uuid;
+
+impl Database {
+    /// セッションの持ち主のtodoに付与されたタグを、(todoのid, タグ名)の組で取得する。
+    /// todoのid・タグ名の順に並べる。
+    pub async fn get_todo_tags(&self, sess: Uuid) -> Result<Vec<(u32, String)>, DbError> {
+        let sql = r#"
+            select tt.todo_id, tt.tag_name
+            from todo_tag tt
+            join todo t on t.id = tt.todo_id
+            join sessions s on s.user_name = t.user_name
+            where s.id = ?
+            order by tt.todo_id, tt.tag_name;
+            "#;
+        query_as::<_, (u32, String)>(sql)
+            .bind(sess.to_string())
+            .fetch_all(&self.pool)
+            .await
+            .map_err(DbError::FailDbAccess)
+    }
+
+    /// todoのタグを、指定のタグに置き換える。
+    /// 存在しないタグは、新たに登録する。
+    pub async fn set_todo_tags(&self, id: u32, tags: &[String]) -> Result<(), DbError> {
+        let mut tx = self.pool.begin().await.map_err(DbError::FailDbAccess)?;
+        query("delete from todo_tag where todo_id = ?;")
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .map_err(DbError::FailDbAccess)?;
+        for tag in tags {
+            query("insert ignore into tag(name) values (?);")
+                .bind(tag)
+                .execute(&mut *tx)
+                .await
+                .map_err(DbError::FailDbAccess)?;
+            query("insert ignore into todo_tag(todo_id, tag_name) values (?, ?);")
+                .bind(id)
+                .bind(tag)
+                .execute(&mut *tx)
+                .await
+                .map_err(DbError::FailDbAccess)?;
+        }
+        tx.commit().await.map_err(DbError::FailDbAccess)
+    }
+}
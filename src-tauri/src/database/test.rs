@@ -1,8 +1,9 @@
 //! databaseモジュールテスト
 
 use crate::config::ItemSortOrder;
+use crate::filter::TodoFilter;
 use chrono::{Days, Local};
-use sqlx::query;
+use sqlx::{mysql::MySqlSslMode, query};
 use uuid::Uuid;
 
 use super::*;
@@ -14,6 +15,14 @@ impl Database {
     }
 }
 
+/// テスト用の変更元
+fn test_source() -> ChangeSource {
+    ChangeSource {
+        session: Uuid::nil(),
+        client: "test".to_string(),
+    }
+}
+
 /// ユーザー生成のテスト
 #[sqlx::test]
 async fn test_add_user_and_get_user(pool: MySqlPool) {
@@ -103,15 +112,24 @@ async fn test_add_todo(pool: MySqlPool) {
         start_date: Some(Local::now().date_naive()),
         end_date: Some(Local::now().date_naive() + Days::new(3)),
         done: true,
+        revision: 0,
     };
-    db.add_todo_item(&item).await.unwrap();
+    db.add_todo_item(&item, &test_source()).await.unwrap();
 
     println!("テストデータを読み出す。一件しかないはず");
     let last_day = Local::now().date_naive() + Days::new(1);
     let res = db
-        .get_todo_item(sess, last_day, true, ItemSortOrder::EndAsc)
+        .get_todo_item(
+            sess,
+            last_day,
+            true,
+            ItemSortOrder::EndAsc,
+            &TodoFilter::default(),
+            &PageRequest::default(),
+        )
         .await
-        .unwrap();
+        .unwrap()
+        .items;
     assert_eq!(res.len(), 1, "あれ?一件のはずだよ");
     item.id = res[0].id;
     item.update_date = Some(Local::now().date_naive());
@@ -136,15 +154,24 @@ async fn test_add_todo_without_work(pool: MySqlPool) {
         start_date: Some(Local::now().date_naive()),
         end_date: Some(Local::now().date_naive() + Days::new(3)),
         done: true,
+        revision: 0,
     };
-    db.add_todo_item(&item).await.unwrap();
+    db.add_todo_item(&item, &test_source()).await.unwrap();
 
     println!("テストデータを読み出す。一件しかないはず");
     let last_day = Local::now().date_naive() + Days::new(1);
     let res = db
-        .get_todo_item(sess, last_day, true, ItemSortOrder::EndAsc)
+        .get_todo_item(
+            sess,
+            last_day,
+            true,
+            ItemSortOrder::EndAsc,
+            &TodoFilter::default(),
+            &PageRequest::default(),
+        )
         .await
-        .unwrap();
+        .unwrap()
+        .items;
     assert_eq!(res.len(), 1, "あれ?一件のはずだよ");
     item.id = res[0].id;
     item.update_date = Some(Local::now().date_naive());
@@ -169,34 +196,67 @@ async fn test_get_todo_done_param(pool: MySqlPool) {
         start_date: Some(Local::now().date_naive()),
         end_date: Some(Local::now().date_naive() + Days::new(3)),
         done: true,
+        revision: 0,
     };
-    db.add_todo_item(&item).await.unwrap();
+    db.add_todo_item(&item, &test_source()).await.unwrap();
 
     println!("テストデータを読み出す。一件しかないはず");
     let last_day = Local::now().date_naive() + Days::new(1);
     let res = db
-        .get_todo_item(sess, last_day, false, ItemSortOrder::EndAsc)
+        .get_todo_item(
+            sess,
+            last_day,
+            false,
+            ItemSortOrder::EndAsc,
+            &TodoFilter::default(),
+            &PageRequest::default(),
+        )
         .await
-        .unwrap();
+        .unwrap()
+        .items;
     assert_eq!(res.len(), 1, "全部読み出しだけど一件あるはず。");
     let res = db
-        .get_todo_item(sess, last_day, true, ItemSortOrder::EndAsc)
+        .get_todo_item(
+            sess,
+            last_day,
+            true,
+            ItemSortOrder::EndAsc,
+            &TodoFilter::default(),
+            &PageRequest::default(),
+        )
         .await
-        .unwrap();
+        .unwrap()
+        .items;
     assert_eq!(res.len(), 1, "未完了だけだけど、一件あるはず。");
 
     println!("今作ったjobを完了済みにする。");
     let sql = "update todo set done=true where id=?;";
     query(sql).bind(res[0].id).execute(&pool).await.unwrap();
     let res = db
-        .get_todo_item(sess, last_day, false, ItemSortOrder::EndAsc)
+        .get_todo_item(
+            sess,
+            last_day,
+            false,
+            ItemSortOrder::EndAsc,
+            &TodoFilter::default(),
+            &PageRequest::default(),
+        )
         .await
-        .unwrap();
+        .unwrap()
+        .items;
     assert_eq!(res.len(), 1, "全部読み出しだけど一件あるはず。");
     let res = db
-        .get_todo_item(sess, last_day, true, ItemSortOrder::EndAsc)
+        .get_todo_item(
+            sess,
+            last_day,
+            true,
+            ItemSortOrder::EndAsc,
+            &TodoFilter::default(),
+            &PageRequest::default(),
+        )
         .await
-        .unwrap();
+        .unwrap()
+        .items;
     assert_eq!(res.len(), 0, "未完了だけだけだから、なにもないはず。");
 }
 
@@ -218,29 +278,62 @@ async fn test_get_todo_ref_date(pool: MySqlPool) {
         start_date: Some(Local::now().date_naive()),
         end_date: Some(Local::now().date_naive() + Days::new(3)),
         done: false,
+        revision: 0,
     };
-    db.add_todo_item(&item).await.unwrap();
+    db.add_todo_item(&item, &test_source()).await.unwrap();
 
     let ref_date = Local::now().date_naive();
     let res = db
-        .get_todo_item(sess, ref_date, true, ItemSortOrder::EndAsc)
+        .get_todo_item(
+            sess,
+            ref_date,
+            true,
+            ItemSortOrder::EndAsc,
+            &TodoFilter::default(),
+            &PageRequest::default(),
+        )
         .await
-        .unwrap();
+        .unwrap()
+        .items;
     assert_eq!(res.len(), 1, "基準日と開始日が同じだからみつかる。");
     let res = db
-        .get_todo_item(sess, ref_date + Days::new(1), true, ItemSortOrder::EndAsc)
+        .get_todo_item(
+            sess,
+            ref_date + Days::new(1),
+            true,
+            ItemSortOrder::EndAsc,
+            &TodoFilter::default(),
+            &PageRequest::default(),
+        )
         .await
-        .unwrap();
+        .unwrap()
+        .items;
     assert_eq!(res.len(), 1, "開始日の翌日が基準日だからみつかる。");
     let res = db
-        .get_todo_item(sess, ref_date - Days::new(1), true, ItemSortOrder::EndAsc)
+        .get_todo_item(
+            sess,
+            ref_date - Days::new(1),
+            true,
+            ItemSortOrder::EndAsc,
+            &TodoFilter::default(),
+            &PageRequest::default(),
+        )
         .await
-        .unwrap();
+        .unwrap()
+        .items;
     assert_eq!(res.len(), 0, "基準日が開始日の前日だからみつからない。");
     let res = db
-        .get_todo_item(sess, ref_date + Days::new(4), true, ItemSortOrder::EndAsc)
+        .get_todo_item(
+            sess,
+            ref_date + Days::new(4),
+            true,
+            ItemSortOrder::EndAsc,
+            &TodoFilter::default(),
+            &PageRequest::default(),
+        )
         .await
-        .unwrap();
+        .unwrap()
+        .items;
     assert_eq!(res.len(), 1, "基準日が期限を過ぎているけどみつかるの。");
 }
 
@@ -270,23 +363,49 @@ async fn test_change_done(pool: MySqlPool) {
     create_todo_for_test(&db, sess).await;
 
     let items = db
-        .get_todo_item(sess, ref_date, true, ItemSortOrder::EndAsc)
+        .get_todo_item(
+            sess,
+            ref_date,
+            true,
+            ItemSortOrder::EndAsc,
+            &TodoFilter::default(),
+            &PageRequest::default(),
+        )
         .await
-        .unwrap();
+        .unwrap()
+        .items;
     let item = items.iter().find(|&i| i.title.contains("二件目")).unwrap();
-    db.change_done(item.id, true).await.unwrap();
+    db.change_done(item.id, true, item.revision, &test_source())
+        .await
+        .unwrap();
 
     let items = db
-        .get_todo_item(sess, ref_date, true, ItemSortOrder::EndAsc)
+        .get_todo_item(
+            sess,
+            ref_date,
+            true,
+            ItemSortOrder::EndAsc,
+            &TodoFilter::default(),
+            &PageRequest::default(),
+        )
         .await
-        .unwrap();
+        .unwrap()
+        .items;
     let item = items.iter().find(|&i| i.title.contains("二件目"));
     assert!(item.is_none(), "状態を完了にしたので見つからないはず。");
 
     let items = db
-        .get_todo_item(sess, ref_date, false, ItemSortOrder::EndAsc)
+        .get_todo_item(
+            sess,
+            ref_date,
+            false,
+            ItemSortOrder::EndAsc,
+            &TodoFilter::default(),
+            &PageRequest::default(),
+        )
         .await
-        .unwrap();
+        .unwrap()
+        .items;
     let item = items.iter().find(|&i| i.title.contains("二件目"));
     match item {
         Some(i) => assert!(i.done, "完了済みになっているはずですね?"),
@@ -311,9 +430,12 @@ async fn test_get_todo_with_id(pool: MySqlPool) {
             Local::now().date_naive(),
             false,
             ItemSortOrder::EndAsc,
+            &TodoFilter::default(),
+            &PageRequest::default(),
         )
         .await
-        .unwrap();
+        .unwrap()
+        .items;
     let id = items
         .iter()
         .find(|&i| i.title.contains("一件目"))
@@ -357,9 +479,17 @@ async fn test_edit(pool: MySqlPool) {
     // 書き込みテスト用レコードの取得
     let today = Local::now().date_naive();
     let items = db
-        .get_todo_item(sess, today, false, ItemSortOrder::EndAsc)
+        .get_todo_item(
+            sess,
+            today,
+            false,
+            ItemSortOrder::EndAsc,
+            &TodoFilter::default(),
+            &PageRequest::default(),
+        )
         .await
-        .unwrap();
+        .unwrap()
+        .items;
     let mut item = items
         .iter()
         .find(|&i| i.title.contains("一件目"))
@@ -369,12 +499,22 @@ async fn test_edit(pool: MySqlPool) {
     item.work = Some("書き換え後".to_string());
     item.start_date = Some(today - Days::new(5));
     item.end_date = Some(today + Days::new(10));
-    db.edit_todo(&item).await.expect("更新がエラーを起こした。");
+    db.edit_todo(&item, &test_source())
+        .await
+        .expect("更新がエラーを起こした。");
     // 書き込み後の照合
     let items_new = db
-        .get_todo_item(sess, today, false, ItemSortOrder::EndAsc)
+        .get_todo_item(
+            sess,
+            today,
+            false,
+            ItemSortOrder::EndAsc,
+            &TodoFilter::default(),
+            &PageRequest::default(),
+        )
         .await
-        .unwrap();
+        .unwrap()
+        .items;
     let item_new = items_new
         .iter()
         .find(|&i| i.title.contains("更新しました。"))
@@ -398,7 +538,7 @@ async fn test_edit(pool: MySqlPool) {
     // 存在しないレコードの更新
     let id_max_plus_one = items.iter().max_by_key(|&i| i.id).unwrap().id + 1;
     item.id = id_max_plus_one;
-    let res = db.edit_todo(&item).await;
+    let res = db.edit_todo(&item, &test_source()).await;
     match res {
         Ok(_) => unreachable!("更新できちゃだめっ"),
         Err(DbError::NotFoundTodo) => {}
@@ -406,6 +546,182 @@ async fn test_edit(pool: MySqlPool) {
     }
 }
 
+#[sqlx::test]
+async fn test_todo_history(pool: MySqlPool) {
+    let db = Database::new_test(pool);
+    let user_name = "nekodayo";
+    db.add_user(user_name, "password").await.unwrap();
+    let source = ChangeSource {
+        session: Uuid::now_v7(),
+        client: "cli".to_string(),
+    };
+
+    let today = Local::now().date_naive();
+    let mut item = ItemTodo {
+        id: 0,
+        user_name: user_name.to_string(),
+        title: "履歴のテスト".to_string(),
+        work: None,
+        update_date: None,
+        start_date: Some(today),
+        end_date: Some(today + Days::new(3)),
+        done: false,
+        revision: 0,
+    };
+    let id = db.add_todo_item(&item, &source).await.unwrap();
+    item.id = id;
+    item.work = Some("内容を追加".to_string());
+    let change = db.edit_todo(&item, &source).await.unwrap();
+    assert_eq!(change.before.work, None, "変更前の内容を返す");
+    assert_eq!(change.after.work.as_deref(), Some("内容を追加"));
+    item.revision = change.revision;
+    // 内容が変わらない編集・完了状態の変更は記録しない。
+    let change = db.edit_todo(&item, &source).await.unwrap();
+    assert_eq!(change.revision, item.revision);
+    assert_eq!(change.before, change.after);
+    let revision = db
+        .change_done(id, false, item.revision, &source)
+        .await
+        .unwrap()
+        .revision;
+    let change = db.change_done(id, true, revision, &source).await.unwrap();
+    assert!(!change.before.done && change.after.done);
+    assert_eq!(change.after.done_date, Some(today));
+    let deleted = db.delete_todo(id, &source).await.unwrap();
+    assert_eq!(deleted, change.after, "削除前の内容を返す");
+
+    let history = db.get_todo_history(id, user_name).await.unwrap();
+    let actions = history.iter().map(|h| h.action).collect::<Vec<_>>();
+    assert_eq!(
+        actions,
+        [
+            HistoryAction::Create,
+            HistoryAction::Edit,
+            HistoryAction::Done,
+            HistoryAction::Delete
+        ]
+    );
+    assert!(history.iter().all(|h| h.todo_id == id
+        && h.client == "cli"
+        && h.session == Some(source.session.to_string())));
+    assert!(
+        history
+            .windows(2)
+            .all(|w| w[0].changed_at <= w[1].changed_at),
+        "古い順に並ぶ"
+    );
+
+    let created = history[0].new_value.as_ref().unwrap();
+    assert_eq!(history[0].old_value, None);
+    assert_eq!(created.title, "履歴のテスト");
+    assert_eq!(created.work, None);
+    assert_eq!(created.end_date, today + Days::new(3));
+
+    let edited_old = history[1].old_value.as_ref().unwrap();
+    let edited_new = history[1].new_value.as_ref().unwrap();
+    assert_eq!(edited_old, created);
+    assert_eq!(edited_new.work.as_deref(), Some("内容を追加"));
+
+    assert!(!history[2].old_value.as_ref().unwrap().done);
+    assert!(history[2].new_value.as_ref().unwrap().done);
+
+    assert!(history[3].old_value.as_ref().unwrap().done);
+    assert_eq!(history[3].new_value, None);
+
+    // 削除済みのtodoは操作できないが、履歴は残る。
+    match db.delete_todo(id, &source).await {
+        Err(DbError::NotFoundTodo) => {}
+        res => unreachable!("削除済みのtodoは削除できない。{res:?}"),
+    }
+    match db.change_done(id, false, 0, &source).await {
+        Err(DbError::NotFoundTodo) => {}
+        res => unreachable!("削除済みのtodoは変更できない。{res:?}"),
+    }
+    assert_eq!(db.get_todo_history(id, user_name).await.unwrap().len(), 4);
+
+    // 他のユーザーからは見えない。
+    assert!(db
+        .get_todo_history(id, "detarame")
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[sqlx::test]
+async fn test_revision_conflict(pool: MySqlPool) {
+    let db = Database::new_test(pool);
+    let sess = login_for_test(&db).await;
+    create_todo_for_test(&db, sess).await;
+
+    let today = Local::now().date_naive();
+    let item = db
+        .get_todo_item(
+            sess,
+            today,
+            false,
+            ItemSortOrder::EndAsc,
+            &TodoFilter::default(),
+            &PageRequest::default(),
+        )
+        .await
+        .unwrap()
+        .items
+        .remove(0);
+    assert_eq!(item.revision, 0, "追加直後の版番号は0");
+
+    // 二つの端末が、同じ版を元に編集する。
+    let mut desktop = item.clone();
+    desktop.title = "デスクトップで編集".to_string();
+    let mut laptop = item.clone();
+    laptop.title = "ノートで編集".to_string();
+
+    let revision = db
+        .edit_todo(&desktop, &test_source())
+        .await
+        .unwrap()
+        .revision;
+    assert_eq!(revision, 1);
+    match db.edit_todo(&laptop, &test_source()).await {
+        Err(DbError::Conflict) => {}
+        res => unreachable!("古い版での編集は競合になる。{res:?}"),
+    }
+    match db
+        .change_done(item.id, true, item.revision, &test_source())
+        .await
+    {
+        Err(DbError::Conflict) => {}
+        res => unreachable!("古い版での完了は競合になる。{res:?}"),
+    }
+    let current = db.get_todo_item_with_id(item.id, sess).await.unwrap();
+    assert_eq!(
+        current.title, "デスクトップで編集",
+        "後の編集で上書きされない"
+    );
+    assert_eq!(current.revision, 1);
+    assert!(!current.done);
+
+    // 最新の版を元にすれば更新できる。
+    laptop.revision = current.revision;
+    assert_eq!(
+        db.edit_todo(&laptop, &test_source())
+            .await
+            .unwrap()
+            .revision,
+        2
+    );
+    assert_eq!(
+        db.change_done(item.id, true, 2, &test_source())
+            .await
+            .unwrap()
+            .revision,
+        3
+    );
+    let current = db.get_todo_item_with_id(item.id, sess).await.unwrap();
+    assert_eq!(current.title, "ノートで編集");
+    assert!(current.done);
+    assert_eq!(current.revision, 3);
+}
+
 #[sqlx::test]
 async fn test_sort_end_date(pool: MySqlPool) {
     let db = Database::new_test(pool);
@@ -414,9 +730,17 @@ async fn test_sort_end_date(pool: MySqlPool) {
 
     let today = Local::now().date_naive();
     let recs = db
-        .get_todo_item(sess, today, false, ItemSortOrder::EndAsc)
+        .get_todo_item(
+            sess,
+            today,
+            false,
+            ItemSortOrder::EndAsc,
+            &TodoFilter::default(),
+            &PageRequest::default(),
+        )
         .await
-        .expect("取得時にエラーを起こした。");
+        .expect("取得時にエラーを起こした。")
+        .items;
     eprintln!("取得データ(昇順)");
     eprintln!("0 => {:?}", recs[0]);
     eprintln!("1 => {:?}", recs[1]);
@@ -431,9 +755,17 @@ async fn test_sort_end_date(pool: MySqlPool) {
     );
 
     let recs = db
-        .get_todo_item(sess, today, false, ItemSortOrder::EndDesc)
+        .get_todo_item(
+            sess,
+            today,
+            false,
+            ItemSortOrder::EndDesc,
+            &TodoFilter::default(),
+            &PageRequest::default(),
+        )
         .await
-        .expect("取得時にエラーを起こした(2)");
+        .expect("取得時にエラーを起こした(2)")
+        .items;
     eprintln!("取得データ(降順)");
     eprintln!("0 => {:?}", recs[0]);
     eprintln!("1 => {:?}", recs[1]);
@@ -456,9 +788,17 @@ async fn test_sort_start_date(pool: MySqlPool) {
 
     let today = Local::now().date_naive();
     let recs = db
-        .get_todo_item(sess, today, false, ItemSortOrder::StartAsc)
+        .get_todo_item(
+            sess,
+            today,
+            false,
+            ItemSortOrder::StartAsc,
+            &TodoFilter::default(),
+            &PageRequest::default(),
+        )
         .await
-        .expect("取得時にエラーを起こした。");
+        .expect("取得時にエラーを起こした。")
+        .items;
     assert!(
         recs[0].start_date <= recs[1].start_date,
         "開始日が昇順になってない。"
@@ -469,9 +809,17 @@ async fn test_sort_start_date(pool: MySqlPool) {
     );
 
     let recs = db
-        .get_todo_item(sess, today, false, ItemSortOrder::StartDesc)
+        .get_todo_item(
+            sess,
+            today,
+            false,
+            ItemSortOrder::StartDesc,
+            &TodoFilter::default(),
+            &PageRequest::default(),
+        )
         .await
-        .expect("取得時にエラーを起こした(2)");
+        .expect("取得時にエラーを起こした(2)")
+        .items;
     assert!(
         recs[0].start_date >= recs[1].start_date,
         "開始日が降順になってない(1)"
@@ -491,9 +839,17 @@ async fn test_sort_update_date(pool: MySqlPool) {
 
     // Databaseのインターフェースでupdate_dateを更新するすべはないので直接編集
     let keys = db
-        .get_todo_item(sess, today, false, ItemSortOrder::EndAsc)
+        .get_todo_item(
+            sess,
+            today,
+            false,
+            ItemSortOrder::EndAsc,
+            &TodoFilter::default(),
+            &PageRequest::default(),
+        )
         .await
         .unwrap()
+        .items
         .iter()
         .map(|r| r.id)
         .collect::<Vec<_>>();
@@ -513,9 +869,17 @@ async fn test_sort_update_date(pool: MySqlPool) {
     }
 
     let recs = db
-        .get_todo_item(sess, today, false, ItemSortOrder::UpdateAsc)
+        .get_todo_item(
+            sess,
+            today,
+            false,
+            ItemSortOrder::UpdateAsc,
+            &TodoFilter::default(),
+            &PageRequest::default(),
+        )
         .await
-        .expect("取得時にエラーを起こした。");
+        .expect("取得時にエラーを起こした。")
+        .items;
     assert!(
         recs[0].update_date <= recs[1].update_date,
         "更新日が昇順になってない。"
@@ -526,9 +890,17 @@ async fn test_sort_update_date(pool: MySqlPool) {
     );
 
     let recs = db
-        .get_todo_item(sess, today, false, ItemSortOrder::UpdateDesc)
+        .get_todo_item(
+            sess,
+            today,
+            false,
+            ItemSortOrder::UpdateDesc,
+            &TodoFilter::default(),
+            &PageRequest::default(),
+        )
         .await
-        .expect("取得時にエラーを起こした(2)");
+        .expect("取得時にエラーを起こした(2)")
+        .items;
     assert!(
         recs[0].update_date >= recs[1].update_date,
         "更新日が降順になってない(1)"
@@ -539,6 +911,302 @@ async fn test_sort_update_date(pool: MySqlPool) {
     );
 }
 
+#[sqlx::test]
+async fn test_get_todo_filter(pool: MySqlPool) {
+    let db = Database::new_test(pool);
+    let sess = login_for_test(&db).await;
+    create_todo_for_test(&db, sess).await;
+    let today = Local::now().date_naive();
+
+    let get = |filter: &str| {
+        let filter = filter.parse::<TodoFilter>().unwrap();
+        let db = db.clone();
+        async move {
+            db.get_todo_item(
+                sess,
+                today,
+                false,
+                ItemSortOrder::EndAsc,
+                &filter,
+                &PageRequest::default(),
+            )
+            .await
+            .expect("取得時にエラーを起こした。")
+            .items
+        }
+    };
+
+    let recs = get("due<=2d").await;
+    assert_eq!(recs.len(), 2, "期限が2日以内のものは2件");
+    let recs = get("work:働いて").await;
+    assert_eq!(recs.len(), 1, "workに「働いて」を含むのは一件目だけ");
+    let recs = get("-work:働いて").await;
+    assert_eq!(recs.len(), 2, "workがnullのものも否定条件には含まれる");
+    let recs = get("三件目").await;
+    assert_eq!(recs.len(), 1, "タイトルに「三件目」を含むのは一件");
+    assert!(recs[0].title.contains("三件目"));
+    let recs = get("done").await;
+    assert_eq!(recs.len(), 0, "完了済みはまだない");
+    let recs = get("%").await;
+    assert_eq!(
+        recs.len(),
+        0,
+        "ワイルドカードはエスケープされるので一致しない"
+    );
+
+    // タグの絞り込み
+    let id = recs_id(&get("一件目").await);
+    query("insert into tag(name) values ('work');")
+        .execute(&db.pool)
+        .await
+        .unwrap();
+    query("insert into todo_tag(todo_id, tag_name) values (?, 'work');")
+        .bind(id)
+        .execute(&db.pool)
+        .await
+        .unwrap();
+    let recs = get("tag:work").await;
+    assert_eq!(recs.len(), 1, "タグworkが付いているのは一件");
+    assert_eq!(recs[0].id, id, "タグを付けたのは一件目");
+    let recs = get("-tag:work due<3d").await;
+    assert_eq!(recs.len(), 1, "タグ無しで期限3日未満は二件目のみ");
+}
+
+fn recs_id(recs: &[ItemTodo]) -> u32 {
+    assert_eq!(recs.len(), 1, "一件だけのはず");
+    recs[0].id
+}
+
+/// エクスポート用の全件取得・タグ取得のテスト
+#[sqlx::test]
+async fn test_get_all_todo_and_tags(pool: MySqlPool) {
+    let db = Database::new_test(pool);
+    let sess = login_for_test(&db).await;
+    create_todo_for_test(&db, sess).await;
+    let ids = db
+        .get_all_todo_item(sess)
+        .await
+        .unwrap()
+        .iter()
+        .map(|i| i.id)
+        .collect::<Vec<_>>();
+    assert_eq!(ids.len(), 3, "全件取得する");
+    assert!(ids.is_sorted(), "id順のはず");
+
+    query("insert into tag(name) values ('b'), ('a');")
+        .execute(&db.pool)
+        .await
+        .unwrap();
+    query("insert into todo_tag(todo_id, tag_name) values (?, 'b'), (?, 'a'), (?, 'a');")
+        .bind(ids[0])
+        .bind(ids[0])
+        .bind(ids[2])
+        .execute(&db.pool)
+        .await
+        .unwrap();
+    let tags = db.get_todo_tags(sess).await.unwrap();
+    assert_eq!(
+        tags,
+        [
+            (ids[0], "a".to_string()),
+            (ids[0], "b".to_string()),
+            (ids[2], "a".to_string())
+        ]
+    );
+
+    let other = db.get_all_todo_item(Uuid::now_v7()).await.unwrap();
+    assert!(other.is_empty(), "無効なセッションでは取得できない");
+}
+
+/// 保存済みビューの一連の操作テスト
+#[sqlx::test]
+async fn test_saved_view(pool: MySqlPool) {
+    let db = Database::new_test(pool);
+    let sess = login_for_test(&db).await;
+    let name = db.get_user_from_sess(sess).await.unwrap().name;
+
+    let mut view = SavedView {
+        id: 0,
+        user_name: name.clone(),
+        name: "今週期限".to_string(),
+        filter: "due<7d".to_string(),
+        sort_order: ItemSortOrder::EndAsc,
+        only_incomplete: true,
+    };
+    let id = db.add_view(&view).await.expect("ビューの追加に失敗");
+    view.id = id;
+
+    println!("同じ名前のビューは追加できない。");
+    match db.add_view(&view).await {
+        Ok(_) => unreachable!("名前が重複しているのに追加できた。"),
+        Err(DbError::DuplicateViewName(_)) => { /* 正常 */ }
+        Err(e) => unreachable!("このエラーはおかしい。{e}"),
+    }
+
+    let views = db.get_views(sess).await.unwrap();
+    assert_eq!(views, vec![view.clone()], "追加したビューが取得できない");
+    let res = db.get_view_with_id(id, sess).await.unwrap();
+    assert_eq!(res, view, "idで取得したビューが違う");
+    match db.get_view_with_id(id, Uuid::now_v7()).await {
+        Ok(_) => unreachable!("偽のセッションで取得できてはいけない。"),
+        Err(DbError::NotFoundView) => { /* 正常 */ }
+        Err(e) => unreachable!("このエラーはおかしい。{e}"),
+    }
+
+    println!("ビューを更新する。");
+    view.name = "期限切れ".to_string();
+    view.filter = "due<today".to_string();
+    view.sort_order = ItemSortOrder::UpdateDesc;
+    view.only_incomplete = false;
+    db.edit_view(&view).await.expect("ビューの更新に失敗");
+    let res = db.get_view_with_id(id, sess).await.unwrap();
+    assert_eq!(res, view, "更新内容が反映されていない");
+
+    println!("他人のビューは更新・削除できない。");
+    let mut other = view.clone();
+    other.user_name = "dareka".to_string();
+    match db.edit_view(&other).await {
+        Ok(_) => unreachable!("他人のビューを更新できてはいけない。"),
+        Err(DbError::NotFoundView) => { /* 正常 */ }
+        Err(e) => unreachable!("このエラーはおかしい。{e}"),
+    }
+    match db.delete_view(id, "dareka").await {
+        Ok(_) => unreachable!("他人のビューを削除できてはいけない。"),
+        Err(DbError::NotFoundView) => { /* 正常 */ }
+        Err(e) => unreachable!("このエラーはおかしい。{e}"),
+    }
+
+    println!("ビューを削除する。");
+    db.delete_view(id, &name).await.expect("ビューの削除に失敗");
+    assert!(
+        db.get_views(sess).await.unwrap().is_empty(),
+        "削除できてない"
+    );
+}
+
+/// ユーザー設定の読み書きテスト
+#[sqlx::test]
+async fn test_user_settings(pool: MySqlPool) {
+    use crate::config::Theme;
+
+    let db = Database::new_test(pool);
+    let sess = login_for_test(&db).await;
+    let name = db.get_user_from_sess(sess).await.unwrap().name;
+
+    println!("未保存なら初期値が返る。");
+    let settings = db.get_user_settings(&name).await.unwrap();
+    assert_eq!(settings, UserSettings::new(&name), "初期値が返っていない");
+
+    println!("保存して読み出す。");
+    let mut settings = UserSettings {
+        user_name: name.clone(),
+        only_incomplete: false,
+        item_sort_order: ItemSortOrder::StartDesc,
+        default_start_offset: 1,
+        default_end_offset: Some(7),
+        theme: Theme::Dark,
+    };
+    db.save_user_settings(&settings).await.unwrap();
+    assert_eq!(db.get_user_settings(&name).await.unwrap(), settings);
+
+    println!("上書き保存する。");
+    settings.default_end_offset = None;
+    settings.theme = Theme::Light;
+    db.save_user_settings(&settings).await.unwrap();
+    assert_eq!(db.get_user_settings(&name).await.unwrap(), settings);
+}
+
+/// ページング(キーセット方式)のテスト
+#[sqlx::test]
+async fn test_get_todo_page(pool: MySqlPool) {
+    let db = Database::new_test(pool);
+    let sess = login_for_test(&db).await;
+    create_todo_for_test(&db, sess).await;
+    create_todo_for_test(&db, sess).await;
+    let today = Local::now().date_naive();
+
+    for sort_order in [
+        ItemSortOrder::EndAsc,
+        ItemSortOrder::EndDesc,
+        ItemSortOrder::StartAsc,
+        ItemSortOrder::StartDesc,
+        ItemSortOrder::UpdateAsc,
+        ItemSortOrder::UpdateDesc,
+    ] {
+        let all = db
+            .get_todo_item(
+                sess,
+                today,
+                false,
+                sort_order,
+                &TodoFilter::default(),
+                &PageRequest::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(all.total, 6, "全件数がおかしい");
+        assert_eq!(all.items.len(), 6, "全件取得のはず");
+        assert!(all.next_cursor.is_none(), "全件取得なら次はない");
+
+        // 4件ずつ取得して、全件取得と同じ順番になるか。
+        let mut paged = Vec::new();
+        let mut page = PageRequest {
+            cursor: None,
+            limit: Some(4),
+        };
+        loop {
+            let res = db
+                .get_todo_item(
+                    sess,
+                    today,
+                    false,
+                    sort_order,
+                    &TodoFilter::default(),
+                    &page,
+                )
+                .await
+                .unwrap();
+            assert_eq!(res.total, 6, "ページ取得でも全件数は変わらない");
+            assert!(res.items.len() <= 4, "limitを超えて取得している");
+            paged.extend(res.items);
+            match res.next_cursor {
+                Some(c) => page.cursor = Some(c.parse().unwrap()),
+                None => break,
+            }
+        }
+        assert_eq!(
+            paged, all.items,
+            "{sort_order}:ページングの結果が全件取得と異なる"
+        );
+    }
+}
+
+/// カーソル文字列の変換テスト
+#[test]
+fn test_todo_cursor_parse() {
+    let cursor = TodoCursor {
+        key1: NaiveDate::from_ymd_opt(2024, 1, 31).unwrap(),
+        key2: NaiveDate::from_ymd_opt(9999, 12, 31).unwrap(),
+        id: 42,
+    };
+    let s = cursor.to_string();
+    assert_eq!(s, "2024-01-31|9999-12-31|42");
+    assert_eq!(s.parse::<TodoCursor>(), Ok(cursor));
+    for bad in [
+        "",
+        "2024-01-31|9999-12-31",
+        "2024-01-31|9999-12-31|x",
+        "a|b|1",
+        "2024-01-31|9999-12-31|1|2",
+    ] {
+        assert_eq!(
+            bad.parse::<TodoCursor>(),
+            Err(CursorParseError::InvalidFormat),
+            "[{bad}]は不正なカーソル"
+        );
+    }
+}
+
 async fn login_for_test(db: &Database) -> Uuid {
     println!("テスト用ユーザー及びセッションの生成");
     let name = "test";
@@ -560,8 +1228,9 @@ async fn create_todo_for_test(db: &Database, sess: Uuid) {
         start_date: Some(Local::now().date_naive() - Days::new(4)),
         end_date: Some(Local::now().date_naive() + Days::new(2)),
         done: false,
+        revision: 0,
     };
-    db.add_todo_item(&item).await.unwrap();
+    db.add_todo_item(&item, &test_source()).await.unwrap();
 
     let item = ItemTodo {
         id: 0,
@@ -572,8 +1241,9 @@ async fn create_todo_for_test(db: &Database, sess: Uuid) {
         start_date: Some(Local::now().date_naive() - Days::new(5)),
         end_date: Some(Local::now().date_naive() + Days::new(1)),
         done: false,
+        revision: 0,
     };
-    db.add_todo_item(&item).await.unwrap();
+    db.add_todo_item(&item, &test_source()).await.unwrap();
 
     let item = ItemTodo {
         id: 0,
@@ -584,6 +1254,147 @@ async fn create_todo_for_test(db: &Database, sess: Uuid) {
         start_date: Some(Local::now().date_naive()),
         end_date: Some(Local::now().date_naive() + Days::new(3)),
         done: false,
+        revision: 0,
+    };
+    db.add_todo_item(&item, &test_source()).await.unwrap();
+}
+
+#[sqlx::test]
+async fn test_health_check(pool: MySqlPool) {
+    let db = Database::new_test(pool);
+    db.health_check().await.unwrap();
+}
+
+#[test]
+fn test_error_classification() {
+    let io = || sqlx::Error::Io(std::io::Error::from(std::io::ErrorKind::ConnectionRefused));
+    assert!(is_connection_error(&io()));
+    assert!(is_transient(&io()));
+    assert!(is_transient(&sqlx::Error::PoolTimedOut));
+    assert!(!is_transient(&sqlx::Error::RowNotFound));
+
+    assert!(matches!(DbError::connect(io()), DbError::Unavailable(_)));
+    assert!(DbError::connect(io()).is_transient());
+    let permanent = DbError::connect(sqlx::Error::Configuration("url".into()));
+    assert!(matches!(permanent, DbError::FailConnect(_)));
+    assert!(!permanent.is_transient());
+    let tls = || sqlx::Error::Tls("certificate verify failed".into());
+    assert!(!is_connection_error(&tls()));
+    let tls = DbError::connect(tls());
+    assert!(
+        matches!(tls, DbError::FailConnect(_)),
+        "証明書の誤りは再試行しない"
+    );
+    assert!(!tls.is_transient());
+    assert!(!DbError::NotFoundTodo.is_transient());
+}
+
+/// マイグレーションの適用。適用済みであれば何もしない。
+#[sqlx::test(migrations = false)]
+async fn test_migrate(pool: MySqlPool) {
+    let db = Database::new_test(pool);
+    db.migrate().await.unwrap();
+    db.migrate().await.unwrap();
+
+    let version: Option<i64> = sqlx::query_scalar("select max(version) from _sqlx_migrations;")
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+    assert_eq!(version, Some(SCHEMA_VERSION as i64));
+    let count: i64 = sqlx::query_scalar("select count(*) from todo;")
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+    assert_eq!(count, 0);
+}
+
+#[test]
+fn test_ssl_mode_parse() {
+    for mode in [
+        SslMode::Disabled,
+        SslMode::Preferred,
+        SslMode::Required,
+        SslMode::VerifyCa,
+        SslMode::VerifyIdentity,
+    ] {
+        assert_eq!(mode.to_string().parse::<SslMode>(), Ok(mode));
+    }
+    assert_eq!("verify-ca".parse::<SslMode>(), Ok(SslMode::VerifyCa));
+    assert_eq!(
+        "verify_ca".parse::<SslMode>(),
+        Err(SslModeParseError::InvalidArgument)
+    );
+}
+
+#[test]
+fn test_connect_complete() {
+    let conn = ConnectConfig {
+        host: "localhost".to_string(),
+        user: "neko".to_string(),
+        pass: Secret::new("pass"),
+        ..Default::default()
+    };
+    assert!(conn.is_complete());
+    let no_pass = ConnectConfig {
+        pass: Secret::default(),
+        ..conn.clone()
+    };
+    assert!(!no_pass.is_complete());
+    // ソケット接続では、パスワードを省略できる。
+    let socket = ConnectConfig {
+        host: String::new(),
+        socket: Some("/run/mysqld/mysqld.sock".into()),
+        ..no_pass.clone()
     };
-    db.add_todo_item(&item).await.unwrap();
+    assert!(socket.is_complete());
+    assert!(!ConnectConfig {
+        user: String::new(),
+        ..socket
+    }
+    .is_complete());
+}
+
+#[test]
+fn test_connect_options() {
+    // 既定値
+    let conn = ConnectConfig {
+        host: "db.example.com".to_string(),
+        user: "user".to_string(),
+        pass: Secret::new("p@ss:w/rd#?"),
+        ..Default::default()
+    };
+    let options = conn.connect_options();
+    assert_eq!(options.get_host(), "db.example.com");
+    assert_eq!(options.get_port(), 3306);
+    assert_eq!(options.get_username(), "user");
+    assert_eq!(options.get_database(), Some(DEFAULT_DATABASE));
+    assert!(matches!(options.get_ssl_mode(), MySqlSslMode::Preferred));
+    assert!(options.get_socket().is_none());
+
+    // ポート・データベース名・SSLモードの指定
+    let conn = ConnectConfig {
+        port: Some(3307),
+        database: Some("todo_test".to_string()),
+        ssl_mode: SslMode::VerifyIdentity,
+        ssl_ca: Some(PathBuf::from("/etc/ssl/ca.pem")),
+        ..conn
+    };
+    let options = conn.connect_options();
+    assert_eq!(options.get_port(), 3307);
+    assert_eq!(options.get_database(), Some("todo_test"));
+    assert!(matches!(
+        options.get_ssl_mode(),
+        MySqlSslMode::VerifyIdentity
+    ));
+
+    // ソケットの指定
+    let conn = ConnectConfig {
+        socket: Some(PathBuf::from("/run/mysqld/mysqld.sock")),
+        ..conn
+    };
+    let options = conn.connect_options();
+    assert_eq!(
+        options.get_socket(),
+        Some(&PathBuf::from("/run/mysqld/mysqld.sock"))
+    );
 }
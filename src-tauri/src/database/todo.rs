@@ -1,8 +1,12 @@
 //! todoアイテム操作
+use super::backup::insert_todo_record;
+use super::history::{lock_snapshot, record_history};
+use super::page::{after_cursor_clause, order_by_clause};
 use super::*;
 use crate::config::ItemSortOrder;
+use crate::filter::{FilterParam, TodoFilter};
 use chrono::{Local, NaiveDate};
-use sqlx::{query, query_as};
+use sqlx::{query, query_as, query_scalar, MySqlConnection};
 use uuid::Uuid;
 
 impl Database {
@@ -10,72 +14,257 @@ impl Database {
     /// item引数のうち、id, update_date, doneは、無視される
     /// 各々、自動値・今日の日付・falseがはいる。
     /// start_date, end_dateのデフォルト値は、今日・NaiveDate::MAXである。
-    pub async fn add_todo_item(&self, item: &ItemTodo) -> Result<(), DbError> {
+    /// 作成日(create_date)には、今日の日付を記録する。
+    /// 追加したtodoのidを返す。
+    pub async fn add_todo_item(
+        &self,
+        item: &ItemTodo,
+        source: &ChangeSource,
+    ) -> Result<u32, DbError> {
         let sql = r#"
-            insert into todo(user_name, title, work, update_date, start_date, end_date, done)
-            values (?, ?, ?, curdate(), ?, ?, false);
+            insert into todo(user_name, title, work, update_date, start_date, end_date, done, create_date)
+            values (?, ?, ?, curdate(), ?, ?, false, curdate());
         "#;
         let start_date = item.start_date.unwrap_or(Local::now().date_naive());
         let end_date = item
             .end_date
             .unwrap_or(NaiveDate::from_ymd_opt(9999, 12, 31).unwrap());
-        query(sql)
+        let mut tx = self.pool.begin().await.map_err(DbError::FailDbAccess)?;
+        let id = query(sql)
             .bind(&item.user_name)
             .bind(&item.title)
             .bind(&item.work)
             .bind(start_date)
             .bind(end_date)
-            .execute(&self.pool)
+            .execute(&mut *tx)
             .await
-            .map_err(DbError::FailDbAccess)?;
-        Ok(())
+            .map_err(DbError::FailDbAccess)?
+            .last_insert_id() as u32;
+        let snapshot = TodoSnapshot {
+            title: item.title.clone(),
+            work: item.work.clone(),
+            start_date,
+            end_date,
+            done: false,
+        };
+        record_history(
+            &mut tx,
+            id,
+            &item.user_name,
+            HistoryAction::Create,
+            None,
+            Some(&snapshot),
+            source,
+        )
+        .await?;
+        tx.commit().await.map_err(DbError::FailDbAccess)?;
+        Ok(id)
+    }
+
+    /// 複数のTodo項目を、タグ・繰り返し規則と合わせて一つのトランザクションで追加する。
+    /// itemのidは無視され、update_dateは今日の日付となる。
+    /// 完了状態(done)は、指定された値で登録する。作成日と、完了済みなら完了日は今日の日付とする。
+    /// 一件でも失敗した場合は、すべて取り消される。追加したtodoのidを、itemsの順に返す。
+    pub async fn add_todo_items(
+        &self,
+        items: &[NewTodoItem],
+        source: &ChangeSource,
+    ) -> Result<Vec<u32>, DbError> {
+        let todo_sql = r#"
+            insert into todo(user_name, title, work, update_date, start_date, end_date, done,
+                create_date, done_date)
+            values (?, ?, ?, curdate(), ?, ?, ?, curdate(), if(?, curdate(), null));
+        "#;
+        let tag_sql = "insert ignore into tag(name) values (?);";
+        let todo_tag_sql = "insert ignore into todo_tag(todo_id, tag_name) values (?, ?);";
+        let recurrence_sql = "insert into todo_recurrence(todo_id, rrule) values (?, ?);";
+
+        let mut ids = Vec::with_capacity(items.len());
+        let mut tx = self.pool.begin().await.map_err(DbError::FailDbAccess)?;
+        for NewTodoItem {
+            item,
+            tags,
+            recurrence,
+        } in items
+        {
+            let start_date = item.start_date.unwrap_or(Local::now().date_naive());
+            let end_date = item
+                .end_date
+                .unwrap_or(NaiveDate::from_ymd_opt(9999, 12, 31).unwrap());
+            let res = query(todo_sql)
+                .bind(&item.user_name)
+                .bind(&item.title)
+                .bind(&item.work)
+                .bind(start_date)
+                .bind(end_date)
+                .bind(item.done)
+                .bind(item.done)
+                .execute(&mut *tx)
+                .await
+                .map_err(DbError::FailDbAccess)?;
+            let id = res.last_insert_id();
+            ids.push(id as u32);
+            let snapshot = TodoSnapshot {
+                title: item.title.clone(),
+                work: item.work.clone(),
+                start_date,
+                end_date,
+                done: item.done,
+            };
+            record_history(
+                &mut tx,
+                id as u32,
+                &item.user_name,
+                HistoryAction::Create,
+                None,
+                Some(&snapshot),
+                source,
+            )
+            .await?;
+            for tag in tags {
+                query(tag_sql)
+                    .bind(tag)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(DbError::FailDbAccess)?;
+                query(todo_tag_sql)
+                    .bind(id)
+                    .bind(tag)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(DbError::FailDbAccess)?;
+            }
+            if let Some(rrule) = recurrence {
+                query(recurrence_sql)
+                    .bind(id)
+                    .bind(rrule)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(DbError::FailDbAccess)?;
+            }
+        }
+        tx.commit().await.map_err(DbError::FailDbAccess)?;
+        Ok(ids)
     }
 
     /// Todoの一覧を取得する。
     /// 基準日(ref_date)以降のアイテムを選別する。
     /// セッションIDを必要とする。
-    /// 検索オプションのとり方は未確定。インターフェース変更の可能性大。
+    /// filterの条件はパラメータとしてバインドされる。相対日付は基準日から求める。
+    /// pageで指定された範囲を取得し、次のページのカーソルと全件数を合わせて返す。
     pub async fn get_todo_item(
         &self,
         sess: Uuid,
         ref_date: NaiveDate,
         only_incomplete: bool,
         sort_order: ItemSortOrder,
-    ) -> Result<Vec<ItemTodo>, DbError> {
-        let sql1 = r#"
-            select t.id, t.user_name, title, work, update_date, start_date, end_date, done 
-            from todo t join sessions s on s.user_name = t.user_name 
-            where s.id=? and t.start_date <= ? 
-            "#;
-        let sql2 = " and done = false";
-        let sql3 = match sort_order {
-            ItemSortOrder::EndAsc => " order by end_date, update_date",
-            ItemSortOrder::EndDesc => " order by end_date desc,  update_date",
-            ItemSortOrder::StartAsc => " order by start_date, update_date",
-            ItemSortOrder::StartDesc => " order by start_date desc, update_date",
-            ItemSortOrder::UpdateAsc => " order by update_date, end_date",
-            ItemSortOrder::UpdateDesc => " order by update_date desc, end_date",
-        };
-        let sql = if only_incomplete {
-            format!("{} {} {};", sql1, sql2, sql3)
-        } else {
-            format!("{} {};", sql1, sql3)
-        };
-        let items = query_as::<_, ItemTodo>(&sql)
+        filter: &TodoFilter,
+        page: &PageRequest,
+    ) -> Result<TodoPage, DbError> {
+        let mut where_sql = " where s.id=? and t.start_date <= ?".to_string();
+        if only_incomplete {
+            where_sql.push_str(" and t.done = false");
+        }
+        let (filter_sql, filter_params) = filter.to_sql(ref_date);
+        if !filter_sql.is_empty() {
+            where_sql.push_str(" and ");
+            where_sql.push_str(&filter_sql);
+        }
+        let from_sql = " from todo t join sessions s on s.user_name = t.user_name";
+
+        // 全件数
+        let count_sql = format!("select count(*){}{};", from_sql, where_sql);
+        let mut count_query = query_scalar::<_, i64>(&count_sql)
+            .bind(sess.to_string())
+            .bind(ref_date);
+        for param in filter_params.iter().cloned() {
+            count_query = match param {
+                FilterParam::Date(d) => count_query.bind(d),
+                FilterParam::Text(s) => count_query.bind(s),
+            };
+        }
+        let total = count_query
+            .fetch_one(&self.pool)
+            .await
+            .map_err(DbError::FailDbAccess)?;
+
+        // ページ内のアイテム
+        let mut sql = format!(
+            "select t.id, t.user_name, t.title, t.work, t.update_date, t.start_date, t.end_date, t.done, t.revision{}{}",
+            from_sql, where_sql
+        );
+        if page.cursor.is_some() {
+            sql.push_str(" and ");
+            sql.push_str(&after_cursor_clause(sort_order));
+        }
+        sql.push_str(&order_by_clause(sort_order));
+        if page.limit.is_some() {
+            // 次のページの有無を判定するため、一件多く取得する。
+            sql.push_str(" limit ?");
+        }
+        sql.push(';');
+
+        let mut items_query = query_as::<_, ItemTodo>(&sql)
             .bind(sess.to_string())
-            .bind(ref_date)
+            .bind(ref_date);
+        for param in filter_params {
+            items_query = match param {
+                FilterParam::Date(d) => items_query.bind(d),
+                FilterParam::Text(s) => items_query.bind(s),
+            };
+        }
+        if let Some(cursor) = page.cursor {
+            items_query = items_query
+                .bind(cursor.key1)
+                .bind(cursor.key1)
+                .bind(cursor.key2)
+                .bind(cursor.key2)
+                .bind(cursor.id);
+        }
+        if let Some(limit) = page.limit {
+            items_query = items_query.bind(limit.saturating_add(1));
+        }
+        let mut items = items_query
             .fetch_all(&self.pool)
             .await
             .map_err(DbError::FailDbAccess)?;
 
-        Ok(items)
+        let next_cursor = match page.limit {
+            Some(limit) if items.len() > limit as usize => {
+                items.truncate(limit as usize);
+                items
+                    .last()
+                    .map(|item| TodoCursor::from_item(item, sort_order).to_string())
+            }
+            _ => None,
+        };
+        Ok(TodoPage {
+            items,
+            next_cursor,
+            total,
+        })
+    }
+
+    /// セッションの持ち主のTodoを、基準日・完了状態に関わらず、すべてid順に取得する。
+    pub async fn get_all_todo_item(&self, sess: Uuid) -> Result<Vec<ItemTodo>, DbError> {
+        let sql = r#"
+            select t.id, t.user_name, t.title, t.work, t.update_date, t.start_date, t.end_date, t.done, t.revision
+            from todo t join sessions s on s.user_name = t.user_name
+            where s.id = ?
+            order by t.id;
+            "#;
+        query_as::<_, ItemTodo>(sql)
+            .bind(sess.to_string())
+            .fetch_all(&self.pool)
+            .await
+            .map_err(DbError::FailDbAccess)
     }
 
     /// 指定idのTodo項目を取得する。
     /// 有効なセッションが指定されていなければ、未発見とする。
     pub async fn get_todo_item_with_id(&self, id: u32, sess: Uuid) -> Result<ItemTodo, DbError> {
         let sql = r#"
-            select t.id, t.user_name, t.title, t.work, t.update_date, t.start_date, t.end_date, t.done 
+            select t.id, t.user_name, t.title, t.work, t.update_date, t.start_date, t.end_date, t.done, t.revision 
             from todo t join sessions s on s.user_name = t.user_name 
             where s.id=? and t.id=?
             "#;
@@ -91,23 +280,79 @@ impl Database {
     }
 
     /// Todoの完了状態を更新する。
-    pub async fn change_done(&self, id: u32, done: bool) -> Result<(), DbError> {
-        let sql = "update todo set done = ? where id = ?";
-        let res = query(sql)
+    /// 新たに完了にした場合は今日の日付を完了日とし、未完了に戻した場合は完了日を消去する。
+    /// revisionが現在の版番号と異なる場合は、他で更新されたものとしてDbError::Conflictを返す。
+    /// 状態が変わった場合は、版番号を増やして変更履歴を記録する。
+    /// 更新後の版番号と、変更前後の内容を返す。
+    pub async fn change_done(
+        &self,
+        id: u32,
+        done: bool,
+        revision: u32,
+        source: &ChangeSource,
+    ) -> Result<TodoChange, DbError> {
+        // 完了日の判定に更新前のdoneを使うため、doneより先に代入する。
+        let sql = r#"
+            update todo
+            set done_date = if(?, if(done and done_date is not null, done_date, curdate()), null),
+                done = ?,
+                revision = revision + 1
+            where id = ?;
+            "#;
+        let mut tx = self.pool.begin().await.map_err(DbError::FailDbAccess)?;
+        let Some((user_name, current, old)) = lock_snapshot(&mut tx, id).await? else {
+            return Err(DbError::NotFoundTodo);
+        };
+        if current != revision {
+            return Err(DbError::Conflict);
+        }
+        let before = read_todo_record(&mut tx, id).await?;
+        if old.done == done {
+            return Ok(TodoChange {
+                revision: current,
+                after: before.clone(),
+                before,
+            });
+        }
+        query(sql)
+            .bind(done)
             .bind(done)
             .bind(id)
-            .execute(&self.pool)
+            .execute(&mut *tx)
             .await
             .map_err(DbError::FailDbAccess)?;
-        if res.rows_affected() > 0 {
-            Ok(())
-        } else {
-            Err(DbError::NotFoundTodo)
-        }
+        let after = read_todo_record(&mut tx, id).await?;
+        let new = TodoSnapshot {
+            done,
+            ..old.clone()
+        };
+        record_history(
+            &mut tx,
+            id,
+            &user_name,
+            HistoryAction::Done,
+            Some(&old),
+            Some(&new),
+            source,
+        )
+        .await?;
+        tx.commit().await.map_err(DbError::FailDbAccess)?;
+        Ok(TodoChange {
+            revision: current + 1,
+            before,
+            after,
+        })
     }
 
     /// Todoの項目編集
-    pub async fn edit_todo(&self, item: &ItemTodo) -> Result<(), DbError> {
+    /// item.revisionが現在の版番号と異なる場合は、他で更新されたものとしてDbError::Conflictを返す。
+    /// 内容が変わった場合は、版番号を増やして、変更前後の内容を変更履歴に記録する。
+    /// 更新後の版番号と、変更前後の内容を返す。
+    pub async fn edit_todo(
+        &self,
+        item: &ItemTodo,
+        source: &ChangeSource,
+    ) -> Result<TodoChange, DbError> {
         let start_date = item.start_date.unwrap_or(Local::now().date_naive());
         let end_date = item
             .end_date
@@ -115,22 +360,134 @@ impl Database {
 
         let sql = r#"
             update todo 
-            set title=?, work=?, update_date=curdate(), start_date=?, end_date=? 
+            set title=?, work=?, update_date=curdate(), start_date=?, end_date=?,
+                revision = revision + 1
             where id=?;
             "#;
-        let res = query(sql)
+        let mut tx = self.pool.begin().await.map_err(DbError::FailDbAccess)?;
+        let Some((user_name, current, old)) = lock_snapshot(&mut tx, item.id).await? else {
+            return Err(DbError::NotFoundTodo);
+        };
+        if current != item.revision {
+            return Err(DbError::Conflict);
+        }
+        let new = TodoSnapshot {
+            title: item.title.clone(),
+            work: item.work.clone(),
+            start_date,
+            end_date,
+            done: old.done,
+        };
+        let before = read_todo_record(&mut tx, item.id).await?;
+        if new == old {
+            return Ok(TodoChange {
+                revision: current,
+                after: before.clone(),
+                before,
+            });
+        }
+        query(sql)
             .bind(&item.title)
             .bind(&item.work)
             .bind(start_date)
             .bind(end_date)
             .bind(item.id)
-            .execute(&self.pool)
+            .execute(&mut *tx)
             .await
             .map_err(DbError::FailDbAccess)?;
-        if res.rows_affected() > 0 {
-            Ok(())
-        } else {
-            Err(DbError::NotFoundTodo)
-        }
+        let after = read_todo_record(&mut tx, item.id).await?;
+        record_history(
+            &mut tx,
+            item.id,
+            &user_name,
+            HistoryAction::Edit,
+            Some(&old),
+            Some(&new),
+            source,
+        )
+        .await?;
+        tx.commit().await.map_err(DbError::FailDbAccess)?;
+        Ok(TodoChange {
+            revision: current + 1,
+            before,
+            after,
+        })
+    }
+
+    /// Todoを削除する。タグ・繰り返し規則も合わせて削除される。
+    /// 変更履歴には、削除前の内容を記録する。削除前の内容を返す。
+    pub async fn delete_todo(&self, id: u32, source: &ChangeSource) -> Result<TodoRecord, DbError> {
+        let mut tx = self.pool.begin().await.map_err(DbError::FailDbAccess)?;
+        let Some((user_name, _, old)) = lock_snapshot(&mut tx, id).await? else {
+            return Err(DbError::NotFoundTodo);
+        };
+        let before = read_todo_record(&mut tx, id).await?;
+        query("delete from todo where id = ?;")
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .map_err(DbError::FailDbAccess)?;
+        record_history(
+            &mut tx,
+            id,
+            &user_name,
+            HistoryAction::Delete,
+            Some(&old),
+            None,
+            source,
+        )
+        .await?;
+        tx.commit().await.map_err(DbError::FailDbAccess)?;
+        Ok(before)
+    }
+
+    /// idを指定して、todoのすべての列と、タグ・繰り返し規則を取得する。
+    /// 持ち主の確認は行わない。
+    pub async fn get_todo_record(&self, id: u32) -> Result<TodoRecord, DbError> {
+        let mut conn = self.pool.acquire().await.map_err(DbError::FailDbAccess)?;
+        read_todo_record(&mut conn, id).await
     }
+
+    /// get_todo_recordで取得したtodoを、日付・タグ・繰り返し規則を保ったまま追加する。
+    /// idは新たに採番され、そのidを返す。
+    pub async fn insert_todo(
+        &self,
+        user_name: &str,
+        record: &TodoRecord,
+        source: &ChangeSource,
+    ) -> Result<u32, DbError> {
+        let mut tx = self.pool.begin().await.map_err(DbError::FailDbAccess)?;
+        let id = insert_todo_record(&mut tx, user_name, record, Some(source)).await?;
+        tx.commit().await.map_err(DbError::FailDbAccess)?;
+        Ok(id)
+    }
+}
+
+/// idを指定して、todoのすべての列と、タグ・繰り返し規則を取得する。
+/// トランザクション内で呼べば、ロック中の内容を取得できる。
+async fn read_todo_record(conn: &mut MySqlConnection, id: u32) -> Result<TodoRecord, DbError> {
+    let sql = r#"
+        select id, title, work, update_date, start_date, end_date, done, create_date, done_date
+        from todo where id = ?;
+        "#;
+    let mut record = query_as::<_, TodoRecord>(sql)
+        .bind(id)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(DbError::FailDbAccess)?
+        .ok_or(DbError::NotFoundTodo)?;
+    record.tags = query_scalar::<_, String>(
+        "select tag_name from todo_tag where todo_id = ? order by tag_name;",
+    )
+    .bind(id)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(DbError::FailDbAccess)?;
+    record.recurrence =
+        query_scalar::<_, String>("select rrule from todo_recurrence where todo_id = ?;")
+            .bind(id)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(DbError::FailDbAccess)?;
+    Ok(record)
 }
@@ -0,0 +1,113 @@
+//! 保存済みビューの操作
+use super::*;
+use sqlx::{query, query_as};
+use uuid::Uuid;
+
+impl Database {
+    /// ビューを追加する。
+    /// 引数viewのidは無視される。生成されたidを返す。
+    pub async fn add_view(&self, view: &SavedView) -> Result<u32, DbError> {
+        let sql = r#"
+            insert into saved_views(user_name, name, filter, sort_order, only_incomplete)
+            values (?, ?, ?, ?, ?);
+        "#;
+        let res = query(sql)
+            .bind(&view.user_name)
+            .bind(&view.name)
+            .bind(&view.filter)
+            .bind(view.sort_order.to_string())
+            .bind(view.only_incomplete)
+            .execute(&self.pool)
+            .await
+            .map_err(map_view_write_err)?;
+        Ok(res.last_insert_id() as u32)
+    }
+
+    /// セッションの持ち主のビューの一覧を、名前順に取得する。
+    pub async fn get_views(&self, sess: Uuid) -> Result<Vec<SavedView>, DbError> {
+        let sql = r#"
+            select v.id, v.user_name, v.name, v.filter, v.sort_order, v.only_incomplete
+            from saved_views v join sessions s on s.user_name = v.user_name
+            where s.id = ?
+            order by v.name;
+            "#;
+        query_as::<_, SavedView>(sql)
+            .bind(sess.to_string())
+            .fetch_all(&self.pool)
+            .await
+            .map_err(DbError::FailDbAccess)
+    }
+
+    /// 指定idのビューを取得する。
+    /// 有効なセッションが指定されていなければ、未発見とする。
+    pub async fn get_view_with_id(&self, id: u32, sess: Uuid) -> Result<SavedView, DbError> {
+        let sql = r#"
+            select v.id, v.user_name, v.name, v.filter, v.sort_order, v.only_incomplete
+            from saved_views v join sessions s on s.user_name = v.user_name
+            where s.id = ? and v.id = ?;
+            "#;
+        query_as::<_, SavedView>(sql)
+            .bind(sess.to_string())
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| match e {
+                sqlx::Error::RowNotFound => DbError::NotFoundView,
+                e => DbError::FailDbAccess(e),
+            })
+    }
+
+    /// ビューを更新する。
+    /// idとuser_nameが一致するビューのみを更新対象とする。
+    pub async fn edit_view(&self, view: &SavedView) -> Result<(), DbError> {
+        let sql = r#"
+            update saved_views
+            set name=?, filter=?, sort_order=?, only_incomplete=?
+            where id=? and user_name=?;
+            "#;
+        let res = query(sql)
+            .bind(&view.name)
+            .bind(&view.filter)
+            .bind(view.sort_order.to_string())
+            .bind(view.only_incomplete)
+            .bind(view.id)
+            .bind(&view.user_name)
+            .execute(&self.pool)
+            .await
+            .map_err(map_view_write_err)?;
+        if res.rows_affected() > 0 {
+            Ok(())
+        } else {
+            Err(DbError::NotFoundView)
+        }
+    }
+
+    /// ビューを削除する。
+    /// idとuser_nameが一致するビューのみを削除対象とする。
+    pub async fn delete_view(&self, id: u32, user_name: &str) -> Result<(), DbError> {
+        let sql = "delete from saved_views where id=? and user_name=?;";
+        let res = query(sql)
+            .bind(id)
+            .bind(user_name)
+            .execute(&self.pool)
+            .await
+            .map_err(DbError::FailDbAccess)?;
+        if res.rows_affected() > 0 {
+            Ok(())
+        } else {
+            Err(DbError::NotFoundView)
+        }
+    }
+}
+
+/// ビュー書き込み時のエラー変換。名前の重複を識別する。
+fn map_view_write_err(e: sqlx::Error) -> DbError {
+    match e {
+        sqlx::Error::Database(ref db_err)
+            if db_err.kind() == sqlx::error::ErrorKind::UniqueViolation =>
+        {
+            DbError::DuplicateViewName(e)
+        }
+        _ => DbError::FailDbAccess(e),
+    }
+}
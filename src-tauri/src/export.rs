@@ -0,0 +1,126 @@
+//! todoのエクスポート
+//!
+//! ユーザーのtodoを、タグ・状態・日付を含めて、次の形式に変換する。
+//! - JSON(バージョン付き)
+//! - CSV(RFC 4180。Excel向けにBOMの付与も可能)
+//! - Markdownのチェックリスト
+mod csv;
+mod json;
+mod markdown;
+#[cfg(test)]
+mod test;
+
+use chrono::{DateTime, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use thiserror::Error;
+
+pub use csv::TAG_SEPARATOR;
+
+/// エクスポート形式のバージョン
+pub const EXPORT_VERSION: u32 = 1;
+
+/// エクスポートするtodo一件分
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ExportItem {
+    pub id: u32,
+    pub title: String,
+    pub work: Option<String>,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    pub update_date: Option<NaiveDate>,
+    pub done: bool,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// iCalendarのRRULEの値
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recurrence: Option<String>,
+    /// 取得時の版番号。todo.txtとの同期での更新に使用し、エクスポートには含めない。
+    #[serde(skip)]
+    pub revision: u32,
+}
+
+/// エクスポートするデータ全体
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ExportDocument {
+    pub version: u32,
+    pub exported_at: DateTime<Local>,
+    pub user_name: String,
+    pub items: Vec<ExportItem>,
+}
+
+/// エクスポート形式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Json,
+    Csv,
+    Markdown,
+}
+
+impl std::fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Json => write!(f, "json"),
+            Self::Csv => write!(f, "csv"),
+            Self::Markdown => write!(f, "md"),
+        }
+    }
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = ExportError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            "md" | "markdown" => Ok(Self::Markdown),
+            _ => Err(ExportError::InvalidFormat(s.to_string())),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ExportError {
+    #[error("不明なエクスポート形式です。:{0}")]
+    InvalidFormat(String),
+    #[error("JSONへの変換に失敗:{0}")]
+    Json(#[from] serde_json::Error),
+    #[error("ファイルの書き込みに失敗:{0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl ExportDocument {
+    /// 現在時刻で、エクスポートデータを生成する。
+    pub fn new(user_name: &str, items: Vec<ExportItem>) -> Self {
+        Self {
+            version: EXPORT_VERSION,
+            exported_at: Local::now(),
+            user_name: user_name.to_string(),
+            items,
+        }
+    }
+
+    /// 指定の形式に変換する。
+    /// bomがtrueの場合、CSVの先頭にBOMを付与する。(他の形式では無視)
+    pub fn render(&self, format: ExportFormat, bom: bool) -> Result<Vec<u8>, ExportError> {
+        let text = match format {
+            ExportFormat::Json => json::render(self)?,
+            ExportFormat::Csv => csv::render(self, bom),
+            ExportFormat::Markdown => markdown::render(self),
+        };
+        Ok(text.into_bytes())
+    }
+
+    /// 指定の形式に変換し、ファイルに書き込む。
+    pub fn write_to_file(
+        &self,
+        path: &Path,
+        format: ExportFormat,
+        bom: bool,
+    ) -> Result<(), ExportError> {
+        let data = self.render(format, bom)?;
+        std::fs::write(path, data)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,62 @@
+//! CSV形式への変換(RFC 4180)
+
+use super::*;
+
+/// CSVの見出し行
+pub const CSV_HEADER: [&str; 8] = [
+    "id",
+    "title",
+    "work",
+    "start_date",
+    "end_date",
+    "update_date",
+    "done",
+    "tags",
+];
+
+/// CSV中のタグの区切り文字
+pub const TAG_SEPARATOR: char = ';';
+
+/// CSV文字列に変換する。改行はCRLFとする。
+/// bomがtrueの場合、Excelで文字化けしないよう、先頭にBOMを付与する。
+pub(super) fn render(doc: &ExportDocument, bom: bool) -> String {
+    let mut out = String::new();
+    if bom {
+        out.push('\u{feff}');
+    }
+    write_record(&mut out, CSV_HEADER.iter().map(|s| s.to_string()));
+    for item in &doc.items {
+        let date = |d: Option<NaiveDate>| d.map(|d| d.to_string()).unwrap_or_default();
+        write_record(
+            &mut out,
+            [
+                item.id.to_string(),
+                item.title.clone(),
+                item.work.clone().unwrap_or_default(),
+                date(item.start_date),
+                date(item.end_date),
+                date(item.update_date),
+                item.done.to_string(),
+                item.tags.join(&TAG_SEPARATOR.to_string()),
+            ],
+        );
+    }
+    out
+}
+
+/// 一行分を書き出す。
+fn write_record(out: &mut String, fields: impl IntoIterator<Item = String>) {
+    let fields = fields.into_iter().map(|f| escape(&f)).collect::<Vec<_>>();
+    out.push_str(&fields.join(","));
+    out.push_str("\r\n");
+}
+
+/// 必要に応じて、フィールドを引用符で囲む。
+/// 引用符自体は、二重にする。
+fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
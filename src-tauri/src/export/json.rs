@@ -0,0 +1,8 @@
+//! JSON形式への変換
+
+use super::*;
+
+/// バージョン付きのJSON文字列に変換する。
+pub(super) fn render(doc: &ExportDocument) -> Result<String, serde_json::Error> {
+    serde_json::to_string_pretty(doc)
+}
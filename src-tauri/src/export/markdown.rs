@@ -0,0 +1,45 @@
+//! Markdownのチェックリストへの変換
+
+use super::*;
+use std::fmt::Write;
+
+/// Markdownのチェックリストに変換する。
+/// 詳細(work)は、項目の下に字下げして出力する。
+pub(super) fn render(doc: &ExportDocument) -> String {
+    let no_end_date = NaiveDate::from_ymd_opt(9999, 12, 31).unwrap();
+    let mut out = String::new();
+    writeln!(out, "# neko_todo ({})", doc.user_name).unwrap();
+    writeln!(out).unwrap();
+    writeln!(
+        out,
+        "出力日時: {}",
+        doc.exported_at.format("%Y/%m/%d %H:%M")
+    )
+    .unwrap();
+    writeln!(out).unwrap();
+    for item in &doc.items {
+        let check = if item.done { "x" } else { " " };
+        write!(out, "- [{}] {}", check, item.title).unwrap();
+        let start = item.start_date.map(|d| d.format("%Y/%m/%d").to_string());
+        let end = match item.end_date {
+            Some(d) if d != no_end_date => Some(d.format("%Y/%m/%d").to_string()),
+            _ => None,
+        };
+        match (start, end) {
+            (Some(s), Some(e)) => write!(out, " ({} 〜 {})", s, e).unwrap(),
+            (Some(s), None) => write!(out, " ({} 〜)", s).unwrap(),
+            (None, Some(e)) => write!(out, " (〜 {})", e).unwrap(),
+            (None, None) => {}
+        }
+        for tag in &item.tags {
+            write!(out, " #{}", tag).unwrap();
+        }
+        writeln!(out).unwrap();
+        if let Some(ref work) = item.work {
+            for line in work.lines() {
+                writeln!(out, "    {}", line).unwrap();
+            }
+        }
+    }
+    out
+}
@@ -0,0 +1,108 @@
+//! exportモジュールテスト
+
+use super::*;
+use chrono::TimeZone;
+
+fn date(y: i32, m: u32, d: u32) -> Option<NaiveDate> {
+    NaiveDate::from_ymd_opt(y, m, d)
+}
+
+fn sample() -> ExportDocument {
+    let mut doc = ExportDocument::new(
+        "testdayo",
+        vec![
+            ExportItem {
+                id: 1,
+                title: "普通の項目".to_string(),
+                work: None,
+                start_date: date(2024, 6, 1),
+                end_date: date(2024, 6, 10),
+                update_date: date(2024, 6, 1),
+                done: false,
+                tags: vec!["work".to_string(), "猫".to_string()],
+                recurrence: None,
+                revision: 0,
+            },
+            ExportItem {
+                id: 2,
+                title: "カンマ,と\"引用符\"".to_string(),
+                work: Some("一行目\n二行目".to_string()),
+                start_date: date(2024, 6, 2),
+                end_date: date(9999, 12, 31),
+                update_date: date(2024, 6, 3),
+                done: true,
+                tags: vec![],
+                recurrence: None,
+                revision: 0,
+            },
+        ],
+    );
+    doc.exported_at = Local.with_ymd_and_hms(2024, 6, 5, 12, 34, 56).unwrap();
+    doc
+}
+
+fn render(doc: &ExportDocument, format: ExportFormat, bom: bool) -> String {
+    String::from_utf8(doc.render(format, bom).unwrap()).unwrap()
+}
+
+#[test]
+fn test_format_parse() {
+    assert_eq!("json".parse::<ExportFormat>().unwrap(), ExportFormat::Json);
+    assert_eq!("CSV".parse::<ExportFormat>().unwrap(), ExportFormat::Csv);
+    assert_eq!(
+        "md".parse::<ExportFormat>().unwrap(),
+        ExportFormat::Markdown
+    );
+    assert_eq!(
+        "markdown".parse::<ExportFormat>().unwrap(),
+        ExportFormat::Markdown
+    );
+    assert!(matches!(
+        "xml".parse::<ExportFormat>(),
+        Err(ExportError::InvalidFormat(s)) if s == "xml"
+    ));
+}
+
+#[test]
+fn test_json() {
+    let doc = sample();
+    let json = render(&doc, ExportFormat::Json, true);
+    assert!(!json.starts_with('\u{feff}'), "JSONにはBOMを付与しない");
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["version"], EXPORT_VERSION);
+    assert_eq!(value["user_name"], "testdayo");
+    assert_eq!(value["items"][0]["end_date"], "2024-06-10");
+    assert_eq!(value["items"][0]["tags"][1], "猫");
+
+    let back: ExportDocument = serde_json::from_str(&json).unwrap();
+    assert_eq!(back, doc, "読み戻したら同じになるはず");
+}
+
+#[test]
+fn test_csv() {
+    let doc = sample();
+    let csv = render(&doc, ExportFormat::Csv, false);
+    assert_eq!(
+        csv,
+        "id,title,work,start_date,end_date,update_date,done,tags\r\n\
+         1,普通の項目,,2024-06-01,2024-06-10,2024-06-01,false,work;猫\r\n\
+         2,\"カンマ,と\"\"引用符\"\"\",\"一行目\n二行目\",2024-06-02,9999-12-31,2024-06-03,true,\r\n"
+    );
+
+    let csv = render(&doc, ExportFormat::Csv, true);
+    assert!(csv.starts_with("\u{feff}id,"), "BOMが付与されていない");
+}
+
+#[test]
+fn test_markdown() {
+    let md = render(&sample(), ExportFormat::Markdown, false);
+    assert_eq!(
+        md,
+        "# neko_todo (testdayo)\n\
+         \n\
+         出力日時: 2024/06/05 12:34\n\
+         \n\
+         - [ ] 普通の項目 (2024/06/01 〜 2024/06/10) #work #猫\n\
+         - [x] カンマ,と\"引用符\" (2024/06/02 〜)\n    一行目\n    二行目\n"
+    );
+}
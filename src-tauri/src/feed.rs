@@ -0,0 +1,170 @@
+//! ローカルホスト向けのICSフィード
+//!
+//! カレンダーアプリから購読できるよう、`http://127.0.0.1:<port>/feed/<token>.ics`で
+//! ユーザーの未完了のtodoをiCalendar形式で提供する。
+//! トークンはユーザーごとの秘密の値で、ログインの代わりとなる。
+//! 生成したフィードはキャッシュし、todoの変更時に破棄する。
+mod cache;
+#[cfg(test)]
+mod test;
+
+use log::{error, info, warn};
+use std::future::Future;
+use std::time::Duration;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+pub use cache::FeedCache;
+
+/// 要求ヘッダの最大長
+const MAX_REQUEST_HEAD: usize = 8 * 1024;
+/// 要求の受信待ちの上限時間
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// HTTPの応答
+#[derive(Debug, PartialEq)]
+struct Response {
+    status: u16,
+    reason: &'static str,
+    body: String,
+}
+
+/// 指定ポートのローカルホストで、フィードの提供を開始する。
+/// handlerは、トークンを受け取り、フィードの内容を返す。
+/// トークンが不明な場合はNone、生成に失敗した場合はエラーを返すこと。
+pub async fn serve<F, Fut>(listener: TcpListener, handler: F)
+where
+    F: Fn(String) -> Fut + Clone + Send + Sync + 'static,
+    Fut: Future<Output = Result<Option<String>, String>> + Send,
+{
+    if let Ok(addr) = listener.local_addr() {
+        info!("ICSフィードの提供開始:http://{}/feed/", addr);
+    }
+    loop {
+        let (stream, _) = match listener.accept().await {
+            Ok(s) => s,
+            Err(e) => {
+                warn!("ICSフィード:接続の受付に失敗:{e}");
+                continue;
+            }
+        };
+        let handler = handler.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, handler).await {
+                warn!("ICSフィード:応答に失敗:{e}");
+            }
+        });
+    }
+}
+
+/// 一接続分の要求を処理する。
+async fn handle_connection<F, Fut>(mut stream: TcpStream, handler: F) -> std::io::Result<()>
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<Option<String>, String>>,
+{
+    let head = match tokio::time::timeout(READ_TIMEOUT, read_head(&mut stream)).await {
+        Ok(head) => head?,
+        Err(_) => return Ok(()),
+    };
+    let (is_head, response) = match parse_request(&head) {
+        Ok((is_head, token)) => match handler(token).await {
+            Ok(Some(body)) => (is_head, Response::ok(body)),
+            Ok(None) => (is_head, Response::error(404, "Not Found")),
+            Err(e) => {
+                error!("ICSフィードの生成に失敗:{e}");
+                (is_head, Response::error(500, "Internal Server Error"))
+            }
+        },
+        Err(response) => (false, response),
+    };
+    stream.write_all(&response.to_bytes(is_head)).await?;
+    stream.shutdown().await
+}
+
+/// 要求ヘッダ(空行まで)を読み込む。
+async fn read_head(stream: &mut TcpStream) -> std::io::Result<String> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 1024];
+    while !buf.windows(4).any(|w| w == b"\r\n\r\n") && buf.len() < MAX_REQUEST_HEAD {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+/// 要求行を解析し、(HEADメソッドか, トークン)を返す。
+/// フィードの要求でなければ、返すべきエラー応答を返す。
+fn parse_request(head: &str) -> Result<(bool, String), Response> {
+    let line = head.lines().next().unwrap_or_default();
+    let mut parts = line.split(' ');
+    let (Some(method), Some(target), Some(version)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return Err(Response::error(400, "Bad Request"));
+    };
+    if !version.starts_with("HTTP/1.") {
+        return Err(Response::error(400, "Bad Request"));
+    }
+    let is_head = match method {
+        "GET" => false,
+        "HEAD" => true,
+        _ => return Err(Response::error(405, "Method Not Allowed")),
+    };
+    let path = target.split('?').next().unwrap_or_default();
+    let token = path
+        .strip_prefix("/feed/")
+        .and_then(|s| s.strip_suffix(".ics"))
+        .filter(|t| !t.is_empty() && t.bytes().all(|b| b.is_ascii_alphanumeric()))
+        .ok_or_else(|| Response::error(404, "Not Found"))?;
+    Ok((is_head, token.to_string()))
+}
+
+impl Response {
+    fn ok(body: String) -> Self {
+        Self {
+            status: 200,
+            reason: "OK",
+            body,
+        }
+    }
+
+    fn error(status: u16, reason: &'static str) -> Self {
+        Self {
+            status,
+            reason,
+            body: format!("{} {}\r\n", status, reason),
+        }
+    }
+
+    /// 応答を送信するバイト列に変換する。
+    /// is_headがtrueなら、本文を省略する。
+    fn to_bytes(&self, is_head: bool) -> Vec<u8> {
+        let content_type = if self.status == 200 {
+            "text/calendar; charset=utf-8"
+        } else {
+            "text/plain; charset=utf-8"
+        };
+        let mut ret = format!(
+            "HTTP/1.1 {} {}\r\n\
+             Content-Type: {}\r\n\
+             Content-Length: {}\r\n\
+             Cache-Control: no-cache\r\n\
+             Connection: close\r\n\
+             \r\n",
+            self.status,
+            self.reason,
+            content_type,
+            self.body.len()
+        )
+        .into_bytes();
+        if !is_head {
+            ret.extend_from_slice(self.body.as_bytes());
+        }
+        ret
+    }
+}
@@ -0,0 +1,73 @@
+//! 生成済みフィードのキャッシュ
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// ユーザーごとの、生成済みフィードのキャッシュ
+/// このプロセスでのtodoの変更時には、invalidateでそのユーザーのキャッシュを破棄する。
+/// 他のプロセス(CLI・他のPC・リストア)による変更は、変更履歴の状態(件数と最新のid)が
+/// 生成時と異なることで検知する。
+#[derive(Debug, Default)]
+pub struct FeedCache {
+    users: Mutex<HashMap<String, CacheEntry>>,
+}
+
+#[derive(Debug, Default)]
+struct CacheEntry {
+    /// 破棄するたびに増える世代番号
+    generation: u64,
+    /// フィードを生成した時点の、変更履歴の件数と最新のid
+    history: (u64, u64),
+    /// 終日予定形式か否かごとの、フィードの内容
+    feeds: HashMap<bool, String>,
+}
+
+impl FeedCache {
+    /// キャッシュされたフィードを取得する。
+    /// historyには、現在の変更履歴の件数と最新のidを指定する。生成時と異なれば、Noneを返す。
+    pub fn get(&self, user_name: &str, all_day: bool, history: (u64, u64)) -> Option<String> {
+        let users = self.users.lock().unwrap();
+        let entry = users.get(user_name)?;
+        if entry.history != history {
+            return None;
+        }
+        entry.feeds.get(&all_day).cloned()
+    }
+
+    /// 現在の世代番号を取得する。フィードの生成前に取得し、insertに渡す。
+    pub fn generation(&self, user_name: &str) -> u64 {
+        let users = self.users.lock().unwrap();
+        users.get(user_name).map(|e| e.generation).unwrap_or(0)
+    }
+
+    /// 生成したフィードを登録する。
+    /// 生成中にキャッシュが破棄された(世代が変わった)場合は、古い内容なので登録しない。
+    /// historyには、生成前に取得した変更履歴の件数と最新のidを指定する。
+    pub fn insert(
+        &self,
+        user_name: &str,
+        all_day: bool,
+        generation: u64,
+        history: (u64, u64),
+        feed: String,
+    ) {
+        let mut users = self.users.lock().unwrap();
+        let entry = users.entry(user_name.to_string()).or_default();
+        if entry.generation != generation {
+            return;
+        }
+        if entry.history != history {
+            entry.history = history;
+            entry.feeds.clear();
+        }
+        entry.feeds.insert(all_day, feed);
+    }
+
+    /// ユーザーのキャッシュを破棄する。
+    pub fn invalidate(&self, user_name: &str) {
+        let mut users = self.users.lock().unwrap();
+        let entry = users.entry(user_name.to_string()).or_default();
+        entry.generation += 1;
+        entry.feeds.clear();
+    }
+}
@@ -0,0 +1,105 @@
+//! feedモジュールテスト
+
+use super::*;
+
+#[test]
+fn test_parse_request() {
+    assert_eq!(
+        parse_request("GET /feed/abc123.ics HTTP/1.1\r\nHost: localhost\r\n\r\n"),
+        Ok((false, "abc123".to_string()))
+    );
+    assert_eq!(
+        parse_request("HEAD /feed/abc123.ics?x=1 HTTP/1.0\r\n\r\n"),
+        Ok((true, "abc123".to_string()))
+    );
+    let status = |head: &str| parse_request(head).unwrap_err().status;
+    assert_eq!(status("POST /feed/abc.ics HTTP/1.1\r\n\r\n"), 405);
+    assert_eq!(status("GET /other HTTP/1.1\r\n\r\n"), 404);
+    assert_eq!(status("GET /feed/.ics HTTP/1.1\r\n\r\n"), 404);
+    assert_eq!(status("GET /feed/../x.ics HTTP/1.1\r\n\r\n"), 404);
+    assert_eq!(status("GET /feed/abc.ics\r\n\r\n"), 400);
+    assert_eq!(status(""), 400);
+}
+
+#[test]
+fn test_response() {
+    let res = Response::ok("BEGIN:VCALENDAR".to_string());
+    let text = String::from_utf8(res.to_bytes(false)).unwrap();
+    assert!(text.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(text.contains("Content-Type: text/calendar; charset=utf-8\r\n"));
+    assert!(text.contains("Content-Length: 15\r\n"));
+    assert!(text.ends_with("\r\n\r\nBEGIN:VCALENDAR"));
+
+    let text = String::from_utf8(res.to_bytes(true)).unwrap();
+    assert!(text.ends_with("\r\n\r\n"), "HEADでは本文を送らない");
+    assert!(text.contains("Content-Length: 15\r\n"));
+}
+
+#[test]
+fn test_cache() {
+    let history = (3, 10);
+    let cache = FeedCache::default();
+    let gen = cache.generation("neko");
+    cache.insert("neko", false, gen, history, "feed".to_string());
+    assert_eq!(cache.get("neko", false, history).as_deref(), Some("feed"));
+    assert_eq!(
+        cache.get("neko", true, history),
+        None,
+        "形式ごとにキャッシュする"
+    );
+    assert_eq!(cache.get("inu", false, history), None);
+    assert_eq!(
+        cache.get("neko", false, (4, 11)),
+        None,
+        "他のプロセスで変更されていれば使わない"
+    );
+
+    cache.invalidate("neko");
+    assert_eq!(
+        cache.get("neko", false, history),
+        None,
+        "破棄されているはず"
+    );
+
+    // 生成中に破棄されたら、古い内容は登録しない。
+    let gen = cache.generation("neko");
+    cache.invalidate("neko");
+    cache.insert("neko", false, gen, history, "old".to_string());
+    assert_eq!(cache.get("neko", false, history), None);
+
+    // 変更履歴の状態が変われば、別の形式の古い内容も破棄する。
+    let gen = cache.generation("neko");
+    cache.insert("neko", true, gen, history, "all_day".to_string());
+    cache.insert("neko", false, gen, (4, 11), "new".to_string());
+    assert_eq!(cache.get("neko", true, (4, 11)), None);
+    assert_eq!(cache.get("neko", false, (4, 11)).as_deref(), Some("new"));
+}
+
+#[tokio::test]
+async fn test_serve() {
+    let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(serve(listener, |token: String| async move {
+        match token.as_str() {
+            "good" => Ok(Some("BEGIN:VCALENDAR\r\nEND:VCALENDAR\r\n".to_string())),
+            "broken" => Err("失敗".to_string()),
+            _ => Ok(None),
+        }
+    }));
+
+    let get = |path: &'static str| async move {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).as_bytes())
+            .await
+            .unwrap();
+        let mut res = String::new();
+        stream.read_to_string(&mut res).await.unwrap();
+        res
+    };
+    let res = get("/feed/good.ics").await;
+    assert!(res.starts_with("HTTP/1.1 200 OK"), "{res}");
+    assert!(res.ends_with("END:VCALENDAR\r\n"));
+    assert!(get("/feed/unknown.ics").await.starts_with("HTTP/1.1 404"));
+    assert!(get("/feed/broken.ics").await.starts_with("HTTP/1.1 500"));
+}
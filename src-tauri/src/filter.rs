@@ -0,0 +1,108 @@
+//! todo一覧の絞り込み条件(フィルタクエリ言語)
+//!
+//! `due<7d tag:work -done title:"report"` のような文字列を解析し、
+//! 型付きの条件(AST)に変換する。変換した条件は、パラメータ付きのSQLに
+//! コンパイルされ、データベースの検索に使用される。
+mod parser;
+mod sql;
+#[cfg(test)]
+mod test;
+
+use chrono::NaiveDate;
+use thiserror::Error;
+
+/// todo一覧の絞り込み条件。
+/// 各条件(term)は、すべてandで結合される。
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TodoFilter {
+    terms: Vec<FilterTerm>,
+}
+
+/// 絞り込み条件の一項目
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterTerm {
+    /// 先頭に"-"が付与され、条件が否定されているか。
+    pub negated: bool,
+    pub cond: FilterCond,
+}
+
+/// 絞り込み条件の内容
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterCond {
+    /// 完了済み (`done`)
+    Done,
+    /// 日付の比較 (`due<7d`, `start>=2024-01-01`など)
+    Date {
+        field: DateField,
+        op: CompareOp,
+        value: DateValue,
+    },
+    /// タグの一致 (`tag:work`)
+    Tag(String),
+    /// タイトルの部分一致 (`title:"report"`)
+    Title(String),
+    /// 内容の部分一致 (`work:"report"`)
+    Work(String),
+    /// タイトルまたは内容の部分一致 (キーなしの語)
+    Text(String),
+}
+
+/// 比較対象となる日付項目
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DateField {
+    Start,
+    End,
+    Update,
+}
+
+/// 比較演算子
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompareOp {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+}
+
+/// 比較する日付の値
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DateValue {
+    /// 日付の直接指定
+    Absolute(NaiveDate),
+    /// 基準日からの相対日数
+    Relative(i64),
+}
+
+/// SQLにバインドするパラメータ
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterParam {
+    Date(NaiveDate),
+    Text(String),
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum FilterParseError {
+    #[error("引用符が閉じられていません。")]
+    UnterminatedQuote,
+    #[error("空の条件があります。")]
+    EmptyTerm,
+    #[error("不明な項目です。:{0}")]
+    UnknownField(String),
+    #[error("この項目には使用できない演算子です。:{0}")]
+    InvalidOperator(String),
+    #[error("日付の形式が不正です。:{0}")]
+    InvalidDate(String),
+    #[error("値が指定されていません。:{0}")]
+    EmptyValue(String),
+}
+
+impl DateValue {
+    /// 基準日を元に、実際の日付を求める。
+    pub fn resolve(&self, ref_date: NaiveDate) -> NaiveDate {
+        match self {
+            Self::Absolute(d) => *d,
+            Self::Relative(days) => ref_date + chrono::Duration::days(*days),
+        }
+    }
+}
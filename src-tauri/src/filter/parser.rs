@@ -0,0 +1,184 @@
+//! フィルタ文字列の解析
+
+use super::*;
+
+/// 分割された一語分の文字列
+#[derive(Debug)]
+struct Token {
+    text: String,
+    /// 最初の引用符が現れた位置(text内の文字位置)
+    quote_pos: Option<usize>,
+}
+
+impl std::str::FromStr for TodoFilter {
+    type Err = FilterParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let terms = tokenize(s)?
+            .into_iter()
+            .map(parse_term)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { terms })
+    }
+}
+
+/// 空白で語に分割する。
+/// ダブルクオートで囲まれた部分は、空白を含めて一語とする。
+fn tokenize(s: &str) -> Result<Vec<Token>, FilterParseError> {
+    let mut tokens = Vec::new();
+    let mut text = String::new();
+    let mut quote_pos = None;
+    let mut in_quote = false;
+    let mut in_token = false;
+
+    for c in s.chars() {
+        match c {
+            '"' => {
+                if !in_quote && quote_pos.is_none() {
+                    quote_pos = Some(text.chars().count());
+                }
+                in_quote = !in_quote;
+                in_token = true;
+            }
+            c if c.is_whitespace() && !in_quote => {
+                if in_token {
+                    tokens.push(Token {
+                        text: std::mem::take(&mut text),
+                        quote_pos: quote_pos.take(),
+                    });
+                    in_token = false;
+                }
+            }
+            c => {
+                text.push(c);
+                in_token = true;
+            }
+        }
+    }
+    if in_quote {
+        return Err(FilterParseError::UnterminatedQuote);
+    }
+    if in_token {
+        tokens.push(Token { text, quote_pos });
+    }
+    Ok(tokens)
+}
+
+/// 一語分を条件に変換する。
+fn parse_term(token: Token) -> Result<FilterTerm, FilterParseError> {
+    let Token {
+        mut text,
+        mut quote_pos,
+    } = token;
+
+    // 先頭の"-"は否定。ただし、引用符の中の"-"は文字として扱う。
+    let negated = text.starts_with('-') && quote_pos != Some(0);
+    if negated {
+        text.remove(0);
+        quote_pos = quote_pos.map(|p| p - 1);
+    }
+    if text.is_empty() {
+        return Err(FilterParseError::EmptyTerm);
+    }
+
+    // 引用符で始まる語は、単なる検索語
+    if quote_pos == Some(0) {
+        return Ok(FilterTerm {
+            negated,
+            cond: FilterCond::Text(text),
+        });
+    }
+
+    // 演算子の検索は、引用符より前の部分のみ
+    let head: String = match quote_pos {
+        Some(p) => text.chars().take(p).collect(),
+        None => text.clone(),
+    };
+    let Some(op_pos) = head.find([':', '<', '>', '=']) else {
+        let cond = if quote_pos.is_none() && text.eq_ignore_ascii_case("done") {
+            FilterCond::Done
+        } else {
+            FilterCond::Text(text)
+        };
+        return Ok(FilterTerm { negated, cond });
+    };
+
+    let key = text[..op_pos].to_ascii_lowercase();
+    let rest = &text[op_pos..];
+    let (op_str, op) = parse_operator(rest);
+    let value = &rest[op_str.len()..];
+    if value.is_empty() {
+        return Err(FilterParseError::EmptyValue(key));
+    }
+
+    let date_field = match key.as_str() {
+        "due" | "end" => Some(DateField::End),
+        "start" => Some(DateField::Start),
+        "update" => Some(DateField::Update),
+        _ => None,
+    };
+    let cond = if let Some(field) = date_field {
+        FilterCond::Date {
+            field,
+            op,
+            value: parse_date_value(value)?,
+        }
+    } else {
+        if op != CompareOp::Eq {
+            return Err(FilterParseError::InvalidOperator(format!("{key}{op_str}")));
+        }
+        let value = value.to_string();
+        match key.as_str() {
+            "tag" => FilterCond::Tag(value),
+            "title" => FilterCond::Title(value),
+            "work" => FilterCond::Work(value),
+            _ => return Err(FilterParseError::UnknownField(key)),
+        }
+    };
+    Ok(FilterTerm { negated, cond })
+}
+
+/// 文字列先頭の演算子を解析する。":"は"="と同じ扱い。
+fn parse_operator(s: &str) -> (&'static str, CompareOp) {
+    if s.starts_with("<=") {
+        ("<=", CompareOp::Le)
+    } else if s.starts_with(">=") {
+        (">=", CompareOp::Ge)
+    } else if s.starts_with('<') {
+        ("<", CompareOp::Lt)
+    } else if s.starts_with('>') {
+        (">", CompareOp::Gt)
+    } else if s.starts_with('=') {
+        ("=", CompareOp::Eq)
+    } else {
+        (":", CompareOp::Eq)
+    }
+}
+
+/// 日付の値を解析する。
+/// today/yesterday/tomorrow, 相対指定(7d, -2w など), 日付(2024-01-31, 2024/01/31)
+/// を受け付ける。
+fn parse_date_value(s: &str) -> Result<DateValue, FilterParseError> {
+    match s.to_ascii_lowercase().as_str() {
+        "today" => return Ok(DateValue::Relative(0)),
+        "yesterday" => return Ok(DateValue::Relative(-1)),
+        "tomorrow" => return Ok(DateValue::Relative(1)),
+        _ => {}
+    }
+
+    let unit = match s.chars().next_back() {
+        Some('d') | Some('D') => Some(1),
+        Some('w') | Some('W') => Some(7),
+        _ => None,
+    };
+    if let Some(unit) = unit {
+        if let Ok(n) = s[..s.len() - 1].parse::<i64>() {
+            return Ok(DateValue::Relative(n * unit));
+        }
+    }
+
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(s, "%Y/%m/%d"))
+        .map(DateValue::Absolute)
+        .map_err(|_| FilterParseError::InvalidDate(s.to_string()))
+}
@@ -0,0 +1,94 @@
+//! フィルタ条件のSQLへの変換
+//! ユーザーの入力値は、すべてパラメータとしてバインドし、SQL文には埋め込まない。
+
+use super::*;
+
+impl TodoFilter {
+    /// 条件をSQLのwhere句の断片に変換する。
+    /// todoテーブルの別名は"t"であることを前提とする。
+    /// 戻り値は、andで結合された条件式と、その中の"?"に順にバインドするパラメータ。
+    /// 条件が無い場合は、空文字列を返す。
+    pub fn to_sql(&self, ref_date: NaiveDate) -> (String, Vec<FilterParam>) {
+        let mut params = Vec::new();
+        let conds = self
+            .terms
+            .iter()
+            .map(|term| {
+                let cond = term.cond.to_sql(ref_date, &mut params);
+                if term.negated {
+                    format!("not ({})", cond)
+                } else {
+                    format!("({})", cond)
+                }
+            })
+            .collect::<Vec<_>>();
+        (conds.join(" and "), params)
+    }
+}
+
+impl FilterCond {
+    fn to_sql(&self, ref_date: NaiveDate, params: &mut Vec<FilterParam>) -> String {
+        match self {
+            Self::Done => "t.done = true".to_string(),
+            Self::Date { field, op, value } => {
+                params.push(FilterParam::Date(value.resolve(ref_date)));
+                format!("t.{} {} ?", field.column(), op.as_sql())
+            }
+            Self::Tag(tag) => {
+                params.push(FilterParam::Text(tag.clone()));
+                "exists (select 1 from todo_tag tt where tt.todo_id = t.id and tt.tag_name = ?)"
+                    .to_string()
+            }
+            Self::Title(s) => {
+                params.push(FilterParam::Text(like_pattern(s)));
+                "t.title like ?".to_string()
+            }
+            Self::Work(s) => {
+                params.push(FilterParam::Text(like_pattern(s)));
+                "coalesce(t.work, '') like ?".to_string()
+            }
+            Self::Text(s) => {
+                params.push(FilterParam::Text(like_pattern(s)));
+                params.push(FilterParam::Text(like_pattern(s)));
+                "t.title like ? or coalesce(t.work, '') like ?".to_string()
+            }
+        }
+    }
+}
+
+impl DateField {
+    fn column(&self) -> &'static str {
+        match self {
+            Self::Start => "start_date",
+            Self::End => "end_date",
+            Self::Update => "update_date",
+        }
+    }
+}
+
+impl CompareOp {
+    fn as_sql(&self) -> &'static str {
+        match self {
+            Self::Lt => "<",
+            Self::Le => "<=",
+            Self::Gt => ">",
+            Self::Ge => ">=",
+            Self::Eq => "=",
+        }
+    }
+}
+
+/// 部分一致検索用のlikeパターンを生成する。
+/// ワイルドカード文字は、エスケープする。
+fn like_pattern(s: &str) -> String {
+    let mut pattern = String::with_capacity(s.len() + 2);
+    pattern.push('%');
+    for c in s.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
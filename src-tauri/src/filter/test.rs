@@ -0,0 +1,167 @@
+//! filterモジュールテスト
+
+use super::*;
+
+fn parse(s: &str) -> TodoFilter {
+    s.parse::<TodoFilter>()
+        .unwrap_or_else(|e| unreachable!("解析に失敗[{s}]:{e}"))
+}
+
+fn term(negated: bool, cond: FilterCond) -> FilterTerm {
+    FilterTerm { negated, cond }
+}
+
+#[test]
+fn test_parse_empty() {
+    assert!(parse("").terms.is_empty(), "空文字列は条件なし");
+    assert!(parse("  \t ").terms.is_empty(), "空白のみも条件なし");
+}
+
+#[test]
+fn test_parse_sample() {
+    let filter = parse(r#"due<7d tag:work -done title:"report""#);
+    assert_eq!(
+        filter.terms,
+        [
+            term(
+                false,
+                FilterCond::Date {
+                    field: DateField::End,
+                    op: CompareOp::Lt,
+                    value: DateValue::Relative(7),
+                }
+            ),
+            term(false, FilterCond::Tag("work".to_string())),
+            term(true, FilterCond::Done),
+            term(false, FilterCond::Title("report".to_string())),
+        ]
+    );
+}
+
+#[test]
+fn test_parse_date() {
+    let cases = [
+        (
+            "due<=today",
+            DateField::End,
+            CompareOp::Le,
+            DateValue::Relative(0),
+        ),
+        (
+            "end>yesterday",
+            DateField::End,
+            CompareOp::Gt,
+            DateValue::Relative(-1),
+        ),
+        (
+            "start>=tomorrow",
+            DateField::Start,
+            CompareOp::Ge,
+            DateValue::Relative(1),
+        ),
+        (
+            "update:-3d",
+            DateField::Update,
+            CompareOp::Eq,
+            DateValue::Relative(-3),
+        ),
+        (
+            "due=2w",
+            DateField::End,
+            CompareOp::Eq,
+            DateValue::Relative(14),
+        ),
+        (
+            "due<2024-12-31",
+            DateField::End,
+            CompareOp::Lt,
+            DateValue::Absolute(NaiveDate::from_ymd_opt(2024, 12, 31).unwrap()),
+        ),
+        (
+            "start>2024/01/05",
+            DateField::Start,
+            CompareOp::Gt,
+            DateValue::Absolute(NaiveDate::from_ymd_opt(2024, 1, 5).unwrap()),
+        ),
+    ];
+    for (s, field, op, value) in cases {
+        assert_eq!(
+            parse(s).terms,
+            [term(false, FilterCond::Date { field, op, value })],
+            "[{s}]の解析結果がおかしい"
+        );
+    }
+}
+
+#[test]
+fn test_parse_text() {
+    let filter = parse(r#"猫 "週次 報告" -"-除外" work:"a b" TITLE:x"#);
+    assert_eq!(
+        filter.terms,
+        [
+            term(false, FilterCond::Text("猫".to_string())),
+            term(false, FilterCond::Text("週次 報告".to_string())),
+            term(true, FilterCond::Text("-除外".to_string())),
+            term(false, FilterCond::Work("a b".to_string())),
+            term(false, FilterCond::Title("x".to_string())),
+        ]
+    );
+    // 引用符で囲まれたdoneは、ただの文字列
+    assert_eq!(
+        parse(r#""done""#).terms,
+        [term(false, FilterCond::Text("done".to_string()))]
+    );
+    // 引用符の中の演算子記号は、ただの文字列
+    assert_eq!(
+        parse(r#"title:"a:b<c""#).terms,
+        [term(false, FilterCond::Title("a:b<c".to_string()))]
+    );
+}
+
+#[test]
+fn test_parse_error() {
+    let cases = [
+        (r#"title:"abc"#, FilterParseError::UnterminatedQuote),
+        ("-", FilterParseError::EmptyTerm),
+        (r#""""#, FilterParseError::EmptyTerm),
+        ("foo:bar", FilterParseError::UnknownField("foo".to_string())),
+        (
+            "tag<work",
+            FilterParseError::InvalidOperator("tag<".to_string()),
+        ),
+        ("due<abc", FilterParseError::InvalidDate("abc".to_string())),
+        (
+            "due<2024-13-01",
+            FilterParseError::InvalidDate("2024-13-01".to_string()),
+        ),
+        ("tag:", FilterParseError::EmptyValue("tag".to_string())),
+    ];
+    for (s, err) in cases {
+        assert_eq!(s.parse::<TodoFilter>(), Err(err), "[{s}]のエラーがおかしい");
+    }
+}
+
+#[test]
+fn test_to_sql() {
+    let ref_date = NaiveDate::from_ymd_opt(2024, 6, 10).unwrap();
+    let (sql, params) = parse(r#"due<7d tag:work -done title:"50%_off""#).to_sql(ref_date);
+    assert_eq!(
+        sql,
+        "(t.end_date < ?) and \
+         (exists (select 1 from todo_tag tt where tt.todo_id = t.id and tt.tag_name = ?)) and \
+         not (t.done = true) and \
+         (t.title like ?)"
+    );
+    assert_eq!(
+        params,
+        vec![
+            FilterParam::Date(NaiveDate::from_ymd_opt(2024, 6, 17).unwrap()),
+            FilterParam::Text("work".to_string()),
+            FilterParam::Text(r"%50\%\_off%".to_string()),
+        ]
+    );
+
+    let (sql, params) = TodoFilter::default().to_sql(ref_date);
+    assert!(sql.is_empty(), "条件なしなら空文字列");
+    assert!(params.is_empty(), "条件なしならパラメータなし");
+}
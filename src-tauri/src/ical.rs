@@ -0,0 +1,36 @@
+//! iCalendar(RFC 5545)のVTODOとの相互変換
+//!
+//! todoの各項目は、次のように対応付ける。
+//! - title ⇔ SUMMARY
+//! - work ⇔ DESCRIPTION
+//! - start_date ⇔ DTSTART (終了日と同じ日なら出力しない。読み込み時にDTSTARTがなければ、DUEの日付とする)
+//! - end_date ⇔ DUE (期限なし(9999/12/31)の場合は出力しない)
+//! - done ⇔ STATUS:COMPLETED
+//! - タグ ⇔ CATEGORIES
+//! - 繰り返し規則 ⇔ RRULE (値はそのまま保存する)
+//!
+//! 日時(DATE-TIME)で指定された値は、日付部分のみ使用する。
+mod line;
+mod parser;
+#[cfg(test)]
+mod test;
+mod writer;
+
+use crate::database::ItemTodo;
+use crate::export::ExportDocument;
+use crate::import::{ImportRecord, ImportRowError, ParsedImport};
+use chrono::NaiveDate;
+use thiserror::Error;
+
+pub use parser::parse;
+pub use writer::{render, render_all_day};
+
+#[derive(Error, Debug, PartialEq)]
+pub enum IcalError {
+    #[error("iCalendar形式ではありません。")]
+    NotCalendar,
+    #[error("BEGIN:{1}に対応するENDがありません。({0}行目)")]
+    Unterminated(usize, String),
+    #[error("行の形式が不正です。({0}行目)")]
+    InvalidLine(usize),
+}
@@ -0,0 +1,130 @@
+//! コンテンツ行の処理(折り返し・エスケープ・プロパティの分解)
+
+/// 一行の最大オクテット数(改行を除く)
+const MAX_LINE_OCTETS: usize = 75;
+
+/// プロパティ一つ分
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Property {
+    /// プロパティ名(大文字)
+    pub name: String,
+    /// パラメータ(名前は大文字)
+    pub params: Vec<(String, String)>,
+    pub value: String,
+}
+
+/// 折り返された行を元に戻す。
+/// 戻り値は、(開始行番号, 論理行)の一覧。空行は除く。
+pub(super) fn unfold(data: &str) -> Vec<(usize, String)> {
+    let mut lines: Vec<(usize, String)> = Vec::new();
+    for (i, line) in data.split('\n').enumerate() {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some((_, last))) => last.push_str(rest),
+            _ if line.is_empty() => {}
+            _ => lines.push((i + 1, line.to_string())),
+        }
+    }
+    lines
+}
+
+/// 一行を、75オクテット以内に折り返して出力する。改行はCRLFとする。
+/// 多バイト文字の途中では、折り返さない。
+pub(super) fn fold_into(out: &mut String, line: &str) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > MAX_LINE_OCTETS {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+/// 論理行をプロパティに分解する。
+/// 名前とパラメータは";"で、値とは最初の(引用符外の)":"で区切られる。
+pub(super) fn parse_property(line: &str) -> Option<Property> {
+    let mut in_quote = false;
+    let colon = line.char_indices().find_map(|(i, c)| match c {
+        '"' => {
+            in_quote = !in_quote;
+            None
+        }
+        ':' if !in_quote => Some(i),
+        _ => None,
+    })?;
+    let (head, value) = (&line[..colon], &line[colon + 1..]);
+    let mut parts = head.split(';');
+    let name = parts.next()?.trim().to_ascii_uppercase();
+    if name.is_empty() {
+        return None;
+    }
+    let params = parts
+        .map(|p| {
+            let (k, v) = p.split_once('=')?;
+            Some((k.to_ascii_uppercase(), v.trim_matches('"').to_string()))
+        })
+        .collect::<Option<Vec<_>>>()?;
+    Some(Property {
+        name,
+        params,
+        value: value.to_string(),
+    })
+}
+
+/// TEXT型の値をエスケープする。
+pub(super) fn escape_text(s: &str) -> String {
+    let mut ret = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => ret.push_str("\\\\"),
+            ';' => ret.push_str("\\;"),
+            ',' => ret.push_str("\\,"),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\r' | '\n' => ret.push_str("\\n"),
+            c => ret.push(c),
+        }
+    }
+    ret
+}
+
+/// TEXT型の値のエスケープを戻す。
+pub(super) fn unescape_text(s: &str) -> String {
+    let mut ret = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            ret.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => ret.push('\n'),
+            Some(c) => ret.push(c),
+            None => ret.push('\\'),
+        }
+    }
+    ret
+}
+
+/// TEXT型の値のリストを、エスケープされていない","で分割する。
+pub(super) fn split_text_list(s: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            ',' => {
+                items.push(unescape_text(&s[start..i]));
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(unescape_text(&s[start..]));
+    items
+}
@@ -0,0 +1,129 @@
+//! VTODOの読み込み
+
+use super::line::{parse_property, split_text_list, unescape_text, unfold, Property};
+use super::*;
+
+/// iCalendar形式の文字列を読み込み、VTODOをインポートするtodoに変換する。
+/// VTODO以外のコンポーネント(VEVENTなど)、VTODO中のVALARMは無視する。
+/// 行番号は、VTODOのBEGIN行の位置とする。
+pub fn parse(data: &str) -> Result<ParsedImport, IcalError> {
+    let data = data.strip_prefix('\u{feff}').unwrap_or(data);
+    let lines = unfold(data);
+    match lines.first().and_then(|(_, l)| parse_property(l)) {
+        Some(p) if p.name == "BEGIN" && p.value.eq_ignore_ascii_case("VCALENDAR") => {}
+        _ => return Err(IcalError::NotCalendar),
+    }
+
+    let mut ret = ParsedImport::default();
+    // (開始行番号, コンポーネント名)
+    let mut stack: Vec<(usize, String)> = Vec::new();
+    let mut props: Vec<Property> = Vec::new();
+    for (row, line) in lines {
+        let prop = parse_property(&line).ok_or(IcalError::InvalidLine(row))?;
+        match prop.name.as_str() {
+            "BEGIN" => stack.push((row, prop.value.to_ascii_uppercase())),
+            "END" => {
+                let Some((begin_row, name)) = stack.pop() else {
+                    return Err(IcalError::InvalidLine(row));
+                };
+                if !name.eq_ignore_ascii_case(&prop.value) {
+                    return Err(IcalError::Unterminated(begin_row, name));
+                }
+                if name == "VTODO" && is_vtodo_body(&stack) {
+                    match to_record(begin_row, &props) {
+                        Ok(r) => ret.records.push(r),
+                        Err(message) => ret.errors.push(ImportRowError {
+                            row: begin_row,
+                            message,
+                        }),
+                    }
+                    props.clear();
+                }
+            }
+            _ => {
+                let in_vtodo = matches!(stack.last(), Some((_, n)) if n == "VTODO")
+                    && is_vtodo_body(&stack[..stack.len() - 1]);
+                if in_vtodo {
+                    props.push(prop);
+                }
+            }
+        }
+    }
+    if let Some((row, name)) = stack.pop() {
+        return Err(IcalError::Unterminated(row, name));
+    }
+    Ok(ret)
+}
+
+/// VCALENDAR直下か。(VTODOの外側のコンポーネントのスタックを判定する)
+fn is_vtodo_body(outer: &[(usize, String)]) -> bool {
+    matches!(outer, [(_, n)] if n == "VCALENDAR")
+}
+
+/// VTODO一つ分のプロパティを、todoに変換する。
+fn to_record(row: usize, props: &[Property]) -> Result<ImportRecord, String> {
+    let mut item = ItemTodo {
+        id: 0,
+        user_name: String::new(),
+        title: String::new(),
+        work: None,
+        update_date: None,
+        start_date: None,
+        end_date: None,
+        done: false,
+        revision: 0,
+    };
+    let mut tags = Vec::new();
+    let mut recurrence = None;
+    for prop in props {
+        match prop.name.as_str() {
+            "SUMMARY" => item.title = unescape_text(&prop.value),
+            "DESCRIPTION" => item.work = Some(unescape_text(&prop.value)),
+            "DTSTART" => item.start_date = Some(parse_date(&prop.value)?),
+            "DUE" => item.end_date = Some(parse_date(&prop.value)?),
+            "STATUS" => item.done = prop.value.eq_ignore_ascii_case("COMPLETED"),
+            "COMPLETED" => item.done = true,
+            "CATEGORIES" => tags.extend(
+                split_text_list(&prop.value)
+                    .into_iter()
+                    .map(|t| t.trim().to_string())
+                    .filter(|t| !t.is_empty()),
+            ),
+            "RRULE" => {
+                if !prop.value.to_ascii_uppercase().contains("FREQ=") {
+                    return Err(format!("RRULEにFREQがありません。:{}", prop.value));
+                }
+                recurrence = Some(prop.value.clone());
+            }
+            _ => { /* 対応しないプロパティは無視する */ }
+        }
+    }
+    // DTSTARTがなく、DUEがあれば、開始日は終了日と同じ日とする。
+    if item.start_date.is_none() {
+        item.start_date = item.end_date;
+    }
+    Ok(ImportRecord {
+        row,
+        item,
+        tags,
+        recurrence,
+    })
+}
+
+/// DATE(YYYYMMDD)またはDATE-TIME(YYYYMMDDTHHMMSS[Z])の値から、日付を取り出す。
+fn parse_date(value: &str) -> Result<NaiveDate, String> {
+    let err = || format!("日付の形式が不正です。:{}", value);
+    let (date, time) = match value.split_once('T') {
+        Some((d, t)) => (d, Some(t.strip_suffix('Z').unwrap_or(t))),
+        None => (value, None),
+    };
+    if let Some(time) = time {
+        if time.len() != 6 || !time.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(err());
+        }
+    }
+    if date.len() != 8 || !date.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(err());
+    }
+    NaiveDate::parse_from_str(date, "%Y%m%d").map_err(|_| err())
+}
@@ -0,0 +1,207 @@
+//! icalモジュールテスト
+
+use super::line::*;
+use super::*;
+use crate::export::ExportItem;
+use chrono::{Local, TimeZone};
+
+const SAMPLE: &str = include_str!("testdata/sample.ics");
+const UNTERMINATED: &str = include_str!("testdata/unterminated.ics");
+
+fn date(y: i32, m: u32, d: u32) -> Option<NaiveDate> {
+    NaiveDate::from_ymd_opt(y, m, d)
+}
+
+#[test]
+fn test_parse_sample() {
+    let parsed = parse(SAMPLE).unwrap();
+    assert_eq!(parsed.records.len(), 3, "VEVENTは無視する");
+
+    let rec = &parsed.records[0];
+    assert_eq!(rec.row, 10, "BEGIN:VTODOの行");
+    assert_eq!(rec.item.title, "週次報告を書く");
+    assert_eq!(
+        rec.item.work.as_deref(),
+        Some("先週の進捗\n来週の予定, 課題"),
+        "VALARMのDESCRIPTIONで上書きされない"
+    );
+    assert_eq!(rec.item.start_date, date(2024, 6, 1));
+    assert_eq!(rec.item.end_date, date(2024, 6, 15));
+    assert!(!rec.item.done);
+    assert_eq!(rec.tags, ["work", "報告"]);
+    assert_eq!(rec.recurrence.as_deref(), Some("FREQ=WEEKLY;BYDAY=FR"));
+
+    let rec = &parsed.records[1];
+    assert_eq!(
+        rec.item.title,
+        "とても長いタイトルの項目で、カレンダーアプリが七十五オクテットで折り返して出力したもの"
+    );
+    assert_eq!(rec.item.start_date, date(2024, 5, 20), "日時は日付部分のみ");
+    assert_eq!(rec.item.end_date, date(2024, 5, 31));
+    assert!(rec.item.done, "COMPLETEDがあれば完了");
+    assert_eq!(rec.tags, ["家", "買い物,日用品"]);
+    assert_eq!(rec.recurrence, None);
+
+    let rec = &parsed.records[2];
+    assert_eq!(rec.item.title, "期限なし");
+    assert_eq!(rec.item.start_date, None);
+    assert_eq!(rec.item.end_date, None);
+
+    assert_eq!(parsed.errors.len(), 1);
+    assert_eq!(parsed.errors[0].row, 42);
+    assert!(parsed.errors[0].message.contains("2024-06-01"));
+}
+
+#[test]
+fn test_parse_error() {
+    assert_eq!(
+        parse(UNTERMINATED).unwrap_err(),
+        IcalError::Unterminated(3, "VTODO".to_string())
+    );
+    assert_eq!(parse("").unwrap_err(), IcalError::NotCalendar);
+    assert_eq!(
+        parse("BEGIN:VEVENT\r\nEND:VEVENT\r\n").unwrap_err(),
+        IcalError::NotCalendar
+    );
+    assert_eq!(
+        parse("BEGIN:VCALENDAR\r\nこれは不正な行\r\nEND:VCALENDAR\r\n").unwrap_err(),
+        IcalError::InvalidLine(2)
+    );
+}
+
+#[test]
+fn test_roundtrip() {
+    let mut doc = ExportDocument::new(
+        "testdayo",
+        vec![
+            ExportItem {
+                id: 1,
+                title: "記号;,\\と改行を含む".to_string(),
+                work: Some(format!("一行目\n{}", "長い内容".repeat(30))),
+                start_date: date(2024, 6, 1),
+                end_date: date(2024, 6, 30),
+                update_date: date(2024, 6, 1),
+                done: true,
+                tags: vec!["a,b".to_string(), "猫".to_string()],
+                recurrence: Some("FREQ=MONTHLY;BYMONTHDAY=1".to_string()),
+                revision: 0,
+            },
+            ExportItem {
+                id: 2,
+                title: "期限なし".to_string(),
+                work: None,
+                start_date: date(2024, 6, 2),
+                end_date: date(9999, 12, 31),
+                update_date: date(2024, 6, 2),
+                done: false,
+                tags: vec![],
+                recurrence: None,
+                revision: 0,
+            },
+            ExportItem {
+                id: 3,
+                title: "当日のみ".to_string(),
+                work: None,
+                start_date: date(2024, 6, 3),
+                end_date: date(2024, 6, 3),
+                update_date: date(2024, 6, 2),
+                done: false,
+                tags: vec![],
+                recurrence: None,
+                revision: 0,
+            },
+        ],
+    );
+    doc.exported_at = Local.with_ymd_and_hms(2024, 6, 5, 12, 34, 56).unwrap();
+    let ics = render(&doc);
+    assert!(
+        ics.split("\r\n").all(|l| l.len() <= 75),
+        "75オクテットで折り返す"
+    );
+    assert!(
+        !ics.contains("DUE;VALUE=DATE:99991231"),
+        "期限なしはDUEなし"
+    );
+    assert!(
+        !ics.contains("DTSTART;VALUE=DATE:20240603"),
+        "DUEと同じ日のDTSTARTは出力しない"
+    );
+
+    let parsed = parse(&ics).unwrap();
+    assert!(parsed.errors.is_empty());
+    assert_eq!(parsed.records.len(), 3);
+    for (rec, item) in parsed.records.iter().zip(&doc.items) {
+        assert_eq!(rec.item.title, item.title);
+        assert_eq!(rec.item.work, item.work);
+        assert_eq!(rec.item.start_date, item.start_date);
+        assert_eq!(rec.item.done, item.done);
+        assert_eq!(rec.tags, item.tags);
+        assert_eq!(rec.recurrence, item.recurrence);
+    }
+    assert_eq!(parsed.records[0].item.end_date, date(2024, 6, 30));
+    assert_eq!(parsed.records[1].item.end_date, None);
+    assert_eq!(parsed.records[2].item.end_date, date(2024, 6, 3));
+}
+
+#[test]
+fn test_line() {
+    assert_eq!(
+        unfold("A:1\r\n B\r\n\tC\r\n\r\nD:2"),
+        [(1, "A:1BC".to_string()), (5, "D:2".to_string())]
+    );
+    assert_eq!(
+        parse_property(r#"DTSTART;TZID="Asia/Tokyo:x":20240520T100000"#),
+        Some(Property {
+            name: "DTSTART".to_string(),
+            params: vec![("TZID".to_string(), "Asia/Tokyo:x".to_string())],
+            value: "20240520T100000".to_string(),
+        })
+    );
+    assert_eq!(escape_text("a;b,c\\d\r\ne"), r"a\;b\,c\\d\ne");
+    assert_eq!(unescape_text(r"a\;b\,c\\d\Ne"), "a;b,c\\d\ne");
+    assert_eq!(split_text_list(r"a\,b,c\\,d"), ["a,b", "c\\", "d"]);
+}
+
+#[test]
+fn test_render_all_day() {
+    let mut doc = ExportDocument::new(
+        "testdayo",
+        vec![
+            ExportItem {
+                id: 1,
+                title: "締め切り".to_string(),
+                work: None,
+                start_date: date(2024, 6, 1),
+                end_date: date(2024, 6, 30),
+                update_date: date(2024, 6, 1),
+                done: false,
+                tags: vec!["work".to_string()],
+                recurrence: None,
+                revision: 0,
+            },
+            ExportItem {
+                id: 2,
+                title: "期限なし".to_string(),
+                work: None,
+                start_date: date(2024, 6, 2),
+                end_date: date(9999, 12, 31),
+                update_date: date(2024, 6, 2),
+                done: false,
+                tags: vec![],
+                recurrence: None,
+                revision: 0,
+            },
+        ],
+    );
+    doc.exported_at = Local.with_ymd_and_hms(2024, 6, 5, 12, 34, 56).unwrap();
+    let ics = render_all_day(&doc);
+    assert_eq!(
+        ics.matches("BEGIN:VEVENT").count(),
+        1,
+        "期限なしは出力しない"
+    );
+    assert!(ics.contains("\r\nDTSTART;VALUE=DATE:20240630\r\n"));
+    assert!(ics.contains("\r\nDTEND;VALUE=DATE:20240701\r\n"));
+    assert!(ics.contains("\r\nCATEGORIES:work\r\n"));
+    assert!(!ics.contains("VTODO"));
+}
@@ -0,0 +1,115 @@
+//! VTODOの出力
+
+use super::line::{escape_text, fold_into};
+use super::*;
+use crate::export::ExportItem;
+use chrono::Utc;
+
+/// エクスポート用データを、iCalendar形式の文字列に変換する。
+pub fn render(doc: &ExportDocument) -> String {
+    let no_end_date = NaiveDate::from_ymd_opt(9999, 12, 31).unwrap();
+    let dtstamp = doc
+        .exported_at
+        .with_timezone(&Utc)
+        .format("%Y%m%dT%H%M%SZ")
+        .to_string();
+    let mut out = String::new();
+    let mut line = |s: String| fold_into(&mut out, &s);
+
+    calendar_header(&mut line, doc);
+    for item in &doc.items {
+        line("BEGIN:VTODO".to_string());
+        line(format!(
+            "UID:{}",
+            escape_text(&format!("{}-{}@neko_todo", item.id, doc.user_name))
+        ));
+        line(format!("DTSTAMP:{}", dtstamp));
+        line(format!("SUMMARY:{}", escape_text(&item.title)));
+        if let Some(ref work) = item.work {
+            line(format!("DESCRIPTION:{}", escape_text(work)));
+        }
+        let end = item.end_date.filter(|d| *d != no_end_date);
+        // DUEはDTSTARTより後でなければならないため、同じ日であればDTSTARTを出力しない。
+        if let Some(start) = item.start_date.filter(|d| Some(*d) != end) {
+            line(format!("DTSTART;VALUE=DATE:{}", start.format("%Y%m%d")));
+        }
+        if let Some(end) = end {
+            line(format!("DUE;VALUE=DATE:{}", end.format("%Y%m%d")));
+        }
+        if item.done {
+            line("STATUS:COMPLETED".to_string());
+        } else {
+            line("STATUS:NEEDS-ACTION".to_string());
+        }
+        if !item.tags.is_empty() {
+            let tags = item.tags.iter().map(|t| escape_text(t)).collect::<Vec<_>>();
+            line(format!("CATEGORIES:{}", tags.join(",")));
+        }
+        if let Some(ref rrule) = item.recurrence {
+            line(format!("RRULE:{}", rrule));
+        }
+        line("END:VTODO".to_string());
+    }
+    line("END:VCALENDAR".to_string());
+    out
+}
+
+/// エクスポート用データを、終了日の終日予定(VEVENT)のiCalendar形式の文字列に変換する。
+/// 期限なし(9999/12/31)のtodoは、出力しない。
+pub fn render_all_day(doc: &ExportDocument) -> String {
+    let no_end_date = NaiveDate::from_ymd_opt(9999, 12, 31).unwrap();
+    let dtstamp = doc
+        .exported_at
+        .with_timezone(&Utc)
+        .format("%Y%m%dT%H%M%SZ")
+        .to_string();
+    let mut out = String::new();
+    let mut line = |s: String| fold_into(&mut out, &s);
+
+    calendar_header(&mut line, doc);
+    for item in &doc.items {
+        let Some(end) = item.end_date.filter(|d| *d != no_end_date) else {
+            continue;
+        };
+        line("BEGIN:VEVENT".to_string());
+        line(format!(
+            "UID:{}",
+            escape_text(&format!("{}-{}@neko_todo", item.id, doc.user_name))
+        ));
+        line(format!("DTSTAMP:{}", dtstamp));
+        line(format!("SUMMARY:{}", escape_text(&item.title)));
+        if let Some(ref work) = item.work {
+            line(format!("DESCRIPTION:{}", escape_text(work)));
+        }
+        line(format!("DTSTART;VALUE=DATE:{}", end.format("%Y%m%d")));
+        line(format!(
+            "DTEND;VALUE=DATE:{}",
+            (end + chrono::Days::new(1)).format("%Y%m%d")
+        ));
+        line("TRANSP:TRANSPARENT".to_string());
+        categories(&mut line, item);
+        line("END:VEVENT".to_string());
+    }
+    line("END:VCALENDAR".to_string());
+    out
+}
+
+/// VCALENDARの開始部分を出力する。
+fn calendar_header(line: &mut impl FnMut(String), doc: &ExportDocument) {
+    line("BEGIN:VCALENDAR".to_string());
+    line("VERSION:2.0".to_string());
+    line("PRODID:-//laki//neko_todo//JA".to_string());
+    line("CALSCALE:GREGORIAN".to_string());
+    line(format!(
+        "X-WR-CALNAME:{}",
+        escape_text(&format!("neko_todo ({})", doc.user_name))
+    ));
+}
+
+/// タグをCATEGORIESとして出力する。
+fn categories(line: &mut impl FnMut(String), item: &ExportItem) {
+    if !item.tags.is_empty() {
+        let tags = item.tags.iter().map(|t| escape_text(t)).collect::<Vec<_>>();
+        line(format!("CATEGORIES:{}", tags.join(",")));
+    }
+}
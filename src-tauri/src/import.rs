@@ -0,0 +1,185 @@
+//! todoのインポート
+//!
+//! エクスポート形式(JSON、CSV)のファイルを読み込み、追加するtodoの一覧に変換する。
+//! CSVは、列の対応付け(CsvMapping)を指定することで、表計算ソフトで作成した
+//! 任意の見出しのファイルも読み込める。
+//! 読み込めなかった行は、行番号付きのエラーとして報告する。
+mod csv;
+mod json;
+#[cfg(test)]
+mod test;
+
+use crate::database::ItemTodo;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// インポート形式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImportFormat {
+    Json,
+    Csv,
+}
+
+impl std::str::FromStr for ImportFormat {
+    type Err = ImportError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            _ => Err(ImportError::InvalidFormat(s.to_string())),
+        }
+    }
+}
+
+/// CSVの列の対応付け。各項目に、対応する列の見出しを指定する。
+/// Noneの項目は、読み込まない。(titleは必須)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct CsvMapping {
+    pub title: String,
+    pub work: Option<String>,
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+    pub done: Option<String>,
+    pub tags: Option<String>,
+}
+
+impl Default for CsvMapping {
+    /// エクスポートしたCSVの見出しと対応付ける。
+    fn default() -> Self {
+        Self {
+            title: "title".to_string(),
+            work: Some("work".to_string()),
+            start_date: Some("start_date".to_string()),
+            end_date: Some("end_date".to_string()),
+            done: Some("done".to_string()),
+            tags: Some("tags".to_string()),
+        }
+    }
+}
+
+impl CsvMapping {
+    /// "項目名=見出し"の形式で、一項目の対応付けを変更する。
+    /// 見出しが空なら、その項目を読み込まない。
+    pub fn set(&mut self, spec: &str) -> Result<(), ImportError> {
+        let Some((field, column)) = spec.split_once('=') else {
+            return Err(ImportError::InvalidMapping(spec.to_string()));
+        };
+        let column = (!column.is_empty()).then(|| column.to_string());
+        match field.trim() {
+            "title" => self.title = column.ok_or(ImportError::InvalidMapping(spec.to_string()))?,
+            "work" => self.work = column,
+            "start_date" => self.start_date = column,
+            "end_date" => self.end_date = column,
+            "done" => self.done = column,
+            "tags" => self.tags = column,
+            _ => return Err(ImportError::InvalidMapping(spec.to_string())),
+        }
+        Ok(())
+    }
+}
+
+/// インポートする一件分
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportRecord {
+    /// ファイル中の位置(CSVは行番号、JSONは何件目か)
+    pub row: usize,
+    pub item: ItemTodo,
+    pub tags: Vec<String>,
+    /// iCalendarのRRULEの値
+    pub recurrence: Option<String>,
+}
+
+/// 一件分のエラー
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ImportRowError {
+    pub row: usize,
+    pub message: String,
+}
+
+/// ファイルの読み込み結果
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParsedImport {
+    pub records: Vec<ImportRecord>,
+    pub errors: Vec<ImportRowError>,
+}
+
+/// 既存のtodo(または同じファイル中の先の行)と重複するため、追加しない一件
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ImportConflict {
+    pub row: usize,
+    pub title: String,
+    /// 重複する既存のtodoのid。同じファイル中の重複ならNone。
+    pub existing_id: Option<u32>,
+}
+
+/// インポートの結果報告
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ImportReport {
+    /// 試行のみで、登録を行わなかったか
+    pub dry_run: bool,
+    /// 読み込んだ件数
+    pub total: usize,
+    /// 登録した(dry_runの場合は、登録できる)件数
+    pub imported: usize,
+    /// 重複のため追加しない行
+    pub conflicts: Vec<ImportConflict>,
+    /// エラーとなった行。一件でもあれば、何も登録しない。
+    pub errors: Vec<ImportRowError>,
+}
+
+/// ファイル全体に関するエラー
+#[derive(Error, Debug)]
+pub enum ImportError {
+    #[error("不明なインポート形式です。:{0}")]
+    InvalidFormat(String),
+    #[error("列の対応付けが不正です。:{0}")]
+    InvalidMapping(String),
+    #[error("CSVに列[{0}]が見つかりません。")]
+    MissingColumn(String),
+    #[error("CSVの形式が不正です。({0}行目)")]
+    InvalidCsv(usize),
+    #[error("JSONの形式が不正です。:{0}")]
+    Json(#[from] serde_json::Error),
+    #[error("対応していないバージョンです。:{0}")]
+    UnsupportedVersion(u32),
+    #[error("ファイルが読み込めません。:{0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// ファイルの内容を読み込む。
+/// mappingは、CSVの時のみ使用する。
+pub fn parse(
+    data: &str,
+    format: ImportFormat,
+    mapping: &CsvMapping,
+) -> Result<ParsedImport, ImportError> {
+    let data = data.strip_prefix('\u{feff}').unwrap_or(data);
+    match format {
+        ImportFormat::Json => json::parse(data),
+        ImportFormat::Csv => csv::parse(data, mapping),
+    }
+}
+
+/// 日付を読み込む。空文字列はNoneとする。
+fn parse_date(s: &str) -> Result<Option<NaiveDate>, String> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Ok(None);
+    }
+    s.replace('/', "-")
+        .parse::<NaiveDate>()
+        .map(Some)
+        .map_err(|_| format!("日付の形式が不正です。:{}", s))
+}
+
+/// 完了状態を読み込む。空文字列は未完了とする。
+fn parse_done(s: &str) -> Result<bool, String> {
+    match s.trim().to_lowercase().as_str() {
+        "" | "false" | "0" | "no" | "未完了" => Ok(false),
+        "true" | "1" | "yes" | "x" | "完了" | "済" => Ok(true),
+        _ => Err(format!("完了状態の値が不正です。:{}", s)),
+    }
+}
@@ -0,0 +1,124 @@
+//! CSV形式の読み込み(RFC 4180)
+
+use super::*;
+use crate::export::TAG_SEPARATOR;
+
+/// CSVを読み込む。一行目は見出しとする。
+/// 行番号は、見出しを1行目として数える。
+pub(super) fn parse(data: &str, mapping: &CsvMapping) -> Result<ParsedImport, ImportError> {
+    let mut rows = read_records(data)?.into_iter();
+    let Some((_, header)) = rows.next() else {
+        return Err(ImportError::MissingColumn(mapping.title.clone()));
+    };
+    let column = |name: &String| {
+        header
+            .iter()
+            .position(|h| h.trim() == name)
+            .ok_or_else(|| ImportError::MissingColumn(name.clone()))
+    };
+    let optional = |name: &Option<String>| name.as_ref().map(column).transpose();
+    let title = column(&mapping.title)?;
+    let work = optional(&mapping.work)?;
+    let start_date = optional(&mapping.start_date)?;
+    let end_date = optional(&mapping.end_date)?;
+    let done = optional(&mapping.done)?;
+    let tags = optional(&mapping.tags)?;
+
+    let mut ret = ParsedImport::default();
+    for (row, fields) in rows {
+        if fields.iter().all(|f| f.is_empty()) {
+            continue; // 空行は無視する。
+        }
+        let get = |i: Option<usize>| {
+            i.and_then(|i| fields.get(i))
+                .map(|s| s.as_str())
+                .unwrap_or_default()
+        };
+        let record = (|| {
+            let work = get(work);
+            Ok::<_, String>(ImportRecord {
+                row,
+                item: ItemTodo {
+                    id: 0,
+                    user_name: String::new(),
+                    title: get(Some(title)).to_string(),
+                    work: (!work.is_empty()).then(|| work.to_string()),
+                    update_date: None,
+                    start_date: parse_date(get(start_date))?,
+                    end_date: parse_date(get(end_date))?,
+                    done: parse_done(get(done))?,
+                    revision: 0,
+                },
+                tags: get(tags)
+                    .split(TAG_SEPARATOR)
+                    .map(|t| t.trim().to_string())
+                    .filter(|t| !t.is_empty())
+                    .collect(),
+                recurrence: None,
+            })
+        })();
+        match record {
+            Ok(r) => ret.records.push(r),
+            Err(message) => ret.errors.push(ImportRowError { row, message }),
+        }
+    }
+    Ok(ret)
+}
+
+/// CSVをレコード単位に分割する。
+/// 引用符で囲まれたフィールド中の改行・カンマ・二重の引用符に対応する。
+/// 戻り値は、(レコードの開始行番号, フィールドの一覧)。
+fn read_records(data: &str) -> Result<Vec<(usize, Vec<String>)>, ImportError> {
+    let mut records = Vec::new();
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut line = 1;
+    let mut start_line = 1;
+    let mut in_quote = false;
+    let mut chars = data.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quote {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => {
+                    in_quote = false;
+                    if !matches!(chars.peek(), None | Some(',' | '\r' | '\n')) {
+                        return Err(ImportError::InvalidCsv(line));
+                    }
+                }
+                c => {
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    field.push(c);
+                }
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => in_quote = true,
+            '"' => return Err(ImportError::InvalidCsv(line)),
+            ',' => fields.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => { /* 次の\nで処理する */ }
+            '\n' => {
+                fields.push(std::mem::take(&mut field));
+                records.push((start_line, std::mem::take(&mut fields)));
+                line += 1;
+                start_line = line;
+            }
+            c => field.push(c),
+        }
+    }
+    if in_quote {
+        return Err(ImportError::InvalidCsv(start_line));
+    }
+    if !field.is_empty() || !fields.is_empty() {
+        fields.push(field);
+        records.push((start_line, fields));
+    }
+    Ok(records)
+}
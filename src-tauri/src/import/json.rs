@@ -0,0 +1,38 @@
+//! JSON形式の読み込み
+
+use super::*;
+use crate::export::{ExportDocument, EXPORT_VERSION};
+
+/// エクスポートしたJSONを読み込む。
+/// JSONは型付きで読み込むため、不正な値があればファイル全体のエラーとなる。
+pub(super) fn parse(data: &str) -> Result<ParsedImport, ImportError> {
+    let doc: ExportDocument = serde_json::from_str(data)?;
+    if doc.version > EXPORT_VERSION {
+        return Err(ImportError::UnsupportedVersion(doc.version));
+    }
+    let records = doc
+        .items
+        .into_iter()
+        .enumerate()
+        .map(|(i, item)| ImportRecord {
+            row: i + 1,
+            item: ItemTodo {
+                id: 0,
+                user_name: String::new(),
+                title: item.title,
+                work: item.work,
+                update_date: None,
+                start_date: item.start_date,
+                end_date: item.end_date,
+                done: item.done,
+                revision: 0,
+            },
+            tags: item.tags,
+            recurrence: item.recurrence,
+        })
+        .collect();
+    Ok(ParsedImport {
+        records,
+        errors: Vec::new(),
+    })
+}
@@ -0,0 +1,143 @@
+//! importモジュールテスト
+
+use super::*;
+use crate::export::{ExportDocument, ExportFormat, ExportItem};
+
+fn date(y: i32, m: u32, d: u32) -> Option<NaiveDate> {
+    NaiveDate::from_ymd_opt(y, m, d)
+}
+
+fn parse_csv(data: &str, mapping: &CsvMapping) -> ParsedImport {
+    parse(data, ImportFormat::Csv, mapping).unwrap_or_else(|e| unreachable!("解析失敗:{e}"))
+}
+
+#[test]
+fn test_csv_roundtrip() {
+    let doc = ExportDocument::new(
+        "testdayo",
+        vec![ExportItem {
+            id: 5,
+            title: "カンマ,と\"引用符\"".to_string(),
+            work: Some("一行目\r\n二行目".to_string()),
+            start_date: date(2024, 6, 2),
+            end_date: date(2024, 6, 9),
+            update_date: date(2024, 6, 3),
+            done: true,
+            tags: vec!["a".to_string(), "猫".to_string()],
+            recurrence: None,
+            revision: 0,
+        }],
+    );
+    let csv = String::from_utf8(doc.render(ExportFormat::Csv, true).unwrap()).unwrap();
+    let parsed = parse_csv(&csv, &CsvMapping::default());
+    assert!(parsed.errors.is_empty());
+    assert_eq!(parsed.records.len(), 1);
+    let rec = &parsed.records[0];
+    assert_eq!(rec.row, 2, "見出しの次の行");
+    assert_eq!(rec.item.title, "カンマ,と\"引用符\"");
+    assert_eq!(rec.item.work.as_deref(), Some("一行目\r\n二行目"));
+    assert_eq!(rec.item.start_date, date(2024, 6, 2));
+    assert_eq!(rec.item.end_date, date(2024, 6, 9));
+    assert!(rec.item.done);
+    assert_eq!(rec.tags, ["a", "猫"]);
+}
+
+#[test]
+fn test_json_roundtrip() {
+    let doc = ExportDocument::new(
+        "testdayo",
+        vec![ExportItem {
+            id: 5,
+            title: "JSONの項目".to_string(),
+            work: None,
+            start_date: date(2024, 6, 2),
+            end_date: None,
+            update_date: date(2024, 6, 3),
+            done: false,
+            tags: vec!["work".to_string()],
+            recurrence: None,
+            revision: 0,
+        }],
+    );
+    let json = String::from_utf8(doc.render(ExportFormat::Json, false).unwrap()).unwrap();
+    let parsed = parse(&json, ImportFormat::Json, &CsvMapping::default()).unwrap();
+    assert_eq!(parsed.records.len(), 1);
+    assert_eq!(parsed.records[0].row, 1);
+    assert_eq!(parsed.records[0].item.title, "JSONの項目");
+    assert_eq!(parsed.records[0].item.end_date, None);
+    assert_eq!(parsed.records[0].tags, ["work"]);
+
+    let future = json.replace(
+        &format!("\"version\": {}", crate::export::EXPORT_VERSION),
+        "\"version\": 999",
+    );
+    assert!(matches!(
+        parse(&future, ImportFormat::Json, &CsvMapping::default()),
+        Err(ImportError::UnsupportedVersion(999))
+    ));
+}
+
+#[test]
+fn test_csv_mapping() {
+    let data = "件名,期限,メモ,状態\n\
+                書類作成,2024/07/01,,済\n\
+                \n\
+                会議,,議事録,\n";
+    let mut mapping = CsvMapping::default();
+    for spec in ["title=件名", "end_date=期限", "work=メモ", "done=状態"] {
+        mapping.set(spec).unwrap();
+    }
+    for spec in ["start_date=", "tags="] {
+        mapping.set(spec).unwrap();
+    }
+    let parsed = parse_csv(data, &mapping);
+    assert!(parsed.errors.is_empty());
+    assert_eq!(parsed.records.len(), 2, "空行は無視する");
+    assert_eq!(parsed.records[0].item.title, "書類作成");
+    assert_eq!(parsed.records[0].item.end_date, date(2024, 7, 1));
+    assert_eq!(parsed.records[0].item.work, None);
+    assert!(parsed.records[0].item.done);
+    assert_eq!(parsed.records[1].row, 4);
+    assert_eq!(parsed.records[1].item.work.as_deref(), Some("議事録"));
+    assert!(!parsed.records[1].item.done);
+
+    assert!(matches!(
+        parse(data, ImportFormat::Csv, &CsvMapping::default()),
+        Err(ImportError::MissingColumn(c)) if c == "title"
+    ));
+    assert!(mapping.set("foo=bar").is_err(), "不明な項目");
+    assert!(mapping.set("title=").is_err(), "titleは必須");
+    assert!(mapping.set("title").is_err(), "=がない");
+}
+
+#[test]
+fn test_csv_row_error() {
+    let data = "title,start_date,done\n\
+                正常,2024-01-01,false\n\
+                日付不正,2024-13-01,false\n\
+                状態不正,,たぶん\n";
+    let mut mapping = CsvMapping::default();
+    for spec in ["work=", "end_date=", "tags="] {
+        mapping.set(spec).unwrap();
+    }
+    let parsed = parse_csv(data, &mapping);
+    assert_eq!(parsed.records.len(), 1);
+    assert_eq!(
+        parsed.errors.iter().map(|e| e.row).collect::<Vec<_>>(),
+        [3, 4]
+    );
+    assert!(parsed.errors[0].message.contains("2024-13-01"));
+
+    assert!(matches!(
+        parse(
+            "title\n\"閉じてない\n",
+            ImportFormat::Csv,
+            &CsvMapping::default()
+        ),
+        Err(ImportError::InvalidCsv(2))
+    ));
+    assert!(matches!(
+        parse("title\n\"a\"b\n", ImportFormat::Csv, &CsvMapping::default()),
+        Err(ImportError::InvalidCsv(2))
+    ));
+}
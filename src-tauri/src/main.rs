@@ -2,19 +2,50 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 mod app_status;
+mod backup;
+mod change_feed;
+mod cli;
 mod command;
 mod config;
+mod credential;
 mod database;
+mod export;
+mod feed;
+mod filter;
+mod ical;
+mod import;
+mod offline;
+mod pdf;
+mod report;
 mod setup;
 mod todo;
+mod todotxt;
 
 use app_status::AppStatus;
 use command::app_state::{
-    get_is_incomplete, get_item_sort_order, set_is_incomplete, set_item_sort_order,
+    get_is_incomplete, get_item_sort_order, get_user_settings, set_is_incomplete,
+    set_item_sort_order, set_user_settings,
 };
+use command::backup::{backup_account, restore_account};
+use command::connection::get_connection_status;
+use command::export::export_todo;
+use command::feed::{get_feed_all_day, get_feed_url, reset_feed_url, set_feed_all_day};
+use command::ical::{export_ical, import_ical};
+use command::import::import_todo;
+use command::offline::{clear_offline_conflicts, get_offline_status};
+use command::print::export_todo_pdf;
+use command::profile::{get_profiles, switch_profile};
+use command::report::generate_report;
 use command::session::is_valid_session;
-use command::todo::{add_todo, edit_todo, get_todo_list, get_todo_with_id, update_done};
+use command::setup::{
+    apply_migrations, get_connection_settings, save_connection_settings, test_connection,
+};
+use command::todo::{
+    add_todo, delete_todo, edit_todo, get_todo_history, get_todo_list, get_todo_with_id,
+    get_undo_state, redo, undo, update_done,
+};
 use command::user::{login, regist_user};
+use command::view::{add_view, delete_view, edit_view, get_todo_list_for_view, get_views};
 use directories::ProjectDirs;
 use log::{error, info};
 use setup::setup;
@@ -41,10 +72,43 @@ fn run(app_status: AppStatus) {
             add_todo,
             update_done,
             edit_todo,
+            delete_todo,
+            get_todo_history,
+            undo,
+            redo,
+            get_undo_state,
             set_is_incomplete,
             get_is_incomplete,
             set_item_sort_order,
             get_item_sort_order,
+            get_user_settings,
+            set_user_settings,
+            get_views,
+            add_view,
+            edit_view,
+            delete_view,
+            get_todo_list_for_view,
+            export_todo,
+            import_todo,
+            export_ical,
+            import_ical,
+            get_feed_url,
+            reset_feed_url,
+            get_feed_all_day,
+            set_feed_all_day,
+            generate_report,
+            export_todo_pdf,
+            backup_account,
+            restore_account,
+            get_offline_status,
+            clear_offline_conflicts,
+            get_connection_status,
+            get_profiles,
+            switch_profile,
+            get_connection_settings,
+            test_connection,
+            apply_migrations,
+            save_connection_settings,
         ])
         .setup(|app| {
             let win = app.get_webview_window("main").unwrap();
@@ -74,6 +138,50 @@ fn run(app_status: AppStatus) {
                     _ => { /* 何もしない */ }
                 }
             });
+
+            // ICSフィードの提供開始
+            let port = app
+                .state::<AppStatus>()
+                .config()
+                .lock()
+                .unwrap()
+                .get_ics_feed_port();
+            if let Some(port) = port {
+                start_ics_feed(app.handle().clone(), port);
+            }
+
+            // todo.txtファイルとの同期開始
+            let todotxt = {
+                let app_state = app.state::<AppStatus>();
+                let conf = app_state.config().lock().unwrap();
+                conf.get_todotxt_path()
+                    .map(|p| (p.to_path_buf(), conf.get_todotxt_interval()))
+            };
+            if let Some((path, interval)) = todotxt {
+                todotxt::spawn_todotxt_sync(
+                    app.handle().clone(),
+                    path,
+                    std::time::Duration::from_secs(interval.max(1)),
+                );
+            }
+
+            // 他のプロセスによる設定ファイルの変更の監視開始
+            config::spawn_config_watcher(app.handle().clone());
+
+            // todoの変更通知の開始
+            change_feed::spawn_change_feed(app.handle().clone());
+
+            // データベースへの接続の監視開始
+            let interval = app
+                .state::<AppStatus>()
+                .config()
+                .lock()
+                .unwrap()
+                .get_health_interval();
+            start_connection_monitor(
+                app.handle().clone(),
+                std::time::Duration::from_secs(interval.max(1)),
+            );
             Ok(())
         })
         .build(tauri::generate_context!())
@@ -83,7 +191,52 @@ fn run(app_status: AppStatus) {
         let state = app.state::<AppStatus>();
         if let tauri::RunEvent::Exit = event {
             info!("終了処理開始");
-            state.config().lock().unwrap().save().unwrap();
+            if let Err(e) = state.config().lock().unwrap().save() {
+                error!("設定ファイルの保存に失敗:{e}");
+            }
+        }
+    });
+}
+
+/// ICSフィードの提供を、バックグラウンドで開始する。
+fn start_ics_feed(app: tauri::AppHandle, port: u16) {
+    tauri::async_runtime::spawn(async move {
+        let listener = match tokio::net::TcpListener::bind(("127.0.0.1", port)).await {
+            Ok(l) => l,
+            Err(e) => {
+                error!("ICSフィードのポート({})を開けません:{}", port, e);
+                return;
+            }
+        };
+        feed::serve(listener, move |token| {
+            let app = app.clone();
+            async move {
+                let state = app.state::<AppStatus>();
+                let all_day = state.config().lock().unwrap().get_ics_feed_all_day();
+                state
+                    .todo()
+                    .render_feed(&token, all_day)
+                    .await
+                    .map_err(|e| e.to_string())
+            }
+        })
+        .await;
+    });
+}
+
+/// データベースへの接続の監視を、バックグラウンドで開始する。
+/// 接続できない間は、間隔を延ばしながら再接続を試みる。
+fn start_connection_monitor(app: tauri::AppHandle, interval: std::time::Duration) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let state = app.state::<AppStatus>();
+            // 接続情報が設定されるまでは、確認しない。
+            let wait = if state.is_configured() {
+                state.todo().check_connection(interval).await
+            } else {
+                interval
+            };
+            tokio::time::sleep(wait).await;
         }
     });
 }
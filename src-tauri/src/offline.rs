@@ -0,0 +1,432 @@
+//! オフライン時のローカルキャッシュ
+//!
+//! データベースに接続できない間も、最後にログインしたユーザーのtodoを閲覧・変更できるよう、
+//! todoの一覧をローカルのファイルに保持する。
+//! オフライン中の変更は、キャッシュに反映したうえで送信待ちの列に積み、
+//! 再接続後に順に送信する。他で更新されていた変更は、データベース側を優先し、競合として記録する。
+//! オフライン中に追加したtodoには、データベースのidと重ならない仮のidを割り当てる。
+#[cfg(test)]
+mod test;
+
+use crate::config::ItemSortOrder;
+use crate::database::ItemTodo;
+use chrono::{Local, NaiveDate, NaiveDateTime};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use thiserror::Error;
+use uuid::Uuid;
+
+/// キャッシュファイルのファイル名
+pub const CACHE_FILE_NAME: &str = "offline_cache.json";
+/// 仮のidの最小値。これ以上のidは、オフライン中に追加したtodoを表す。
+const LOCAL_ID_MIN: u32 = 0xF000_0000;
+
+/// オフライン時のローカルキャッシュ
+#[derive(Debug)]
+pub struct OfflineCache {
+    path: PathBuf,
+    data: Mutex<CacheData>,
+    online: AtomicBool,
+}
+
+/// キャッシュファイルの内容
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct CacheData {
+    /// キャッシュの持ち主
+    pub user_name: String,
+    /// 持ち主の最新のセッション
+    pub session: Option<Uuid>,
+    pub todos: Vec<ItemTodo>,
+    /// 送信待ちの変更(古い順)
+    pub queue: Vec<QueuedChange>,
+    /// 再接続後の送信で、反映できなかった変更
+    pub conflicts: Vec<OfflineConflict>,
+    /// 最後にデータベースと同期した日時
+    pub synced_at: Option<NaiveDateTime>,
+    /// 最後に同期した時点の、最新の変更履歴のid
+    pub history_id: Option<u64>,
+}
+
+/// オフライン中の変更
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum QueuedChange {
+    /// 追加。item.idは仮のid。
+    Add {
+        item: ItemTodo,
+    },
+    /// 編集。item.revisionは、変更の元にした版番号。
+    Edit {
+        item: ItemTodo,
+    },
+    Done {
+        id: u32,
+        done: bool,
+        revision: u32,
+    },
+    Delete {
+        id: u32,
+    },
+}
+
+/// 反映できなかった変更
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct OfflineConflict {
+    pub change: QueuedChange,
+    /// データベース上の最新のtodo。削除されていた場合はNone。
+    pub current: Option<ItemTodo>,
+}
+
+/// 送信待ちの変更を、データベースへ送信した結果
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplayOutcome {
+    /// 反映できた。データベース上のidと、反映後の版番号を持つ。
+    Applied { id: u32, revision: u32 },
+    /// 反映できなかった。
+    Conflict(OfflineConflict),
+}
+
+/// 接続状態と、送信待ちの状況
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct OfflineStatus {
+    pub online: bool,
+    /// 送信待ちの変更の件数
+    pub pending: usize,
+    pub conflicts: Vec<OfflineConflict>,
+    pub synced_at: Option<NaiveDateTime>,
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum OfflineError {
+    #[error("NotFoundTodo")]
+    NotFoundTodo,
+    /// キャッシュ上で、他の版番号に更新されていた。キャッシュ上のtodoを持つ。
+    #[error("Conflict")]
+    Conflict(Box<ItemTodo>),
+}
+
+/// オフライン中に追加したtodoの、仮のidか。
+pub fn is_local_id(id: u32) -> bool {
+    id >= LOCAL_ID_MIN
+}
+
+impl OfflineCache {
+    /// キャッシュファイルを読み込む。ファイルがないか壊れていれば、空のキャッシュとする。
+    pub fn open(path: &Path) -> Self {
+        let data = match std::fs::read_to_string(path) {
+            Ok(s) => serde_json::from_str(&s).unwrap_or_else(|e| {
+                warn!("オフラインキャッシュを読み込めません。破棄します。:{e}");
+                CacheData::default()
+            }),
+            Err(_) => CacheData::default(),
+        };
+        Self {
+            path: path.to_path_buf(),
+            data: Mutex::new(data),
+            online: AtomicBool::new(true),
+        }
+    }
+
+    pub fn is_online(&self) -> bool {
+        self.online.load(Ordering::Relaxed)
+    }
+
+    pub fn set_online(&self, online: bool) {
+        self.online.store(online, Ordering::Relaxed);
+    }
+
+    /// キャッシュが指定セッションのものであれば、持ち主のユーザー名を返す。
+    pub fn user_of(&self, sess: Uuid) -> Option<String> {
+        let data = self.data.lock().unwrap();
+        (data.session == Some(sess)).then(|| data.user_name.clone())
+    }
+
+    /// キャッシュの持ち主とセッションを取得する。
+    pub fn owner(&self) -> Option<(String, Uuid)> {
+        let data = self.data.lock().unwrap();
+        data.session.map(|s| (data.user_name.clone(), s))
+    }
+
+    /// ログイン・セッションの更新を記録する。
+    /// 別のユーザーになった場合は、キャッシュを作り直す。
+    pub fn set_session(&self, user_name: &str, sess: Uuid) {
+        self.update(|data| {
+            if data.user_name != user_name {
+                if !data.queue.is_empty() {
+                    warn!(
+                        "{}の送信待ちの変更{}件を破棄します。",
+                        data.user_name,
+                        data.queue.len()
+                    );
+                }
+                *data = CacheData {
+                    user_name: user_name.to_string(),
+                    ..Default::default()
+                };
+            }
+            data.session = Some(sess);
+        });
+    }
+
+    /// 最後に同期した時点の、最新の変更履歴のidを取得する。
+    pub fn history_id(&self) -> Option<u64> {
+        self.data.lock().unwrap().history_id
+    }
+
+    /// データベースから取得したtodoで、キャッシュを置き換える。
+    /// 送信待ちの変更があれば、その上に反映し直す。
+    pub fn replace_todos(&self, todos: Vec<ItemTodo>, history_id: u64) {
+        self.update(|data| {
+            data.todos = todos;
+            for change in data.queue.clone() {
+                data.apply(&change);
+            }
+            data.synced_at = Some(Local::now().naive_local());
+            data.history_id = Some(history_id);
+        });
+    }
+
+    /// キャッシュから、todoの一覧を取得する。
+    pub fn list(
+        &self,
+        ref_date: NaiveDate,
+        only_incomplete: bool,
+        sort_order: ItemSortOrder,
+    ) -> Vec<ItemTodo> {
+        self.data
+            .lock()
+            .unwrap()
+            .list(ref_date, only_incomplete, sort_order)
+    }
+
+    /// キャッシュから、todoを一件取得する。
+    pub fn get(&self, id: u32) -> Option<ItemTodo> {
+        let data = self.data.lock().unwrap();
+        data.todos.iter().find(|t| t.id == id).cloned()
+    }
+
+    /// オフライン中の変更を、キャッシュに反映して送信待ちの列に積む。
+    /// 追加の場合は仮のidを、それ以外は変更後の版番号を返す。
+    pub fn enqueue(&self, change: QueuedChange) -> Result<u32, OfflineError> {
+        let mut data = self.data.lock().unwrap();
+        let ret = data.enqueue(change)?;
+        self.save(&data);
+        Ok(ret)
+    }
+
+    /// 最も古い送信待ちの変更を取得する。
+    pub fn front(&self) -> Option<QueuedChange> {
+        self.data.lock().unwrap().queue.first().cloned()
+    }
+
+    /// 送信の済んだ、最も古い変更を列から除き、結果を記録する。
+    pub fn complete_front(&self, outcome: ReplayOutcome) {
+        self.update(|data| data.complete_front(outcome));
+    }
+
+    /// 接続状態と、送信待ちの状況を取得する。
+    pub fn status(&self) -> OfflineStatus {
+        let data = self.data.lock().unwrap();
+        OfflineStatus {
+            online: self.is_online(),
+            pending: data.queue.len(),
+            conflicts: data.conflicts.clone(),
+            synced_at: data.synced_at,
+        }
+    }
+
+    /// 記録した競合を破棄する。
+    pub fn clear_conflicts(&self) {
+        self.update(|data| data.conflicts.clear());
+    }
+
+    fn update(&self, f: impl FnOnce(&mut CacheData)) {
+        let mut data = self.data.lock().unwrap();
+        f(&mut data);
+        self.save(&data);
+    }
+
+    /// キャッシュファイルに保存する。書きかけのファイルが残らないよう、置き換えで保存する。
+    fn save(&self, data: &CacheData) {
+        let tmp = self.path.with_extension("json.tmp");
+        let res = serde_json::to_vec(data)
+            .map_err(std::io::Error::other)
+            .and_then(|json| std::fs::write(&tmp, json))
+            .and_then(|_| std::fs::rename(&tmp, &self.path));
+        if let Err(e) = res {
+            warn!("オフラインキャッシュを保存できません。:{e}");
+        }
+    }
+}
+
+impl CacheData {
+    /// 基準日に開始済みのtodoを、指定の順に並べて取得する。
+    fn list(
+        &self,
+        ref_date: NaiveDate,
+        only_incomplete: bool,
+        sort_order: ItemSortOrder,
+    ) -> Vec<ItemTodo> {
+        let mut items = self
+            .todos
+            .iter()
+            .filter(|t| t.start_date.is_none_or(|d| d <= ref_date))
+            .filter(|t| !only_incomplete || !t.done)
+            .cloned()
+            .collect::<Vec<_>>();
+        items.sort_by(|a, b| {
+            let ord = match sort_order {
+                ItemSortOrder::StartAsc => a.start_date.cmp(&b.start_date),
+                ItemSortOrder::StartDesc => b.start_date.cmp(&a.start_date),
+                ItemSortOrder::EndAsc => a.end_date.cmp(&b.end_date),
+                ItemSortOrder::EndDesc => b.end_date.cmp(&a.end_date),
+                ItemSortOrder::UpdateAsc => a.update_date.cmp(&b.update_date),
+                ItemSortOrder::UpdateDesc => b.update_date.cmp(&a.update_date),
+            };
+            ord.then(a.id.cmp(&b.id))
+        });
+        items
+    }
+
+    fn enqueue(&mut self, change: QueuedChange) -> Result<u32, OfflineError> {
+        let change = match change {
+            QueuedChange::Add { mut item } => {
+                item.id = self
+                    .todos
+                    .iter()
+                    .map(|t| t.id)
+                    .chain(self.queue.iter().filter_map(|c| match c {
+                        QueuedChange::Add { item } => Some(item.id),
+                        _ => None,
+                    }))
+                    .filter(|&id| is_local_id(id))
+                    .max()
+                    .map_or(LOCAL_ID_MIN, |id| id + 1);
+                item.user_name = self.user_name.clone();
+                item.done = false;
+                item.revision = 0;
+                QueuedChange::Add { item }
+            }
+            QueuedChange::Edit { ref item } => {
+                self.check_revision(item.id, item.revision)?;
+                change
+            }
+            QueuedChange::Done { id, revision, .. } => {
+                self.check_revision(id, revision)?;
+                change
+            }
+            QueuedChange::Delete { id } => {
+                self.todos
+                    .iter()
+                    .find(|t| t.id == id)
+                    .ok_or(OfflineError::NotFoundTodo)?;
+                change
+            }
+        };
+        let ret = match change {
+            QueuedChange::Add { ref item } => item.id,
+            QueuedChange::Edit { ref item } => item.revision,
+            QueuedChange::Done { revision, .. } => revision,
+            QueuedChange::Delete { .. } => 0,
+        };
+        self.apply(&change);
+        self.queue.push(change);
+        Ok(ret)
+    }
+
+    /// キャッシュ上のtodoの版番号が、変更の元にした版番号と一致するか確認する。
+    /// オフライン中の変更では版番号を進めないため、送信時の版番号の確認にそのまま使える。
+    fn check_revision(&self, id: u32, revision: u32) -> Result<(), OfflineError> {
+        let item = self
+            .todos
+            .iter()
+            .find(|t| t.id == id)
+            .ok_or(OfflineError::NotFoundTodo)?;
+        if item.revision != revision {
+            return Err(OfflineError::Conflict(Box::new(item.clone())));
+        }
+        Ok(())
+    }
+
+    fn complete_front(&mut self, outcome: ReplayOutcome) {
+        if self.queue.is_empty() {
+            return;
+        }
+        let done = self.queue.remove(0);
+        let (old_id, base) = done.target();
+        match outcome {
+            // 同じtodoへの後続の変更が、反映後のidと版番号を元にするよう置き換える。
+            ReplayOutcome::Applied { id, revision } => {
+                for change in self.queue.iter_mut() {
+                    change.retarget(old_id, base, id, revision);
+                }
+                if let Some(t) = self.todos.iter_mut().find(|t| t.id == old_id) {
+                    t.id = id;
+                    t.revision = revision;
+                }
+            }
+            ReplayOutcome::Conflict(conflict) => self.conflicts.push(conflict),
+        }
+    }
+
+    /// 変更をキャッシュ上のtodoに反映する。
+    fn apply(&mut self, change: &QueuedChange) {
+        let today = Some(Local::now().date_naive());
+        match change {
+            QueuedChange::Add { item } => {
+                self.todos.retain(|t| t.id != item.id);
+                self.todos.push(ItemTodo {
+                    update_date: today,
+                    ..item.clone()
+                });
+            }
+            QueuedChange::Edit { item } => {
+                if let Some(t) = self.todos.iter_mut().find(|t| t.id == item.id) {
+                    t.title = item.title.clone();
+                    t.work = item.work.clone();
+                    t.start_date = item.start_date;
+                    t.end_date = item.end_date;
+                    t.update_date = today;
+                }
+            }
+            QueuedChange::Done { id, done, .. } => {
+                if let Some(t) = self.todos.iter_mut().find(|t| t.id == *id) {
+                    t.done = *done;
+                }
+            }
+            QueuedChange::Delete { id } => self.todos.retain(|t| t.id != *id),
+        }
+    }
+}
+
+impl QueuedChange {
+    /// 変更するtodoのidと、変更の元にした版番号
+    fn target(&self) -> (u32, u32) {
+        match self {
+            Self::Add { item } | Self::Edit { item } => (item.id, item.revision),
+            Self::Done { id, revision, .. } => (*id, *revision),
+            Self::Delete { id } => (*id, 0),
+        }
+    }
+
+    /// 指定のtodoの指定の版番号への変更を、新たなidと版番号への変更に置き換える。
+    fn retarget(&mut self, old_id: u32, base: u32, id: u32, revision: u32) {
+        let (target_id, target_rev) = match self {
+            Self::Add { item } | Self::Edit { item } => (&mut item.id, Some(&mut item.revision)),
+            Self::Done {
+                id, revision: r, ..
+            } => (id, Some(r)),
+            Self::Delete { id } => (id, None),
+        };
+        if *target_id != old_id {
+            return;
+        }
+        *target_id = id;
+        if let Some(r) = target_rev.filter(|r| **r == base) {
+            *r = revision;
+        }
+    }
+}
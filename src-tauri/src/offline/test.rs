@@ -0,0 +1,189 @@
+//! offlineモジュールテスト
+
+use super::*;
+
+fn date(m: u32, d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2024, m, d).unwrap()
+}
+
+fn item(id: u32, title: &str, start: NaiveDate, end: NaiveDate, revision: u32) -> ItemTodo {
+    ItemTodo {
+        id,
+        user_name: "testdayo".to_string(),
+        title: title.to_string(),
+        work: None,
+        update_date: Some(date(1, 1)),
+        start_date: Some(start),
+        end_date: Some(end),
+        done: false,
+        revision,
+    }
+}
+
+fn sample() -> CacheData {
+    CacheData {
+        user_name: "testdayo".to_string(),
+        session: Some(Uuid::now_v7()),
+        todos: vec![
+            item(1, "一件目", date(1, 1), date(3, 1), 2),
+            item(2, "二件目", date(1, 5), date(2, 1), 0),
+            item(3, "未来", date(12, 1), date(12, 31), 0),
+        ],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_list() {
+    let mut data = sample();
+    data.todos[1].done = true;
+    let ids = |items: Vec<ItemTodo>| items.iter().map(|t| t.id).collect::<Vec<_>>();
+    assert_eq!(
+        ids(data.list(date(6, 1), false, ItemSortOrder::EndAsc)),
+        [2, 1]
+    );
+    assert_eq!(
+        ids(data.list(date(6, 1), false, ItemSortOrder::StartDesc)),
+        [2, 1]
+    );
+    assert_eq!(ids(data.list(date(6, 1), true, ItemSortOrder::EndAsc)), [1]);
+    assert_eq!(
+        ids(data.list(date(12, 31), false, ItemSortOrder::EndDesc)),
+        [3, 1, 2]
+    );
+}
+
+#[test]
+fn test_enqueue() {
+    let mut data = sample();
+
+    // 追加には仮のidを割り当てる。
+    let new = item(0, "追加", date(1, 1), date(1, 31), 9);
+    let id1 = data
+        .enqueue(QueuedChange::Add { item: new.clone() })
+        .unwrap();
+    let id2 = data.enqueue(QueuedChange::Add { item: new }).unwrap();
+    assert_eq!(id1, LOCAL_ID_MIN);
+    assert_eq!(id2, LOCAL_ID_MIN + 1);
+    assert!(is_local_id(id1) && !is_local_id(3));
+    let added = data.todos.iter().find(|t| t.id == id1).unwrap();
+    assert_eq!(added.revision, 0);
+    assert_eq!(added.update_date, Some(Local::now().date_naive()));
+
+    // 版番号が異なれば競合。オフライン中は版番号を進めない。
+    let mut edit = data.todos[0].clone();
+    edit.title = "編集".to_string();
+    edit.revision = 1;
+    match data.enqueue(QueuedChange::Edit { item: edit.clone() }) {
+        Err(OfflineError::Conflict(current)) => assert_eq!(current.revision, 2),
+        res => unreachable!("競合になるはず。{res:?}"),
+    }
+    edit.revision = 2;
+    assert_eq!(data.enqueue(QueuedChange::Edit { item: edit }), Ok(2));
+    assert_eq!(
+        data.enqueue(QueuedChange::Done {
+            id: 1,
+            done: true,
+            revision: 2
+        }),
+        Ok(2)
+    );
+    assert_eq!(data.todos[0].title, "編集");
+    assert!(data.todos[0].done);
+
+    assert_eq!(data.enqueue(QueuedChange::Delete { id: 2 }), Ok(0));
+    assert_eq!(
+        data.enqueue(QueuedChange::Delete { id: 2 }),
+        Err(OfflineError::NotFoundTodo)
+    );
+    assert!(data.todos.iter().all(|t| t.id != 2));
+    assert_eq!(data.queue.len(), 5);
+}
+
+#[test]
+fn test_complete_front() {
+    let mut data = sample();
+    let local = data
+        .enqueue(QueuedChange::Add {
+            item: item(0, "追加", date(1, 1), date(1, 31), 0),
+        })
+        .unwrap();
+    let mut edit = data.todos.iter().find(|t| t.id == local).unwrap().clone();
+    edit.title = "追加後に編集".to_string();
+    data.enqueue(QueuedChange::Edit { item: edit }).unwrap();
+    data.enqueue(QueuedChange::Done {
+        id: local,
+        done: true,
+        revision: 0,
+    })
+    .unwrap();
+    data.enqueue(QueuedChange::Done {
+        id: 1,
+        done: true,
+        revision: 2,
+    })
+    .unwrap();
+
+    // 追加の送信後は、後続の変更が新しいidを対象とする。
+    data.complete_front(ReplayOutcome::Applied {
+        id: 50,
+        revision: 0,
+    });
+    assert!(data.todos.iter().any(|t| t.id == 50));
+    assert_eq!(data.queue[0].target(), (50, 0));
+    assert_eq!(data.queue[1].target(), (50, 0));
+
+    // 編集の送信後は、後続の変更が新しい版番号を元にする。
+    data.complete_front(ReplayOutcome::Applied {
+        id: 50,
+        revision: 1,
+    });
+    assert_eq!(data.queue[0].target(), (50, 1));
+    assert_eq!(data.queue[1].target(), (1, 2));
+    data.complete_front(ReplayOutcome::Applied {
+        id: 50,
+        revision: 2,
+    });
+
+    // 反映できなかった変更は、競合として記録する。
+    let change = data.queue[0].clone();
+    data.complete_front(ReplayOutcome::Conflict(OfflineConflict {
+        change: change.clone(),
+        current: None,
+    }));
+    assert!(data.queue.is_empty());
+    assert_eq!(data.conflicts.len(), 1);
+    assert_eq!(data.conflicts[0].change, change);
+}
+
+#[test]
+fn test_open_and_save() {
+    let path = std::env::temp_dir().join(format!("offline_test_{}.json", Uuid::now_v7()));
+    let sess = Uuid::now_v7();
+    {
+        let cache = OfflineCache::open(&path);
+        assert_eq!(cache.owner(), None);
+        cache.set_session("testdayo", sess);
+        cache.replace_todos(sample().todos, 10);
+        cache.enqueue(QueuedChange::Delete { id: 1 }).unwrap();
+    }
+    let cache = OfflineCache::open(&path);
+    assert_eq!(cache.owner(), Some(("testdayo".to_string(), sess)));
+    assert_eq!(cache.user_of(sess), Some("testdayo".to_string()));
+    assert_eq!(cache.user_of(Uuid::now_v7()), None);
+    assert_eq!(cache.history_id(), Some(10));
+    assert_eq!(cache.get(1), None);
+    assert_eq!(cache.status().pending, 1);
+
+    // データベースから取得し直しても、送信待ちの変更は反映されたまま。
+    cache.replace_todos(sample().todos, 11);
+    assert_eq!(cache.get(1), None);
+    assert!(cache.get(2).is_some());
+
+    // 別のユーザーになれば、作り直す。
+    cache.set_session("tanin", sess);
+    assert_eq!(cache.get(2), None);
+    assert_eq!(cache.status().pending, 0);
+    assert_eq!(cache.history_id(), None);
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,97 @@
+//! 印刷用のtodo一覧のPDF出力
+//!
+//! todoの一覧を、タイトル・日付・状態・内容を含めて、ページ分割したPDFにする。
+//! 用紙はA4・レターの縦・横に対応する。
+//! 日本語は、埋め込みなしのCIDフォントで出力するため、フォントファイルを必要としない。
+mod layout;
+#[cfg(test)]
+mod test;
+mod writer;
+
+use chrono::{Local, NaiveDate};
+use thiserror::Error;
+
+pub use layout::render_todo_list;
+
+/// ミリメートルをポイントに変換する係数
+const MM: f32 = 72.0 / 25.4;
+
+/// 用紙サイズ
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PaperSize {
+    #[default]
+    A4,
+    Letter,
+}
+
+/// 用紙の向き
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Orientation {
+    #[default]
+    Portrait,
+    Landscape,
+}
+
+/// ページの設定
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct PageSetup {
+    pub paper: PaperSize,
+    pub orientation: Orientation,
+}
+
+/// 一覧の見出しに載せる情報
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListHeading {
+    pub title: String,
+    pub user_name: String,
+    /// 絞り込み条件。空なら出力しない。
+    pub filter: String,
+    /// 期限切れの判定と、出力日に使う基準日
+    pub ref_date: NaiveDate,
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum PdfError {
+    #[error("未対応の用紙サイズです。:{0}")]
+    InvalidPaper(String),
+    #[error("未対応の用紙の向きです。:{0}")]
+    InvalidOrientation(String),
+}
+
+impl std::str::FromStr for PaperSize {
+    type Err = PdfError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "a4" => Ok(Self::A4),
+            "letter" => Ok(Self::Letter),
+            _ => Err(PdfError::InvalidPaper(s.to_string())),
+        }
+    }
+}
+
+impl std::str::FromStr for Orientation {
+    type Err = PdfError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "portrait" => Ok(Self::Portrait),
+            "landscape" => Ok(Self::Landscape),
+            _ => Err(PdfError::InvalidOrientation(s.to_string())),
+        }
+    }
+}
+
+impl PageSetup {
+    /// 用紙の幅と高さ(ポイント)
+    pub fn size(&self) -> (f32, f32) {
+        let (w, h) = match self.paper {
+            PaperSize::A4 => (210.0 * MM, 297.0 * MM),
+            PaperSize::Letter => (612.0, 792.0),
+        };
+        match self.orientation {
+            Orientation::Portrait => (w, h),
+            Orientation::Landscape => (h, w),
+        }
+    }
+}
@@ -0,0 +1,266 @@
+//! todo一覧のページ割り付け
+
+use super::writer::{char_width, text_width, Document, Page};
+use super::*;
+use crate::database::ItemTodo;
+
+/// 余白
+const MARGIN: f32 = 15.0 * MM;
+/// 見出しの表題の文字サイズ
+const HEADING_SIZE: f32 = 14.0;
+/// 見出しの補足情報・ページ番号の文字サイズ
+const INFO_SIZE: f32 = 8.0;
+/// todoのタイトルの文字サイズ
+const TITLE_SIZE: f32 = 11.0;
+/// 日付の文字サイズ
+const DATE_SIZE: f32 = 8.5;
+/// 内容の文字サイズ
+const WORK_SIZE: f32 = 9.0;
+/// 行の高さの、文字サイズに対する比率
+const LINE_HEIGHT: f32 = 1.4;
+/// todoの間隔
+const ITEM_GAP: f32 = 6.0;
+
+/// 行頭に置かない文字。直前の行にぶら下げる。
+const NO_LINE_START: &[char] = &[
+    '、', '。', '，', '．', ',', '.', '）', ')', '」', '』', '】', '〕', '・', 'ー', '！', '？',
+    '!', '?',
+];
+
+/// 一行分の描画内容。位置は、行の左端・上端からの相対。
+#[derive(Debug, Clone, PartialEq)]
+struct Row {
+    height: f32,
+    texts: Vec<(f32, f32, String)>,
+    /// 行の下端に罫線を引くか
+    rule: bool,
+}
+
+impl Row {
+    fn new(height: f32) -> Self {
+        Self {
+            height,
+            texts: Vec::new(),
+            rule: false,
+        }
+    }
+
+    /// x位置に、文字サイズsizeで文字列を置く。
+    fn text(mut self, x: f32, size: f32, s: &str) -> Self {
+        self.texts.push((x, size, s.to_string()));
+        self
+    }
+}
+
+/// 文字列を、幅max_width(ポイント)に収まるように折り返す。
+/// 改行文字でも改行する。句読点などは、行頭に来る場合は前の行にぶら下げる。
+pub(super) fn wrap(s: &str, size: f32, max_width: f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    for para in s.lines() {
+        let mut line = String::new();
+        let mut width = 0.0;
+        for c in para.chars() {
+            let w = char_width(c) * size;
+            if width + w > max_width && !line.is_empty() && !NO_LINE_START.contains(&c) {
+                lines.push(std::mem::take(&mut line));
+                width = 0.0;
+            }
+            line.push(c);
+            width += w;
+        }
+        lines.push(line);
+    }
+    if lines.is_empty() {
+        lines.push(String::new());
+    }
+    lines
+}
+
+/// todoの状態の表示
+pub(super) fn status(item: &ItemTodo, ref_date: NaiveDate) -> &'static str {
+    if item.done {
+        "【完了】"
+    } else if item.end_date.is_some_and(|d| d < ref_date) {
+        "【期限切れ】"
+    } else {
+        "【未完了】"
+    }
+}
+
+/// todo一件分の行を生成する。
+fn item_rows(item: &ItemTodo, ref_date: NaiveDate, width: f32) -> Vec<Row> {
+    let no_end_date = NaiveDate::from_ymd_opt(9999, 12, 31).unwrap();
+    let mut rows = Vec::new();
+
+    let status = status(item, ref_date);
+    let status_width = text_width(status, TITLE_SIZE);
+    let title_width = width - status_width - TITLE_SIZE;
+    for (i, line) in wrap(&item.title, TITLE_SIZE, title_width)
+        .iter()
+        .enumerate()
+    {
+        let mut row = Row::new(TITLE_SIZE * LINE_HEIGHT).text(0.0, TITLE_SIZE, line);
+        if i == 0 {
+            row = row.text(width - status_width, TITLE_SIZE, status);
+        }
+        rows.push(row);
+    }
+
+    let date = |d: Option<NaiveDate>| {
+        d.map(|d| d.format("%Y-%m-%d").to_string())
+            .unwrap_or_default()
+    };
+    let end_date = match item.end_date {
+        Some(d) if d == no_end_date => "期限なし".to_string(),
+        d => date(d),
+    };
+    let dates = format!(
+        "開始 {}　終了 {}　更新 {}",
+        date(item.start_date),
+        end_date,
+        date(item.update_date)
+    );
+    rows.push(Row::new(DATE_SIZE * LINE_HEIGHT).text(WORK_SIZE, DATE_SIZE, &dates));
+
+    if let Some(work) = item.work.as_deref().filter(|w| !w.trim().is_empty()) {
+        for line in wrap(work, WORK_SIZE, width - WORK_SIZE) {
+            rows.push(Row::new(WORK_SIZE * LINE_HEIGHT).text(WORK_SIZE, WORK_SIZE, &line));
+        }
+    }
+    if let Some(last) = rows.last_mut() {
+        last.height += ITEM_GAP / 2.0;
+        last.rule = true;
+    }
+    rows
+}
+
+/// ページの割り付け状態
+struct Layout<'a> {
+    heading: &'a ListHeading,
+    width: f32,
+    height: f32,
+    pages: Vec<Page>,
+    page: Page,
+    /// 次に置く行の上端
+    y: f32,
+    /// 本文の上端
+    top: f32,
+}
+
+impl Layout<'_> {
+    /// 本文の下端(ページ番号の上)
+    fn bottom(&self) -> f32 {
+        MARGIN + INFO_SIZE * 2.0
+    }
+
+    /// 見出しを描き、本文の位置を決める。
+    fn start_page(&mut self) {
+        let right = self.width - MARGIN;
+        let mut y = self.height - MARGIN - HEADING_SIZE;
+        self.page.text(MARGIN, y, HEADING_SIZE, &self.heading.title);
+        let date = format!("出力日 {}", self.heading.ref_date.format("%Y-%m-%d"));
+        self.page
+            .text(right - text_width(&date, INFO_SIZE), y, INFO_SIZE, &date);
+        y -= INFO_SIZE * LINE_HEIGHT * 1.5;
+        let mut info = self.heading.user_name.clone();
+        if !self.heading.filter.is_empty() {
+            info.push_str("　条件: ");
+            info.push_str(&self.heading.filter);
+        }
+        for line in wrap(&info, INFO_SIZE, right - MARGIN) {
+            self.page.text(MARGIN, y, INFO_SIZE, &line);
+            y -= INFO_SIZE * LINE_HEIGHT;
+        }
+        self.page.line(MARGIN, y, right, y, 0.8);
+        self.top = y - ITEM_GAP;
+        self.y = self.top;
+    }
+
+    fn new_page(&mut self) {
+        self.pages.push(std::mem::take(&mut self.page));
+        self.start_page();
+    }
+
+    fn put_row(&mut self, row: &Row) {
+        if self.y - row.height < self.bottom() && self.y < self.top {
+            self.new_page();
+        }
+        for (x, size, s) in &row.texts {
+            // ベースラインは、行の上端から文字サイズ分下とする。
+            self.page.text(MARGIN + x, self.y - size, *size, s);
+        }
+        self.y -= row.height;
+        if row.rule {
+            self.page
+                .line(MARGIN, self.y, self.width - MARGIN, self.y, 0.3);
+            self.y -= ITEM_GAP / 2.0;
+        }
+    }
+
+    /// todo一件分の行を置く。ページに収まらなければ、改ページしてから置く。
+    fn put_item(&mut self, rows: &[Row]) {
+        let height = rows.iter().map(|r| r.height).sum::<f32>() + ITEM_GAP / 2.0;
+        if self.y - height < self.bottom() && self.y < self.top {
+            self.new_page();
+        }
+        for row in rows {
+            self.put_row(row);
+        }
+    }
+
+    /// 最後のページを閉じ、すべてのページにページ番号を描く。
+    fn finish(mut self) -> Vec<Page> {
+        self.pages.push(self.page);
+        let total = self.pages.len();
+        for (i, page) in self.pages.iter_mut().enumerate() {
+            let number = format!("{} / {}", i + 1, total);
+            page.text(
+                (self.width - text_width(&number, INFO_SIZE)) / 2.0,
+                MARGIN,
+                INFO_SIZE,
+                &number,
+            );
+        }
+        self.pages
+    }
+}
+
+/// todoの一覧を、ページに割り付ける。
+pub(super) fn layout_todo_list(
+    items: &[ItemTodo],
+    heading: &ListHeading,
+    setup: PageSetup,
+) -> Document {
+    let (width, height) = setup.size();
+    let mut layout = Layout {
+        heading,
+        width,
+        height,
+        pages: Vec::new(),
+        page: Page::default(),
+        y: 0.0,
+        top: 0.0,
+    };
+    layout.start_page();
+    if items.is_empty() {
+        layout.put_row(&Row::new(WORK_SIZE * LINE_HEIGHT).text(
+            0.0,
+            WORK_SIZE,
+            "該当するtodoはありません。",
+        ));
+    }
+    for item in items {
+        layout.put_item(&item_rows(item, heading.ref_date, width - MARGIN * 2.0));
+    }
+
+    let mut doc = Document::new(&heading.title, (width, height));
+    for page in layout.finish() {
+        doc.push_page(page);
+    }
+    doc
+}
+
+/// todoの一覧を、PDFファイルのバイト列にする。
+pub fn render_todo_list(items: &[ItemTodo], heading: &ListHeading, setup: PageSetup) -> Vec<u8> {
+    layout_todo_list(items, heading, setup).to_bytes()
+}
@@ -0,0 +1,169 @@
+use super::layout::{layout_todo_list, status, wrap};
+use super::writer::text_width;
+use super::*;
+use crate::database::ItemTodo;
+
+fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(y, m, d).unwrap()
+}
+
+fn item(id: u32, title: &str, work: Option<&str>, end_date: NaiveDate, done: bool) -> ItemTodo {
+    ItemTodo {
+        id,
+        user_name: "美都".to_string(),
+        title: title.to_string(),
+        work: work.map(str::to_string),
+        update_date: Some(date(2024, 6, 1)),
+        start_date: Some(date(2024, 6, 1)),
+        end_date: Some(end_date),
+        done,
+        revision: 0,
+    }
+}
+
+fn heading() -> ListHeading {
+    ListHeading {
+        title: "todo一覧".to_string(),
+        user_name: "美都".to_string(),
+        filter: "tag:仕事".to_string(),
+        ref_date: date(2024, 6, 10),
+    }
+}
+
+#[test]
+fn paper_size_and_orientation() {
+    assert_eq!("A4".parse::<PaperSize>(), Ok(PaperSize::A4));
+    assert_eq!("letter".parse::<PaperSize>(), Ok(PaperSize::Letter));
+    assert_eq!(
+        "b5".parse::<PaperSize>(),
+        Err(PdfError::InvalidPaper("b5".to_string()))
+    );
+    assert_eq!(
+        "landscape".parse::<Orientation>(),
+        Ok(Orientation::Landscape)
+    );
+    assert!("yoko".parse::<Orientation>().is_err());
+
+    let (w, h) = PageSetup::default().size();
+    assert!((w - 595.28).abs() < 0.01 && (h - 841.89).abs() < 0.01);
+    let setup = PageSetup {
+        paper: PaperSize::Letter,
+        orientation: Orientation::Landscape,
+    };
+    assert_eq!(setup.size(), (792.0, 612.0));
+}
+
+#[test]
+fn wrap_text() {
+    // 全角10pt、幅50ptなら一行5文字
+    assert_eq!(
+        wrap("あいうえおかきくけこさ", 10.0, 50.0),
+        vec!["あいうえお", "かきくけこ", "さ"]
+    );
+    // 半角は全角の半分の幅
+    assert_eq!(wrap("abcdefghijk", 10.0, 50.0), vec!["abcdefghij", "k"]);
+    // 句読点は行頭に置かず、前の行にぶら下げる。
+    assert_eq!(
+        wrap("あいうえお。かきくけこ", 10.0, 50.0),
+        vec!["あいうえお。", "かきくけこ"]
+    );
+    // 改行はそのまま改行する。
+    assert_eq!(
+        wrap("一行目\r\n二行目", 10.0, 100.0),
+        vec!["一行目", "二行目"]
+    );
+    assert_eq!(wrap("", 10.0, 100.0), vec![""]);
+    assert_eq!(text_width("aあ", 10.0), 15.0);
+}
+
+#[test]
+fn todo_status() {
+    let ref_date = date(2024, 6, 10);
+    assert_eq!(
+        status(&item(1, "", None, date(2024, 6, 1), true), ref_date),
+        "【完了】"
+    );
+    assert_eq!(
+        status(&item(1, "", None, date(2024, 6, 9), false), ref_date),
+        "【期限切れ】"
+    );
+    assert_eq!(
+        status(&item(1, "", None, date(2024, 6, 10), false), ref_date),
+        "【未完了】"
+    );
+}
+
+#[test]
+fn paginate_todo_list() {
+    let setup = PageSetup::default();
+    let doc = layout_todo_list(&[], &heading(), setup);
+    assert_eq!(doc.pages.len(), 1, "0件でも1ページ");
+
+    let items = (0..100)
+        .map(|i| {
+            item(
+                i,
+                &format!("todo{i}件目"),
+                Some("内容の一行目\n内容の二行目"),
+                date(2024, 6, 20),
+                i % 2 == 0,
+            )
+        })
+        .collect::<Vec<_>>();
+    let portrait = layout_todo_list(&items, &heading(), setup).pages.len();
+    assert!(portrait > 1, "100件は1ページに収まらない");
+    let landscape = layout_todo_list(
+        &items,
+        &heading(),
+        PageSetup {
+            orientation: Orientation::Landscape,
+            ..setup
+        },
+    )
+    .pages
+    .len();
+    assert!(landscape > portrait, "横向きの方がページ数が多い");
+}
+
+#[test]
+fn render_pdf_structure() {
+    let items = [
+        item(1, "買い物", Some("牛乳(1L)"), date(9999, 12, 31), false),
+        item(2, "報告書", None, date(2024, 6, 5), false),
+    ];
+    let pdf = render_todo_list(&items, &heading(), PageSetup::default());
+    let text = String::from_utf8_lossy(&pdf);
+    assert!(text.starts_with("%PDF-1.4\n"));
+    assert!(text.ends_with("%%EOF\n"));
+    assert!(text.contains("/Encoding /UniJIS-UTF16-H"));
+    assert!(text.contains("/Ordering (Japan1)"));
+    // 「買い物」のUTF-16BE
+    assert!(text.contains("<8CB730447269> Tj"));
+    assert!(text.contains("/Count 1"));
+
+    // xrefの各オフセットが、オブジェクトの先頭を指していること
+    let find = |needle: &[u8]| {
+        pdf.windows(needle.len())
+            .rposition(|w| w == needle)
+            .unwrap()
+    };
+    let startxref = find(b"startxref\n") + 10;
+    let tail = std::str::from_utf8(&pdf[startxref..]).unwrap();
+    let xref = tail.lines().next().unwrap().parse::<usize>().unwrap();
+    assert_eq!(xref, find(b"xref\n0 "));
+    let table = std::str::from_utf8(&pdf[xref..]).unwrap();
+    let mut count = 0;
+    for (i, line) in table.lines().skip(3).enumerate() {
+        if line.starts_with("trailer") {
+            break;
+        }
+        let offset = line[..10].parse::<usize>().unwrap();
+        assert!(
+            pdf[offset..].starts_with(format!("{} 0 obj\n", i + 1).as_bytes()),
+            "{}番目のオブジェクトの位置が不正",
+            i + 1
+        );
+        count += 1;
+    }
+    assert_eq!(count, 8, "カタログ等6個と、1ページ分のページとコンテンツ");
+}
@@ -0,0 +1,184 @@
+//! PDFファイルの組み立て
+//!
+//! フォントは埋め込まず、Adobe-Japan1のCIDフォント(平成角ゴシック)を
+//! UniJIS-UTF16-Hで参照する。日本語対応のビューアーでは、同等のフォントで表示される。
+
+use super::*;
+use std::fmt::Write;
+
+/// 使用するフォント名
+const FONT_NAME: &str = "HeiseiKakuGo-W5";
+/// コンテンツストリーム中のフォントのリソース名
+pub(super) const FONT_RESOURCE: &str = "F1";
+
+/// 文字の送り幅(em単位)。
+/// CMapで半角のCIDに対応付けられる、ASCIIと半角カナは0.5、それ以外は全角とする。
+pub(super) fn char_width(c: char) -> f32 {
+    match c {
+        '\u{20}'..='\u{7e}' | '\u{ff61}'..='\u{ff9f}' => 0.5,
+        _ => 1.0,
+    }
+}
+
+/// 文字列の幅(ポイント)
+pub(super) fn text_width(s: &str, size: f32) -> f32 {
+    s.chars().map(char_width).sum::<f32>() * size
+}
+
+/// 数値を、PDFの実数表記にする。
+fn num(v: f32) -> String {
+    let s = format!("{v:.2}");
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    if s == "-0" {
+        "0".to_string()
+    } else {
+        s.to_string()
+    }
+}
+
+/// 文字列を、UTF-16BEの16進文字列にする。
+fn hex_utf16(s: &str, bom: bool) -> String {
+    let mut out = String::from("<");
+    if bom {
+        out.push_str("FEFF");
+    }
+    for u in s.encode_utf16() {
+        write!(out, "{u:04X}").unwrap();
+    }
+    out.push('>');
+    out
+}
+
+/// 一ページ分の描画命令
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Page {
+    content: String,
+}
+
+impl Page {
+    /// 左下を原点として、(x, y)をベースラインの始点に文字列を描く。
+    pub fn text(&mut self, x: f32, y: f32, size: f32, s: &str) {
+        // 制御文字は、フォントに字形がないため出力しない。
+        let s = s.chars().filter(|c| !c.is_control()).collect::<String>();
+        writeln!(
+            self.content,
+            "BT /{FONT_RESOURCE} {} Tf {} {} Td {} Tj ET",
+            num(size),
+            num(x),
+            num(y),
+            hex_utf16(&s, false)
+        )
+        .unwrap();
+    }
+
+    /// 線を描く。
+    pub fn line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, width: f32) {
+        writeln!(
+            self.content,
+            "{} w {} {} m {} {} l S",
+            num(width),
+            num(x1),
+            num(y1),
+            num(x2),
+            num(y2)
+        )
+        .unwrap();
+    }
+}
+
+/// ページを集めて、PDFファイルを生成する。
+#[derive(Debug, Clone, PartialEq)]
+pub struct Document {
+    title: String,
+    width: f32,
+    height: f32,
+    pub(super) pages: Vec<Page>,
+}
+
+impl Document {
+    pub fn new(title: &str, (width, height): (f32, f32)) -> Self {
+        Self {
+            title: title.to_string(),
+            width,
+            height,
+            pages: Vec::new(),
+        }
+    }
+
+    pub fn push_page(&mut self, page: Page) {
+        self.pages.push(page);
+    }
+
+    /// PDFファイルのバイト列を生成する。
+    /// オブジェクト番号は、1:カタログ, 2:ページツリー, 3〜5:フォント, 6:文書情報,
+    /// 7以降:ページとコンテンツの組とする。
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let page_ids = (0..self.pages.len()).map(|i| 7 + i * 2).collect::<Vec<_>>();
+        let mut objects = vec![
+            "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                page_ids
+                    .iter()
+                    .map(|id| format!("{id} 0 R"))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                self.pages.len()
+            ),
+            format!(
+                "<< /Type /Font /Subtype /Type0 /BaseFont /{FONT_NAME}-UniJIS-UTF16-H \
+                 /Encoding /UniJIS-UTF16-H /DescendantFonts [4 0 R] >>"
+            ),
+            format!(
+                "<< /Type /Font /Subtype /CIDFontType0 /BaseFont /{FONT_NAME} \
+                 /CIDSystemInfo << /Registry (Adobe) /Ordering (Japan1) /Supplement 6 >> \
+                 /FontDescriptor 5 0 R /DW 1000 /W [1 95 500 231 632 500] >>"
+            ),
+            format!(
+                "<< /Type /FontDescriptor /FontName /{FONT_NAME} /Flags 4 \
+                 /FontBBox [-92 -250 1010 922] /ItalicAngle 0 /Ascent 752 /Descent -221 \
+                 /CapHeight 737 /StemV 114 >>"
+            ),
+            format!(
+                "<< /Title {} /Producer (neko_todo) /CreationDate (D:{}) >>",
+                hex_utf16(&self.title, true),
+                Local::now().format("%Y%m%d%H%M%S")
+            ),
+        ];
+        for (page, id) in self.pages.iter().zip(&page_ids) {
+            objects.push(format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+                 /Resources << /Font << /{FONT_RESOURCE} 3 0 R >> >> /Contents {} 0 R >>",
+                num(self.width),
+                num(self.height),
+                id + 1
+            ));
+            objects.push(format!(
+                "<< /Length {} >>\nstream\n{}\nendstream",
+                page.content.len(),
+                page.content
+            ));
+        }
+
+        // バイナリを含むファイルであることを示すコメントを、ヘッダの次の行に置く。
+        let mut out = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (i, obj) in objects.iter().enumerate() {
+            offsets.push(out.len());
+            out.extend_from_slice(format!("{} 0 obj\n{obj}\nendobj\n", i + 1).as_bytes());
+        }
+        let xref = out.len();
+        let mut trailer = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+        for offset in offsets {
+            writeln!(trailer, "{offset:010} 00000 n ").unwrap();
+        }
+        write!(
+            trailer,
+            "trailer\n<< /Size {} /Root 1 0 R /Info 6 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+            objects.len() + 1
+        )
+        .unwrap();
+        out.extend_from_slice(trailer.as_bytes());
+        out
+    }
+}
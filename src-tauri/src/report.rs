@@ -0,0 +1,331 @@
+//! 日報・週報などの作業報告の生成
+//!
+//! 期間内に完了したtodo・作成したtodoと、期限を過ぎた未完了のtodoを集め、
+//! テンプレートに当てはめて、Markdown・テキスト・LaTeXで出力する。
+//! テンプレートは、設定ディレクトリの`templates`に置き、ユーザーが編集できる。
+//! ファイルがなければ、組み込みのテンプレートを書き出してから使用する。
+mod template;
+#[cfg(test)]
+mod test;
+
+use crate::config::NekoTodoConfig;
+use chrono::{DateTime, Local, NaiveDate};
+use std::path::PathBuf;
+use thiserror::Error;
+
+pub use template::{Scope, Template, Value};
+
+/// テンプレートを置くディレクトリ名(設定ディレクトリからの相対)
+const TEMPLATE_DIR: &str = "templates";
+/// テンプレートのファイル名(拡張子を除く)
+const TEMPLATE_NAME: &str = "report";
+/// 日付の入出力形式
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+const DEFAULT_MARKDOWN: &str = include_str!("report/templates/report.md");
+const DEFAULT_TEXT: &str = include_str!("report/templates/report.txt");
+const DEFAULT_LATEX: &str = include_str!("report/templates/report.tex");
+
+/// 作業報告に載せるtodo一件分
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportItem {
+    pub id: u32,
+    pub title: String,
+    pub work: Option<String>,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub create_date: Option<NaiveDate>,
+    pub done_date: Option<NaiveDate>,
+    pub tags: Vec<String>,
+}
+
+/// 作業報告の内容
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub user_name: String,
+    /// 対象期間の初日
+    pub from: NaiveDate,
+    /// 対象期間の最終日
+    pub to: NaiveDate,
+    /// 期限切れを判定する基準日(期間の最終日と今日の、早い方)
+    pub as_of: NaiveDate,
+    pub generated_at: DateTime<Local>,
+    /// 期間内に完了したtodo
+    pub completed: Vec<ReportItem>,
+    /// 期間内に作成したtodo
+    pub created: Vec<ReportItem>,
+    /// 基準日の時点で、期限を過ぎている未完了のtodo
+    pub overdue: Vec<ReportItem>,
+}
+
+/// 作業報告の出力形式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReportFormat {
+    Markdown,
+    Text,
+    Latex,
+}
+
+#[derive(Error, Debug)]
+pub enum ReportError {
+    #[error("未対応の出力形式です。:{0}")]
+    InvalidFormat(String),
+    #[error("日付の形式が不正です。(YYYY-MM-DD):{0}")]
+    InvalidDate(String),
+    #[error("期間の初日が最終日より後です。")]
+    InvalidRange,
+    #[error("テンプレートの誤り({path}):{source}")]
+    Template { path: String, source: TemplateError },
+    #[error("ファイルの読み書きに失敗しました。:{0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// テンプレートの誤り
+#[derive(Error, Debug, PartialEq)]
+pub enum TemplateError {
+    #[error("タグが閉じていません。({0}行目)")]
+    UnclosedTag(usize),
+    #[error("タグの名前がありません。({0}行目)")]
+    EmptyName(usize),
+    #[error("セクション{0}が閉じていません。({1}行目)")]
+    UnclosedSection(String, usize),
+    #[error("対応するセクションのない終了タグ{0}です。({1}行目)")]
+    UnexpectedClose(String, usize),
+    #[error("未定義の名前{0}です。({1}行目)")]
+    UnknownName(String, usize),
+    #[error("{0}はリストのため、値として出力できません。({1}行目)")]
+    NotText(String, usize),
+}
+
+impl std::fmt::Display for ReportFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Markdown => write!(f, "md"),
+            Self::Text => write!(f, "txt"),
+            Self::Latex => write!(f, "tex"),
+        }
+    }
+}
+
+impl std::str::FromStr for ReportFormat {
+    type Err = ReportError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "md" | "markdown" => Ok(Self::Markdown),
+            "txt" | "text" => Ok(Self::Text),
+            "tex" | "latex" => Ok(Self::Latex),
+            _ => Err(ReportError::InvalidFormat(s.to_string())),
+        }
+    }
+}
+
+impl ReportFormat {
+    /// 組み込みのテンプレート
+    pub fn default_template(&self) -> &'static str {
+        match self {
+            Self::Markdown => DEFAULT_MARKDOWN,
+            Self::Text => DEFAULT_TEXT,
+            Self::Latex => DEFAULT_LATEX,
+        }
+    }
+
+    /// 値のエスケープ方法
+    fn escape(&self) -> fn(&str) -> String {
+        match self {
+            Self::Markdown | Self::Text => str::to_string,
+            Self::Latex => escape_latex,
+        }
+    }
+
+    /// ユーザーが編集できるテンプレートのパス
+    pub fn template_path(&self) -> std::io::Result<PathBuf> {
+        let mut path = NekoTodoConfig::get_config_dir()?;
+        path.push(TEMPLATE_DIR);
+        path.push(format!("{TEMPLATE_NAME}.{self}"));
+        Ok(path)
+    }
+
+    /// ユーザーのテンプレートを読み込む。
+    /// ファイルがなければ、組み込みのテンプレートを書き出して、それを返す。
+    pub fn load_template(&self) -> Result<(PathBuf, String), ReportError> {
+        let path = self.template_path()?;
+        match std::fs::read_to_string(&path) {
+            Ok(s) => Ok((path, s)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                if let Some(dir) = path.parent() {
+                    std::fs::create_dir_all(dir)?;
+                }
+                std::fs::write(&path, self.default_template())?;
+                Ok((path, self.default_template().to_string()))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// LaTeXの特殊文字をエスケープする。改行は強制改行にする。
+fn escape_latex(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str(r"\textbackslash{}"),
+            '^' => out.push_str(r"\textasciicircum{}"),
+            '~' => out.push_str(r"\textasciitilde{}"),
+            '{' | '}' | '$' | '&' | '#' | '%' | '_' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\n' => out.push_str("\\newline\n"),
+            '\r' => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// 日付の文字列を解釈する。
+fn parse_date(s: &str) -> Result<NaiveDate, ReportError> {
+    NaiveDate::parse_from_str(s.trim(), DATE_FORMAT)
+        .map_err(|_| ReportError::InvalidDate(s.to_string()))
+}
+
+/// 対象期間を決める。
+/// toを省略すると今日、fromを省略するとtoと同じ日(日報)とする。
+pub fn parse_range(
+    from: Option<&str>,
+    to: Option<&str>,
+    today: NaiveDate,
+) -> Result<(NaiveDate, NaiveDate), ReportError> {
+    let to = to.map(parse_date).transpose()?.unwrap_or(today);
+    let from = from.map(parse_date).transpose()?.unwrap_or(to);
+    if from > to {
+        return Err(ReportError::InvalidRange);
+    }
+    Ok((from, to))
+}
+
+impl ReportItem {
+    fn to_scope(&self, as_of: NaiveDate) -> Scope {
+        let no_end_date = NaiveDate::from_ymd_opt(9999, 12, 31).unwrap();
+        let date = |d: Option<NaiveDate>| {
+            Value::from(
+                d.map(|d| d.format(DATE_FORMAT).to_string())
+                    .unwrap_or_default(),
+            )
+        };
+        let overdue_days = if self.done_date.is_none() && self.end_date < as_of {
+            (as_of - self.end_date).num_days().to_string()
+        } else {
+            String::new()
+        };
+        Scope::from([
+            ("id".to_string(), Value::from(self.id.to_string())),
+            ("title".to_string(), Value::from(self.title.as_str())),
+            (
+                "work".to_string(),
+                Value::from(self.work.clone().unwrap_or_default()),
+            ),
+            ("start_date".to_string(), date(Some(self.start_date))),
+            (
+                "end_date".to_string(),
+                date(Some(self.end_date).filter(|d| *d != no_end_date)),
+            ),
+            ("create_date".to_string(), date(self.create_date)),
+            ("done_date".to_string(), date(self.done_date)),
+            ("tags".to_string(), Value::from(self.tags.join(", "))),
+            ("overdue_days".to_string(), Value::from(overdue_days)),
+        ])
+    }
+}
+
+impl Report {
+    /// 報告の表題。一日なら日報、七日間なら週報とする。
+    pub fn title(&self) -> &'static str {
+        match (self.to - self.from).num_days() {
+            0 => "日報",
+            6 => "週報",
+            _ => "作業報告",
+        }
+    }
+
+    /// テンプレートに当てはめる値を生成する。
+    pub fn to_scope(&self) -> Scope {
+        let period = if self.from == self.to {
+            self.from.format(DATE_FORMAT).to_string()
+        } else {
+            format!(
+                "{}〜{}",
+                self.from.format(DATE_FORMAT),
+                self.to.format(DATE_FORMAT)
+            )
+        };
+        let list = |items: &[ReportItem]| {
+            Value::List(items.iter().map(|i| i.to_scope(self.as_of)).collect())
+        };
+        // セクションの条件に使う値。空文字列は偽となる。
+        let flag = |b: bool| Value::from(if b { "true" } else { "" });
+        Scope::from([
+            ("title".to_string(), Value::from(self.title())),
+            (
+                "user_name".to_string(),
+                Value::from(self.user_name.as_str()),
+            ),
+            (
+                "from".to_string(),
+                Value::from(self.from.format(DATE_FORMAT).to_string()),
+            ),
+            (
+                "to".to_string(),
+                Value::from(self.to.format(DATE_FORMAT).to_string()),
+            ),
+            ("period".to_string(), Value::from(period)),
+            (
+                "as_of".to_string(),
+                Value::from(self.as_of.format(DATE_FORMAT).to_string()),
+            ),
+            (
+                "generated_at".to_string(),
+                Value::from(self.generated_at.format("%Y-%m-%d %H:%M").to_string()),
+            ),
+            ("completed".to_string(), list(&self.completed)),
+            ("created".to_string(), list(&self.created)),
+            ("overdue".to_string(), list(&self.overdue)),
+            (
+                "has_completed".to_string(),
+                flag(!self.completed.is_empty()),
+            ),
+            ("has_created".to_string(), flag(!self.created.is_empty())),
+            ("has_overdue".to_string(), flag(!self.overdue.is_empty())),
+            (
+                "completed_count".to_string(),
+                Value::from(self.completed.len().to_string()),
+            ),
+            (
+                "created_count".to_string(),
+                Value::from(self.created.len().to_string()),
+            ),
+            (
+                "overdue_count".to_string(),
+                Value::from(self.overdue.len().to_string()),
+            ),
+        ])
+    }
+
+    /// テンプレートの文字列に当てはめて出力する。
+    pub fn render(&self, format: ReportFormat, template: &str) -> Result<String, TemplateError> {
+        template
+            .parse::<Template>()?
+            .render(&self.to_scope(), format.escape())
+    }
+
+    /// ユーザーのテンプレートに当てはめて出力する。
+    pub fn render_with_user_template(&self, format: ReportFormat) -> Result<String, ReportError> {
+        let (path, template) = format.load_template()?;
+        self.render(format, &template)
+            .map_err(|source| ReportError::Template {
+                path: path.display().to_string(),
+                source,
+            })
+    }
+}
@@ -0,0 +1,219 @@
+//! 作業報告用の簡易テンプレートエンジン
+//!
+//! Mustacheの一部の記法に対応する。
+//! - `{{name}}` 値に置き換える。値は出力形式に合わせてエスケープする。
+//! - `{{#name}}...{{/name}}` 値がリストなら要素ごとに繰り返し、文字列なら空でない場合のみ出力する。
+//! - `{{^name}}...{{/name}}` 値が空のリスト・空の文字列の場合のみ出力する。
+//! - `{{! ...}}` コメント。出力しない。
+//!
+//! セクションの開始・終了タグとコメントだけの行は、行ごと取り除く。
+
+use super::*;
+use std::collections::HashMap;
+
+/// テンプレートに当てはめる値
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Text(String),
+    List(Vec<Scope>),
+}
+
+/// 名前と値の対応
+pub type Scope = HashMap<String, Value>;
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Self::Text(value.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Self::Text(value)
+    }
+}
+
+/// 解析済みのテンプレート
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Text(String),
+    Var {
+        name: String,
+        line: usize,
+    },
+    Section {
+        name: String,
+        line: usize,
+        inverted: bool,
+        children: Vec<Node>,
+    },
+}
+
+/// 解析中のセクション
+struct OpenSection {
+    name: String,
+    line: usize,
+    inverted: bool,
+    nodes: Vec<Node>,
+}
+
+impl std::str::FromStr for Template {
+    type Err = TemplateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut stack: Vec<OpenSection> = Vec::new();
+        let mut nodes = Vec::new();
+        let mut rest = s;
+        let mut line = 1;
+        // 直前のタグまでの出力が、行頭または空白のみで終わっているか
+        let mut blank_so_far = true;
+
+        while let Some(start) = rest.find("{{") {
+            let tag_line = line + rest[..start].matches('\n').count();
+            let Some(len) = rest[start + 2..].find("}}") else {
+                return Err(TemplateError::UnclosedTag(tag_line));
+            };
+            let tag = rest[start + 2..start + 2 + len].trim();
+            let after = &rest[start + 4 + len..];
+            let mut text = &rest[..start];
+
+            let (sigil, name) = match tag.chars().next() {
+                Some(c @ ('#' | '^' | '/' | '!')) => (Some(c), tag[1..].trim()),
+                _ => (None, tag),
+            };
+            if sigil != Some('!') && name.is_empty() {
+                return Err(TemplateError::EmptyName(tag_line));
+            }
+
+            // 単独行のタグは、前後の空白と改行を含めて取り除く。
+            let mut next = after;
+            let head = &text[text.rfind('\n').map_or(0, |i| i + 1)..];
+            let blank_head = (text.len() > head.len() || blank_so_far)
+                && head.trim_matches([' ', '\t']).is_empty();
+            blank_so_far = false;
+            if sigil.is_some() && blank_head {
+                let tail_len = after.find('\n').map_or(after.len(), |i| i + 1);
+                if after[..tail_len].trim().is_empty() {
+                    text = &text[..text.len() - head.len()];
+                    next = &after[tail_len..];
+                }
+                blank_so_far = true;
+            }
+
+            let current = stack.last_mut().map_or(&mut nodes, |s| &mut s.nodes);
+            if !text.is_empty() {
+                current.push(Node::Text(text.to_string()));
+            }
+            match sigil {
+                None => current.push(Node::Var {
+                    name: name.to_string(),
+                    line: tag_line,
+                }),
+                Some('!') => {}
+                Some('/') => {
+                    let Some(open) = stack.pop() else {
+                        return Err(TemplateError::UnexpectedClose(name.to_string(), tag_line));
+                    };
+                    if open.name != name {
+                        return Err(TemplateError::UnexpectedClose(name.to_string(), tag_line));
+                    }
+                    let current = stack.last_mut().map_or(&mut nodes, |s| &mut s.nodes);
+                    current.push(Node::Section {
+                        name: open.name,
+                        line: open.line,
+                        inverted: open.inverted,
+                        children: open.nodes,
+                    });
+                }
+                Some(c) => stack.push(OpenSection {
+                    name: name.to_string(),
+                    line: tag_line,
+                    inverted: c == '^',
+                    nodes: Vec::new(),
+                }),
+            }
+
+            line += rest[..rest.len() - next.len()].matches('\n').count();
+            rest = next;
+        }
+
+        if let Some(open) = stack.pop() {
+            return Err(TemplateError::UnclosedSection(open.name, open.line));
+        }
+        if !rest.is_empty() {
+            nodes.push(Node::Text(rest.to_string()));
+        }
+        Ok(Self { nodes })
+    }
+}
+
+impl Template {
+    /// 値を当てはめて出力する。
+    /// 文字列の値は、escapeで変換してから出力する。
+    pub fn render(
+        &self,
+        scope: &Scope,
+        escape: fn(&str) -> String,
+    ) -> Result<String, TemplateError> {
+        let mut out = String::new();
+        render_nodes(&self.nodes, &mut vec![scope], escape, &mut out)?;
+        Ok(out)
+    }
+}
+
+/// 内側のスコープから順に名前を探す。
+fn lookup<'a>(scopes: &[&'a Scope], name: &str, line: usize) -> Result<&'a Value, TemplateError> {
+    scopes
+        .iter()
+        .rev()
+        .find_map(|s| s.get(name))
+        .ok_or_else(|| TemplateError::UnknownName(name.to_string(), line))
+}
+
+fn render_nodes<'a>(
+    nodes: &[Node],
+    scopes: &mut Vec<&'a Scope>,
+    escape: fn(&str) -> String,
+    out: &mut String,
+) -> Result<(), TemplateError> {
+    for node in nodes {
+        match node {
+            Node::Text(s) => out.push_str(s),
+            Node::Var { name, line } => match lookup(scopes, name, *line)? {
+                Value::Text(s) => out.push_str(&escape(s)),
+                Value::List(_) => return Err(TemplateError::NotText(name.clone(), *line)),
+            },
+            Node::Section {
+                name,
+                line,
+                inverted,
+                children,
+            } => match (lookup(scopes, name, *line)?, inverted) {
+                (Value::List(list), false) => {
+                    for item in list {
+                        scopes.push(item);
+                        let res = render_nodes(children, scopes, escape, out);
+                        scopes.pop();
+                        res?;
+                    }
+                }
+                (Value::Text(s), false) if !s.is_empty() => {
+                    render_nodes(children, scopes, escape, out)?
+                }
+                (Value::List(list), true) if list.is_empty() => {
+                    render_nodes(children, scopes, escape, out)?
+                }
+                (Value::Text(s), true) if s.is_empty() => {
+                    render_nodes(children, scopes, escape, out)?
+                }
+                _ => {}
+            },
+        }
+    }
+    Ok(())
+}
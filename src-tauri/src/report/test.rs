@@ -0,0 +1,248 @@
+use super::*;
+use chrono::TimeZone;
+
+fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(y, m, d).unwrap()
+}
+
+fn sample_report() -> Report {
+    Report {
+        user_name: "美都".to_string(),
+        from: date(2024, 6, 10),
+        to: date(2024, 6, 16),
+        as_of: date(2024, 6, 16),
+        generated_at: Local.with_ymd_and_hms(2024, 6, 16, 18, 30, 0).unwrap(),
+        completed: vec![ReportItem {
+            id: 1,
+            title: "設計書_第1版".to_string(),
+            work: Some("レビュー&修正".to_string()),
+            start_date: date(2024, 6, 1),
+            end_date: date(2024, 6, 12),
+            create_date: Some(date(2024, 6, 1)),
+            done_date: Some(date(2024, 6, 11)),
+            tags: vec!["仕事".to_string(), "設計".to_string()],
+        }],
+        created: vec![ReportItem {
+            id: 2,
+            title: "買い物".to_string(),
+            work: None,
+            start_date: date(2024, 6, 12),
+            end_date: date(9999, 12, 31),
+            create_date: Some(date(2024, 6, 12)),
+            done_date: None,
+            tags: vec![],
+        }],
+        overdue: vec![ReportItem {
+            id: 3,
+            title: "請求書 100%".to_string(),
+            work: None,
+            start_date: date(2024, 6, 1),
+            end_date: date(2024, 6, 13),
+            create_date: None,
+            done_date: None,
+            tags: vec![],
+        }],
+    }
+}
+
+fn scope(pairs: &[(&str, Value)]) -> Scope {
+    pairs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.clone()))
+        .collect()
+}
+
+#[test]
+fn template_var_and_section() {
+    let template =
+        "{{name}}さん\n{{#items}}- {{title}}({{name}})\n{{/items}}{{^items}}なし\n{{/items}}"
+            .parse::<Template>()
+            .unwrap();
+    let items = Value::List(vec![
+        scope(&[("title", Value::from("一件目"))]),
+        scope(&[("title", Value::from("二件目"))]),
+    ]);
+    let out = template
+        .render(
+            &scope(&[("name", Value::from("美都")), ("items", items)]),
+            str::to_string,
+        )
+        .unwrap();
+    assert_eq!(out, "美都さん\n- 一件目(美都)\n- 二件目(美都)\n");
+
+    let out = template
+        .render(
+            &scope(&[
+                ("name", Value::from("美都")),
+                ("items", Value::List(vec![])),
+            ]),
+            str::to_string,
+        )
+        .unwrap();
+    assert_eq!(out, "美都さん\nなし\n");
+}
+
+#[test]
+fn template_standalone_lines() {
+    let template =
+        "{{! コメント }}\n見出し\n  {{#flag}}  \n本文\n{{/flag}}\n末尾{{#flag}}!{{/flag}}\n"
+            .parse::<Template>()
+            .unwrap();
+    let out = template
+        .render(&scope(&[("flag", Value::from("true"))]), str::to_string)
+        .unwrap();
+    assert_eq!(out, "見出し\n本文\n末尾!\n");
+    let out = template
+        .render(&scope(&[("flag", Value::from(""))]), str::to_string)
+        .unwrap();
+    assert_eq!(out, "見出し\n末尾\n");
+}
+
+#[test]
+fn template_errors() {
+    assert_eq!(
+        "一行目\n{{name".parse::<Template>(),
+        Err(TemplateError::UnclosedTag(2))
+    );
+    assert_eq!("{{}}".parse::<Template>(), Err(TemplateError::EmptyName(1)));
+    assert_eq!(
+        "\n\n{{#items}}\n".parse::<Template>(),
+        Err(TemplateError::UnclosedSection("items".to_string(), 3))
+    );
+    assert_eq!(
+        "{{#items}}\n{{/item}}".parse::<Template>(),
+        Err(TemplateError::UnexpectedClose("item".to_string(), 2))
+    );
+    let template = "\n{{nama}}".parse::<Template>().unwrap();
+    assert_eq!(
+        template.render(&Scope::new(), str::to_string),
+        Err(TemplateError::UnknownName("nama".to_string(), 2))
+    );
+    let template = "{{items}}".parse::<Template>().unwrap();
+    assert_eq!(
+        template.render(&scope(&[("items", Value::List(vec![]))]), str::to_string),
+        Err(TemplateError::NotText("items".to_string(), 1))
+    );
+}
+
+#[test]
+fn escape_latex_special_chars() {
+    assert_eq!(
+        escape_latex(r"a_b&c%d$e#f{g}h\i^j~k"),
+        r"a\_b\&c\%d\$e\#f\{g\}h\textbackslash{}i\textasciicircum{}j\textasciitilde{}k"
+    );
+    assert_eq!(escape_latex("一行目\r\n二行目"), "一行目\\newline\n二行目");
+}
+
+#[test]
+fn parse_range_defaults() {
+    let today = date(2024, 6, 16);
+    assert_eq!(parse_range(None, None, today).unwrap(), (today, today));
+    assert_eq!(
+        parse_range(Some("2024-06-10"), None, today).unwrap(),
+        (date(2024, 6, 10), today)
+    );
+    assert_eq!(
+        parse_range(None, Some("2024-06-01"), today).unwrap(),
+        (date(2024, 6, 1), date(2024, 6, 1))
+    );
+    assert!(matches!(
+        parse_range(Some("2024/06/10"), None, today),
+        Err(ReportError::InvalidDate(_))
+    ));
+    assert!(matches!(
+        parse_range(Some("2024-06-17"), None, today),
+        Err(ReportError::InvalidRange)
+    ));
+}
+
+#[test]
+fn report_format_from_str() {
+    assert_eq!(
+        "md".parse::<ReportFormat>().unwrap(),
+        ReportFormat::Markdown
+    );
+    assert_eq!("Text".parse::<ReportFormat>().unwrap(), ReportFormat::Text);
+    assert_eq!(
+        "latex".parse::<ReportFormat>().unwrap(),
+        ReportFormat::Latex
+    );
+    assert!(matches!(
+        "pdf".parse::<ReportFormat>(),
+        Err(ReportError::InvalidFormat(_))
+    ));
+}
+
+#[test]
+fn report_title() {
+    let mut report = sample_report();
+    assert_eq!(report.title(), "週報");
+    report.from = report.to;
+    assert_eq!(report.title(), "日報");
+    report.from = date(2024, 6, 1);
+    assert_eq!(report.title(), "作業報告");
+}
+
+#[test]
+fn render_default_markdown() {
+    let report = sample_report();
+    let out = report
+        .render(
+            ReportFormat::Markdown,
+            ReportFormat::Markdown.default_template(),
+        )
+        .unwrap();
+    assert!(out.starts_with("# 週報 2024-06-10〜2024-06-16\n"), "{out}");
+    assert!(
+        out.contains("- [x] 設計書_第1版 `仕事, 設計` (完了日: 2024-06-11)\n    - レビュー&修正\n"),
+        "{out}"
+    );
+    assert!(
+        out.contains("- 買い物\n"),
+        "期限なしは期限を出力しない。{out}"
+    );
+    assert!(
+        out.contains("- [ ] 請求書 100% (期限: 2024-06-13、3日超過)\n"),
+        "{out}"
+    );
+    assert!(out.ends_with("2024-06-16 18:30 作成\n"), "{out}");
+    assert!(!out.contains("{{"), "{out}");
+}
+
+#[test]
+fn render_default_text_empty() {
+    let mut report = sample_report();
+    report.completed.clear();
+    report.created.clear();
+    report.overdue.clear();
+    let out = report
+        .render(ReportFormat::Text, ReportFormat::Text.default_template())
+        .unwrap();
+    assert!(out.contains("■完了したtodo(0件)\n・なし\n"), "{out}");
+    assert!(
+        out.contains("■期限を過ぎたtodo(0件、2024-06-16時点)\n・なし\n"),
+        "{out}"
+    );
+}
+
+#[test]
+fn render_default_latex() {
+    let report = sample_report();
+    let out = report
+        .render(ReportFormat::Latex, ReportFormat::Latex.default_template())
+        .unwrap();
+    assert!(out.contains(r"\documentclass[paper=a4paper"), "{out}");
+    assert!(out.contains("{jlreq}"), "{out}");
+    assert!(out.contains(r"\item 設計書\_第1版"), "{out}");
+    assert!(out.contains(r"レビュー\&修正"), "{out}");
+    assert!(out.contains(r"\item 請求書 100\%"), "{out}");
+    assert_eq!(out.matches(r"\begin{itemize}").count(), 3, "{out}");
+    assert_eq!(out.matches(r"\end{itemize}").count(), 3, "{out}");
+
+    let mut report = report;
+    report.created.clear();
+    let out = report
+        .render(ReportFormat::Latex, ReportFormat::Latex.default_template())
+        .unwrap();
+    assert_eq!(out.matches(r"\begin{itemize}").count(), 2, "{out}");
+}
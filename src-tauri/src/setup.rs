@@ -1,14 +1,21 @@
 //! アプリケーション環境の構築を実施する
 use clap::Parser;
-use log::{error, info};
-use std::process::exit;
+use log::{info, warn};
+use std::{
+    path::{Path, PathBuf},
+    process::exit,
+};
 use tauri::async_runtime::block_on;
 use thiserror::Error;
 
 use crate::{
     app_status::AppStatus,
-    config::NekoTodoConfig,
-    todo::{Todo, TodoError},
+    cli::{CliCommand, CliError},
+    config::{is_valid_profile_name, ConfigError, NekoTodoConfig, ProfileError, DEFAULT_PROFILE},
+    credential::Secret,
+    database::{ConnectConfig, PoolConfig, SslMode, DEFAULT_DATABASE},
+    offline::OfflineCache,
+    todo::{Client, Todo, TodoError},
 };
 
 /// アプリケーション環境の構築を行う。
@@ -18,53 +25,114 @@ pub fn setup() -> Result<AppStatus, SetupError> {
         database_param_setup(&args)?;
     }
 
-    let conf = NekoTodoConfig::new()?;
+    let mut conf = NekoTodoConfig::new()?;
+    if let Some(ref profile) = args.profile {
+        conf.select_profile(profile, false)?;
+    }
 
-    if conf.get_db_host().is_empty()
-        || conf.get_db_user().is_empty()
-        || conf.get_db_pass().is_empty()
-    {
-        return Err(SetupError::Argument);
+    let conn = conf.get_connect_config();
+    if !conn.is_complete() {
+        if args.command.is_some() {
+            return Err(SetupError::Argument);
+        }
+        // GUIでは、接続情報の設定画面から始める。
+        info!("データベースの接続情報が未設定です。設定画面から起動します。");
+        return Ok(AppStatus::not_configured(conf));
     }
 
-    let todo = block_on(async {
-        Todo::new(conf.get_db_host(), conf.get_db_user(), conf.get_db_pass()).await
-    })?;
+    if let Some(ref command) = args.command {
+        let todo = block_on(async { Todo::new(&conn, &conf.get_pool_config()).await })?
+            .with_client(Client::Cli);
+        block_on(command.run(&conf, &todo))?;
+        exit(0);
+    }
 
+    let cache_path = conf.get_offline_cache_path();
+    let todo = block_on(open_todo(&conn, &conf.get_pool_config(), &cache_path))?;
     Ok(AppStatus::new(conf, todo))
 }
 
+/// GUI用のTodoを生成する。
+/// 接続できなくても、以前にログインしていれば、オフラインで生成する。
+pub async fn open_todo(
+    conn: &ConnectConfig,
+    pool: &PoolConfig,
+    cache_path: &Path,
+) -> Result<Todo, SetupError> {
+    let cache = OfflineCache::open(cache_path);
+    let todo = match Todo::new(conn, pool).await {
+        Ok(todo) => todo,
+        Err(e) if e.is_disconnected() && cache.owner().is_some() => {
+            warn!("データベースに接続できません。オフラインで起動します。:{e}");
+            cache.set_online(false);
+            Todo::new_offline(conn, pool)
+        }
+        Err(e) => return Err(e.into()),
+    };
+    Ok(todo.with_offline_cache(cache))
+}
+
 /// データベース接続パラメータの設定を設定ファイルに行い終了する。
 fn database_param_setup(args: &Args) -> Result<(), SetupError> {
-    let Some(ref host) = args.server else {
+    let profile = args.profile.as_deref().unwrap_or(DEFAULT_PROFILE);
+    if !is_valid_profile_name(profile) {
+        return Err(ProfileError::InvalidName(profile.to_string()).into());
+    }
+    if args.server.is_none() && args.socket.is_none() {
         return Err(SetupError::Argument);
-    };
+    }
     let Some(ref user) = args.user else {
         return Err(SetupError::Argument);
     };
-    let Some(ref pass) = args.pass else {
-        return Err(SetupError::Argument);
+    // ソケット接続(unix_socket認証)では、パスワードを省略できる。
+    let pass = match (&args.pass, &args.socket) {
+        (Some(pass), _) => pass.as_str(),
+        (None, Some(_)) => "",
+        (None, None) => return Err(SetupError::Argument),
+    };
+
+    let conn = ConnectConfig {
+        host: args.server.clone().unwrap_or_default(),
+        port: args.port,
+        user: user.clone(),
+        pass: Secret::new(pass),
+        database: args.database.clone(),
+        ssl_mode: args.ssl_mode,
+        ssl_ca: args.ssl_ca.clone(),
+        socket: args.socket.clone(),
     };
 
     // 一度試しに接続してみる。
     info!("次のパラメータを使用します。");
-    info!("ホスト名:{}", host);
+    info!("プロファイル:{}", profile);
+    match conn.socket {
+        Some(ref socket) => info!("ソケット:{}", socket.display()),
+        None => info!(
+            "ホスト名:{}:{}",
+            conn.host,
+            conn.port.map_or("既定".to_string(), |p| p.to_string())
+        ),
+    }
+    info!(
+        "データベース名:{}",
+        conn.database.as_deref().unwrap_or(DEFAULT_DATABASE)
+    );
     info!("ユーザー名:{}", user);
-    info!("パスワード:{}", pass);
+    info!("SSLモード:{}", conn.ssl_mode);
+    if let Some(ref ca) = conn.ssl_ca {
+        info!("CA証明書:{}", ca.display());
+    }
     info!("データベースへの接続を試行します。");
-    block_on(async { Todo::new(host, user, pass).await })?;
+    block_on(async { Todo::new(&conn, &PoolConfig::default()).await })?;
 
     info!("データベースへの接続に成功しました。");
     info!("設定ファイルに接続情報を保存します。");
     {
-        let mut conf = match NekoTodoConfig::new() {
-            Ok(c) => Ok(c),
-            Err(e) => Err(SetupError::SetupFile(e)),
-        }?;
+        let mut conf = NekoTodoConfig::new()?;
+        conf.add_profile(profile)?;
 
-        conf.set_db_host(host);
-        conf.set_db_user(user);
-        conf.set_db_pass(pass);
+        conf.set_connect_config(&conn);
+        conf.save()?;
     }
     eprintln!("アプリケーションを終了します。");
     exit(0);
@@ -77,23 +145,50 @@ struct Args {
     /// データベース接続情報のセットアップを行う。
     #[arg(long)]
     setup: bool,
+    /// 使用するプロファイル。--setup時は、プロファイルがなければ追加する。
+    #[arg(long)]
+    profile: Option<String>,
     /// データベースのサーバー名
     #[arg(short, long)]
     server: Option<String>,
+    /// データベースのポート番号
+    #[arg(long)]
+    port: Option<u16>,
+    /// データベースのUnixドメインソケット。指定時は、サーバー名・ポート番号より優先する。
+    #[arg(long)]
+    socket: Option<PathBuf>,
+    /// データベース名
+    #[arg(long)]
+    database: Option<String>,
+    /// SSLの使用方法(disabled,preferred,required,verify-ca,verify-identity)
+    #[arg(long, default_value_t = SslMode::Preferred)]
+    ssl_mode: SslMode,
+    /// サーバー証明書の検証に使用するCA証明書
+    #[arg(long)]
+    ssl_ca: Option<PathBuf>,
     /// データベースのユーザー名
     #[arg(short, long)]
     user: Option<String>,
-    /// データベースのパスワード
+    /// データベースのパスワード。ソケット接続では省略できる。
     #[arg(short, long)]
     pass: Option<String>,
+    /// GUIを起動せずに実行する処理
+    #[command(subcommand)]
+    command: Option<CliCommand>,
 }
 
 #[derive(Error, Debug)]
 pub enum SetupError {
-    #[error("設定ファイルへのアクセスに失敗")]
-    SetupFile(#[from] dotenvy::Error),
-    #[error("--setup時には、server,user,passの設定が必須です")]
+    #[error("{0}")]
+    SetupFile(#[from] ConfigError),
+    #[error("--setup時には、server(またはsocket),user,pass(socket時は省略可)の設定が必須です")]
     Argument,
     #[error("データベースへの接続に失敗")]
     ConnectDatabase(#[from] TodoError),
+    #[error("コマンドの実行に失敗:{0}")]
+    Cli(#[from] CliError),
+    #[error("{0}")]
+    Profile(#[from] ProfileError),
+    #[error("設定ディレクトリへのアクセスに失敗:{0}")]
+    ConfigDir(#[from] std::io::Error),
 }
@@ -1,24 +1,74 @@
 //! Todoアプリのビジネスロジック実装
 mod app_state;
+mod backup;
+mod connection;
 mod edit_todo;
+mod export;
+mod feed;
 mod get_todo;
+mod history;
+mod import;
 mod new;
+mod offline;
+mod print;
+mod report;
+mod settings;
+mod setup;
 #[cfg(test)]
 mod test;
+mod todotxt;
+mod undo;
 mod user;
+mod validate;
+mod view;
 
 use crate::database::*;
-use log::error;
+use crate::feed::FeedCache;
+use crate::filter::FilterParseError;
+use crate::offline::OfflineCache;
+use connection::ConnectionMonitor;
 use thiserror::Error;
+use undo::UndoStack;
+
+pub use connection::ConnectionStatus;
+pub use history::ChangeCursor;
+pub use undo::UndoState;
 
 /// todoアプリのビジネスロジック実装
 pub struct Todo {
     database: Database,
+    /// ICSフィードのキャッシュ。todoの変更時に破棄する。
+    feed_cache: FeedCache,
+    /// 変更履歴に記録する、クライアントの種類
+    client: Client,
+    /// 取り消し・やり直しの記録
+    undo_stack: UndoStack,
+    /// オフライン時のローカルキャッシュ。Noneなら、オフラインでは動作しない。
+    offline: Option<OfflineCache>,
+    /// データベースへの接続状況
+    connection: ConnectionMonitor,
+}
+
+/// todoを変更するクライアントの種類
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Client {
+    #[default]
+    Gui,
+    Cli,
+}
+
+impl std::fmt::Display for Client {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Gui => write!(f, "gui"),
+            Self::Cli => write!(f, "cli"),
+        }
+    }
 }
 
 #[derive(Error, Debug)]
 pub enum TodoError {
-    #[error("FailInitDatabase")]
+    #[error("FailInitDatabase:{0}")]
     DbInit(sqlx::Error),
     #[error("DuplicateUserName")]
     DuplicateUser(sqlx::Error),
@@ -32,8 +82,56 @@ pub enum TodoError {
     NotFoundSession,
     #[error("NotFoundTodo")]
     NotFoundTodo,
+    #[error("NotFoundView")]
+    NotFoundView,
+    #[error("DuplicateViewName")]
+    DuplicateViewName(sqlx::Error),
+    #[error("InvalidTodo:{0}")]
+    InvalidTodo(#[from] InvalidTodo),
+    #[error("InvalidFilter:{0}")]
+    InvalidFilter(#[from] FilterParseError),
+    /// 他で更新されていた。最新のtodoを持つ。
+    #[error("Conflict:{}", conflict_json(.0))]
+    Conflict(Box<ItemTodo>),
+    #[error("NothingToUndo")]
+    NothingToUndo,
+    #[error("NothingToRedo")]
+    NothingToRedo,
+    /// オフライン中には行えない操作
+    #[error("Offline")]
+    Offline,
     #[error("DatabaseError:{0}")]
     FailDbAccess(sqlx::Error),
+    #[error("MigrateError:{0}")]
+    Migrate(sqlx::migrate::MigrateError),
+}
+
+/// todoの入力値の誤り
+#[derive(Error, Debug, PartialEq)]
+pub enum InvalidTodo {
+    #[error("タイトルが空です。")]
+    EmptyTitle,
+    #[error("タイトルが長すぎます。(最大{0}文字)")]
+    TitleTooLong(usize),
+    #[error("内容が長すぎます。(最大{0}文字)")]
+    WorkTooLong(usize),
+    #[error("開始日が終了日より後です。")]
+    StartAfterEnd,
+}
+
+/// 競合時の最新のtodoを、画面で扱えるようJSONにする。
+fn conflict_json(item: &ItemTodo) -> String {
+    serde_json::to_string(item).unwrap_or_default()
+}
+
+impl TodoError {
+    /// データベースに接続できないことによるエラーか。
+    pub fn is_disconnected(&self) -> bool {
+        match self {
+            Self::DbInit(e) | Self::FailDbAccess(e) => is_connection_error(e),
+            _ => false,
+        }
+    }
 }
 
 impl From<TodoError> for String {
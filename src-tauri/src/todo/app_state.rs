@@ -29,13 +29,23 @@ impl Todo {
                     unreachable!("[Todo::login] Database::make_new_session:[{e}]")
                 }
             })?;
+        self.remember_session(&user.name, session);
         Ok(session)
     }
 
     /// 現在のログインの有効性を確認し、セッションIDを更新する。
     /// もし指定されたセッションIDが無効な場合は、Noneを返す。
     /// セッションが有効な場合は、更新されたセッションIDを返す。
+    /// 取り消しの記録は、更新後のセッションIDに引き継ぐ。
+    /// オフライン中は、ローカルキャッシュのセッションであれば、有効とし更新しない。
     pub async fn is_valid_session(&self, sess: &Uuid) -> Result<Option<Uuid>, TodoError> {
+        self.with_offline(*sess, self.is_valid_session_online(sess), |_| {
+            Ok(Some(*sess))
+        })
+        .await
+    }
+
+    async fn is_valid_session_online(&self, sess: &Uuid) -> Result<Option<Uuid>, TodoError> {
         let is_valid = self
             .database
             .is_session_valid(sess)
@@ -48,7 +58,13 @@ impl Todo {
             })?;
         if is_valid {
             match self.database.update_session(sess).await {
-                Ok(s) => Ok(Some(s)),
+                Ok(s) => {
+                    self.undo_stack.rename(*sess, s);
+                    if let Some(user_name) = self.cached_user_of(*sess) {
+                        self.remember_session(&user_name, s);
+                    }
+                    Ok(Some(s))
+                }
                 Err(DbError::NotFoundSession) => Ok(None),
                 Err(DbError::FailDbAccess(e)) => Err(TodoError::FailDbAccess(e)),
                 Err(e) => {
@@ -0,0 +1,58 @@
+//! ユーザーデータ一式のバックアップと復元
+
+use super::*;
+use crate::backup::{Backup, RestoreMode};
+use log::{error, info};
+use uuid::Uuid;
+
+impl Todo {
+    /// セッションの持ち主のデータ一式から、バックアップを生成する。
+    pub async fn backup(&self, sess: Uuid) -> Result<Backup, TodoError> {
+        let user_name = self.get_user_name(sess).await?;
+        let data = self
+            .database
+            .get_user_data(&user_name)
+            .await
+            .map_err(|e| match e {
+                DbError::FailDbAccess(e) => {
+                    error!("[Todo::backup]get_user_data:[{e}]");
+                    TodoError::FailDbAccess(e)
+                }
+                e => unreachable!("[Todo::backup]get_user_data[{e}]"),
+            })?;
+        Ok(Backup::new(&user_name, data))
+    }
+
+    /// バックアップを、セッションの持ち主のデータとして復元する。
+    /// バックアップ元と異なるユーザーへも復元できる。
+    pub async fn restore(
+        &self,
+        sess: Uuid,
+        backup: &Backup,
+        mode: RestoreMode,
+    ) -> Result<RestoreCount, TodoError> {
+        let user_name = self.get_user_name(sess).await?;
+        let count = self
+            .database
+            .restore_user_data(
+                &user_name,
+                &backup.data,
+                mode == RestoreMode::Replace,
+                &self.change_source(sess),
+            )
+            .await
+            .map_err(|e| match e {
+                DbError::FailDbAccess(e) => {
+                    error!("[Todo::restore]restore_user_data:[{e}]");
+                    TodoError::FailDbAccess(e)
+                }
+                e => unreachable!("[Todo::restore]restore_user_data[{e}]"),
+            })?;
+        info!(
+            "[Todo::restore]{}のバックアップ({})を{}へ{}で復元",
+            backup.user_name, backup.created_at, user_name, mode
+        );
+        self.feed_cache.invalidate(&user_name);
+        Ok(count)
+    }
+}
@@ -0,0 +1,122 @@
+//! データベースへの接続の監視
+//!
+//! 一定間隔で接続を確認し、接続できなければ、間隔を倍々に延ばしながら再接続を試みる。
+//! 再接続できれば、オフライン中の変更を送信する。
+
+use super::*;
+use chrono::{Local, NaiveDateTime};
+use log::{info, warn};
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// 再接続を試みる間隔の初期値
+const BACKOFF_BASE: Duration = Duration::from_secs(1);
+/// 再接続を試みる間隔の上限
+const BACKOFF_MAX: Duration = Duration::from_secs(60);
+
+/// データベースへの接続状態
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ConnectionState {
+    /// まだ確認していない
+    #[default]
+    Unknown,
+    Connected,
+    /// 一時的に接続できず、再接続を試みている
+    Reconnecting,
+    /// 接続情報の誤りなど、再試行しても解消しない理由で接続できない
+    Failed,
+}
+
+/// データベースへの接続状況
+#[derive(Serialize, Debug, Clone, PartialEq, Default)]
+pub struct ConnectionStatus {
+    pub state: ConnectionState,
+    /// 連続して接続に失敗した回数
+    pub failures: u32,
+    /// 最後に失敗した理由
+    pub last_error: Option<String>,
+    /// 最後に確認した日時
+    pub checked_at: Option<NaiveDateTime>,
+    /// 次に確認する日時
+    pub next_check: Option<NaiveDateTime>,
+}
+
+/// 接続状況の記録
+#[derive(Debug, Default)]
+pub struct ConnectionMonitor {
+    status: Mutex<ConnectionStatus>,
+}
+
+impl ConnectionMonitor {
+    /// 確認の結果を記録し、次に確認するまでの待ち時間を返す。
+    fn record(&self, res: &Result<(), DbError>, interval: Duration) -> Duration {
+        let mut status = self.status.lock().unwrap();
+        let (state, wait) = match res {
+            Ok(()) => {
+                if status.state != ConnectionState::Connected {
+                    info!("データベースに接続しました。");
+                }
+                status.failures = 0;
+                status.last_error = None;
+                (ConnectionState::Connected, interval)
+            }
+            Err(e) => {
+                status.failures = status.failures.saturating_add(1);
+                status.last_error = Some(e.to_string());
+                let state = if e.is_transient() {
+                    ConnectionState::Reconnecting
+                } else {
+                    ConnectionState::Failed
+                };
+                let wait = backoff(status.failures);
+                warn!(
+                    "データベースに接続できません。({}回目、{}秒後に再試行):{e:?}",
+                    status.failures,
+                    wait.as_secs()
+                );
+                (state, wait)
+            }
+        };
+        let now = Local::now().naive_local();
+        status.state = state;
+        status.checked_at = Some(now);
+        status.next_check = chrono::Duration::from_std(wait).ok().map(|w| now + w);
+        wait
+    }
+
+    fn status(&self) -> ConnectionStatus {
+        self.status.lock().unwrap().clone()
+    }
+}
+
+/// n回連続で失敗した後の、再接続までの待ち時間。
+/// 初期値から倍々に延ばし、上限で止める。
+pub(super) fn backoff(failures: u32) -> Duration {
+    let factor = 2u32.saturating_pow(failures.saturating_sub(1));
+    BACKOFF_BASE.saturating_mul(factor).min(BACKOFF_MAX)
+}
+
+impl Todo {
+    /// データベースへの接続を確認し、次に確認するまでの待ち時間を返す。
+    /// intervalは、接続できている間の確認の間隔。
+    /// 接続できれば、オフライン中の変更の送信とローカルキャッシュの更新を行う。
+    pub async fn check_connection(&self, interval: Duration) -> Duration {
+        let res = self.database.health_check().await;
+        let wait = self.connection.record(&res, interval);
+        if res.is_ok() {
+            if let Err(e) = self.sync_offline().await {
+                warn!("オフラインキャッシュの同期に失敗:{e}");
+            }
+        } else {
+            self.set_offline();
+        }
+        wait
+    }
+
+    /// データベースへの接続状況を取得する。
+    pub fn connection_status(&self) -> ConnectionStatus {
+        self.connection.status()
+    }
+}
@@ -1,14 +1,28 @@
 //! todoデータの編集
 
+use super::validate::normalize_todo;
 use super::*;
 use crate::database::*;
+use crate::offline::QueuedChange;
 use log::error;
 use uuid::Uuid;
 
 impl Todo {
     /// 新規のtodoを追加する
     /// 引数itemのid, user_name, update_date, update_dateは無視される。
+    /// 入力値は、normalize_todoの規則で検査する。
+    /// オフライン中は、ローカルキャッシュに追加し、再接続後に送信する。
     pub async fn add_todo(&self, sess: Uuid, item: &ItemTodo) -> Result<(), TodoError> {
+        let item = normalize_todo(item)?;
+        self.with_offline(sess, self.add_todo_online(sess, &item), |cache| {
+            cache.enqueue(QueuedChange::Add { item: item.clone() })?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn add_todo_online(&self, sess: Uuid, item: &ItemTodo) -> Result<(), TodoError> {
+        let mut item = item.clone();
         // ユーザー名を取得
         let user = self
             .database
@@ -23,15 +37,10 @@ impl Todo {
                 e => unreachable!("[add_todo]get_user_from_sess[{e}]"),
             })?;
         // アイテムを登録
-        let mut item = item.clone();
         item.user_name = user.name.clone();
-        if let Some(ref s) = item.work {
-            if s.trim().is_empty() {
-                item.work = None;
-            }
-        }
-        self.database
-            .add_todo_item(&item)
+        let id = self
+            .database
+            .add_todo_item(&item, &self.change_source(sess))
             .await
             .map_err(|e| match e {
                 DbError::FailDbAccess(e) => {
@@ -39,41 +48,126 @@ impl Todo {
                     TodoError::FailDbAccess(e)
                 }
                 e => unreachable!("[add_todo]add_todo_item[{e}]"),
-            })
+            })?;
+        let after = self.get_todo_record(id).await?;
+        self.record_change(sess, id, None, Some(after));
+        self.feed_cache.invalidate(&user.name);
+        Ok(())
     }
 
     /// Todoの完了状態を変更する
-    pub async fn change_done(&self, id: u32, sess: Uuid, done: bool) -> Result<(), TodoError> {
-        self.get_todo_with_id(id, sess).await?;
-        self.database
-            .change_done(id, done)
+    /// revisionには、画面に表示しているtodoの版番号を指定する。
+    /// 他で更新されていた場合は、最新のtodoを持つTodoError::Conflictを返す。
+    /// 変更後の版番号を返す。
+    pub async fn change_done(
+        &self,
+        id: u32,
+        sess: Uuid,
+        done: bool,
+        revision: u32,
+    ) -> Result<u32, TodoError> {
+        self.with_offline(
+            sess,
+            self.change_done_online(id, sess, done, revision),
+            |cache| Ok(cache.enqueue(QueuedChange::Done { id, done, revision })?),
+        )
+        .await
+    }
+
+    async fn change_done_online(
+        &self,
+        id: u32,
+        sess: Uuid,
+        done: bool,
+        revision: u32,
+    ) -> Result<u32, TodoError> {
+        let current = self.get_todo_with_id(id, sess).await?;
+        let change = match self
+            .database
+            .change_done(id, done, revision, &self.change_source(sess))
             .await
-            .map_err(|e| match e {
-                DbError::FailDbAccess(e) => {
-                    error!("[Todo::change_done]change_done:[{e}]");
-                    TodoError::FailDbAccess(e)
-                }
-                DbError::NotFoundTodo => TodoError::NotFoundTodo,
-                e => unreachable!("[change_done]change_done[{e}]"),
-            })
+        {
+            Ok(change) => change,
+            Err(DbError::Conflict) => return Err(self.conflict(id, sess).await),
+            Err(DbError::FailDbAccess(e)) => {
+                error!("[Todo::change_done]change_done:[{e}]");
+                return Err(TodoError::FailDbAccess(e));
+            }
+            Err(DbError::NotFoundTodo) => return Err(TodoError::NotFoundTodo),
+            Err(e) => unreachable!("[change_done]change_done[{e}]"),
+        };
+        self.record_change(sess, id, Some(change.before), Some(change.after));
+        self.feed_cache.invalidate(&current.user_name);
+        Ok(change.revision)
     }
 
     /// Todoの編集を行う。
-    pub async fn edit_todo(&self, item: &ItemTodo, sess: Uuid) -> Result<(), TodoError> {
-        let mut item = item.clone();
-        if let Some(ref s) = item.work {
-            if s.trim().is_empty() {
-                item.work = None;
-            }
-        }
-        self.get_todo_with_id(item.id, sess).await?;
-        self.database.edit_todo(&item).await.map_err(|e| match e {
-            DbError::FailDbAccess(e) => {
+    /// item.revisionには、編集を始めたときのtodoの版番号を指定する。
+    /// 他で更新されていた場合は、最新のtodoを持つTodoError::Conflictを返す。
+    /// 変更後の版番号を返す。
+    /// 入力値は、add_todoと同じくnormalize_todoの規則で検査する。
+    pub async fn edit_todo(&self, item: &ItemTodo, sess: Uuid) -> Result<u32, TodoError> {
+        let item = normalize_todo(item)?;
+        self.with_offline(sess, self.edit_todo_online(&item, sess), |cache| {
+            Ok(cache.enqueue(QueuedChange::Edit { item: item.clone() })?)
+        })
+        .await
+    }
+
+    async fn edit_todo_online(&self, item: &ItemTodo, sess: Uuid) -> Result<u32, TodoError> {
+        let current = self.get_todo_with_id(item.id, sess).await?;
+        let change = match self
+            .database
+            .edit_todo(item, &self.change_source(sess))
+            .await
+        {
+            Ok(change) => change,
+            Err(DbError::Conflict) => return Err(self.conflict(item.id, sess).await),
+            Err(DbError::FailDbAccess(e)) => {
                 error!("[Todo::edit_todo]edit_todo:[{e}]");
-                TodoError::FailDbAccess(e)
+                return Err(TodoError::FailDbAccess(e));
             }
-            DbError::NotFoundTodo => TodoError::NotFoundTodo,
-            e => unreachable!("[edit_todo]edit_todo[{e}]"),
+            Err(DbError::NotFoundTodo) => return Err(TodoError::NotFoundTodo),
+            Err(e) => unreachable!("[edit_todo]edit_todo[{e}]"),
+        };
+        self.record_change(sess, item.id, Some(change.before), Some(change.after));
+        self.feed_cache.invalidate(&current.user_name);
+        Ok(change.revision)
+    }
+
+    /// 他で更新されていた場合のエラーを、最新のtodoを取得して生成する。
+    pub(super) async fn conflict(&self, id: u32, sess: Uuid) -> TodoError {
+        match self.get_todo_with_id(id, sess).await {
+            Ok(current) => TodoError::Conflict(Box::new(current)),
+            Err(e) => e,
+        }
+    }
+
+    /// Todoを削除する。
+    pub async fn delete_todo(&self, id: u32, sess: Uuid) -> Result<(), TodoError> {
+        self.with_offline(sess, self.delete_todo_online(id, sess), |cache| {
+            cache.enqueue(QueuedChange::Delete { id })?;
+            Ok(())
         })
+        .await
+    }
+
+    async fn delete_todo_online(&self, id: u32, sess: Uuid) -> Result<(), TodoError> {
+        let current = self.get_todo_with_id(id, sess).await?;
+        let before = self
+            .database
+            .delete_todo(id, &self.change_source(sess))
+            .await
+            .map_err(|e| match e {
+                DbError::FailDbAccess(e) => {
+                    error!("[Todo::delete_todo]delete_todo:[{e}]");
+                    TodoError::FailDbAccess(e)
+                }
+                DbError::NotFoundTodo => TodoError::NotFoundTodo,
+                e => unreachable!("[delete_todo]delete_todo[{e}]"),
+            })?;
+        self.record_change(sess, id, Some(before), None);
+        self.feed_cache.invalidate(&current.user_name);
+        Ok(())
     }
 }
@@ -0,0 +1,72 @@
+//! todoのエクスポート用データの生成
+
+use super::*;
+use crate::export::{ExportDocument, ExportItem};
+use log::error;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+impl Todo {
+    /// セッションの持ち主のtodoを、タグ・繰り返し規則も含めてすべて取得し、
+    /// エクスポート用データを生成する。
+    pub async fn export_todo(&self, sess: Uuid) -> Result<ExportDocument, TodoError> {
+        let user_name = self.get_user_name(sess).await?;
+        let items = self
+            .database
+            .get_all_todo_item(sess)
+            .await
+            .map_err(|e| match e {
+                DbError::FailDbAccess(e) => {
+                    error!("[Todo::export_todo]get_all_todo_item:[{e}]");
+                    TodoError::FailDbAccess(e)
+                }
+                e => unreachable!("[Todo::export_todo]get_all_todo_item[{e}]"),
+            })?;
+        let mut tags = HashMap::<u32, Vec<String>>::new();
+        for (id, tag) in self
+            .database
+            .get_todo_tags(sess)
+            .await
+            .map_err(|e| match e {
+                DbError::FailDbAccess(e) => {
+                    error!("[Todo::export_todo]get_todo_tags:[{e}]");
+                    TodoError::FailDbAccess(e)
+                }
+                e => unreachable!("[Todo::export_todo]get_todo_tags[{e}]"),
+            })?
+        {
+            tags.entry(id).or_default().push(tag);
+        }
+
+        let mut recurrences = self
+            .database
+            .get_todo_recurrences(sess)
+            .await
+            .map_err(|e| match e {
+                DbError::FailDbAccess(e) => {
+                    error!("[Todo::export_todo]get_todo_recurrences:[{e}]");
+                    TodoError::FailDbAccess(e)
+                }
+                e => unreachable!("[Todo::export_todo]get_todo_recurrences[{e}]"),
+            })?
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+
+        let items = items
+            .into_iter()
+            .map(|item| ExportItem {
+                tags: tags.remove(&item.id).unwrap_or_default(),
+                recurrence: recurrences.remove(&item.id),
+                id: item.id,
+                title: item.title,
+                work: item.work,
+                start_date: item.start_date,
+                end_date: item.end_date,
+                update_date: item.update_date,
+                done: item.done,
+                revision: item.revision,
+            })
+            .collect();
+        Ok(ExportDocument::new(&user_name, items))
+    }
+}
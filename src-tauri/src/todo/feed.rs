@@ -0,0 +1,130 @@
+//! ICSフィードの生成
+
+use super::*;
+use crate::export::{ExportDocument, ExportItem};
+use crate::ical;
+use log::error;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+impl Todo {
+    /// ログイン中のユーザーのフィード用トークンを取得する。
+    /// 未発行なら、新たに発行する。
+    pub async fn get_feed_token(&self, sess: Uuid) -> Result<String, TodoError> {
+        let user_name = self.get_user_name(sess).await?;
+        let token = self
+            .database
+            .get_feed_token(&user_name)
+            .await
+            .map_err(|e| match e {
+                DbError::FailDbAccess(e) => {
+                    error!("[Todo::get_feed_token]get_feed_token:[{e}]");
+                    TodoError::FailDbAccess(e)
+                }
+                e => unreachable!("[Todo::get_feed_token]get_feed_token[{e}]"),
+            })?;
+        match token {
+            Some(token) => Ok(token),
+            None => self.issue_feed_token(&user_name).await,
+        }
+    }
+
+    /// ログイン中のユーザーのフィード用トークンを再発行する。
+    /// 以前のトークンは、使用できなくなる。
+    pub async fn reset_feed_token(&self, sess: Uuid) -> Result<String, TodoError> {
+        let user_name = self.get_user_name(sess).await?;
+        self.issue_feed_token(&user_name).await
+    }
+
+    /// トークンを発行し、保存する。
+    async fn issue_feed_token(&self, user_name: &str) -> Result<String, TodoError> {
+        let token = Uuid::new_v4().simple().to_string();
+        self.database
+            .set_feed_token(user_name, &token)
+            .await
+            .map_err(|e| match e {
+                DbError::FailDbAccess(e) => {
+                    error!("[Todo::issue_feed_token]set_feed_token:[{e}]");
+                    TodoError::FailDbAccess(e)
+                }
+                e => unreachable!("[Todo::issue_feed_token]set_feed_token[{e}]"),
+            })?;
+        Ok(token)
+    }
+
+    /// トークンの持ち主の未完了のtodoを、iCalendar形式で生成する。
+    /// all_dayがtrueなら終了日の終日予定、falseならVTODOとして出力する。
+    /// トークンが不明な場合は、Noneを返す。
+    pub async fn render_feed(
+        &self,
+        token: &str,
+        all_day: bool,
+    ) -> Result<Option<String>, TodoError> {
+        let map_err = |e| match e {
+            DbError::FailDbAccess(e) => {
+                error!("[Todo::render_feed]:[{e}]");
+                TodoError::FailDbAccess(e)
+            }
+            e => unreachable!("[Todo::render_feed][{e}]"),
+        };
+        let Some(user_name) = self
+            .database
+            .get_user_name_from_feed_token(token)
+            .await
+            .map_err(map_err)?
+        else {
+            return Ok(None);
+        };
+        // 世代番号と変更履歴の状態は、どちらもtodoの取得より先に取得する。
+        // 生成中にこのプロセスで変更されれば、世代番号が変わってキャッシュに入れない。
+        // 他のプロセスで変更されれば、次回の変更履歴の状態が変わって作り直す。
+        let generation = self.feed_cache.generation(&user_name);
+        let history = self
+            .database
+            .get_history_stamp(&user_name)
+            .await
+            .map_err(map_err)?;
+        if let Some(feed) = self.feed_cache.get(&user_name, all_day, history) {
+            return Ok(Some(feed));
+        }
+
+        let items = self
+            .database
+            .get_open_todo_item_of_user(&user_name)
+            .await
+            .map_err(map_err)?;
+        let mut tags = HashMap::<u32, Vec<String>>::new();
+        for (id, tag) in self
+            .database
+            .get_open_todo_tags_of_user(&user_name)
+            .await
+            .map_err(map_err)?
+        {
+            tags.entry(id).or_default().push(tag);
+        }
+        let items = items
+            .into_iter()
+            .map(|item| ExportItem {
+                tags: tags.remove(&item.id).unwrap_or_default(),
+                recurrence: None,
+                id: item.id,
+                title: item.title,
+                work: item.work,
+                start_date: item.start_date,
+                end_date: item.end_date,
+                update_date: item.update_date,
+                done: item.done,
+                revision: item.revision,
+            })
+            .collect();
+        let doc = ExportDocument::new(&user_name, items);
+        let feed = if all_day {
+            ical::render_all_day(&doc)
+        } else {
+            ical::render(&doc)
+        };
+        self.feed_cache
+            .insert(&user_name, all_day, generation, history, feed.clone());
+        Ok(Some(feed))
+    }
+}
@@ -0,0 +1,125 @@
+//! todoの変更履歴
+
+use super::*;
+use log::error;
+use std::collections::BTreeSet;
+use uuid::Uuid;
+
+/// 監視位置より前に遡って、読み直す変更履歴のidの幅。
+/// 変更履歴のidは、コミット時ではなく登録時に割り当てられるため、
+/// 長いトランザクション(インポート等)の変更履歴は、監視位置より小さいidで後から見えるようになる。
+const RESCAN_IDS: u64 = 1000;
+
+/// 変更の監視位置
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangeCursor {
+    pub user_name: String,
+    /// 通知済みの、最新の変更履歴のid
+    pub last_id: u64,
+    /// 読み直す範囲のうち、通知済みの変更履歴のid
+    pub seen: BTreeSet<u64>,
+}
+
+impl Todo {
+    /// todoの変更履歴を古い順に取得する。
+    /// 削除済みのtodoも、セッションの持ち主のものであれば取得できる。
+    /// 履歴が一件もなければ、TodoError::NotFoundTodoを返す。
+    pub async fn get_todo_history(
+        &self,
+        id: u32,
+        sess: Uuid,
+    ) -> Result<Vec<TodoHistory>, TodoError> {
+        let user_name = self.get_user_name(sess).await?;
+        let history = self
+            .database
+            .get_todo_history(id, &user_name)
+            .await
+            .map_err(|e| match e {
+                DbError::FailDbAccess(e) => {
+                    error!("[Todo::get_todo_history]get_todo_history:[{e}]");
+                    TodoError::FailDbAccess(e)
+                }
+                e => unreachable!("[Todo::get_todo_history]get_todo_history[{e}]"),
+            })?;
+        if history.is_empty() {
+            return Err(TodoError::NotFoundTodo);
+        }
+        Ok(history)
+    }
+
+    /// 前回の監視位置以降の、セッションの持ち主の変更履歴を取得する。
+    /// 監視位置がないか、別のユーザーのものであれば、現在の最新の位置から監視を始める。
+    /// 後からコミットされた変更履歴も通知するため、監視位置より前も一定の幅で読み直し、
+    /// 通知済みのものを除く。
+    /// 新しい監視位置と、その間の変更履歴を返す。
+    pub async fn poll_changes(
+        &self,
+        sess: Uuid,
+        cursor: Option<&ChangeCursor>,
+    ) -> Result<(ChangeCursor, Vec<TodoHistory>), TodoError> {
+        let user_name = self.get_user_name(sess).await?;
+        let map_err = |e| match e {
+            DbError::FailDbAccess(e) => {
+                error!("[Todo::poll_changes]:[{e}]");
+                TodoError::FailDbAccess(e)
+            }
+            e => unreachable!("[Todo::poll_changes][{e}]"),
+        };
+        let Some(cursor) = cursor.filter(|c| c.user_name == user_name) else {
+            let last_id = self
+                .database
+                .get_last_history_id(&user_name)
+                .await
+                .map_err(map_err)?;
+            let seen = self
+                .database
+                .get_history_since(&user_name, last_id.saturating_sub(RESCAN_IDS))
+                .await
+                .map_err(map_err)?
+                .into_iter()
+                .map(|h| h.id)
+                .collect();
+            return Ok((
+                ChangeCursor {
+                    user_name,
+                    last_id,
+                    seen,
+                },
+                vec![],
+            ));
+        };
+        let changes = self
+            .database
+            .get_history_since(&user_name, cursor.last_id.saturating_sub(RESCAN_IDS))
+            .await
+            .map_err(map_err)?
+            .into_iter()
+            .filter(|h| !cursor.seen.contains(&h.id))
+            .collect::<Vec<_>>();
+        let last_id = changes.iter().map(|h| h.id).fold(cursor.last_id, u64::max);
+        let floor = last_id.saturating_sub(RESCAN_IDS);
+        let seen = cursor
+            .seen
+            .iter()
+            .copied()
+            .chain(changes.iter().map(|h| h.id))
+            .filter(|id| *id > floor)
+            .collect();
+        Ok((
+            ChangeCursor {
+                user_name,
+                last_id,
+                seen,
+            },
+            changes,
+        ))
+    }
+
+    /// 変更履歴に記録する変更元を生成する。
+    pub(super) fn change_source(&self, sess: Uuid) -> ChangeSource {
+        ChangeSource {
+            session: sess,
+            client: self.client.to_string(),
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! todoの一括インポート
+
+use super::validate::normalize_todo;
+use super::*;
+use crate::import::{ImportConflict, ImportReport, ImportRowError, ParsedImport};
+use log::error;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+impl Todo {
+    /// 読み込んだtodoを一括で追加する。
+    /// 各行は、add_todoと同じ規則で検査する。
+    /// タイトル・開始日・終了日が既存のtodoと同じ行は、重複として追加しない。
+    /// エラーの行が一件でもあれば、何も追加しない。
+    /// dry_runがtrueの場合は、検査のみ行い、追加しない。
+    /// 追加は一つのトランザクションで行い、失敗時はすべて取り消される。
+    pub async fn import_todo(
+        &self,
+        sess: Uuid,
+        parsed: ParsedImport,
+        dry_run: bool,
+    ) -> Result<ImportReport, TodoError> {
+        let user_name = self.get_user_name(sess).await?;
+        let existing = self
+            .database
+            .get_all_todo_item(sess)
+            .await
+            .map_err(|e| match e {
+                DbError::FailDbAccess(e) => {
+                    error!("[Todo::import_todo]get_all_todo_item:[{e}]");
+                    TodoError::FailDbAccess(e)
+                }
+                e => unreachable!("[Todo::import_todo]get_all_todo_item[{e}]"),
+            })?;
+        let mut known = existing
+            .into_iter()
+            .map(|i| ((i.title, i.start_date, i.end_date), Some(i.id)))
+            .collect::<HashMap<_, _>>();
+
+        let total = parsed.records.len() + parsed.errors.len();
+        let mut errors = parsed.errors;
+        let mut conflicts = Vec::new();
+        let mut items = Vec::new();
+        for record in parsed.records {
+            let mut item = match normalize_todo(&record.item) {
+                Ok(item) => item,
+                Err(e) => {
+                    errors.push(ImportRowError {
+                        row: record.row,
+                        message: e.to_string(),
+                    });
+                    continue;
+                }
+            };
+            let key = (item.title.clone(), item.start_date, item.end_date);
+            if let Some(existing_id) = known.get(&key) {
+                conflicts.push(ImportConflict {
+                    row: record.row,
+                    title: item.title,
+                    existing_id: *existing_id,
+                });
+                continue;
+            }
+            known.insert(key, None);
+            item.user_name = user_name.clone();
+            items.push(NewTodoItem {
+                item,
+                tags: record.tags,
+                recurrence: record.recurrence,
+            });
+        }
+        errors.sort_by_key(|e| e.row);
+
+        let imported = if !errors.is_empty() {
+            0
+        } else if dry_run {
+            items.len()
+        } else {
+            let ids = self
+                .database
+                .add_todo_items(&items, &self.change_source(sess))
+                .await
+                .map_err(|e| match e {
+                    DbError::FailDbAccess(e) => {
+                        error!("[Todo::import_todo]add_todo_items:[{e}]");
+                        TodoError::FailDbAccess(e)
+                    }
+                    e => unreachable!("[Todo::import_todo]add_todo_items[{e}]"),
+                })?;
+            self.feed_cache.invalidate(&user_name);
+            ids.len()
+        };
+        Ok(ImportReport {
+            dry_run,
+            total,
+            imported,
+            conflicts,
+            errors,
+        })
+    }
+}
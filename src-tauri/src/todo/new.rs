@@ -1,13 +1,43 @@
 //! todo構造体新規作成
 use super::*;
+use crate::offline::OfflineCache;
 
 impl Todo {
     /// 初期化
-    pub async fn new(host: &str, user: &str, pass: &str) -> Result<Self, TodoError> {
-        let db = Database::new(host, user, pass).await.map_err(|e| match e {
-            DbError::FailConnect(e2) => TodoError::DbInit(e2),
+    pub async fn new(conn: &ConnectConfig, pool: &PoolConfig) -> Result<Self, TodoError> {
+        let db = Database::new(conn, pool).await.map_err(|e| match e {
+            DbError::FailConnect(e2) | DbError::Unavailable(e2) => TodoError::DbInit(e2),
             e => unreachable!("[ToDo::new] Database::new()[{e}]"),
         })?;
-        Ok(Self { database: db })
+        Ok(Self::with_database(db))
+    }
+
+    /// データベースに接続せずに初期化する。オフラインで起動する場合に使用する。
+    pub fn new_offline(conn: &ConnectConfig, pool: &PoolConfig) -> Self {
+        Self::with_database(Database::new_lazy(conn, pool))
+    }
+
+    fn with_database(database: Database) -> Self {
+        Self {
+            database,
+            feed_cache: FeedCache::default(),
+            client: Client::default(),
+            undo_stack: UndoStack::default(),
+            offline: None,
+            connection: ConnectionMonitor::default(),
+        }
+    }
+
+    /// 変更履歴に記録するクライアントの種類を設定する。
+    pub fn with_client(self, client: Client) -> Self {
+        Self { client, ..self }
+    }
+
+    /// オフライン時に使用するローカルキャッシュを設定する。
+    pub fn with_offline_cache(self, cache: OfflineCache) -> Self {
+        Self {
+            offline: Some(cache),
+            ..self
+        }
     }
 }
@@ -0,0 +1,270 @@
+//! オフライン動作
+//!
+//! データベースに接続できない間は、ローカルキャッシュでtodoの閲覧・変更を行い、
+//! 再接続後に、オフライン中の変更をデータベースへ送信する。
+
+use super::*;
+use crate::offline::{
+    is_local_id, OfflineConflict, OfflineError, OfflineStatus, QueuedChange, ReplayOutcome,
+};
+use log::{error, info, warn};
+use std::future::Future;
+use uuid::Uuid;
+
+impl Todo {
+    /// データベースに接続できているか。ローカルキャッシュがなければ、常に接続中とみなす。
+    pub fn is_online(&self) -> bool {
+        self.offline.as_ref().is_none_or(|c| c.is_online())
+    }
+
+    /// 接続状態と、送信待ちの状況を取得する。
+    pub fn offline_status(&self) -> OfflineStatus {
+        match self.offline {
+            Some(ref cache) => cache.status(),
+            None => OfflineStatus {
+                online: true,
+                pending: 0,
+                conflicts: vec![],
+                synced_at: None,
+            },
+        }
+    }
+
+    /// 記録した競合を破棄する。
+    pub fn clear_offline_conflicts(&self) {
+        if let Some(ref cache) = self.offline {
+            cache.clear_conflicts();
+        }
+    }
+
+    /// ログイン・セッションの更新を、ローカルキャッシュに記録する。
+    pub(super) fn remember_session(&self, user_name: &str, sess: Uuid) {
+        if let Some(ref cache) = self.offline {
+            cache.set_session(user_name, sess);
+        }
+    }
+
+    /// 更新前のセッションの持ち主を、ローカルキャッシュから取得する。
+    pub(super) fn cached_user_of(&self, sess: Uuid) -> Option<String> {
+        self.offline.as_ref()?.user_of(sess)
+    }
+
+    /// オフライン中で、ローカルキャッシュが指定セッションのものであれば、キャッシュを返す。
+    pub(super) fn offline_cache(&self, sess: Uuid) -> Option<&OfflineCache> {
+        self.offline
+            .as_ref()
+            .filter(|c| !c.is_online() && c.user_of(sess).is_some())
+    }
+
+    /// データベースでの処理を行い、接続できなければローカルキャッシュでの処理に切り替える。
+    /// オフライン中は、データベースに接続せずにローカルキャッシュで処理する。
+    pub(super) async fn with_offline<T>(
+        &self,
+        sess: Uuid,
+        online: impl Future<Output = Result<T, TodoError>>,
+        offline: impl FnOnce(&OfflineCache) -> Result<T, TodoError>,
+    ) -> Result<T, TodoError> {
+        if let Some(cache) = self.offline_cache(sess) {
+            return offline(cache);
+        }
+        match online.await {
+            Err(e) if e.is_disconnected() => {
+                let Some(cache) = self.offline.as_ref().filter(|c| c.user_of(sess).is_some())
+                else {
+                    return Err(e);
+                };
+                warn!("データベースに接続できません。オフラインで動作します。:{e}");
+                cache.set_online(false);
+                offline(cache)
+            }
+            res => res,
+        }
+    }
+
+    /// オフライン中の変更をデータベースへ送信し、ローカルキャッシュを最新にする。
+    /// データベースに接続できれば、オンラインに戻す。
+    pub async fn sync_offline(&self) -> Result<(), TodoError> {
+        let Some(ref cache) = self.offline else {
+            return Ok(());
+        };
+        let Some((user_name, sess)) = cache.owner() else {
+            return Ok(());
+        };
+        match self.replay(cache, &user_name, sess).await {
+            Ok(()) => {
+                if !cache.is_online() {
+                    info!("データベースに再接続しました。");
+                    cache.set_online(true);
+                }
+                Ok(())
+            }
+            Err(e) => {
+                if e.is_disconnected() {
+                    self.set_offline();
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// データベースに接続できないため、オフラインでの動作に切り替える。
+    pub(super) fn set_offline(&self) {
+        if let Some(cache) = self.offline.as_ref().filter(|c| c.is_online()) {
+            warn!("データベースに接続できません。オフラインで動作します。");
+            cache.set_online(false);
+        }
+    }
+
+    /// 送信待ちの変更を、古い順に送信する。
+    /// 送信が済めば、データベースに変更があった場合に限り、ローカルキャッシュを取得し直す。
+    async fn replay(
+        &self,
+        cache: &OfflineCache,
+        user_name: &str,
+        sess: Uuid,
+    ) -> Result<(), TodoError> {
+        let map_err = |e| match e {
+            DbError::FailDbAccess(e) => TodoError::FailDbAccess(e),
+            e => unreachable!("[Todo::replay][{e}]"),
+        };
+        // セッションが切れていれば、再度ログインされるまで送信を待つ。
+        if !self
+            .database
+            .is_session_valid(&sess)
+            .await
+            .map_err(map_err)?
+        {
+            return Ok(());
+        }
+        let mut replayed = false;
+        while let Some(change) = cache.front() {
+            let outcome = self.replay_change(&change, user_name, sess).await?;
+            if let ReplayOutcome::Conflict(ref c) = outcome {
+                warn!("オフライン中の変更を反映できません。:{:?}", c.change);
+            }
+            cache.complete_front(outcome);
+            replayed = true;
+        }
+        if replayed {
+            info!("オフライン中の変更を送信しました。");
+            self.feed_cache.invalidate(user_name);
+        }
+        let history_id = self
+            .database
+            .get_last_history_id(user_name)
+            .await
+            .map_err(map_err)?;
+        if replayed || cache.history_id() != Some(history_id) {
+            let todos = self
+                .database
+                .get_all_todo_item(sess)
+                .await
+                .map_err(map_err)?;
+            cache.replace_todos(todos, history_id);
+        }
+        Ok(())
+    }
+
+    /// 送信待ちの変更を一件送信する。
+    /// 接続できなかった場合はエラーを返し、それ以外で反映できなかった場合は競合とする。
+    async fn replay_change(
+        &self,
+        change: &QueuedChange,
+        user_name: &str,
+        sess: Uuid,
+    ) -> Result<ReplayOutcome, TodoError> {
+        let source = self.change_source(sess);
+        let conflict = |current| {
+            Ok(ReplayOutcome::Conflict(OfflineConflict {
+                change: change.clone(),
+                current,
+            }))
+        };
+        let (id, revision) = match change {
+            QueuedChange::Add { item } => {
+                let item = ItemTodo {
+                    user_name: user_name.to_string(),
+                    ..item.clone()
+                };
+                let res = self.database.add_todo_item(&item, &source).await;
+                return match res {
+                    Ok(id) => Ok(ReplayOutcome::Applied { id, revision: 0 }),
+                    Err(e) => {
+                        replay_error(e)?;
+                        conflict(None)
+                    }
+                };
+            }
+            QueuedChange::Edit { item } => (item.id, item.revision),
+            QueuedChange::Done { id, revision, .. } => (*id, *revision),
+            QueuedChange::Delete { id } => (*id, 0),
+        };
+        // 追加を反映できなかったtodoへの変更と、他人のtodoへの変更は、反映しない。
+        if is_local_id(id) {
+            return conflict(None);
+        }
+        match self.database.get_todo_item_with_id(id, sess).await {
+            Ok(_) => {}
+            Err(DbError::NotFoundTodo) => {
+                return match change {
+                    // 削除済みであれば、削除は済んでいる。
+                    QueuedChange::Delete { .. } => Ok(ReplayOutcome::Applied { id, revision }),
+                    _ => conflict(None),
+                };
+            }
+            Err(e) => {
+                replay_error(e)?;
+                return conflict(None);
+            }
+        }
+        let res = match change {
+            QueuedChange::Edit { item } => self
+                .database
+                .edit_todo(item, &source)
+                .await
+                .map(|c| c.revision),
+            QueuedChange::Done { id, done, revision } => self
+                .database
+                .change_done(*id, *done, *revision, &source)
+                .await
+                .map(|c| c.revision),
+            QueuedChange::Delete { id } => self.database.delete_todo(*id, &source).await.map(|_| 0),
+            QueuedChange::Add { .. } => unreachable!("[Todo::replay_change]追加は処理済み"),
+        };
+        match res {
+            Ok(revision) => Ok(ReplayOutcome::Applied { id, revision }),
+            Err(DbError::Conflict) => {
+                let current = self.database.get_todo_item_with_id(id, sess).await.ok();
+                conflict(current)
+            }
+            Err(DbError::NotFoundTodo) => conflict(None),
+            Err(e) => {
+                replay_error(e)?;
+                conflict(None)
+            }
+        }
+    }
+}
+
+/// 送信時のエラーのうち、接続できなかったものはエラーとして返す。
+/// それ以外は、記録したうえで競合として扱う。
+fn replay_error(e: DbError) -> Result<(), TodoError> {
+    let e = match e {
+        DbError::FailDbAccess(e) => TodoError::FailDbAccess(e),
+        e => unreachable!("[Todo::replay_change][{e}]"),
+    };
+    if e.is_disconnected() {
+        return Err(e);
+    }
+    error!("[Todo::replay_change]:[{e}]");
+    Ok(())
+}
+
+impl From<OfflineError> for TodoError {
+    fn from(value: OfflineError) -> Self {
+        match value {
+            OfflineError::NotFoundTodo => Self::NotFoundTodo,
+            OfflineError::Conflict(item) => Self::Conflict(item),
+        }
+    }
+}
@@ -0,0 +1,53 @@
+//! 印刷用のtodo一覧の生成
+
+use super::*;
+use crate::config::ItemSortOrder;
+use crate::filter::TodoFilter;
+use crate::pdf::{render_todo_list, ListHeading, PageSetup};
+use chrono::Local;
+use uuid::Uuid;
+
+/// 印刷用の一覧の表題
+const LIST_TITLE: &str = "todo一覧";
+
+impl Todo {
+    /// get_todo_listと同じ条件で全件を取得し、PDFの一覧を生成する。
+    /// filterは、get_todo_listと同じ形式の絞り込み条件で、見出しにも表示する。
+    /// 出力した件数と、PDFファイルのバイト列を返す。
+    pub async fn render_todo_pdf(
+        &self,
+        sess: Uuid,
+        only_incomplete: bool,
+        sort_order: ItemSortOrder,
+        filter: &str,
+        setup: PageSetup,
+    ) -> Result<(usize, Vec<u8>), TodoError> {
+        let user_name = self.get_user_name(sess).await?;
+        let todo_filter = filter.parse::<TodoFilter>()?;
+        let items = self
+            .get_todo_list(
+                sess,
+                only_incomplete,
+                sort_order,
+                &todo_filter,
+                &PageRequest::default(),
+            )
+            .await?
+            .items;
+
+        let mut conditions = Vec::new();
+        if !filter.trim().is_empty() {
+            conditions.push(filter.trim());
+        }
+        if only_incomplete {
+            conditions.push("未完了のみ");
+        }
+        let heading = ListHeading {
+            title: LIST_TITLE.to_string(),
+            user_name,
+            filter: conditions.join(" / "),
+            ref_date: Local::now().date_naive(),
+        };
+        Ok((items.len(), render_todo_list(&items, &heading, setup)))
+    }
+}
@@ -0,0 +1,82 @@
+//! 作業報告用データの生成
+
+use super::*;
+use crate::report::{Report, ReportItem};
+use chrono::{Local, NaiveDate};
+use log::error;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+impl Todo {
+    /// セッションの持ち主の、期間内(from〜to、両端を含む)の作業報告を生成する。
+    /// 期限切れは、期間の最終日と今日の早い方を基準日として判定する。
+    pub async fn make_report(
+        &self,
+        sess: Uuid,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Report, TodoError> {
+        let user_name = self.get_user_name(sess).await?;
+        let as_of = to.min(Local::now().date_naive());
+        let map_err = |name: &'static str| {
+            move |e| match e {
+                DbError::FailDbAccess(e) => {
+                    error!("[Todo::make_report]{name}:[{e}]");
+                    TodoError::FailDbAccess(e)
+                }
+                e => unreachable!("[Todo::make_report]{name}[{e}]"),
+            }
+        };
+        let completed = self
+            .database
+            .get_completed_todo_item(sess, from, to)
+            .await
+            .map_err(map_err("get_completed_todo_item"))?;
+        let created = self
+            .database
+            .get_created_todo_item(sess, from, to)
+            .await
+            .map_err(map_err("get_created_todo_item"))?;
+        let overdue = self
+            .database
+            .get_overdue_todo_item(sess, as_of)
+            .await
+            .map_err(map_err("get_overdue_todo_item"))?;
+
+        let mut tags = HashMap::<u32, Vec<String>>::new();
+        for (id, tag) in self
+            .database
+            .get_todo_tags(sess)
+            .await
+            .map_err(map_err("get_todo_tags"))?
+        {
+            tags.entry(id).or_default().push(tag);
+        }
+        let to_items = |items: Vec<ReportTodoItem>| {
+            items
+                .into_iter()
+                .map(|item| ReportItem {
+                    tags: tags.get(&item.id).cloned().unwrap_or_default(),
+                    id: item.id,
+                    title: item.title,
+                    work: item.work,
+                    start_date: item.start_date,
+                    end_date: item.end_date,
+                    create_date: item.create_date,
+                    done_date: item.done_date,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        Ok(Report {
+            user_name,
+            from,
+            to,
+            as_of,
+            generated_at: Local::now(),
+            completed: to_items(completed),
+            created: to_items(created),
+            overdue: to_items(overdue),
+        })
+    }
+}
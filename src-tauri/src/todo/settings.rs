@@ -0,0 +1,44 @@
+//! ユーザー設定の操作
+
+use super::*;
+use crate::database::*;
+use log::error;
+use uuid::Uuid;
+
+impl Todo {
+    /// ログイン中のユーザーの設定を取得する。
+    pub async fn get_settings(&self, sess: Uuid) -> Result<UserSettings, TodoError> {
+        let user_name = self.get_user_name(sess).await?;
+        self.database
+            .get_user_settings(&user_name)
+            .await
+            .map_err(|e| match e {
+                DbError::FailDbAccess(e) => {
+                    error!("[Todo::get_settings]get_user_settings:[{e}]");
+                    TodoError::FailDbAccess(e)
+                }
+                e => unreachable!("[get_settings]get_user_settings[{e}]"),
+            })
+    }
+
+    /// ログイン中のユーザーの設定を保存する。
+    /// 引数settingsのuser_nameは無視される。
+    pub async fn save_settings(
+        &self,
+        sess: Uuid,
+        settings: &UserSettings,
+    ) -> Result<(), TodoError> {
+        let mut settings = settings.clone();
+        settings.user_name = self.get_user_name(sess).await?;
+        self.database
+            .save_user_settings(&settings)
+            .await
+            .map_err(|e| match e {
+                DbError::FailDbAccess(e) => {
+                    error!("[Todo::save_settings]save_user_settings:[{e}]");
+                    TodoError::FailDbAccess(e)
+                }
+                e => unreachable!("[save_settings]save_user_settings[{e}]"),
+            })
+    }
+}
@@ -0,0 +1,40 @@
+//! データベースの初期設定
+
+use super::*;
+use log::error;
+
+impl Todo {
+    /// サーバーへの接続を試し、データベースとスキーマの状態を取得する。
+    pub async fn inspect_database(conn: &ConnectConfig) -> Result<DbCheck, TodoError> {
+        conn.inspect().await.map_err(setup_error)
+    }
+
+    /// データベースがなければ作成し、未適用のマイグレーションを適用する。
+    /// 適用後の状態を返す。
+    pub async fn setup_database(conn: &ConnectConfig) -> Result<DbCheck, TodoError> {
+        conn.create_database().await.map_err(setup_error)?;
+        let pool = PoolConfig {
+            max_connections: 1,
+            min_connections: 0,
+            ..Default::default()
+        };
+        let db = Database::new(conn, &pool).await.map_err(setup_error)?;
+        db.migrate().await.map_err(setup_error)?;
+        conn.inspect().await.map_err(setup_error)
+    }
+}
+
+fn setup_error(e: DbError) -> TodoError {
+    match e {
+        DbError::FailConnect(e) | DbError::Unavailable(e) => TodoError::DbInit(e),
+        DbError::FailDbAccess(e) => {
+            error!("[Todo::setup_database]:[{e}]");
+            TodoError::FailDbAccess(e)
+        }
+        DbError::Migrate(e) => {
+            error!("[Todo::setup_database]マイグレーション失敗:[{e}]");
+            TodoError::Migrate(e)
+        }
+        e => unreachable!("[Todo::setup_database][{e}]"),
+    }
+}
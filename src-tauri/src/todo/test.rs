@@ -1,6 +1,7 @@
 use super::*;
 use crate::config::ItemSortOrder;
-use chrono::Local;
+use crate::filter::TodoFilter;
+use chrono::{Local, NaiveDate};
 use sqlx::MySqlPool;
 use uuid::Uuid;
 
@@ -8,6 +9,11 @@ impl Todo {
     fn test_new(pool: MySqlPool) -> Self {
         Self {
             database: Database::new_test(pool),
+            feed_cache: FeedCache::default(),
+            client: Client::default(),
+            undo_stack: UndoStack::default(),
+            offline: None,
+            connection: ConnectionMonitor::default(),
         }
     }
 }
@@ -81,6 +87,7 @@ async fn add_todo_test(pool: MySqlPool) {
         start_date: Some(Local::now().date_naive() - Days::new(1)),
         end_date: Some(Local::now().date_naive() + Days::new(5)),
         done: true,
+        revision: 0,
     };
     let item2 = ItemTodo {
         id: 100,
@@ -91,6 +98,7 @@ async fn add_todo_test(pool: MySqlPool) {
         start_date: Some(Local::now().date_naive() - Days::new(1)),
         end_date: Some(Local::now().date_naive() + Days::new(5)),
         done: true,
+        revision: 0,
     };
     let item3 = ItemTodo {
         id: 100,
@@ -101,14 +109,22 @@ async fn add_todo_test(pool: MySqlPool) {
         start_date: Some(Local::now().date_naive() - Days::new(1)),
         end_date: Some(Local::now().date_naive() + Days::new(5)),
         done: true,
+        revision: 0,
     };
     todo.add_todo(sess, &item1)
         .await
         .expect("1件目の追加に失敗");
     let res = todo
-        .get_todo_list(sess, true, ItemSortOrder::EndAsc)
+        .get_todo_list(
+            sess,
+            true,
+            ItemSortOrder::EndAsc,
+            &TodoFilter::default(),
+            &PageRequest::default(),
+        )
         .await
-        .expect("1件目の取得に失敗");
+        .expect("1件目の取得に失敗")
+        .items;
     assert_eq!(res.len(), 1, "一件目が取得できなかった?");
     assert_eq!(res[0].title, item1.title, "一件目のtitleが違う");
     assert_eq!(res[0].work, item1.work, "一件目のworkが違う");
@@ -126,9 +142,16 @@ async fn add_todo_test(pool: MySqlPool) {
         .await
         .expect("二件目の追加に失敗");
     let res = todo
-        .get_todo_list(sess, true, ItemSortOrder::EndAsc)
+        .get_todo_list(
+            sess,
+            true,
+            ItemSortOrder::EndAsc,
+            &TodoFilter::default(),
+            &PageRequest::default(),
+        )
         .await
-        .expect("二件目の取得に失敗");
+        .expect("二件目の取得に失敗")
+        .items;
     assert_eq!(res.len(), 2, "二件あるはずなんだけど");
     assert!(
         res.iter()
@@ -145,9 +168,16 @@ async fn add_todo_test(pool: MySqlPool) {
         .await
         .expect("三件目の追加に失敗");
     let res = todo
-        .get_todo_list(sess, true, ItemSortOrder::EndAsc)
+        .get_todo_list(
+            sess,
+            true,
+            ItemSortOrder::EndAsc,
+            &TodoFilter::default(),
+            &PageRequest::default(),
+        )
         .await
-        .expect("三件目の取得に失敗");
+        .expect("三件目の取得に失敗")
+        .items;
     assert_eq!(res.len(), 3, "三件あるはずですよ。");
     assert!(
         res.iter()
@@ -169,31 +199,52 @@ async fn change_done_test(pool: MySqlPool) {
     create_todo_for_test(&todo, sess).await;
 
     let items = todo
-        .get_todo_list(sess, true, ItemSortOrder::EndAsc)
+        .get_todo_list(
+            sess,
+            true,
+            ItemSortOrder::EndAsc,
+            &TodoFilter::default(),
+            &PageRequest::default(),
+        )
         .await
-        .unwrap();
+        .unwrap()
+        .items;
     let item = items
         .iter()
         .find(|&i| i.title.contains("1件目"))
         .expect("「1件目」を含むアイテムは必ずあるはず");
     assert!(!item.done, "まだ、未完了のはずです。");
     let id = item.id;
-    todo.change_done(id, sess, true)
+    todo.change_done(id, sess, true, item.revision)
         .await
         .expect("状態更新に失敗。あってはならない。");
     let items = todo
-        .get_todo_list(sess, true, ItemSortOrder::EndAsc)
+        .get_todo_list(
+            sess,
+            true,
+            ItemSortOrder::EndAsc,
+            &TodoFilter::default(),
+            &PageRequest::default(),
+        )
         .await
-        .unwrap();
+        .unwrap()
+        .items;
     assert_eq!(
         items.len(),
         2,
         "一件完了済みにしたので、このリストは2件しかない。"
     );
     let items = todo
-        .get_todo_list(sess, false, ItemSortOrder::EndAsc)
+        .get_todo_list(
+            sess,
+            false,
+            ItemSortOrder::EndAsc,
+            &TodoFilter::default(),
+            &PageRequest::default(),
+        )
         .await
-        .unwrap();
+        .unwrap()
+        .items;
     assert_eq!(items.len(), 3, "完了済みを含むので、3件になる。");
     let item = items
         .iter()
@@ -202,7 +253,7 @@ async fn change_done_test(pool: MySqlPool) {
     assert!(item.done, "さっき完了済みに変更した。");
 
     let max_id = items.iter().max_by_key(|&x| x.id).unwrap().id;
-    let res = todo.change_done(max_id + 1, sess, false).await;
+    let res = todo.change_done(max_id + 1, sess, false, 0).await;
     match res {
         Ok(_) => unreachable!("このidのtodoがあるはずがない。"),
         Err(TodoError::NotFoundTodo) => {}
@@ -210,7 +261,7 @@ async fn change_done_test(pool: MySqlPool) {
     };
 
     // 間違ったセッションのテスト
-    let res = todo.change_done(id, Uuid::now_v7(), true).await;
+    let res = todo.change_done(id, Uuid::now_v7(), true, 0).await;
     match res {
         Ok(_) => unreachable!("このセッションでは、更新を許してはいけない。"),
         Err(TodoError::NotFoundTodo) => { /* 正常 */ }
@@ -225,9 +276,16 @@ async fn edit_todo_test(pool: MySqlPool) {
     create_todo_for_test(&todo, sess).await;
 
     let items = todo
-        .get_todo_list(sess, false, ItemSortOrder::EndAsc)
+        .get_todo_list(
+            sess,
+            false,
+            ItemSortOrder::EndAsc,
+            &TodoFilter::default(),
+            &PageRequest::default(),
+        )
         .await
-        .unwrap();
+        .unwrap()
+        .items;
     let mut item = items
         .iter()
         .find(|&i| i.title.contains("1件目"))
@@ -238,9 +296,16 @@ async fn edit_todo_test(pool: MySqlPool) {
         unreachable!("更新処理に失敗した。[{e}]");
     }
     let Some(item_new) = todo
-        .get_todo_list(sess, false, ItemSortOrder::EndAsc)
+        .get_todo_list(
+            sess,
+            false,
+            ItemSortOrder::EndAsc,
+            &TodoFilter::default(),
+            &PageRequest::default(),
+        )
         .await
         .unwrap()
+        .items
         .iter()
         .find(|&i| i.title.contains("更新した一件目"))
         .cloned()
@@ -255,6 +320,924 @@ async fn edit_todo_test(pool: MySqlPool) {
         Err(TodoError::NotFoundTodo) => { /* 正常 */ }
         Err(e) => unreachable!("偽セッションのときのエラー:{e}"),
     }
+
+    // 追加と同じ規則で検査する。
+    let mut invalid = item_new.clone();
+    invalid.title = " ".to_string();
+    assert!(matches!(
+        todo.edit_todo(&invalid, sess).await,
+        Err(TodoError::InvalidTodo(InvalidTodo::EmptyTitle))
+    ));
+    let mut invalid = item_new.clone();
+    invalid.start_date = NaiveDate::from_ymd_opt(2099, 1, 2);
+    invalid.end_date = NaiveDate::from_ymd_opt(2099, 1, 1);
+    assert!(matches!(
+        todo.edit_todo(&invalid, sess).await,
+        Err(TodoError::InvalidTodo(InvalidTodo::StartAfterEnd))
+    ));
+}
+
+#[sqlx::test]
+async fn saved_view_test(pool: MySqlPool) {
+    let todo = Todo::test_new(pool);
+    let sess = login_for_test(&todo).await;
+    create_todo_for_test(&todo, sess).await;
+
+    let mut view = SavedView {
+        id: 0,
+        user_name: "kore_naihazu".to_string(),
+        name: "一件目だけ".to_string(),
+        filter: "1件目".to_string(),
+        sort_order: ItemSortOrder::EndAsc,
+        only_incomplete: true,
+    };
+    let id = todo
+        .add_view(sess, &view)
+        .await
+        .expect("ビューの追加に失敗");
+    view.id = id;
+
+    let views = todo.get_views(sess).await.unwrap();
+    assert_eq!(views.len(), 1, "ビューは一件のはず");
+    assert_eq!(
+        views[0].user_name, "testdayo",
+        "user_nameはセッションから決まる"
+    );
+
+    let items = todo
+        .get_todo_list_for_view(sess, id, &PageRequest::default())
+        .await
+        .unwrap()
+        .items;
+    assert_eq!(items.len(), 1, "ビューの条件で絞り込まれていない");
+    assert!(items[0].title.contains("1件目"));
+
+    // 不正なフィルタは保存できない
+    view.filter = "due<abc".to_string();
+    match todo.edit_view(sess, &view).await {
+        Ok(_) => unreachable!("不正なフィルタを保存できてはいけない。"),
+        Err(TodoError::InvalidFilter(_)) => { /* 正常 */ }
+        Err(e) => unreachable!("このエラーはおかしい。{e}"),
+    }
+
+    // 他人(無効なセッション)のビューは使えない
+    match todo
+        .get_todo_list_for_view(Uuid::now_v7(), id, &PageRequest::default())
+        .await
+    {
+        Ok(_) => unreachable!("偽のセッションでビューを使えてはいけない。"),
+        Err(TodoError::NotFoundView) => { /* 正常 */ }
+        Err(e) => unreachable!("このエラーはおかしい。{e}"),
+    }
+
+    todo.delete_view(id, sess)
+        .await
+        .expect("ビューの削除に失敗");
+    match todo.delete_view(id, sess).await {
+        Ok(_) => unreachable!("削除済みのビューは削除できない。"),
+        Err(TodoError::NotFoundView) => { /* 正常 */ }
+        Err(e) => unreachable!("このエラーはおかしい。{e}"),
+    }
+}
+
+#[sqlx::test]
+async fn settings_test(pool: MySqlPool) {
+    let todo = Todo::test_new(pool);
+    let sess = login_for_test(&todo).await;
+
+    let mut settings = todo.get_settings(sess).await.unwrap();
+    assert_eq!(
+        settings.user_name, "testdayo",
+        "ログインユーザーの設定のはず"
+    );
+    assert!(settings.only_incomplete, "初期値は未完了のみ表示");
+
+    // 別の端末でログインしても、同じ設定が得られる。
+    settings.user_name = "kore_naihazu".to_string();
+    settings.only_incomplete = false;
+    settings.item_sort_order = ItemSortOrder::UpdateDesc;
+    todo.save_settings(sess, &settings).await.unwrap();
+    let other_sess = todo.login("testdayo", "passrordnona").await.unwrap();
+    let loaded = todo.get_settings(other_sess).await.unwrap();
+    assert_eq!(
+        loaded.user_name, "testdayo",
+        "user_nameはセッションから決まる"
+    );
+    assert!(!loaded.only_incomplete, "保存した設定が反映されていない");
+    assert_eq!(loaded.item_sort_order, ItemSortOrder::UpdateDesc);
+
+    match todo.get_settings(Uuid::now_v7()).await {
+        Ok(_) => unreachable!("偽のセッションで設定を取得できてはいけない。"),
+        Err(TodoError::NotFoundSession) => { /* 正常 */ }
+        Err(e) => unreachable!("このエラーはおかしい。{e}"),
+    }
+}
+
+#[sqlx::test]
+async fn export_todo_test(pool: MySqlPool) {
+    let todo = Todo::test_new(pool);
+    let sess = login_for_test(&todo).await;
+    create_todo_for_test(&todo, sess).await;
+
+    let doc = todo.export_todo(sess).await.unwrap();
+    assert_eq!(doc.user_name, "testdayo");
+    assert_eq!(doc.items.len(), 3, "全件出力されるはず");
+    assert_eq!(doc.items[0].title, "テストアイテム1件目");
+    assert!(doc.items.iter().all(|i| i.tags.is_empty()));
+
+    match todo.export_todo(Uuid::now_v7()).await {
+        Ok(_) => unreachable!("偽のセッションでエクスポートできてはいけない。"),
+        Err(TodoError::NotFoundSession) => { /* 正常 */ }
+        Err(e) => unreachable!("このエラーはおかしい。{e}"),
+    }
+}
+
+#[sqlx::test]
+async fn make_report_test(pool: MySqlPool) {
+    use chrono::Days;
+    let todo = Todo::test_new(pool);
+    let sess = login_for_test(&todo).await;
+    create_todo_for_test(&todo, sess).await;
+    let today = Local::now().date_naive();
+    let overdue = ItemTodo {
+        id: 0,
+        user_name: "".to_string(),
+        title: "期限切れ".to_string(),
+        work: None,
+        update_date: None,
+        start_date: Some(today - Days::new(3)),
+        end_date: Some(today - Days::new(1)),
+        done: false,
+        revision: 0,
+    };
+    todo.add_todo(sess, &overdue).await.unwrap();
+    let items = todo.export_todo(sess).await.unwrap().items;
+    let first = items
+        .iter()
+        .find(|i| i.title == "テストアイテム1件目")
+        .unwrap();
+    let revision = todo.change_done(first.id, sess, true, 0).await.unwrap();
+
+    let report = todo.make_report(sess, today, today).await.unwrap();
+    assert_eq!(report.user_name, "testdayo");
+    assert_eq!(report.as_of, today);
+    assert_eq!(report.completed.len(), 1);
+    assert_eq!(report.completed[0].title, "テストアイテム1件目");
+    assert_eq!(report.completed[0].done_date, Some(today));
+    assert_eq!(report.created.len(), 4, "今日作成したものはすべて");
+    assert_eq!(report.overdue.len(), 1);
+    assert_eq!(report.overdue[0].title, "期限切れ");
+
+    // 過去の期間では、完了・作成はなく、期限切れの基準日は期間の最終日となる。
+    let past = today - Days::new(2);
+    let report = todo.make_report(sess, past, past).await.unwrap();
+    assert!(report.completed.is_empty());
+    assert!(report.created.is_empty());
+    assert!(report.overdue.is_empty(), "二日前には、まだ期限内");
+
+    // 未完了に戻すと、完了日は消える。
+    todo.change_done(first.id, sess, false, revision)
+        .await
+        .unwrap();
+    let report = todo.make_report(sess, today, today).await.unwrap();
+    assert!(report.completed.is_empty());
+
+    match todo.make_report(Uuid::now_v7(), today, today).await {
+        Ok(_) => unreachable!("偽のセッションで作業報告を作れてはいけない。"),
+        Err(TodoError::NotFoundSession) => { /* 正常 */ }
+        Err(e) => unreachable!("このエラーはおかしい。{e}"),
+    }
+}
+
+#[sqlx::test]
+async fn render_todo_pdf_test(pool: MySqlPool) {
+    use crate::pdf::PageSetup;
+    let todo = Todo::test_new(pool);
+    let sess = login_for_test(&todo).await;
+    create_todo_for_test(&todo, sess).await;
+
+    let (count, pdf) = todo
+        .render_todo_pdf(sess, true, ItemSortOrder::EndAsc, "", PageSetup::default())
+        .await
+        .unwrap();
+    assert_eq!(count, 3);
+    assert!(pdf.starts_with(b"%PDF-"));
+
+    let (count, _) = todo
+        .render_todo_pdf(
+            sess,
+            true,
+            ItemSortOrder::EndAsc,
+            "2件目",
+            PageSetup::default(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(count, 1, "get_todo_listと同じ絞り込みになるはず");
+
+    match todo
+        .render_todo_pdf(
+            sess,
+            true,
+            ItemSortOrder::EndAsc,
+            "foo:bar",
+            PageSetup::default(),
+        )
+        .await
+    {
+        Err(TodoError::InvalidFilter(_)) => { /* 正常 */ }
+        _ => unreachable!("不正な絞り込み条件はエラーになるはず"),
+    }
+}
+
+#[sqlx::test]
+async fn delete_todo_and_history_test(pool: MySqlPool) {
+    let todo = Todo::test_new(pool);
+    let sess = login_for_test(&todo).await;
+    create_todo_for_test(&todo, sess).await;
+
+    let items = todo
+        .get_todo_list(
+            sess,
+            false,
+            ItemSortOrder::EndAsc,
+            &TodoFilter::default(),
+            &PageRequest::default(),
+        )
+        .await
+        .unwrap()
+        .items;
+    let mut item = items[0].clone();
+    item.title = "編集後".to_string();
+    let revision = todo.edit_todo(&item, sess).await.unwrap();
+    todo.change_done(item.id, sess, true, revision)
+        .await
+        .unwrap();
+
+    // 他のユーザーのtodoは削除できず、履歴も見えない。
+    todo.add_user("tanin", "passnano").await.unwrap();
+    let other = todo.login("tanin", "passnano").await.unwrap();
+    match todo.delete_todo(item.id, other).await {
+        Err(TodoError::NotFoundTodo) => {}
+        res => unreachable!("他人のtodoは削除できない。{res:?}"),
+    }
+    match todo.get_todo_history(item.id, other).await {
+        Err(TodoError::NotFoundTodo) => {}
+        res => unreachable!("他人の履歴は見えない。{res:?}"),
+    }
+
+    todo.delete_todo(item.id, sess).await.unwrap();
+    match todo.get_todo_with_id(item.id, sess).await {
+        Err(TodoError::NotFoundTodo) => {}
+        res => unreachable!("削除したtodoが残っている。{res:?}"),
+    }
+    let history = todo.get_todo_history(item.id, sess).await.unwrap();
+    let actions = history.iter().map(|h| h.action).collect::<Vec<_>>();
+    assert_eq!(
+        actions,
+        [
+            HistoryAction::Create,
+            HistoryAction::Edit,
+            HistoryAction::Done,
+            HistoryAction::Delete
+        ]
+    );
+    assert!(history.iter().all(|h| h.client == "gui"));
+    assert_eq!(history[1].new_value.as_ref().unwrap().title, "編集後");
+    assert_eq!(history[3].old_value.as_ref().unwrap().title, "編集後");
+}
+
+#[sqlx::test]
+async fn poll_changes_test(pool: MySqlPool) {
+    let todo = Todo::test_new(pool);
+    let sess = login_for_test(&todo).await;
+    create_todo_for_test(&todo, sess).await;
+
+    // 最初は、既存の変更を通知せず、最新の位置から始める。
+    let (cursor, changes) = todo.poll_changes(sess, None).await.unwrap();
+    assert!(changes.is_empty());
+    assert!(cursor.last_id > 0);
+    let (same, changes) = todo.poll_changes(sess, Some(&cursor)).await.unwrap();
+    assert!(changes.is_empty());
+    assert_eq!(same, cursor);
+
+    let item = todo
+        .get_todo_list(
+            sess,
+            false,
+            ItemSortOrder::EndAsc,
+            &TodoFilter::default(),
+            &PageRequest::default(),
+        )
+        .await
+        .unwrap()
+        .items[0]
+        .clone();
+    todo.change_done(item.id, sess, true, item.revision)
+        .await
+        .unwrap();
+    todo.delete_todo(item.id, sess).await.unwrap();
+    let (next, changes) = todo.poll_changes(sess, Some(&cursor)).await.unwrap();
+    let actions = changes.iter().map(|h| h.action).collect::<Vec<_>>();
+    assert_eq!(actions, [HistoryAction::Done, HistoryAction::Delete]);
+    assert!(changes.iter().all(|h| h.todo_id == item.id));
+    assert_eq!(next.last_id, changes[1].id);
+
+    // 監視位置より前のidでも、後からコミットされた変更履歴は通知する。
+    let mut stale = next.clone();
+    stale.seen.remove(&changes[0].id);
+    let (_, late) = todo.poll_changes(sess, Some(&stale)).await.unwrap();
+    assert_eq!(
+        late.iter().map(|h| h.id).collect::<Vec<_>>(),
+        [changes[0].id]
+    );
+    let (_, none) = todo.poll_changes(sess, Some(&next)).await.unwrap();
+    assert!(none.is_empty());
+
+    // 別のユーザーの監視位置は引き継がない。
+    todo.add_user("tanin", "passnano").await.unwrap();
+    let other = todo.login("tanin", "passnano").await.unwrap();
+    let (other_cursor, changes) = todo.poll_changes(other, Some(&next)).await.unwrap();
+    assert!(changes.is_empty());
+    assert_eq!(other_cursor.user_name, "tanin");
+    assert_eq!(other_cursor.last_id, 0);
+}
+
+#[sqlx::test]
+async fn backup_restore_test(pool: MySqlPool) {
+    use crate::backup::{read_archive, write_archive, ArchiveOptions, RestoreMode};
+    use crate::config::Theme;
+    use crate::import::{ImportRecord, ParsedImport};
+    let todo = Todo::test_new(pool);
+    let sess = login_for_test(&todo).await;
+    create_todo_for_test(&todo, sess).await;
+
+    // タグ・繰り返し規則・完了済みのtodo、ビュー、設定を用意する。
+    let item = todo.export_todo(sess).await.unwrap().items[0].clone();
+    let mut record = ItemTodo {
+        id: 0,
+        user_name: "".to_string(),
+        title: "毎週の定例".to_string(),
+        work: Some("議事録を書く".to_string()),
+        update_date: None,
+        start_date: item.start_date,
+        end_date: item.end_date,
+        done: true,
+        revision: 0,
+    };
+    let parsed = ParsedImport {
+        records: vec![ImportRecord {
+            row: 1,
+            item: record.clone(),
+            tags: vec!["仕事".to_string(), "会議".to_string()],
+            recurrence: Some("FREQ=WEEKLY;BYDAY=MO".to_string()),
+        }],
+        errors: vec![],
+    };
+    todo.import_todo(sess, parsed, false).await.unwrap();
+    let view = SavedView {
+        id: 0,
+        user_name: "".to_string(),
+        name: "仕事".to_string(),
+        filter: "tag:仕事".to_string(),
+        sort_order: ItemSortOrder::UpdateDesc,
+        only_incomplete: false,
+    };
+    todo.add_view(sess, &view).await.unwrap();
+    let mut settings = todo.get_settings(sess).await.unwrap();
+    settings.theme = Theme::Dark;
+    settings.default_end_offset = Some(7);
+    todo.save_settings(sess, &settings).await.unwrap();
+
+    // 暗号化・圧縮したアーカイブを経由して、別のユーザーへ置き換えで復元する。
+    let backup = todo.backup(sess).await.unwrap();
+    assert_eq!(backup.data.todos.len(), 4);
+    let options = ArchiveOptions {
+        compress: true,
+        password: Some("ひみつ".to_string()),
+    };
+    let archive = write_archive(&backup, &options).unwrap();
+    let restored = read_archive(&archive, Some("ひみつ")).unwrap();
+    assert_eq!(restored, backup);
+
+    todo.add_user("restore_user", "passnano").await.unwrap();
+    let other = todo.login("restore_user", "passnano").await.unwrap();
+    let count = todo
+        .restore(other, &restored, RestoreMode::Replace)
+        .await
+        .unwrap();
+    assert_eq!(count.todos, 4);
+    assert_eq!(count.views, 1);
+    assert!(count.settings);
+    assert_eq!(count.history, 4, "作成の履歴が一件ずつある");
+
+    let copied = todo.backup(other).await.unwrap();
+    assert_eq!(copied.user_name, "restore_user");
+    let strip = |data: &crate::database::UserData| {
+        data.todos
+            .iter()
+            .map(|t| {
+                let mut t = t.clone();
+                t.id = 0;
+                t
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(strip(&copied.data), strip(&backup.data), "todoは同じ内容");
+    let meeting = copied
+        .data
+        .todos
+        .iter()
+        .find(|t| t.title == "毎週の定例")
+        .unwrap();
+    assert_eq!(meeting.tags, ["仕事", "会議"]);
+    assert_eq!(meeting.recurrence.as_deref(), Some("FREQ=WEEKLY;BYDAY=MO"));
+    assert!(meeting.done);
+    assert_eq!(copied.data.views.len(), 1);
+    assert_eq!(copied.data.views[0].filter, "tag:仕事");
+    assert_eq!(copied.data.views[0].user_name, "restore_user");
+    // 元の履歴を復元し、復元による作成の履歴は重ねて記録しない。
+    assert_eq!(copied.data.history.len(), 4);
+    let meeting_history = todo.get_todo_history(meeting.id, other).await.unwrap();
+    assert_eq!(meeting_history.len(), 1);
+    assert_eq!(meeting_history[0].action, HistoryAction::Create);
+    let copied_settings = copied.data.settings.unwrap();
+    assert_eq!(copied_settings.theme, Theme::Dark);
+    assert_eq!(copied_settings.default_end_offset, Some(7));
+
+    // 元のユーザーへ追加で復元すると、すべて重複となる。
+    let count = todo
+        .restore(sess, &backup, RestoreMode::Merge)
+        .await
+        .unwrap();
+    assert_eq!(count.todos, 0);
+    assert_eq!(count.skipped_todos, 4);
+    assert_eq!(count.skipped_views, 1);
+    assert!(!count.settings, "既存の設定は上書きしない");
+    assert_eq!(count.history, 0, "復元しなかったtodoの履歴は復元しない");
+
+    // 変更後に置き換えで復元すると、バックアップ時点に戻る。
+    record.title = "追加分".to_string();
+    todo.add_todo(sess, &record).await.unwrap();
+    let count = todo
+        .restore(sess, &backup, RestoreMode::Replace)
+        .await
+        .unwrap();
+    assert_eq!(count.todos, 4);
+    let after = todo.backup(sess).await.unwrap();
+    assert_eq!(strip(&after.data), strip(&backup.data));
+}
+
+#[sqlx::test]
+async fn undo_redo_test(pool: MySqlPool) {
+    let todo = Todo::test_new(pool);
+    let sess = login_for_test(&todo).await;
+    match todo.undo(sess).await {
+        Err(TodoError::NothingToUndo) => {}
+        res => unreachable!("まだ何も操作していない。{res:?}"),
+    }
+
+    let item = ItemTodo {
+        id: 0,
+        user_name: "".to_string(),
+        title: "取り消しのテスト".to_string(),
+        work: Some("内容".to_string()),
+        update_date: None,
+        start_date: None,
+        end_date: None,
+        done: false,
+        revision: 0,
+    };
+    todo.add_todo(sess, &item).await.unwrap();
+    let mut added = all_todo_for_test(&todo, sess).await[0].clone();
+    added.title = "編集後".to_string();
+    let revision = todo.edit_todo(&added, sess).await.unwrap();
+    let revision = todo
+        .change_done(added.id, sess, true, revision)
+        .await
+        .unwrap();
+    // 内容の変わらない操作は記録しない。
+    todo.change_done(added.id, sess, true, revision)
+        .await
+        .unwrap();
+    todo.database
+        .set_todo_tags(added.id, &["取り消し".to_string()])
+        .await
+        .unwrap();
+    todo.delete_todo(added.id, sess).await.unwrap();
+    assert_eq!(
+        todo.get_undo_state(sess).await.unwrap(),
+        UndoState { undo: 4, redo: 0 }
+    );
+    assert!(all_todo_for_test(&todo, sess).await.is_empty());
+
+    // 削除の取り消しでは、タグ・完了状態も元に戻る。
+    let state = todo.undo(sess).await.unwrap();
+    assert_eq!(state, UndoState { undo: 3, redo: 1 });
+    let restored = all_todo_for_test(&todo, sess).await;
+    assert_eq!(restored.len(), 1);
+    assert_eq!(restored[0].title, "編集後");
+    assert!(restored[0].done);
+    let id = restored[0].id;
+    let record = todo.get_todo_record(id).await.unwrap();
+    assert_eq!(record.tags, ["取り消し"]);
+
+    todo.undo(sess).await.unwrap();
+    assert!(
+        !all_todo_for_test(&todo, sess).await[0].done,
+        "完了の取り消し"
+    );
+    todo.undo(sess).await.unwrap();
+    assert_eq!(
+        all_todo_for_test(&todo, sess).await[0].title,
+        "取り消しのテスト",
+        "編集の取り消し"
+    );
+    let state = todo.undo(sess).await.unwrap();
+    assert_eq!(state, UndoState { undo: 0, redo: 4 });
+    assert!(
+        all_todo_for_test(&todo, sess).await.is_empty(),
+        "追加の取り消し"
+    );
+
+    // やり直し。追加し直したtodoのidが変わっても、以降の操作を適用できる。
+    for _ in 0..4 {
+        todo.redo(sess).await.unwrap();
+    }
+    assert!(
+        all_todo_for_test(&todo, sess).await.is_empty(),
+        "削除までやり直した"
+    );
+    match todo.redo(sess).await {
+        Err(TodoError::NothingToRedo) => {}
+        res => unreachable!("やり直す操作はもうない。{res:?}"),
+    }
+
+    // 新たな操作で、やり直しの記録は破棄される。
+    todo.undo(sess).await.unwrap();
+    todo.undo(sess).await.unwrap();
+    let current = all_todo_for_test(&todo, sess).await[0].clone();
+    todo.change_done(current.id, sess, true, current.revision)
+        .await
+        .unwrap();
+    assert_eq!(
+        todo.get_undo_state(sess).await.unwrap(),
+        UndoState { undo: 3, redo: 0 }
+    );
+
+    // セッションIDを更新しても、記録は引き継がれる。
+    let sess = todo.is_valid_session(&sess).await.unwrap().unwrap();
+    assert_eq!(
+        todo.get_undo_state(sess).await.unwrap(),
+        UndoState { undo: 3, redo: 0 }
+    );
+
+    // 記録はセッションごとで、同じユーザーでも他のセッションの操作は取り消せない。
+    todo.add_user("tanin", "passnano").await.unwrap();
+    let other = todo.login("tanin", "passnano").await.unwrap();
+    match todo.undo(other).await {
+        Err(TodoError::NothingToUndo) => {}
+        res => unreachable!("他のユーザーの操作は取り消せない。{res:?}"),
+    }
+    let second = todo.login("testdayo", "passrordnona").await.unwrap();
+    assert_eq!(
+        todo.get_undo_state(second).await.unwrap(),
+        UndoState::default()
+    );
+    match todo.undo(second).await {
+        Err(TodoError::NothingToUndo) => {}
+        res => unreachable!("他のセッションの操作は取り消せない。{res:?}"),
+    }
+    assert_eq!(
+        todo.get_undo_state(sess).await.unwrap(),
+        UndoState { undo: 3, redo: 0 }
+    );
+}
+
+#[sqlx::test]
+async fn edit_conflict_test(pool: MySqlPool) {
+    let todo = Todo::test_new(pool);
+    let sess = login_for_test(&todo).await;
+    create_todo_for_test(&todo, sess).await;
+
+    let item = all_todo_for_test(&todo, sess).await.remove(0);
+    let mut desktop = item.clone();
+    desktop.title = "デスクトップで編集".to_string();
+    let mut laptop = item.clone();
+    laptop.title = "ノートで編集".to_string();
+
+    todo.edit_todo(&desktop, sess).await.unwrap();
+    let err = todo.edit_todo(&laptop, sess).await.unwrap_err();
+    let TodoError::Conflict(ref current) = err else {
+        unreachable!("古い版での編集は競合になる。{err}");
+    };
+    assert_eq!(current.title, "デスクトップで編集", "最新のtodoを持つ");
+    assert_eq!(current.revision, 1);
+
+    // 画面には、"Conflict:"に続けて最新のtodoがJSONで渡る。
+    let message = err.to_string();
+    let json = message.strip_prefix("Conflict:").unwrap();
+    assert_eq!(&serde_json::from_str::<ItemTodo>(json).unwrap(), &**current);
+
+    match todo.change_done(item.id, sess, true, item.revision).await {
+        Err(TodoError::Conflict(c)) => assert_eq!(c.revision, 1),
+        res => unreachable!("古い版での完了は競合になる。{res:?}"),
+    }
+
+    // 取り消しの対象が他で更新されていた場合も、競合となり上書きしない。
+    let current = todo.get_todo_with_id(item.id, sess).await.unwrap();
+    let mut other = current.clone();
+    other.work = Some("他の端末で追記".to_string());
+    todo.database
+        .edit_todo(
+            &other,
+            &ChangeSource {
+                session: Uuid::now_v7(),
+                client: "cli".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+    match todo.undo(sess).await {
+        Err(TodoError::Conflict(c)) => {
+            assert_eq!(c.work.as_deref(), Some("他の端末で追記"))
+        }
+        res => unreachable!("他で更新されたtodoは取り消せない。{res:?}"),
+    }
+    let current = todo.get_todo_with_id(item.id, sess).await.unwrap();
+    assert_eq!(current.title, "デスクトップで編集");
+    assert_eq!(current.revision, 2);
+}
+
+#[test]
+fn normalize_todo_test() {
+    use super::validate::*;
+    let base = ItemTodo {
+        id: 0,
+        user_name: "".to_string(),
+        title: "タイトル".to_string(),
+        work: Some(" \t　".to_string()),
+        update_date: None,
+        start_date: None,
+        end_date: None,
+        done: false,
+        revision: 0,
+    };
+    let item = normalize_todo(&base).unwrap();
+    assert_eq!(item.work, None, "空白のみのworkはNone");
+    assert_eq!(item.start_date, Some(Local::now().date_naive()));
+    assert_eq!(item.end_date, NaiveDate::from_ymd_opt(9999, 12, 31));
+
+    let check = |f: &dyn Fn(&mut ItemTodo), err: InvalidTodo| {
+        let mut item = base.clone();
+        f(&mut item);
+        assert_eq!(normalize_todo(&item), Err(err));
+    };
+    check(&|i| i.title = " ".to_string(), InvalidTodo::EmptyTitle);
+    check(
+        &|i| i.title = "あ".repeat(MAX_TITLE_LEN + 1),
+        InvalidTodo::TitleTooLong(MAX_TITLE_LEN),
+    );
+    check(
+        &|i| i.work = Some("あ".repeat(MAX_WORK_LEN + 1)),
+        InvalidTodo::WorkTooLong(MAX_WORK_LEN),
+    );
+    check(
+        &|i| {
+            i.start_date = NaiveDate::from_ymd_opt(2024, 2, 1);
+            i.end_date = NaiveDate::from_ymd_opt(2024, 1, 31);
+        },
+        InvalidTodo::StartAfterEnd,
+    );
+}
+
+#[sqlx::test]
+async fn import_todo_test(pool: MySqlPool) {
+    use crate::import::{parse, CsvMapping, ImportFormat};
+    let todo = Todo::test_new(pool);
+    let sess = login_for_test(&todo).await;
+    create_todo_for_test(&todo, sess).await;
+    let existing = todo.export_todo(sess).await.unwrap().items[0].clone();
+
+    let data = format!(
+        "title,work,start_date,end_date,done,tags\n\
+         新規1,,2024-01-01,2024-01-31,true,a;b\n\
+         新規2,,,,,\n\
+         {},,{},{},,\n\
+         新規1,,2024-01-01,2024-01-31,,\n",
+        existing.title,
+        existing.start_date.unwrap(),
+        existing.end_date.unwrap()
+    );
+    let parsed = parse(&data, ImportFormat::Csv, &CsvMapping::default()).unwrap();
+
+    // 試行では登録しない。
+    let report = todo.import_todo(sess, parsed.clone(), true).await.unwrap();
+    assert!(report.dry_run);
+    assert_eq!(report.total, 4);
+    assert_eq!(report.imported, 2);
+    assert!(report.errors.is_empty());
+    assert_eq!(
+        report
+            .conflicts
+            .iter()
+            .map(|c| (c.row, c.existing_id))
+            .collect::<Vec<_>>(),
+        [(4, Some(existing.id)), (5, None)],
+        "既存のtodoとファイル内の重複"
+    );
+    assert_eq!(todo.export_todo(sess).await.unwrap().items.len(), 3);
+
+    // 本登録
+    let report = todo.import_todo(sess, parsed, false).await.unwrap();
+    assert_eq!(report.imported, 2);
+    let items = todo.export_todo(sess).await.unwrap().items;
+    assert_eq!(items.len(), 5);
+    let new1 = items.iter().find(|i| i.title == "新規1").unwrap();
+    assert!(new1.done, "完了状態も登録される");
+    assert_eq!(new1.tags, ["a", "b"]);
+
+    // エラーの行があれば、何も登録しない。
+    let data = "title,start_date,end_date\n\
+                正常,,\n\
+                 ,,\n\
+                逆転,2024-02-01,2024-01-01\n";
+    let mut mapping = CsvMapping::default();
+    for spec in ["work=", "done=", "tags="] {
+        mapping.set(spec).unwrap();
+    }
+    let parsed = parse(data, ImportFormat::Csv, &mapping).unwrap();
+    let report = todo.import_todo(sess, parsed, false).await.unwrap();
+    assert_eq!(report.imported, 0);
+    assert_eq!(
+        report.errors.iter().map(|e| e.row).collect::<Vec<_>>(),
+        [3, 4]
+    );
+    assert_eq!(todo.export_todo(sess).await.unwrap().items.len(), 5);
+}
+
+#[sqlx::test]
+async fn ical_roundtrip_test(pool: MySqlPool) {
+    let todo = Todo::test_new(pool);
+    let sess = login_for_test(&todo).await;
+
+    let parsed = crate::ical::parse(include_str!("../ical/testdata/sample.ics")).unwrap();
+    let report = todo.import_todo(sess, parsed, false).await.unwrap();
+    assert_eq!(report.errors.len(), 1, "日付が不正なものが一件");
+    assert_eq!(report.imported, 0, "エラーがあれば登録しない");
+
+    let mut parsed = crate::ical::parse(include_str!("../ical/testdata/sample.ics")).unwrap();
+    parsed.errors.clear();
+    let report = todo.import_todo(sess, parsed, false).await.unwrap();
+    assert_eq!(report.imported, 3);
+
+    let doc = todo.export_todo(sess).await.unwrap();
+    let weekly = doc
+        .items
+        .iter()
+        .find(|i| i.title == "週次報告を書く")
+        .unwrap();
+    assert_eq!(weekly.recurrence.as_deref(), Some("FREQ=WEEKLY;BYDAY=FR"));
+    assert_eq!(weekly.tags, ["work", "報告"]);
+
+    // 出力したものを再度取り込むと、すべて重複となる。
+    let parsed = crate::ical::parse(&crate::ical::render(&doc)).unwrap();
+    let report = todo.import_todo(sess, parsed, true).await.unwrap();
+    assert_eq!(report.imported, 0);
+    assert_eq!(report.conflicts.len(), 3);
+}
+
+#[sqlx::test]
+async fn feed_test(pool: MySqlPool) {
+    let todo = Todo::test_new(pool.clone());
+    let sess = login_for_test(&todo).await;
+    create_todo_for_test(&todo, sess).await;
+
+    let token = todo.get_feed_token(sess).await.unwrap();
+    assert_eq!(
+        todo.get_feed_token(sess).await.unwrap(),
+        token,
+        "二回目は同じトークン"
+    );
+    assert_eq!(todo.render_feed("unknown", false).await.unwrap(), None);
+
+    let feed = todo.render_feed(&token, false).await.unwrap().unwrap();
+    assert_eq!(feed.matches("BEGIN:VTODO").count(), 3);
+
+    // 完了にすると、キャッシュが破棄され、フィードから消える。
+    let id = todo.export_todo(sess).await.unwrap().items[0].id;
+    todo.change_done(id, sess, true, 0).await.unwrap();
+    let feed = todo.render_feed(&token, false).await.unwrap().unwrap();
+    assert_eq!(feed.matches("BEGIN:VTODO").count(), 2);
+    let feed = todo.render_feed(&token, true).await.unwrap().unwrap();
+    assert_eq!(feed.matches("BEGIN:VEVENT").count(), 2);
+
+    // 他のプロセスでの変更も、フィードに反映される。
+    let other = Todo::test_new(pool);
+    let id = todo.export_todo(sess).await.unwrap().items[1].id;
+    other.change_done(id, sess, true, 0).await.unwrap();
+    let feed = todo.render_feed(&token, false).await.unwrap().unwrap();
+    assert_eq!(feed.matches("BEGIN:VTODO").count(), 1);
+
+    // 再発行すると、以前のトークンは使えない。
+    let new_token = todo.reset_feed_token(sess).await.unwrap();
+    assert_ne!(new_token, token);
+    assert_eq!(todo.render_feed(&token, false).await.unwrap(), None);
+    assert!(todo.render_feed(&new_token, false).await.unwrap().is_some());
+}
+
+#[sqlx::test]
+async fn sync_todotxt_test(pool: MySqlPool) {
+    use crate::todotxt::SyncBase;
+    let todo = Todo::test_new(pool);
+    let sess = login_for_test(&todo).await;
+    create_todo_for_test(&todo, sess).await;
+
+    // 初回は、データベースの内容を書き出すだけ。
+    let first = todo
+        .sync_todotxt(sess, "", &SyncBase::default())
+        .await
+        .unwrap();
+    assert_eq!(first.content.lines().count(), 3);
+    assert_eq!((first.created, first.updated), (0, 0));
+
+    // ファイル側で、一行目を完了にしてタグを付け、新規の行を追加する。
+    let mut lines = first.content.lines().map(String::from).collect::<Vec<_>>();
+    lines[0] = format!("x {} +猫", lines[0]);
+    lines.push("(B) 新しい項目 @家 due:2099-01-01".to_string());
+    let content = lines.join("\n");
+    let second = todo
+        .sync_todotxt(sess, &content, &first.base)
+        .await
+        .unwrap();
+    assert_eq!((second.created, second.updated), (1, 1));
+    assert!(second.conflicts.is_empty());
+    assert!(second.errors.is_empty());
+    assert_eq!(second.content.lines().count(), 4);
+
+    let items = todo.export_todo(sess).await.unwrap().items;
+    assert!(items[0].done, "完了が反映される");
+    assert_eq!(items[0].tags, ["猫"]);
+    assert_eq!(
+        items[0].work.as_deref(),
+        Some("これは、中身を入れる。"),
+        "workは変更しない"
+    );
+    let new_item = items.iter().find(|i| i.title == "新しい項目").unwrap();
+    assert_eq!(new_item.tags, ["@家", "pri:B"]);
+
+    // 同じ内容で再同期しても、何も変わらない。
+    let third = todo
+        .sync_todotxt(sess, &second.content, &second.base)
+        .await
+        .unwrap();
+    assert_eq!((third.created, third.updated), (0, 0));
+    assert_eq!(third.content, second.content);
+
+    // データベース側も変更されていれば、競合としてデータベース側を優先する。
+    let mut item = todo.get_todo_with_id(items[1].id, sess).await.unwrap();
+    item.title = "DBで変更".to_string();
+    todo.edit_todo(&item, sess).await.unwrap();
+    let content = third
+        .content
+        .replace("テストアイテム2件目", "ファイルで変更");
+    let fourth = todo
+        .sync_todotxt(sess, &content, &third.base)
+        .await
+        .unwrap();
+    assert_eq!(fourth.updated, 0);
+    assert_eq!(fourth.conflicts.len(), 1);
+    assert!(fourth.content.contains("DBで変更"));
+    assert!(!fourth.content.contains("ファイルで変更"));
+
+    // ファイルから削除した行は、データベースからも削除する。
+    let content = fourth
+        .content
+        .lines()
+        .filter(|l| !l.contains("DBで変更"))
+        .map(|l| format!("{l}\n"))
+        .collect::<String>();
+    let fifth = todo
+        .sync_todotxt(sess, &content, &fourth.base)
+        .await
+        .unwrap();
+    assert_eq!(fifth.deleted, 1);
+    assert_eq!(fifth.content, content);
+    assert!(matches!(
+        todo.get_todo_with_id(items[1].id, sess).await,
+        Err(TodoError::NotFoundTodo)
+    ));
+}
+
+#[test]
+fn backoff_test() {
+    use super::connection::backoff;
+    use std::time::Duration;
+    assert_eq!(backoff(1), Duration::from_secs(1));
+    assert_eq!(backoff(2), Duration::from_secs(2));
+    assert_eq!(backoff(4), Duration::from_secs(8));
+    assert_eq!(backoff(7), Duration::from_secs(60));
+    assert_eq!(backoff(u32::MAX), Duration::from_secs(60));
 }
 
 async fn login_for_test(todo: &Todo) -> Uuid {
@@ -276,6 +1259,7 @@ async fn create_todo_for_test(todo: &Todo, sess: Uuid) {
             start_date: Some(Local::now().date_naive() - Days::new(1)),
             end_date: Some(Local::now().date_naive() + Days::new(5)),
             done: false,
+            revision: 0,
         },
         ItemTodo {
             id: 100,
@@ -286,6 +1270,7 @@ async fn create_todo_for_test(todo: &Todo, sess: Uuid) {
             start_date: Some(Local::now().date_naive() - Days::new(1)),
             end_date: Some(Local::now().date_naive() + Days::new(5)),
             done: false,
+            revision: 0,
         },
         ItemTodo {
             id: 100,
@@ -296,9 +1281,24 @@ async fn create_todo_for_test(todo: &Todo, sess: Uuid) {
             start_date: Some(Local::now().date_naive() - Days::new(1)),
             end_date: Some(Local::now().date_naive() + Days::new(5)),
             done: false,
+            revision: 0,
         },
     ];
     for item in items {
         todo.add_todo(sess, &item).await.unwrap();
     }
 }
+
+/// 完了済みを含む、すべてのtodoを取得する。
+async fn all_todo_for_test(todo: &Todo, sess: Uuid) -> Vec<ItemTodo> {
+    todo.get_todo_list(
+        sess,
+        false,
+        ItemSortOrder::EndAsc,
+        &TodoFilter::default(),
+        &PageRequest::default(),
+    )
+    .await
+    .unwrap()
+    .items
+}
@@ -0,0 +1,151 @@
+//! todo.txtファイルとの同期
+
+use super::validate::normalize_todo;
+use super::*;
+use crate::import::ImportRowError;
+use crate::todotxt::{merge, SyncBase, SyncConflict, SyncOutcome, TodoTxtItem};
+use log::error;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+impl Todo {
+    /// todo.txtファイルの内容を、データベースに反映し、
+    /// 書き戻すファイルの内容と新しい同期状態を返す。
+    /// 反映の規則は、todotxt::mergeを参照。
+    /// 各行は、add_todoと同じ規則で検査し、内容(work)は変更しない。
+    /// 取得後に他で更新されていたtodoは、競合とし、残りの行の反映を続ける。
+    pub async fn sync_todotxt(
+        &self,
+        sess: Uuid,
+        content: &str,
+        base: &SyncBase,
+    ) -> Result<SyncOutcome, TodoError> {
+        let user_name = self.get_user_name(sess).await?;
+        let doc = self.export_todo(sess).await?;
+        let plan = merge(content, &doc.items, base);
+        let current = doc
+            .items
+            .iter()
+            .map(|i| (i.id, i))
+            .collect::<HashMap<_, _>>();
+        let mut errors = plan.errors;
+        let mut conflicts = plan.conflicts;
+
+        let mut updated = 0;
+        for (row, txt) in plan.updates {
+            let (mut item, tags) = txt.to_item();
+            let Some(cur) = current.get(&item.id) else {
+                continue;
+            };
+            item.work = cur.work.clone();
+            item.start_date = item.start_date.or(cur.start_date);
+            let mut item = match normalize_todo(&item) {
+                Ok(item) => item,
+                Err(e) => {
+                    errors.push(ImportRowError {
+                        row,
+                        message: e.to_string(),
+                    });
+                    continue;
+                }
+            };
+            // 差分を求めた時点の版番号で更新し、その後の他での更新を上書きしないようにする。
+            item.revision = cur.revision;
+            let result = match self.edit_todo(&item, sess).await {
+                Ok(revision) if item.done != cur.done => self
+                    .change_done(item.id, sess, item.done, revision)
+                    .await
+                    .map(|_| ()),
+                Ok(_) => Ok(()),
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(()) => {}
+                Err(TodoError::Conflict(_)) => {
+                    // 他で更新されていれば、競合とし、データベース側を優先する。
+                    conflicts.push(SyncConflict {
+                        row,
+                        id: item.id,
+                        file_line: txt.to_string(),
+                        db_line: TodoTxtItem::from(*cur).to_string(),
+                    });
+                    continue;
+                }
+                Err(e) => return Err(e),
+            }
+            if tags != cur.tags {
+                self.database
+                    .set_todo_tags(item.id, &tags)
+                    .await
+                    .map_err(|e| match e {
+                        DbError::FailDbAccess(e) => {
+                            error!("[Todo::sync_todotxt]set_todo_tags:[{e}]");
+                            TodoError::FailDbAccess(e)
+                        }
+                        e => unreachable!("[Todo::sync_todotxt]set_todo_tags[{e}]"),
+                    })?;
+            }
+            updated += 1;
+        }
+
+        let mut deleted = 0;
+        for id in plan.deletes {
+            match self.delete_todo(id, sess).await {
+                Ok(()) | Err(TodoError::NotFoundTodo) => deleted += 1,
+                Err(e) => return Err(e),
+            }
+        }
+
+        let mut rows = Vec::new();
+        let mut creates = Vec::new();
+        for (row, txt) in plan.creates {
+            let (item, tags) = txt.to_item();
+            match normalize_todo(&item) {
+                Ok(mut item) => {
+                    item.user_name = user_name.clone();
+                    rows.push(row);
+                    creates.push(NewTodoItem {
+                        item,
+                        tags,
+                        recurrence: None,
+                    });
+                }
+                Err(e) => errors.push(ImportRowError {
+                    row,
+                    message: e.to_string(),
+                }),
+            }
+        }
+        let mut created_rows = Vec::new();
+        if !creates.is_empty() {
+            let ids = self
+                .database
+                .add_todo_items(&creates, &self.change_source(sess))
+                .await
+                .map_err(|e| match e {
+                    DbError::FailDbAccess(e) => {
+                        error!("[Todo::sync_todotxt]add_todo_items:[{e}]");
+                        TodoError::FailDbAccess(e)
+                    }
+                    e => unreachable!("[Todo::sync_todotxt]add_todo_items[{e}]"),
+                })?;
+            created_rows = rows.into_iter().zip(ids).collect();
+            self.feed_cache.invalidate(&user_name);
+        }
+        errors.sort_by_key(|e| e.row);
+        conflicts.sort_by_key(|c| c.row);
+
+        let doc = self.export_todo(sess).await?;
+        let (content, base) = SyncBase::render(&doc.items);
+        Ok(SyncOutcome {
+            content,
+            base,
+            created: creates.len(),
+            created_rows,
+            updated,
+            deleted,
+            conflicts,
+            errors,
+        })
+    }
+}
@@ -0,0 +1,319 @@
+//! todoの操作の取り消し(undo)・やり直し(redo)
+//!
+//! 追加・編集・完了状態の変更・削除を、変更前後の内容の組として、セッションごとに記録する。
+//! 同じユーザーでも、他のセッション(別の端末など)での操作は取り消さない。
+//! 取り消しは変更後から変更前へ、やり直しは変更前から変更後へ、todoを戻す。
+//! 記録はアプリケーションのプロセス内に保持するため、画面を再読み込みしても残る。
+//! ログインし直すと、新たなセッションとなるため、記録は引き継がない。
+
+use super::*;
+use log::{error, info};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+/// セッションごとに記録する、取り消しの最大件数
+const MAX_UNDO: usize = 100;
+
+/// 一件のtodoの変更
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Change {
+    /// 対象のtodoのid。削除された状態では、削除前のid。
+    pub id: u32,
+    /// 変更前の内容。追加の場合はNone。
+    pub before: Option<TodoRecord>,
+    /// 変更後の内容。削除の場合はNone。
+    pub after: Option<TodoRecord>,
+}
+
+/// 取り消し・やり直しができる件数
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Default)]
+pub struct UndoState {
+    pub undo: usize,
+    pub redo: usize,
+}
+
+/// セッションごとの、取り消し・やり直しの記録
+#[derive(Debug, Default)]
+pub struct UndoStack {
+    sessions: Mutex<HashMap<Uuid, Stacks>>,
+}
+
+#[derive(Debug, Default)]
+struct Stacks {
+    undo: Vec<Change>,
+    redo: Vec<Change>,
+}
+
+impl UndoStack {
+    /// 新たな変更を記録する。やり直しの記録は破棄する。
+    pub(super) fn push(&self, sess: Uuid, change: Change) {
+        let mut sessions = self.sessions.lock().unwrap();
+        let stacks = sessions.entry(sess).or_default();
+        stacks.redo.clear();
+        push_limited(&mut stacks.undo, change);
+    }
+
+    /// 取り消す変更を取り出す。
+    pub(super) fn pop_undo(&self, sess: Uuid) -> Option<Change> {
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.get_mut(&sess)?.undo.pop()
+    }
+
+    /// やり直す変更を取り出す。
+    pub(super) fn pop_redo(&self, sess: Uuid) -> Option<Change> {
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.get_mut(&sess)?.redo.pop()
+    }
+
+    /// 取り消した変更を、やり直しの記録に積む。
+    pub(super) fn push_undone(&self, sess: Uuid, change: Change) {
+        let mut sessions = self.sessions.lock().unwrap();
+        let stacks = sessions.entry(sess).or_default();
+        push_limited(&mut stacks.redo, change);
+    }
+
+    /// やり直した変更を、やり直しの記録を残したまま、取り消しの記録に積む。
+    pub(super) fn push_redone(&self, sess: Uuid, change: Change) {
+        let mut sessions = self.sessions.lock().unwrap();
+        let stacks = sessions.entry(sess).or_default();
+        push_limited(&mut stacks.undo, change);
+    }
+
+    /// 削除したtodoを追加し直してidが変わった場合に、記録中のidを置き換える。
+    pub(super) fn replace_id(&self, sess: Uuid, old: u32, new: u32) {
+        let mut sessions = self.sessions.lock().unwrap();
+        if let Some(stacks) = sessions.get_mut(&sess) {
+            for change in stacks.undo.iter_mut().chain(stacks.redo.iter_mut()) {
+                if change.id == old {
+                    change.id = new;
+                }
+            }
+        }
+    }
+
+    /// セッションIDの更新に合わせて、記録を新しいセッションIDに移す。
+    pub(super) fn rename(&self, old: Uuid, new: Uuid) {
+        let mut sessions = self.sessions.lock().unwrap();
+        if let Some(stacks) = sessions.remove(&old) {
+            sessions.insert(new, stacks);
+        }
+    }
+
+    /// 取り消し・やり直しができる件数を取得する。
+    pub(super) fn state(&self, sess: Uuid) -> UndoState {
+        let sessions = self.sessions.lock().unwrap();
+        sessions
+            .get(&sess)
+            .map(|s| UndoState {
+                undo: s.undo.len(),
+                redo: s.redo.len(),
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// 最大件数を超えた場合は、古いものから捨てる。
+fn push_limited(stack: &mut Vec<Change>, change: Change) {
+    if stack.len() >= MAX_UNDO {
+        stack.remove(0);
+    }
+    stack.push(change);
+}
+
+impl Todo {
+    /// 直前の操作を取り消す。
+    pub async fn undo(&self, sess: Uuid) -> Result<UndoState, TodoError> {
+        let user_name = self.get_user_name(sess).await?;
+        let Some(change) = self.undo_stack.pop_undo(sess) else {
+            return Err(TodoError::NothingToUndo);
+        };
+        match self
+            .apply_change(
+                sess,
+                &user_name,
+                change.id,
+                change.after.as_ref(),
+                change.before.as_ref(),
+            )
+            .await
+        {
+            Ok(id) => {
+                info!(
+                    "[Todo::undo]{}の操作を取り消し id=>{}",
+                    user_name, change.id
+                );
+                self.undo_stack.replace_id(sess, change.id, id);
+                self.undo_stack.push_undone(sess, Change { id, ..change });
+            }
+            // 対象のtodoがなくなったか、他で更新された操作は、記録から除く。
+            Err(e @ (TodoError::NotFoundTodo | TodoError::Conflict(_))) => return Err(e),
+            Err(e) => {
+                self.undo_stack.push_redone(sess, change);
+                return Err(e);
+            }
+        }
+        Ok(self.undo_stack.state(sess))
+    }
+
+    /// 取り消した操作をやり直す。
+    pub async fn redo(&self, sess: Uuid) -> Result<UndoState, TodoError> {
+        let user_name = self.get_user_name(sess).await?;
+        let Some(change) = self.undo_stack.pop_redo(sess) else {
+            return Err(TodoError::NothingToRedo);
+        };
+        match self
+            .apply_change(
+                sess,
+                &user_name,
+                change.id,
+                change.before.as_ref(),
+                change.after.as_ref(),
+            )
+            .await
+        {
+            Ok(id) => {
+                info!(
+                    "[Todo::redo]{}の操作をやり直し id=>{}",
+                    user_name, change.id
+                );
+                self.undo_stack.replace_id(sess, change.id, id);
+                self.undo_stack.push_redone(sess, Change { id, ..change });
+            }
+            Err(e @ (TodoError::NotFoundTodo | TodoError::Conflict(_))) => return Err(e),
+            Err(e) => {
+                self.undo_stack.push_undone(sess, change);
+                return Err(e);
+            }
+        }
+        Ok(self.undo_stack.state(sess))
+    }
+
+    /// 取り消し・やり直しができる件数を取得する。
+    pub async fn get_undo_state(&self, sess: Uuid) -> Result<UndoState, TodoError> {
+        self.get_user_name(sess).await?;
+        Ok(self.undo_stack.state(sess))
+    }
+
+    /// todoを、fromの状態からtoの状態にする。
+    /// 現在のtodoがfromの内容と異なる場合は、他で更新されたものとしてTodoError::Conflictを返す。
+    /// 操作後のtodoのidを返す。追加し直した場合は、新たなidとなる。
+    async fn apply_change(
+        &self,
+        sess: Uuid,
+        user_name: &str,
+        id: u32,
+        from: Option<&TodoRecord>,
+        to: Option<&TodoRecord>,
+    ) -> Result<u32, TodoError> {
+        let source = self.change_source(sess);
+        let map_err = |e| match e {
+            DbError::FailDbAccess(e) => {
+                error!("[Todo::apply_change]:[{e}]");
+                TodoError::FailDbAccess(e)
+            }
+            DbError::NotFoundTodo => TodoError::NotFoundTodo,
+            e => unreachable!("[Todo::apply_change][{e}]"),
+        };
+        let current = match from {
+            Some(from) => {
+                // 持ち主の確認を兼ねる。
+                let current = self.get_todo_with_id(id, sess).await?;
+                if !same_content(&current, from) {
+                    return Err(TodoError::Conflict(Box::new(current)));
+                }
+                Some(current)
+            }
+            None => None,
+        };
+        let id = match (current, to) {
+            (None, Some(record)) => self
+                .database
+                .insert_todo(user_name, record, &source)
+                .await
+                .map_err(map_err)?,
+            (Some(_), None) => {
+                self.database
+                    .delete_todo(id, &source)
+                    .await
+                    .map_err(map_err)?;
+                id
+            }
+            (Some(current), Some(record)) => {
+                let item = ItemTodo {
+                    id,
+                    user_name: user_name.to_string(),
+                    title: record.title.clone(),
+                    work: record.work.clone(),
+                    update_date: None,
+                    start_date: Some(record.start_date),
+                    end_date: Some(record.end_date),
+                    done: record.done,
+                    revision: current.revision,
+                };
+                let revision = match self.database.edit_todo(&item, &source).await {
+                    Ok(change) => change.revision,
+                    Err(DbError::Conflict) => return Err(self.conflict(id, sess).await),
+                    Err(e) => return Err(map_err(e)),
+                };
+                if current.done != record.done {
+                    match self
+                        .database
+                        .change_done(id, record.done, revision, &source)
+                        .await
+                    {
+                        Ok(_) => {}
+                        Err(DbError::Conflict) => return Err(self.conflict(id, sess).await),
+                        Err(e) => return Err(map_err(e)),
+                    }
+                }
+                id
+            }
+            (None, None) => id,
+        };
+        self.feed_cache.invalidate(user_name);
+        Ok(id)
+    }
+
+    /// 操作の前後の内容を、取り消しの記録に積む。内容が変わらなければ記録しない。
+    pub(super) fn record_change(
+        &self,
+        sess: Uuid,
+        id: u32,
+        before: Option<TodoRecord>,
+        after: Option<TodoRecord>,
+    ) {
+        let unchanged = match (&before, &after) {
+            (Some(b), Some(a)) => TodoSnapshot::from(b) == TodoSnapshot::from(a),
+            _ => false,
+        };
+        if !unchanged {
+            self.undo_stack.push(sess, Change { id, before, after });
+        }
+    }
+
+    /// 追加したtodoの内容を、取り消し用に取得する。
+    pub(super) async fn get_todo_record(&self, id: u32) -> Result<TodoRecord, TodoError> {
+        self.database
+            .get_todo_record(id)
+            .await
+            .map_err(|e| match e {
+                DbError::FailDbAccess(e) => {
+                    error!("[Todo::get_todo_record]get_todo_record:[{e}]");
+                    TodoError::FailDbAccess(e)
+                }
+                DbError::NotFoundTodo => TodoError::NotFoundTodo,
+                e => unreachable!("[Todo::get_todo_record]get_todo_record[{e}]"),
+            })
+    }
+}
+
+/// todoの内容(タイトル・内容・開始日・終了日・完了状態)が同じか。
+fn same_content(item: &ItemTodo, record: &TodoRecord) -> bool {
+    item.title == record.title
+        && item.work == record.work
+        && item.start_date == Some(record.start_date)
+        && item.end_date == Some(record.end_date)
+        && item.done == record.done
+}
@@ -5,6 +5,7 @@ use bcrypt::{hash, DEFAULT_COST};
 use log::error;
 
 use crate::database::*;
+use uuid::Uuid;
 
 impl Todo {
     /// ユーザーの追加を行う。
@@ -22,4 +23,21 @@ impl Todo {
         }
         Ok(())
     }
+
+    /// セッションIDから、ログイン中のユーザー名を取得する。
+    pub(super) async fn get_user_name(&self, sess: Uuid) -> Result<String, TodoError> {
+        let user = self
+            .database
+            .get_user_from_sess(sess)
+            .await
+            .map_err(|e| match e {
+                DbError::NotFoundSession => TodoError::NotFoundSession,
+                DbError::FailDbAccess(e) => {
+                    error!("[Todo::get_user_name]get_user_from_sess:[{e}]");
+                    TodoError::FailDbAccess(e)
+                }
+                e => unreachable!("[get_user_name]get_user_from_sess[{e}]"),
+            })?;
+        Ok(user.name)
+    }
 }
@@ -0,0 +1,40 @@
+//! todoの入力値の検査
+
+use super::*;
+use chrono::{Local, NaiveDate};
+
+/// タイトルの最大文字数(todo.titleの桁数)
+pub const MAX_TITLE_LEN: usize = 128;
+/// 内容の最大文字数(todo.workの桁数)
+pub const MAX_WORK_LEN: usize = 2048;
+
+/// 追加するtodoを検査し、保存する形に整える。
+/// - 空白のみの内容(work)は、Noneとする。
+/// - 開始日・終了日の省略時は、今日・9999/12/31とする。
+/// - タイトルが空、各項目が長すぎる、開始日が終了日より後の場合はエラーとする。
+pub fn normalize_todo(item: &ItemTodo) -> Result<ItemTodo, InvalidTodo> {
+    let mut item = item.clone();
+    if item.title.trim().is_empty() {
+        return Err(InvalidTodo::EmptyTitle);
+    }
+    if item.title.chars().count() > MAX_TITLE_LEN {
+        return Err(InvalidTodo::TitleTooLong(MAX_TITLE_LEN));
+    }
+    if let Some(ref s) = item.work {
+        if s.trim().is_empty() {
+            item.work = None;
+        } else if s.chars().count() > MAX_WORK_LEN {
+            return Err(InvalidTodo::WorkTooLong(MAX_WORK_LEN));
+        }
+    }
+    let start_date = item.start_date.unwrap_or(Local::now().date_naive());
+    let end_date = item
+        .end_date
+        .unwrap_or(NaiveDate::from_ymd_opt(9999, 12, 31).unwrap());
+    if start_date > end_date {
+        return Err(InvalidTodo::StartAfterEnd);
+    }
+    item.start_date = Some(start_date);
+    item.end_date = Some(end_date);
+    Ok(item)
+}
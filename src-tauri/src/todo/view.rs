@@ -0,0 +1,97 @@
+//! 保存済みビューの操作
+
+use super::*;
+use crate::{database::*, filter::TodoFilter};
+use log::error;
+use uuid::Uuid;
+
+impl Todo {
+    /// ビューを追加する。生成されたビューのidを返す。
+    /// 引数viewのid, user_nameは無視される。
+    pub async fn add_view(&self, sess: Uuid, view: &SavedView) -> Result<u32, TodoError> {
+        let mut view = view.clone();
+        view.user_name = self.get_user_name(sess).await?;
+        view.filter.parse::<TodoFilter>()?;
+        self.database.add_view(&view).await.map_err(|e| match e {
+            DbError::DuplicateViewName(e) => TodoError::DuplicateViewName(e),
+            DbError::FailDbAccess(e) => {
+                error!("[Todo::add_view]add_view:[{e}]");
+                TodoError::FailDbAccess(e)
+            }
+            e => unreachable!("[add_view]add_view[{e}]"),
+        })
+    }
+
+    /// ログイン中のユーザーのビューの一覧を取得する。
+    pub async fn get_views(&self, sess: Uuid) -> Result<Vec<SavedView>, TodoError> {
+        self.database.get_views(sess).await.map_err(|e| match e {
+            DbError::FailDbAccess(e) => {
+                error!("[Todo::get_views]get_views:[{e}]");
+                TodoError::FailDbAccess(e)
+            }
+            e => unreachable!("[get_views]get_views[{e}]"),
+        })
+    }
+
+    /// idとsessを指定してビューを取得する。
+    /// 一致するビューがなければ、エラー、TodoError::NotFoundViewを返す。
+    pub async fn get_view_with_id(&self, id: u32, sess: Uuid) -> Result<SavedView, TodoError> {
+        self.database
+            .get_view_with_id(id, sess)
+            .await
+            .map_err(|e| match e {
+                DbError::NotFoundView => TodoError::NotFoundView,
+                DbError::FailDbAccess(e) => {
+                    error!("[Todo::get_view_with_id]get_view_with_id:[{e}]");
+                    TodoError::FailDbAccess(e)
+                }
+                e => unreachable!("[get_view_with_id]get_view_with_id[{e}]"),
+            })
+    }
+
+    /// ビューの編集を行う。
+    /// 引数viewのuser_nameは無視される。
+    pub async fn edit_view(&self, sess: Uuid, view: &SavedView) -> Result<(), TodoError> {
+        let mut view = view.clone();
+        view.user_name = self.get_user_name(sess).await?;
+        view.filter.parse::<TodoFilter>()?;
+        self.database.edit_view(&view).await.map_err(|e| match e {
+            DbError::NotFoundView => TodoError::NotFoundView,
+            DbError::DuplicateViewName(e) => TodoError::DuplicateViewName(e),
+            DbError::FailDbAccess(e) => {
+                error!("[Todo::edit_view]edit_view:[{e}]");
+                TodoError::FailDbAccess(e)
+            }
+            e => unreachable!("[edit_view]edit_view[{e}]"),
+        })
+    }
+
+    /// ビューを削除する。
+    pub async fn delete_view(&self, id: u32, sess: Uuid) -> Result<(), TodoError> {
+        let user_name = self.get_user_name(sess).await?;
+        self.database
+            .delete_view(id, &user_name)
+            .await
+            .map_err(|e| match e {
+                DbError::NotFoundView => TodoError::NotFoundView,
+                DbError::FailDbAccess(e) => {
+                    error!("[Todo::delete_view]delete_view:[{e}]");
+                    TodoError::FailDbAccess(e)
+                }
+                e => unreachable!("[delete_view]delete_view[{e}]"),
+            })
+    }
+
+    /// 指定したビューの条件で、todoの一覧を取得する。
+    pub async fn get_todo_list_for_view(
+        &self,
+        sess: Uuid,
+        view_id: u32,
+        page: &PageRequest,
+    ) -> Result<TodoPage, TodoError> {
+        let view = self.get_view_with_id(view_id, sess).await?;
+        let filter = view.filter.parse::<TodoFilter>()?;
+        self.get_todo_list(sess, view.only_incomplete, view.sort_order, &filter, page)
+            .await
+    }
+}
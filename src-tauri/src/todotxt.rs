@@ -0,0 +1,89 @@
+//! todo.txt形式との相互変換と、todo.txtファイルとの同期
+//!
+//! todo.txtの各要素は、次のように対応付ける。
+//! - 完了マーク(`x`) ⇔ done
+//! - 作成日 ⇔ start_date
+//! - 完了日 ⇐ update_date (出力のみ)
+//! - `due:` ⇔ end_date
+//! - `+project` ⇔ タグ`project`
+//! - `@context` ⇔ タグ`@context`
+//! - 優先度`(A)` ⇔ タグ`pri:A`
+//! - `id:` ⇔ todoのid (同期用)
+//!
+//! 内容(work)は、todo.txtでは扱わない。
+mod codec;
+mod sync;
+#[cfg(test)]
+mod test;
+mod watcher;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use thiserror::Error;
+
+pub use sync::{merge, SyncConflict};
+pub use watcher::spawn_todotxt_sync;
+
+/// 優先度を表すタグの接頭辞
+pub const PRIORITY_TAG_PREFIX: &str = "pri:";
+
+/// todo.txtの一行分
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TodoTxtItem {
+    pub done: bool,
+    /// 優先度(A〜Z)
+    pub priority: Option<char>,
+    pub completion_date: Option<NaiveDate>,
+    pub creation_date: Option<NaiveDate>,
+    pub title: String,
+    pub projects: Vec<String>,
+    pub contexts: Vec<String>,
+    pub due: Option<NaiveDate>,
+    pub id: Option<u32>,
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum TodoTxtError {
+    #[error("日付の形式が不正です。:{0}")]
+    InvalidDate(String),
+    #[error("idが不正です。:{0}")]
+    InvalidId(String),
+}
+
+/// 同期の結果
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SyncOutcome {
+    /// 書き出すファイルの内容
+    pub content: String,
+    /// 新しい同期状態
+    pub base: SyncBase,
+    /// 追加した件数
+    pub created: usize,
+    /// 追加した行の行番号と、割り当てたid
+    pub created_rows: Vec<(usize, u32)>,
+    /// 更新した件数
+    pub updated: usize,
+    /// 削除した件数
+    pub deleted: usize,
+    /// 競合した行(データベース側を優先した)
+    pub conflicts: Vec<SyncConflict>,
+    /// 反映できなかった行
+    pub errors: Vec<crate::import::ImportRowError>,
+}
+
+/// 前回の同期時点の状態。
+/// ファイル側・データベース側のどちらが変更されたかの判定に使用する。
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct SyncBase {
+    pub entries: HashMap<u32, SyncBaseEntry>,
+}
+
+/// 前回の同期時点の、todo一件分の状態
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SyncBaseEntry {
+    /// ファイルに書き出した行
+    pub line: String,
+    /// 書き出した時点の、データベースの更新日
+    pub update_date: Option<NaiveDate>,
+}
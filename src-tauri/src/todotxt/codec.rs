@@ -0,0 +1,183 @@
+//! todo.txtの一行の解析と出力
+
+use super::*;
+use crate::database::ItemTodo;
+use crate::export::ExportItem;
+use std::fmt::Display;
+
+impl std::str::FromStr for TodoTxtItem {
+    type Err = TodoTxtError;
+
+    /// 一行を解析する。
+    /// 完了マーク・日付・優先度は、行頭にある場合のみ認識する。
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let mut item = TodoTxtItem::default();
+        let mut tokens = line.split_whitespace().peekable();
+
+        if tokens.peek() == Some(&"x") {
+            tokens.next();
+            item.done = true;
+        }
+        if !item.done {
+            if let Some(p) = tokens.peek().and_then(|t| parse_priority(t)) {
+                tokens.next();
+                item.priority = Some(p);
+            }
+        }
+        let mut dates = Vec::new();
+        while dates.len() < if item.done { 2 } else { 1 } {
+            match tokens.peek().and_then(|t| t.parse::<NaiveDate>().ok()) {
+                Some(d) => {
+                    tokens.next();
+                    dates.push(d);
+                }
+                None => break,
+            }
+        }
+        match (item.done, dates.as_slice()) {
+            (true, [completion, creation]) => {
+                item.completion_date = Some(*completion);
+                item.creation_date = Some(*creation);
+            }
+            (true, [completion]) => item.completion_date = Some(*completion),
+            (false, [creation]) => item.creation_date = Some(*creation),
+            _ => {}
+        }
+
+        let mut title = Vec::new();
+        for token in tokens {
+            if let Some(p) = token.strip_prefix('+').filter(|p| !p.is_empty()) {
+                item.projects.push(p.to_string());
+            } else if token.len() > 1 && token.starts_with('@') {
+                item.contexts.push(token.to_string());
+            } else if let Some(d) = token.strip_prefix("due:") {
+                item.due = Some(
+                    d.parse()
+                        .map_err(|_| TodoTxtError::InvalidDate(d.to_string()))?,
+                );
+            } else if let Some(id) = token.strip_prefix("id:") {
+                item.id = Some(
+                    id.parse()
+                        .map_err(|_| TodoTxtError::InvalidId(id.to_string()))?,
+                );
+            } else if let Some(p) = token
+                .strip_prefix(PRIORITY_TAG_PREFIX)
+                .and_then(single_priority)
+            {
+                // 完了済みの項目の優先度
+                item.priority = Some(p);
+            } else {
+                title.push(token);
+            }
+        }
+        item.title = title.join(" ");
+        Ok(item)
+    }
+}
+
+impl Display for TodoTxtItem {
+    /// 一行に出力する。
+    /// 完了済みの項目の優先度は、`pri:`として出力する。
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts = Vec::new();
+        if self.done {
+            parts.push("x".to_string());
+            // 作成日を出力するには、完了日が必要
+            if let Some(d) = self.completion_date.or(self.creation_date) {
+                parts.push(d.to_string());
+            }
+        } else if let Some(p) = self.priority {
+            parts.push(format!("({})", p));
+        }
+        if let Some(d) = self.creation_date {
+            parts.push(d.to_string());
+        }
+        if !self.title.is_empty() {
+            parts.push(self.title.clone());
+        }
+        parts.extend(self.projects.iter().map(|p| format!("+{}", p)));
+        parts.extend(self.contexts.iter().cloned());
+        if let Some(d) = self.due {
+            parts.push(format!("due:{}", d));
+        }
+        if self.done {
+            if let Some(p) = self.priority {
+                parts.push(format!("{}{}", PRIORITY_TAG_PREFIX, p));
+            }
+        }
+        if let Some(id) = self.id {
+            parts.push(format!("id:{}", id));
+        }
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+impl From<&ExportItem> for TodoTxtItem {
+    fn from(item: &ExportItem) -> Self {
+        let no_end_date = NaiveDate::from_ymd_opt(9999, 12, 31).unwrap();
+        let mut ret = TodoTxtItem {
+            done: item.done,
+            completion_date: if item.done { item.update_date } else { None },
+            creation_date: item.start_date,
+            title: item.title.split_whitespace().collect::<Vec<_>>().join(" "),
+            due: item.end_date.filter(|d| *d != no_end_date),
+            id: Some(item.id),
+            ..Default::default()
+        };
+        for tag in &item.tags {
+            let tag = tag.split_whitespace().collect::<Vec<_>>().join("_");
+            if let Some(p) = tag
+                .strip_prefix(PRIORITY_TAG_PREFIX)
+                .and_then(single_priority)
+            {
+                ret.priority = Some(p);
+            } else if tag.starts_with('@') {
+                ret.contexts.push(tag);
+            } else if !tag.is_empty() {
+                ret.projects.push(tag);
+            }
+        }
+        ret
+    }
+}
+
+impl TodoTxtItem {
+    /// todoとタグに変換する。
+    /// 省略された作成日・期限は、Noneのままとする。(追加時に既定値となる)
+    pub fn to_item(&self) -> (ItemTodo, Vec<String>) {
+        let item = ItemTodo {
+            id: self.id.unwrap_or(0),
+            user_name: String::new(),
+            title: self.title.clone(),
+            work: None,
+            update_date: None,
+            start_date: self.creation_date,
+            end_date: self.due,
+            done: self.done,
+            revision: 0,
+        };
+        let mut tags = self.projects.clone();
+        tags.extend(self.contexts.iter().cloned());
+        if let Some(p) = self.priority {
+            tags.push(format!("{}{}", PRIORITY_TAG_PREFIX, p));
+        }
+        (item, tags)
+    }
+}
+
+/// "(A)"形式の優先度を解析する。
+fn parse_priority(token: &str) -> Option<char> {
+    token
+        .strip_prefix('(')
+        .and_then(|s| s.strip_suffix(')'))
+        .and_then(single_priority)
+}
+
+/// "A"〜"Z"の一文字を解析する。
+fn single_priority(s: &str) -> Option<char> {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_uppercase() => Some(c),
+        _ => None,
+    }
+}
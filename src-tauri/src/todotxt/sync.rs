@@ -0,0 +1,146 @@
+//! todo.txtファイルとデータベースの差分の判定
+//!
+//! 前回の同期時点の状態(SyncBase)と比べ、ファイル側で変更された行を
+//! データベースに反映する。データベース側も前回の同期以降に変更されていた場合は、
+//! 競合とし、データベース側を優先する。
+
+use super::*;
+use crate::export::ExportItem;
+use crate::import::ImportRowError;
+use std::collections::HashSet;
+
+/// ファイルの内容を、データベースに反映する計画
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MergePlan {
+    /// 追加する行(行番号, 内容)
+    pub creates: Vec<(usize, TodoTxtItem)>,
+    /// 既存のtodoを更新する行(行番号, 内容)
+    pub updates: Vec<(usize, TodoTxtItem)>,
+    /// ファイルから削除されたtodoのid
+    pub deletes: Vec<u32>,
+    /// 競合した行
+    pub conflicts: Vec<SyncConflict>,
+    /// 解析できなかった行
+    pub errors: Vec<ImportRowError>,
+}
+
+/// 競合した行。データベース側の内容を優先する。
+/// ファイルから削除された行の競合は、行番号を0、ファイル側の行を空とする。
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncConflict {
+    pub row: usize,
+    pub id: u32,
+    /// ファイル側の行
+    pub file_line: String,
+    /// データベース側の行
+    pub db_line: String,
+}
+
+/// ファイルの内容と、データベースのtodoを比較し、反映する計画を立てる。
+/// - idのない行、データベースに存在しないidの行は、追加する。
+/// - 前回の同期から変更された行は、データベースを更新する。
+///   ただし、データベース側も変更(更新日または内容が変化)されていれば、競合とする。
+/// - 前回の同期時にあり、ファイルから削除された行は、データベースから削除する。
+///   ただし、データベース側も変更されていれば、競合とし、削除しない。
+pub fn merge(content: &str, db_items: &[ExportItem], base: &SyncBase) -> MergePlan {
+    let db_items = db_items
+        .iter()
+        .map(|i| (i.id, i))
+        .collect::<HashMap<_, _>>();
+    let mut plan = MergePlan::default();
+    let mut seen = HashSet::new();
+    for (i, line) in content.lines().enumerate() {
+        let row = i + 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut item = match line.parse::<TodoTxtItem>() {
+            Ok(item) => item,
+            Err(e) => {
+                // 解析できない行も、削除されたとは扱わない。
+                seen.extend(line_id(line));
+                plan.errors.push(ImportRowError {
+                    row,
+                    message: e.to_string(),
+                });
+                continue;
+            }
+        };
+        seen.extend(item.id);
+        let Some(db_item) = item.id.and_then(|id| db_items.get(&id)) else {
+            item.id = None;
+            plan.creates.push((row, item));
+            continue;
+        };
+
+        let file_line = item.to_string();
+        let db_line = TodoTxtItem::from(*db_item).to_string();
+        let base_entry = base.entries.get(&db_item.id);
+        let file_changed = base_entry.map(|b| b.line != file_line).unwrap_or(true);
+        if !file_changed || file_line == db_line {
+            continue;
+        }
+        let db_changed = base_entry
+            .map(|b| b.line != db_line || b.update_date != db_item.update_date)
+            .unwrap_or(true);
+        if db_changed {
+            plan.conflicts.push(SyncConflict {
+                row,
+                id: db_item.id,
+                file_line,
+                db_line,
+            });
+        } else {
+            plan.updates.push((row, item));
+        }
+    }
+
+    let mut deleted = base
+        .entries
+        .iter()
+        .filter(|(id, _)| !seen.contains(*id))
+        .filter_map(|(id, b)| db_items.get(id).map(|db_item| (b, *db_item)))
+        .collect::<Vec<_>>();
+    deleted.sort_by_key(|(_, db_item)| db_item.id);
+    for (base_entry, db_item) in deleted {
+        let db_line = TodoTxtItem::from(db_item).to_string();
+        if base_entry.line != db_line || base_entry.update_date != db_item.update_date {
+            plan.conflicts.push(SyncConflict {
+                row: 0,
+                id: db_item.id,
+                file_line: String::new(),
+                db_line,
+            });
+        } else {
+            plan.deletes.push(db_item.id);
+        }
+    }
+    plan
+}
+
+/// 解析できない行から、idのみを取り出す。
+fn line_id(line: &str) -> Option<u32> {
+    line.split_whitespace()
+        .find_map(|token| token.strip_prefix("id:")?.parse().ok())
+}
+
+impl SyncBase {
+    /// データベースのtodoから、ファイルの内容と同期状態を生成する。
+    pub fn render(db_items: &[ExportItem]) -> (String, Self) {
+        let mut content = String::new();
+        let mut base = Self::default();
+        for item in db_items {
+            let line = TodoTxtItem::from(item).to_string();
+            content.push_str(&line);
+            content.push('\n');
+            base.entries.insert(
+                item.id,
+                SyncBaseEntry {
+                    line,
+                    update_date: item.update_date,
+                },
+            );
+        }
+        (content, base)
+    }
+}
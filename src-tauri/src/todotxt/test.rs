@@ -0,0 +1,196 @@
+//! todotxtモジュールテスト
+
+use super::*;
+use crate::export::ExportItem;
+
+fn date(y: i32, m: u32, d: u32) -> Option<NaiveDate> {
+    NaiveDate::from_ymd_opt(y, m, d)
+}
+
+fn parse(line: &str) -> TodoTxtItem {
+    line.parse()
+        .unwrap_or_else(|e| unreachable!("解析失敗[{line}]:{e}"))
+}
+
+fn export_item(id: u32, title: &str) -> ExportItem {
+    ExportItem {
+        id,
+        title: title.to_string(),
+        work: Some("内容".to_string()),
+        start_date: date(2024, 6, 1),
+        end_date: date(2024, 6, 30),
+        update_date: date(2024, 6, 2),
+        done: false,
+        tags: vec![],
+        recurrence: None,
+        revision: 0,
+    }
+}
+
+#[test]
+fn test_parse() {
+    let item = parse("(A) 2024-06-01 電話する +仕事 @外出 due:2024-06-30 id:12");
+    assert_eq!(
+        item,
+        TodoTxtItem {
+            done: false,
+            priority: Some('A'),
+            completion_date: None,
+            creation_date: date(2024, 6, 1),
+            title: "電話する".to_string(),
+            projects: vec!["仕事".to_string()],
+            contexts: vec!["@外出".to_string()],
+            due: date(2024, 6, 30),
+            id: Some(12),
+        }
+    );
+
+    let item = parse("x 2024-06-10 2024-06-01 報告書 pri:B");
+    assert!(item.done);
+    assert_eq!(item.completion_date, date(2024, 6, 10));
+    assert_eq!(item.creation_date, date(2024, 6, 1));
+    assert_eq!(item.priority, Some('B'));
+
+    let item = parse("x 2024-06-10 日付一つは完了日");
+    assert_eq!(item.completion_date, date(2024, 6, 10));
+    assert_eq!(item.creation_date, None);
+
+    let item = parse("途中の(A)や2024-06-01やxはタイトル x + @");
+    assert_eq!(item.priority, None);
+    assert_eq!(item.creation_date, None);
+    assert_eq!(item.title, "途中の(A)や2024-06-01やxはタイトル x + @");
+
+    assert_eq!(
+        "a due:明日".parse::<TodoTxtItem>(),
+        Err(TodoTxtError::InvalidDate("明日".to_string()))
+    );
+    assert_eq!(
+        "a id:x".parse::<TodoTxtItem>(),
+        Err(TodoTxtError::InvalidId("x".to_string()))
+    );
+}
+
+#[test]
+fn test_roundtrip() {
+    for line in [
+        "(A) 2024-06-01 電話する +仕事 @外出 due:2024-06-30 id:12",
+        "x 2024-06-10 2024-06-01 報告書 +仕事 pri:B id:3",
+        "2024-06-01 簡単な項目",
+        "idなし",
+    ] {
+        assert_eq!(parse(line).to_string(), line);
+    }
+}
+
+#[test]
+fn test_from_export_item() {
+    let mut item = export_item(7, "スペース を 含む");
+    item.tags = vec![
+        "仕事".to_string(),
+        "@家".to_string(),
+        "pri:C".to_string(),
+        "二 語".to_string(),
+    ];
+    assert_eq!(
+        TodoTxtItem::from(&item).to_string(),
+        "(C) 2024-06-01 スペース を 含む +仕事 +二_語 @家 due:2024-06-30 id:7"
+    );
+
+    item.done = true;
+    item.end_date = date(9999, 12, 31);
+    item.tags.clear();
+    assert_eq!(
+        TodoTxtItem::from(&item).to_string(),
+        "x 2024-06-02 2024-06-01 スペース を 含む id:7",
+        "完了日は更新日、期限なしはdueなし"
+    );
+
+    let (todo, tags) = parse("(A) 2024-06-01 電話 +仕事 @外出 id:5").to_item();
+    assert_eq!(todo.id, 5);
+    assert_eq!(todo.title, "電話");
+    assert_eq!(todo.end_date, None);
+    assert_eq!(tags, ["仕事", "@外出", "pri:A"]);
+}
+
+#[test]
+fn test_merge() {
+    let db = vec![
+        export_item(1, "変更なし"),
+        export_item(2, "ファイルで変更"),
+        export_item(3, "両方で変更"),
+        export_item(4, "DBで変更"),
+    ];
+    let (_, base) = SyncBase::render(&db);
+
+    let mut db_now = db.clone();
+    db_now[2].title = "両方で変更(DB)".to_string();
+    db_now[3].title = "DBで変更(DB)".to_string();
+    db_now[3].update_date = date(2024, 6, 5);
+
+    let content = "2024-06-01 変更なし due:2024-06-30 id:1\n\
+                   2024-06-01 ファイルで変更(済) due:2024-06-30 id:2\n\
+                   \n\
+                   2024-06-01 両方で変更(file) due:2024-06-30 id:3\n\
+                   2024-06-01 DBで変更 due:2024-06-30 id:4\n\
+                   新規 +追加\n\
+                   存在しないid id:99\n\
+                   不正 due:あした\n";
+    let plan = merge(content, &db_now, &base);
+    assert_eq!(
+        plan.updates
+            .iter()
+            .map(|(row, i)| (*row, i.title.as_str()))
+            .collect::<Vec<_>>(),
+        [(2, "ファイルで変更(済)")]
+    );
+    assert_eq!(plan.conflicts.len(), 1);
+    assert_eq!(plan.conflicts[0].row, 4);
+    assert_eq!(plan.conflicts[0].id, 3);
+    assert!(plan.conflicts[0].db_line.contains("両方で変更(DB)"));
+    assert_eq!(
+        plan.creates
+            .iter()
+            .map(|(row, i)| (*row, i.title.as_str(), i.id))
+            .collect::<Vec<_>>(),
+        [(6, "新規", None), (7, "存在しないid", None)]
+    );
+    assert_eq!(plan.errors.len(), 1);
+    assert_eq!(plan.errors[0].row, 8);
+
+    // 同期状態がなければ、内容が異なる行は、すべて競合とする。
+    let plan = merge(content, &db_now, &SyncBase::default());
+    assert!(plan.updates.is_empty());
+    assert_eq!(
+        plan.conflicts.iter().map(|c| c.id).collect::<Vec<_>>(),
+        [2, 3, 4]
+    );
+}
+
+#[test]
+fn test_merge_delete() {
+    let db = vec![
+        export_item(1, "残す"),
+        export_item(2, "ファイルで削除"),
+        export_item(3, "DBで変更後に削除"),
+        export_item(4, "解析できない行"),
+    ];
+    let (_, base) = SyncBase::render(&db);
+
+    let mut db_now = db.clone();
+    db_now[2].title = "DBで変更".to_string();
+    db_now.push(export_item(5, "他で追加"));
+
+    let content = "2024-06-01 残す due:2024-06-30 id:1\n\
+                   2024-06-01 解析できない行 due:あした id:4\n";
+    let plan = merge(content, &db_now, &base);
+    assert_eq!(plan.deletes, [2]);
+    assert_eq!(plan.conflicts.len(), 1);
+    assert_eq!(plan.conflicts[0].id, 3);
+    assert_eq!(plan.conflicts[0].row, 0);
+    assert!(plan.conflicts[0].file_line.is_empty());
+    assert_eq!(plan.errors.len(), 1);
+
+    // 同期状態がなければ、削除しない。
+    let plan = merge(content, &db_now, &SyncBase::default());
+    assert!(plan.deletes.is_empty());
+}
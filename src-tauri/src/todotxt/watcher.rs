@@ -0,0 +1,212 @@
+//! todo.txtファイルの監視と同期
+//!
+//! ファイルの変更を監視し、変更があればデータベースに反映する。
+//! データベース側の変更は、一定間隔でファイルに書き出す。
+//! 同期状態は、`<ファイル名>.sync`に保存し、アプリケーションの再起動後も引き継ぐ。
+//! 競合・反映できなかった行は、`<ファイル名>.conflict`に追記する。
+
+use super::*;
+use crate::app_status::AppStatus;
+use chrono::Local;
+use log::{error, info, warn};
+use notify::{RecursiveMode, Watcher};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+/// ファイルの変更を検知してから、同期するまでの待ち時間
+/// (エディタの保存が完了するのを待つ)
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// todo.txtファイルとの同期を、バックグラウンドで開始する。
+pub fn spawn_todotxt_sync(app: AppHandle, path: PathBuf, interval: Duration) {
+    tauri::async_runtime::spawn(async move {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let file_name = path.file_name().map(|n| n.to_os_string());
+        let watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            if let Ok(event) = res {
+                if event
+                    .paths
+                    .iter()
+                    .any(|p| p.file_name().map(|n| n.to_os_string()) == file_name)
+                {
+                    let _ = tx.send(());
+                }
+            }
+        });
+        // エディタは、別ファイルに保存して置き換えることがあるため、ディレクトリを監視する。
+        let dir = match path.parent() {
+            Some(d) if !d.as_os_str().is_empty() => d.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let _watcher = match watcher.and_then(|mut w| {
+            w.watch(&dir, RecursiveMode::NonRecursive)?;
+            Ok(w)
+        }) {
+            Ok(w) => Some(w),
+            Err(e) => {
+                warn!("todo.txtの監視を開始できません。定期的な同期のみ行います。:{e}");
+                None
+            }
+        };
+        info!("todo.txtとの同期開始:{}", path.display());
+
+        let mut base = load_base(&path);
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {}
+                Some(()) = rx.recv() => {
+                    tokio::time::sleep(DEBOUNCE).await;
+                    while rx.try_recv().is_ok() {}
+                }
+            }
+            if let Err(e) = sync_once(&app, &path, &mut base).await {
+                error!("todo.txtとの同期に失敗:{e}");
+            }
+        }
+    });
+}
+
+/// 一回分の同期を行う。ログインしていなければ、何もしない。
+/// ファイルがなければ、前回の同期状態を破棄し、データベースの内容を書き出す。
+/// 同期中にファイルが変更されたら、書き出さずに、変更後の内容で同期し直す。
+async fn sync_once(app: &AppHandle, path: &Path, base: &mut SyncBase) -> Result<(), String> {
+    let state = app.state::<AppStatus>();
+    let Some(sess) = state.config().lock().unwrap().get_session_id() else {
+        return Ok(());
+    };
+    let mut content = match read_file(path).map_err(|e| e.to_string())? {
+        Some(s) => s,
+        None => {
+            *base = SyncBase::default();
+            String::new()
+        }
+    };
+    let outcome = loop {
+        let outcome = state
+            .todo()
+            .sync_todotxt(sess, &content, base)
+            .await
+            .map_err(|e| e.to_string())?;
+        if outcome.created > 0 || outcome.updated > 0 || outcome.deleted > 0 {
+            info!(
+                "todo.txtから、追加{}件、更新{}件、削除{}件",
+                outcome.created, outcome.updated, outcome.deleted
+            );
+        }
+        let latest = read_file(path)
+            .map_err(|e| e.to_string())?
+            .unwrap_or_default();
+        if latest == content {
+            break outcome;
+        }
+        info!("同期中にtodo.txtが変更されました。同期し直します。");
+        content = attach_created_ids(&content, &latest, &outcome);
+    };
+
+    if !outcome.conflicts.is_empty() || !outcome.errors.is_empty() {
+        warn!(
+            "todo.txt:競合{}件、エラー{}件。{}を確認してください。",
+            outcome.conflicts.len(),
+            outcome.errors.len(),
+            sidecar(path, "conflict").display()
+        );
+        append_conflicts(path, &content, &outcome).map_err(|e| e.to_string())?;
+    }
+    if outcome.content != content {
+        write_atomic(path, &outcome.content).map_err(|e| e.to_string())?;
+    }
+    if outcome.base != *base {
+        *base = outcome.base;
+        save_base(path, base).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// ファイルを読み込む。なければ、Noneを返す。
+fn read_file(path: &Path) -> std::io::Result<Option<String>> {
+    match std::fs::read_to_string(path) {
+        Ok(s) => Ok(Some(s)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// 同期中に変更されたファイルの内容のうち、同期で追加した行を、idを付けた行に置き換える。
+/// 同期し直したときに、同じtodoを二重に追加しないようにする。
+fn attach_created_ids(synced: &str, latest: &str, outcome: &SyncOutcome) -> String {
+    let synced = synced.lines().collect::<Vec<_>>();
+    let mut lines = latest.lines().map(str::to_string).collect::<Vec<_>>();
+    for (row, id) in &outcome.created_rows {
+        let (Some(line), Some(entry)) = (synced.get(row - 1), outcome.base.entries.get(id)) else {
+            continue;
+        };
+        if let Some(l) = lines.iter_mut().find(|l| l.as_str() == *line) {
+            *l = entry.line.clone();
+        }
+    }
+    lines.into_iter().map(|l| l + "\n").collect()
+}
+
+/// 同期用ファイルの隣に置く、補助ファイルのパス
+fn sidecar(path: &Path, ext: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(ext);
+    path.with_file_name(name)
+}
+
+/// 保存された同期状態を読み込む。なければ、空とする。
+fn load_base(path: &Path) -> SyncBase {
+    std::fs::read_to_string(sidecar(path, "sync"))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+/// 同期状態を保存する。
+fn save_base(path: &Path, base: &SyncBase) -> std::io::Result<()> {
+    let json = serde_json::to_string(base).map_err(std::io::Error::other)?;
+    write_atomic(&sidecar(path, "sync"), &json)
+}
+
+/// 一時ファイルに書き出してから置き換え、書きかけの状態が見えないようにする。
+fn write_atomic(path: &Path, content: &str) -> std::io::Result<()> {
+    let tmp = sidecar(path, "tmp");
+    std::fs::write(&tmp, content)?;
+    std::fs::rename(tmp, path)
+}
+
+/// 競合・反映できなかった行を、競合ファイルに追記する。
+fn append_conflicts(path: &Path, content: &str, outcome: &SyncOutcome) -> std::io::Result<()> {
+    let lines = content.lines().collect::<Vec<_>>();
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(sidecar(path, "conflict"))?;
+    writeln!(file, "# {}", Local::now().format("%Y/%m/%d %H:%M:%S"))?;
+    for c in &outcome.conflicts {
+        if c.file_line.is_empty() {
+            writeln!(
+                file,
+                "# 削除された行: データベース側も変更されていたため、削除しませんでした。"
+            )?;
+            writeln!(file, "#   {}", c.db_line)?;
+            continue;
+        }
+        writeln!(
+            file,
+            "# {}行目: データベース側も変更されていたため、次の内容を優先しました。",
+            c.row
+        )?;
+        writeln!(file, "#   {}", c.db_line)?;
+        writeln!(file, "{}", c.file_line)?;
+    }
+    for e in &outcome.errors {
+        writeln!(file, "# {}行目: {}", e.row, e.message)?;
+        writeln!(file, "{}", lines.get(e.row - 1).unwrap_or(&""))?;
+    }
+    Ok(())
+}
@@ -1,8 +1,19 @@
 import { invoke } from "@tauri-apps/api/core";
+import { useQuery } from "@tanstack/react-query";
 import { str2date } from "./str2date.jsx";
 import { InputTodo } from "./InputTodo.jsx";
 
+// 今日からの日数を、日付入力欄の相対指定("+n"/"-n")に変換する
+const offset2str = (offset) => {
+    if (!offset) { return ""; }
+    return offset > 0 ? "+" + offset : String(offset);
+};
+
 function AddTodo() {
+    const { data: settings, isPending } = useQuery({
+        queryKey: ['user_settings'],
+        queryFn: () => invoke('get_user_settings'),
+    });
 
     const send_data = async (data) => {
         const res = {item : {
@@ -14,11 +25,15 @@ function AddTodo() {
         await invoke('add_todo', res);
     };
 
+    if (isPending) {
+        return (<p> loading... </p>);
+    }
+
     const init_val = {
         title : "",
         work : "",
-        start : "",
-        end : "",
+        start : offset2str(settings?.default_start_offset),
+        end : offset2str(settings?.default_end_offset),
     };
 
     return (
@@ -10,6 +10,7 @@ import RegistUser from "./RegistUser.jsx";
 import Init from "./Init.jsx";
 import EditTodo from "./EditTodo";
 import PasteTodo from "./PasteTodo.jsx";
+import SetupDatabase from "./SetupDatabase.jsx";
 
 export const routes = createBrowserRouter(
     createRoutesFromElements(
@@ -17,6 +18,7 @@ export const routes = createBrowserRouter(
             <Route element={ <BasePage/> }>
                 <Route path="/" element={<Init/>}/>
                 <Route path="/login" element={<Login/>}/>
+                <Route path="/setup" element={<SetupDatabase/>}/>
                 <Route path="/regist_user" element={<RegistUser/>}/>
                 <Route path="/todo" element={<TodoList/>}/>
                 <Route path="/addtodo" element={<AddTodo/>}/>
@@ -1,8 +1,28 @@
+import { useEffect } from "react";
 import { Outlet } from "react-router-dom";
+import { useQuery } from "@tanstack/react-query";
+import { useColorMode } from "@yamada-ui/react";
+import { invoke } from "@tauri-apps/api/core";
 import "./App.css";
 
+// ユーザー設定のテーマを、yamada-uiのカラーモードに変換する
+const THEME_COLOR_MODE = { System: "system", Light: "light", Dark: "dark" };
 
 function BasePage() {
+    const { changeColorMode } = useColorMode();
+    // ログイン前は取得できないため、再試行しない。
+    const { data: settings } = useQuery({
+        queryKey: ['user_settings'],
+        queryFn: () => invoke('get_user_settings'),
+        retry: false,
+    });
+
+    useEffect(() => {
+        const mode = THEME_COLOR_MODE[settings?.theme];
+        if (mode) {
+            changeColorMode(mode);
+        }
+    }, [settings?.theme, changeColorMode]);
 
     return (
         <>
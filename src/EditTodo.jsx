@@ -23,7 +23,23 @@ export default function EditTodo() {
                 end: str2date(data.end)?.toLocaleDateString(),
             }
         };
-        await invoke("edit_todo", res);
+        try {
+            await invoke("edit_todo", {...res, revision: todo.revision});
+        } catch (e) {
+            // 他の端末で更新されていた場合は、最新の内容を示して上書きするか確認する。
+            if (typeof e !== "string" || !e.startsWith("Conflict:")) {
+                throw e;
+            }
+            const latest = JSON.parse(e.slice("Conflict:".length));
+            const message = "他の端末で更新されています。\n"
+                + `最新のタイトル: ${latest.title}\n`
+                + `最新の詳細: ${latest.work ?? ""}\n`
+                + "この内容で上書きしますか?";
+            if (!window.confirm(message)) {
+                throw "他の端末で更新されたため、保存しませんでした。";
+            }
+            await invoke("edit_todo", {...res, revision: latest.revision});
+        }
     };
 
     if (isLoading) {
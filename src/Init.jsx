@@ -1,6 +1,7 @@
 /* アプリケーションの初期化 */
 /* 有効なセッションがあれば、ログイン済みに */
 /* でなければ、ログイン画面へ遷移 */
+/* データベースの接続情報が未設定なら、設定画面へ遷移 */
 
 import { Container, Heading} from "@yamada-ui/react";
 import { invoke } from "@tauri-apps/api/core";
@@ -14,7 +15,9 @@ function Init() {
 
     const { data, isFetching, isSuccess, isError, error } = useQuery({
         queryKey: ['check_login'],
-        queryFn: async () => invoke('is_valid_session')
+        queryFn: async () => invoke('is_valid_session'),
+        // 接続情報が未設定なら、再試行せずに設定画面へ遷移する。
+        retry: (count, e) => e !== "NotConfigured" && count < 3,
         });
 
     useEffect( () => {
@@ -27,6 +30,12 @@ function Init() {
         }
     },[isSuccess, isFetching])
 
+    useEffect( () => {
+        if (isError && error === "NotConfigured") {
+            navi('/setup');
+        }
+    },[isError, error])
+
     return (
         <>
             <Container centerContent>
@@ -17,6 +17,7 @@ function Login() {
         try {
             await invoke('login', { name: data.name, password: data.pass });
             queryClient.invalidateQueries("check_login");
+            queryClient.invalidateQueries({queryKey: ['user_settings']});
             navi('/');
         } catch (e) {
             if (e === "WrongPassword") {
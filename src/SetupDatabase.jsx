@@ -0,0 +1,147 @@
+/* データベース接続の設定画面 */
+/* 接続の確認、スキーマの作成、接続情報の保存を行う */
+
+import { useForm } from "react-hook-form";
+import { VStack, HStack, FormControl, Input, NumberInput, Button, Text, Container, PasswordInput, Select, Option, useAsyncCallback, Heading } from "@yamada-ui/react";
+import { invoke } from "@tauri-apps/api/core";
+import { useNavigate } from "react-router-dom";
+import { useState } from "react";
+import { useQuery, useQueryClient } from "@tanstack/react-query";
+
+function SetupDatabase() {
+    const {data, isPending} = useQuery({
+        queryKey: ['connection_settings'],
+        queryFn: () => invoke('get_connection_settings'),
+    });
+
+    if (isPending) {
+        return (<p> loading </p>);
+    }
+
+    return (<SetupForm settings={data}/>);
+}
+
+function SetupForm({settings}) {
+    const { register, handleSubmit, getValues, formState: {errors} } = useForm({
+        defaultValues: {...settings, port: settings?.port ?? ''},
+    });
+    const [ message, setMessage ] = useState('');
+    const [ check, setCheck ] = useState(null);
+    const navi = useNavigate();
+    const queryClient = useQueryClient();
+
+    // 画面の入力を、コマンドの引数にする。
+    const toSettings = (data) => ({
+        ...data,
+        port: data.port === '' ? null : Number(data.port),
+    });
+
+    const [isTesting, onTest] = useAsyncCallback( async () => {
+        try {
+            const ret = await invoke('test_connection', { settings: toSettings(getValues()) });
+            setCheck(ret);
+            setMessage(checkMessage(ret));
+        } catch (e) {
+            setCheck(null);
+            setMessage('接続できません。{' + e + '}');
+        }
+    },[]);
+
+    const [isMigrating, onMigrate] = useAsyncCallback( async () => {
+        try {
+            const ret = await invoke('apply_migrations', { settings: toSettings(getValues()) });
+            setCheck(ret);
+            setMessage('データベースを準備しました。' + checkMessage(ret));
+        } catch (e) {
+            setMessage('データベースを準備できません。{' + e + '}');
+        }
+    },[]);
+
+    const [isSaving, onSave] = useAsyncCallback( async (data) => {
+        try {
+            await invoke('save_connection_settings', { settings: toSettings(data) });
+            queryClient.removeQueries();
+            navi('/');
+        } catch (e) {
+            setMessage('保存できません。{' + e + '}');
+        }
+    },[]);
+
+    const ready = check?.schema_version === check?.required_version;
+
+    return (
+        <>
+            <Container>
+                <Heading> データベースの設定 </Heading>
+                <VStack as="form" onSubmit={handleSubmit(onSave)}>
+                    <HStack>
+                        <FormControl label="ホスト名">
+                            <Input {...register("host")}/>
+                        </FormControl>
+                        <FormControl w="10em" label="ポート番号">
+                            <NumberInput min={1} max={65535} placeholder="3306" {...register("port")}/>
+                        </FormControl>
+                    </HStack>
+                    <FormControl label="ソケット(指定時はホスト名より優先)">
+                        <Input {...register("socket")}/>
+                    </FormControl>
+                    <FormControl label="データベース名">
+                        <Input placeholder="nekotodo" {...register("database")}/>
+                    </FormControl>
+                    <FormControl
+                        invalid={!!errors.user}
+                        label="ユーザー名"
+                        errorMessage={errors?.user?.message}
+                    >
+                        <Input {...register("user", {required: "入力は必須です。"},)}/>
+                    </FormControl>
+                    <FormControl label="パスワード(未入力なら保存済みのものを使用)">
+                        <PasswordInput {...register("pass")}/>
+                    </FormControl>
+                    <HStack>
+                        <FormControl w="14em" label="SSL">
+                            <Select defaultValue={settings?.ssl_mode ?? "preferred"}
+                                {...register("ssl_mode")}>
+                                <Option value="disabled">使用しない</Option>
+                                <Option value="preferred">可能なら使用</Option>
+                                <Option value="required">必須</Option>
+                                <Option value="verify-ca">必須(証明書を検証)</Option>
+                                <Option value="verify-identity">必須(証明書とホスト名を検証)</Option>
+                            </Select>
+                        </FormControl>
+                        <FormControl label="CA証明書">
+                            <Input {...register("ssl_ca")}/>
+                        </FormControl>
+                    </HStack>
+                    <HStack ml="auto" mr="auto">
+                        <Button onClick={onTest} loading={isTesting} loadingText="確認中">
+                            接続を確認
+                        </Button>
+                        <Button onClick={onMigrate} loading={isMigrating} loadingText="処理中"
+                            disabled={!check || ready}>
+                            データベースを準備
+                        </Button>
+                        <Button type="submit" loading={isSaving} loadingText="処理中"
+                            disabled={!ready}>
+                            保存して開始
+                        </Button>
+                    </HStack>
+                    <Text>{message}</Text>
+                </VStack>
+            </Container>
+        </>
+    );
+}
+
+// データベースの状態の説明
+function checkMessage(check) {
+    if (!check.database_exists) {
+        return '接続できました。データベースがまだありません。「データベースを準備」で作成してください。';
+    }
+    if (check.schema_version !== check.required_version) {
+        return '接続できました。データベースの更新が必要です。「データベースを準備」で更新してください。';
+    }
+    return '接続できました。「保存して開始」で使用を開始できます。';
+}
+
+export default SetupDatabase;
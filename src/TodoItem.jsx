@@ -14,10 +14,14 @@ export default function TodoItem({item}) {
     const queyrClient = useQueryClient();
     const {mutate} = useMutation({
         mutationFn: () => {
-            return invoke("update_done", {id: item.id, done: !item.done})
+            return invoke("update_done", {id: item.id, done: !item.done, revision: item.revision})
         },
         onSuccess: () => {
             queyrClient.invalidateQueries({ queryKey: ["todo_list"]});
+        },
+        // 他の端末で更新されていた場合などは、最新の状態を読み込み直す。
+        onError: () => {
+            queyrClient.invalidateQueries({ queryKey: ["todo_list"]});
         }
     });
 
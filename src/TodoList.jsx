@@ -1,17 +1,60 @@
-import { useQuery, } from "@tanstack/react-query";
-import { Container, Grid, GridItem, } from "@yamada-ui/react";
+import { useEffect, useState } from "react";
+import { useInfiniteQuery, useQueryClient, } from "@tanstack/react-query";
+import { Button, Container, Grid, GridItem, Text, } from "@yamada-ui/react";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import "./App.css";
 import TodoItem from "./TodoItem.jsx";
 import TodoItemToolbar from "./TodoListToolbar.jsx";
 
-const get_todo_list = async () => invoke('get_todo_list') ;
+// 一度に取得する件数
+const PAGE_SIZE = 100;
+
+// バックエンドから通知される、todoの変更イベント
+const CHANGE_EVENTS = ['todo-created', 'todo-updated', 'todo-deleted'];
+
+// 設定ファイルの内容に依存する問い合わせ
+const CONFIG_QUERIES = ['is_incomplete', 'item_sort_order', 'profiles', 'todo_list'];
+
+const get_todo_list = async (viewId, cursor) => {
+    const page = {cursor: cursor, limit: PAGE_SIZE};
+    if (viewId) {
+        return invoke('get_todo_list_for_view', {id: Number(viewId), ...page});
+    }
+    return invoke('get_todo_list', page);
+};
 
 function TodoList() {
+    const [viewId, setViewId] = useState("");
+    const queryClient = useQueryClient();
+
+    // 他のウィンドウ等での変更を、リストに反映する。
+    useEffect(() => {
+        const unlisten = CHANGE_EVENTS.map(name => listen(name, () => {
+            queryClient.invalidateQueries({queryKey: ['todo_list']});
+        }));
+        // 他のプロセスによる設定の変更も、表示に反映する。
+        unlisten.push(listen('config-changed', () => {
+            CONFIG_QUERIES.forEach(key => queryClient.invalidateQueries({queryKey: [key]}));
+        }));
+        return () => {
+            unlisten.forEach(p => p.then(f => f()));
+        };
+    }, [queryClient]);
 
-    const { data: todos, isLoading: isTodoListLoading , isError, error} = useQuery({
-        queryKey: ['todo_list'],
-        queryFn: get_todo_list,
+    const {
+        data,
+        isLoading: isTodoListLoading,
+        isError,
+        error,
+        hasNextPage,
+        fetchNextPage,
+        isFetchingNextPage,
+    } = useInfiniteQuery({
+        queryKey: ['todo_list', viewId],
+        queryFn: ({pageParam}) => get_todo_list(viewId, pageParam),
+        initialPageParam: null,
+        getNextPageParam: (lastPage) => lastPage.next_cursor ?? undefined,
     });
 
     if (isTodoListLoading) {
@@ -22,13 +65,15 @@ function TodoList() {
         return ( <p> エラーだよ。{error}</p> );
     }
 
-    console.log(todos);
+    const todos = data?.pages.flatMap(page => page.items);
+    const total = data?.pages[0]?.total ?? 0;
     return (
         <>
             <Container gap="0" bg="backgound">
-                <TodoItemToolbar/>
+                <TodoItemToolbar viewId={viewId} onViewChange={setViewId}/>
 
                 <h1>現在の予定</h1>
+                <Text>{todos?.length} / {total}件</Text>
                 <Grid templateColumns="repeat(4, 1fr)" gap="md" >
                     {todos?.map( todo_item => {
                         return (
@@ -38,6 +83,11 @@ function TodoList() {
                         )}
                     )}
                 </Grid>
+                {hasNextPage && (
+                    <Button onClick={() => fetchNextPage()} loading={isFetchingNextPage}>
+                        さらに読み込む
+                    </Button>
+                )}
             </Container>
         </>
     );
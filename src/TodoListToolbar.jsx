@@ -1,12 +1,12 @@
 import { useNavigate } from "react-router-dom";
 import { useMutation, useQuery, useQueryClient } from "@tanstack/react-query";
-import { HStack, IconButton, Select, Switch, Option } from "@yamada-ui/react";
+import { Button, HStack, IconButton, Select, Switch, Option, Text } from "@yamada-ui/react";
 import { invoke } from "@tauri-apps/api/core";
 import { AiOutlineFileAdd } from "react-icons/ai";
 import "./App.css";
 
 
-export default function TodoListToolbar() {
+export default function TodoListToolbar({viewId, onViewChange}) {
 
     const navi = useNavigate();
     const handleAddTodo = () => navi('/addtodo');
@@ -15,8 +15,11 @@ export default function TodoListToolbar() {
         <>
             <HStack>
                 <IconButton icon={<AiOutlineFileAdd/>} onClick={handleAddTodo}/>
+                <SelectView viewId={viewId} onViewChange={onViewChange}/>
                 <SwitchIncomplete/>
                 <SelectItemSortOrder/>
+                <OfflineStatus/>
+                <SelectProfile/>
             </HStack>
         </>
     );
@@ -84,3 +87,100 @@ function SelectItemSortOrder() {
         </Select>
     );
 }
+
+function SelectView({viewId, onViewChange}) {
+    const {data: views, isPending} = useQuery({
+        queryKey: ['views'],
+        queryFn: () => invoke('get_views') ,
+    });
+
+    if (isPending) {
+        return (<p> loading </p>);
+    }
+
+    return (
+        <Select w="12em" value={viewId} onChange={onViewChange}>
+            <Option value="">(すべて)</Option>
+            {views?.map( view => (
+                <Option key={view.id} value={String(view.id)}>{view.name}</Option>
+            ))}
+        </Select>
+    );
+}
+
+// 接続プロファイルを切り替える。プロファイルが一つだけなら表示しない。
+function SelectProfile() {
+    const navi = useNavigate();
+    const queryClient = useQueryClient();
+    const {data, isPending} = useQuery({
+        queryKey: ['profiles'],
+        queryFn: () => invoke('get_profiles') ,
+    });
+
+    const {mutate} = useMutation({
+        mutationFn: (name) => invoke('switch_profile', {name: name}) ,
+        onSuccess: () => {
+            // 切り替え先のセッションで、ログイン状態の確認からやり直す。
+            queryClient.removeQueries();
+            navi('/');
+        },
+        onError: (err) => console.log(err),
+    });
+
+    if (isPending || !data || data.profiles.length < 2) {
+        return null;
+    }
+
+    return (
+        <Select w="9em" value={data.current} onChange={(value) => mutate(value)}>
+            {data.profiles.map( name => (
+                <Option key={name} value={name}>{name}</Option>
+            ))}
+        </Select>
+    );
+}
+
+// データベースへの接続状態と、オフライン中の変更の送信状況を表示する。
+function OfflineStatus() {
+    const queryClient = useQueryClient();
+    const {data: status} = useQuery({
+        queryKey: ['offline_status'],
+        queryFn: () => invoke('get_offline_status'),
+        refetchInterval: 5000,
+    });
+    const {data: connection} = useQuery({
+        queryKey: ['connection_status'],
+        queryFn: () => invoke('get_connection_status'),
+        refetchInterval: 5000,
+    });
+    const {mutate: clearConflicts} = useMutation({
+        mutationFn: () => invoke('clear_offline_conflicts'),
+        onSuccess: () => queryClient.invalidateQueries({queryKey: ['offline_status']}),
+        onError: (err) => console.log(err),
+    });
+
+    if (!status) {
+        return null;
+    }
+
+    return (
+        <>
+            {!status.online && <Text color="danger">オフライン</Text>}
+            {connection?.state === "reconnecting" && <Text>再接続中({connection.failures}回目)</Text>}
+            {connection?.state === "failed" && <Text color="danger" title={connection.last_error}>接続エラー</Text>}
+            {status.pending > 0 && <Text>送信待ち{status.pending}件</Text>}
+            {status.conflicts.length > 0 && (
+                <Button size="sm" onClick={() => {
+                    const lines = status.conflicts.map(c =>
+                        `${c.change.op}: ${c.change.item?.title ?? c.change.id}` +
+                        (c.current ? ` → 最新「${c.current.title}」` : " → 削除済み"));
+                    if (window.confirm("反映できなかった変更があります。\n" + lines.join("\n") + "\n\n記録を破棄しますか?")) {
+                        clearConflicts();
+                    }
+                }}>
+                    競合{status.conflicts.length}件
+                </Button>
+            )}
+        </>
+    );
+}
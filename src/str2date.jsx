@@ -17,7 +17,7 @@ export function str2date(str) {
             case 0:
                 return null;
             case 1:
-                if (date_item[0][0] == '+') {
+                if (date_item[0][0] == '+' || date_item[0][0] == '-') {
                     ret_date.setDate(ret_date.getDate() + Number(date_item[0]));
                 } else {
                     ret_date.setDate(Number(date_item[0]));